@@ -30,22 +30,61 @@ MovementMode = {
     ---@field get_transform fun(self:Scene, index:integer):Transform
     ---@field get_rigid_body fun(self:Scene, index:integer):RigidBodyComponent
     ---@field get_camera fun(self:Scene, index:integer):CameraComponent
+    ---@field get_animation fun(self:Scene, index:integer):AnimationComponent
+    ---@field crossfade fun(self:Scene, from_index:integer, to_index:integer, duration:number):nil fades one animation out and another in, starting it if needed, the first stops once faded out
+    ---@field add_ik_constraint fun(self:Scene, solver:string, chain:integer[], target:Entity|Vector):IkConstraint solver is "two_bone", "fabrik", "ccd" or "look_at", chain lists joint entities from the root down, each a child of the previous one
+    ---@field get_ik_constraint fun(self:Scene, index:integer):IkConstraint
+    ---@field remove_ik_constraint fun(self:Scene, index:integer):nil returns the joints to their animated pose, the last constraint takes the removed one's index
     ---@field reset_outlines fun(self:Scene):nil
     ---@field add_outlined_component fun(self:Scene, index:integer):nil
     ---@field add_outlined_body fun(self:Scene, index:integer):nil
-    ---@field load_model fun(self:Scene, parent_index:integer):nil
+    ---@field load_model fun(self:Scene, parent_index:integer):nil picks a .gltf or .glb file to import, errors naming the file and field if the model is invalid
     ---@field running boolean
+    ---@field revert_on_stop boolean when set, the scene returns to its state from before running once running is set to false
+    ---@field physics_rate number fixed physics steps per second, 300 by default
+    ---@field max_physics_steps integer most physics steps taken per update, at least 1, time past this is dropped
+    ---@field physics_interpolation string how rigid bodies are posed between steps, "none", "interpolate" (default) or "extrapolate"
     ---@field step fun(self:Scene, dt:number):nil
+    ---@field save fun(self:Scene, path:string):nil
+    ---@field load fun(self:Scene, path:string):nil
+    ---@field remove_entity fun(self:Scene, index:integer, recursive:boolean):nil
+    ---@field duplicate_entity fun(self:Scene, index:integer):Entity copies the entity, its descendants and all of their components
+    ---@field save_prefab fun(self:Scene, index:integer, path:string):nil saves the entity and its descendants as a prefab, and makes the entity an instance of it
+    ---@field instantiate fun(self:Scene, path:string, parent_index:integer):Entity
+    ---@field resync_prefab fun(self:Scene, path:string):Entity[] rebuilds every instance of the prefab from its file, returning the new instances
+    ---@field query fun(self:Scene, components:string[]):table[] a row per entity with all of the components ("transform", "render_component", "rigid_body", "camera", "light", "sun", or a custom component type), with the entity under "entity" and each component under its name
+    ---@field on fun(self:Scene, event_name:string, handler:fun(event:SceneEvent)):nil handlers are called once per frame before Update, for each event since the last frame
+    ---@field off fun(self:Scene, event_name:string, handler:function):nil
+    ---@field register_component fun(self:Scene, name:string, defaults:table):nil defines a custom component type from field names and default values (number, integer, boolean, string or Vector), registering it again updates existing components
+    ---@field find_by_name fun(self:Scene, name:string):Entity|nil
+    ---@field find_by_path fun(self:Scene, path:string):Entity|nil names from the root down, eg. "Scene/ffocks/Armature/Head"
+    ---@field entities_with_tag fun(self:Scene, tag:string):Entity[]
+    ---@field entities_in_layers fun(self:Scene, layer_mask:integer):Entity[] entities in any of the layers set in the mask
+    ---@field overlap_sphere fun(self:Scene, center:Vector, radius:number):Entity[] entities whose world bounds, around their meshes and hitboxes, overlap the sphere
+    ---@field overlap_box fun(self:Scene, min:Vector, max:Vector):Entity[] entities whose world bounds overlap the box
+    ---@field raycast_bounds fun(self:Scene, origin:Vector, direction:Vector, max_distance:number|nil):{entity:Entity, distance:number}[] entities whose world bounds the ray passes through, nearest first
+    ---@field undo fun(self:Scene):string|nil reverts the last edit made through the scene API (transforms, names, layers, static, parents, added and removed entities), returning its name, edits made while running are not recorded
+    ---@field redo fun(self:Scene):string|nil reapplies the last undone edit, returning its name
+    ---@field can_undo boolean
+    ---@field can_redo boolean
+    ---@field begin_transaction fun(self:Scene, name:string):nil groups the edits until commit_transaction into one undoable entry, consecutive edits to the same field are merged
+    ---@field commit_transaction fun(self:Scene):nil
+    ---@field cancel_transaction fun(self:Scene):nil reverts and discards the edits of the open transaction
+    ---@field history fun(self:Scene):{name:string, done:boolean}[] undoable and redoable entries, oldest first, done is false for the ones that have been undone
+    ---@field clear_history fun(self:Scene):nil
     
+        --- Transforms, render components, rigid bodies, cameras, animations and scripts error once they or their entity have been removed,
+        --- instead of reaching the component that took their index.
         ---@class Transform
         ---@field translation Vector
         ---@field rotation Vector
         ---@field scale Vector
-        ---@field owner Transform
+        ---@field owner Entity
         ---@field owner_index integer
     
         ---@class RenderComponent
         ---@field index integer
+        ---@field owner Entity
 
         ---@class RigidBodyComponent
         ---@field index integer
@@ -61,10 +100,68 @@ MovementMode = {
         ---@field aspect_ratio number
         ---@field near number
         ---@field far number
+        ---@field orthographic number[]|nil { half_width, half_height } of an orthographic view, nil for a perspective one
+
+        --- Animations advance with the scene's delta time, so they only play while the scene is running or being stepped
+        ---@class AnimationComponent
+        ---@field index integer
+        ---@field owner Entity
+        ---@field name string
+        ---@field duration number seconds
+        ---@field running boolean
+        ---@field paused boolean holds the current pose without stopping
+        ---@field speed number playback rate, negative to play backwards
+        ---@field time number seconds into the animation, setting it seeks without passing markers
+        ---@field normalized_time number time from 0 at the start to 1 at the end
+        ---@field repeat boolean
+        ---@field ping_pong boolean plays back to the start after reaching the end, indefinitely with repeat
+        ---@field snap_back boolean returns the animated entities to their own transforms when stopped
+        ---@field markers {name:string, time:number}[]
+        ---@field weight number influence when blended with other animations of the same entities, setting it cancels fades
+        ---@field layer integer layers are applied in ascending order, each over the pose of the layers below
+        ---@field additive boolean adds the difference from the first keyframe instead of blending towards the animation's pose
+        ---@field mask integer[]|nil indices of the entities the animation may affect, all of its targets if nil
+        ---@field start fun(self:AnimationComponent):nil
+        ---@field stop fun(self:AnimationComponent):nil
+        ---@field seek fun(self:AnimationComponent, time:number):nil
+        ---@field fade_to fun(self:AnimationComponent, weight:number, duration:number, stop:boolean|nil):nil with stop, the animation stops once the weight is reached
+        ---@field crossfade_to fun(self:AnimationComponent, other_index:integer, duration:number):nil
+        ---@field set_mask fun(self:AnimationComponent, entity_index:integer, recursive:boolean):nil limits the animation to the entity, and its descendants if recursive, eg. the spine for an upper body layer
+        ---@field clear_mask fun(self:AnimationComponent):nil
+        ---@field add_marker fun(self:AnimationComponent, name:string, time:number):nil playback passing the time sends an "animation_marker" scene event
+        ---@field remove_marker fun(self:AnimationComponent, name:string):nil
+
+        --- A state machine playing the animations of its entity, loaded from a JSON file, see AnimationController in animation_controller.rs for the format
+        ---@class AnimationController
+        ---@field owner Entity
+        ---@field uri string
+        ---@field state string name of the current state
+        ---@field state_names string[]
+        ---@field parameter_names string[]
+        ---@field get_parameter fun(self:AnimationController, name:string):number|boolean|nil
+        ---@field set_parameter fun(self:AnimationController, name:string, value:number|boolean):nil errors if the parameter is not declared or has a different type
+        ---@field set_trigger fun(self:AnimationController, name:string):nil triggers stay set until a transition uses them
+        ---@field reset_trigger fun(self:AnimationController, name:string):nil
+        ---@field play fun(self:AnimationController, state:string, duration:number|nil):nil switches to the state directly, crossfading over duration seconds
+
+        --- Poses a chain of joints after animations are applied and before skins are updated, blended over the animated pose by weight
+        --- Errors once the constraint has been removed, its index follows it when other constraints are removed.
+        ---@class IkConstraint
+        ---@field index integer
+        ---@field solver string
+        ---@field chain Entity[]
+        ---@field target Entity|Vector an entity target is followed through its world translation
+        ---@field pole Entity|Vector|nil two_bone only, where the middle joint bends towards, nil to keep its current bend
+        ---@field aim_axis Vector look_at only, the local axis turned towards the target, +Z by default
+        ---@field weight number 0 to 1
+        ---@field enabled boolean
+        ---@field iterations integer fabrik and ccd, 10 by default
+        ---@field tolerance number fabrik and ccd stop once the last joint is this close to the target
 
         ---@class Entity
         ---@field transform_index integer
         ---@field rigid_body_index integer
+        ---@field camera_index integer -1 if the entity has no camera
         ---@field children_indices integer[]
         ---@field name string
         ---@field render_component_indices integer[]
@@ -73,6 +170,67 @@ MovementMode = {
         ---@field index integer
         ---@field parent Entity
         ---@field parent_index integer
+        ---@field valid boolean false once the entity has been removed
+        ---@field remove fun(self:Entity, recursive:boolean):nil
+        ---@field remove_rigid_body fun(self:Entity):nil
+        ---@field remove_render_component fun(self:Entity, index:integer):nil
+        ---@field duplicate fun(self:Entity):Entity
+        ---@field set_parent fun(self:Entity, parent_index:integer, keep_world_transform:boolean):nil errors if the parent is the entity itself or one of its descendants
+        ---@field script_component_indices integer[]
+        ---@field animation_indices integer[]
+        ---@field get_animation fun(self:Entity, index:integer):AnimationComponent
+        ---@field add_animation_controller fun(self:Entity, path:string):AnimationController errors if the file is invalid or names clips the entity has no animations for
+        ---@field get_animation_controller fun(self:Entity):AnimationController|nil
+        ---@field remove_animation_controller fun(self:Entity):boolean false if the entity had no controller
+        ---@field add_script fun(self:Entity, uri:string):Script
+        ---@field get_script fun(self:Entity, index:integer):Script
+        ---@field get_field fun(self:Entity, name:string):any the field from the first of the entity's scripts that exports it
+        ---@field set_field fun(self:Entity, name:string, value:any):nil errors if no script exports the field, or the value has a different type than its declaration
+        ---@field find_child fun(self:Entity, path:string):Entity|nil path of names relative to the entity
+        ---@field tags string[]
+        ---@field has_tag fun(self:Entity, tag:string):boolean
+        ---@field add_tag fun(self:Entity, tag:string):nil
+        ---@field remove_tag fun(self:Entity, tag:string):nil
+        ---@field layers integer bitmask, layer 0 by default
+        ---@field morph_weights number[] weights of the mesh's morph targets, reads give the animated weights while animations drive them
+        ---@field component_names string[] types of the entity's custom components
+        ---@field add_component fun(self:Entity, name:string):CustomComponent errors if the type is not registered or the entity already has one
+        ---@field get_component fun(self:Entity, name:string):CustomComponent|nil
+        ---@field remove_component fun(self:Entity, name:string):boolean false if the entity had no such component
+
+        --- Scripts attached to entities get their own environment per entity while the scene is running, with `self` set to the entity.
+        --- Awake is called when the instance is created, Start before its first Update.
+        --- Top-level number, integer, boolean, string and Vector globals of the script are exported as fields, stored per script component. Values an instance changed are kept when the scene is saved.
+        ---@class Script
+        ---@field index integer
+        ---@field uri string
+        ---@field owner Entity
+        ---@field owner_index integer
+        ---@field field_names string[]
+        ---@field get_field fun(self:Script, name:string):any
+        ---@field set_field fun(self:Script, name:string, value:any):nil errors if the script does not export the field, or the value has a different type than the field's declaration
+        ---@field call_method fun(self:Script, name:string, ...):any only while the scene is running
+
+        --- Events are "entity_added", "entity_removed", "entity_reparented", "rigid_body_attached", "transform_changed" (from Transform setters), "animation_started", "animation_stopped" and "animation_marker".
+        --- "entity_added" is sent for every new entity, including the nodes of imported models.
+        ---@class SceneEvent
+        ---@field name string
+        ---@field entity_index integer
+        ---@field entity Entity|nil nil once the entity has been removed, even if its index has been reused
+        ---@field old_parent_index integer entity_reparented only
+        ---@field parent_index integer entity_reparented only
+        ---@field rigid_body RigidBodyComponent rigid_body_attached only
+        ---@field transform Transform transform_changed only
+        ---@field animation_index integer animation events only
+        ---@field marker string animation_marker only
+
+        --- Fields of custom components can also be read and written directly, eg. `component.health = 10`
+        ---@class CustomComponent
+        ---@field owner Entity
+        ---@field type_name string
+        ---@field field_names string[]
+        ---@field get fun(self:CustomComponent, name:string):any
+        ---@field set fun(self:CustomComponent, name:string, value:any):nil errors if the value's type differs from the field's default
     
     ---@class RenderComponent
 
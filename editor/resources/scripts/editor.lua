@@ -6,6 +6,8 @@ local has_set_target_cursor = false
 local resize_called_last_tick = false
 local resize_called_this_tick = false
 
+local graph_dirty = false
+
 local gui
 
 	local root_node
@@ -38,8 +40,14 @@ function Awake()
 				scene_graph_scroll_bar_node = scene_graph_area_node:get_child(1):get_child(0)
 		scene_view_area_node = root_node:get_child(2)
 	
+	Engine.scene.revert_on_stop = true
+
 	build_graph()
 
+	-- the graph is rebuilt whenever the hierarchy changes, from scripts as well as the editor
+	for _, event_name in ipairs({"entity_added", "entity_removed", "entity_reparented"}) do
+		Engine.scene:on(event_name, function() graph_dirty = true end)
+	end
 end
 
 
@@ -74,6 +82,13 @@ function Update()
 		math.min(1.0, scene_graph_area_node.size.y / graph_height) --- visible pixels / total pixels
 	)
 
+	update_graph_drag()
+	update_history()
+	if graph_dirty then
+		graph_dirty = false
+		build_graph()
+	end
+
 	resize_called_last_tick = resize_called_this_tick
 	resize_called_this_tick = false
 
@@ -134,9 +149,63 @@ end
 function import_model_as_child() 
 	local entity_index = _G.node_to_entity_map[gui.ActiveNode.index]
 	Engine.scene:load_model(entity_index)
-	build_graph()
 end
 local expanded_entities = {}
+--- drag an entity onto another in the scene graph to reparent it
+local left_was_pressed = false
+local dragged_entity = nil
+local function get_hovered_graph_entity()
+	for entity_index, node_index in pairs(entity_to_node_map) do
+		if gui:is_node_hovered(node_index) then
+			return entity_index
+		end
+	end
+	return nil
+end
+function update_graph_drag()
+	local left_pressed = Engine.client:mouse_button_pressed(MouseButton.Left)
+	if left_pressed and not left_was_pressed then
+		dragged_entity = get_hovered_graph_entity()
+	elseif not left_pressed and left_was_pressed and dragged_entity ~= nil then
+		local target_entity = get_hovered_graph_entity()
+		if target_entity ~= nil and target_entity ~= dragged_entity then
+			local ok, err = pcall(function()
+				Engine.scene:get_entity(dragged_entity):set_parent(target_entity, true)
+			end)
+			if ok then
+				expanded_entities[target_entity] = true
+				build_graph()
+				_G.Editor.select_entity(dragged_entity)
+			else
+				print(err)
+			end
+		end
+		dragged_entity = nil
+	end
+	left_was_pressed = left_pressed
+end
+--- transform drags are one undoable transaction, ended here since their sliders only see the release while hovered
+_G.transform_drag_active = false
+function update_history()
+	if _G.transform_drag_active and not Engine.client:mouse_button_pressed(MouseButton.Left) then
+		Engine.scene:commit_transaction()
+		_G.transform_drag_active = false
+	end
+
+	if not Engine.client:key_pressed(KeyCode.ControlLeft) or Engine.scene.running then return end
+	local ok, err = true, nil
+	if Engine.client:new_key_pressed(KeyCode.KeyZ) and not Engine.client:key_pressed(KeyCode.ShiftLeft) then
+		ok, err = pcall(function() Engine.scene:undo() end)
+		graph_dirty = true
+	elseif Engine.client:new_key_pressed(KeyCode.KeyY) or Engine.client:new_key_pressed(KeyCode.KeyZ) then
+		ok, err = pcall(function() Engine.scene:redo() end)
+		graph_dirty = true
+	end
+	if not ok then
+		print(err)
+	end
+end
+
 _G.node_to_entity_map = {}
 _G.node_to_render_components_map = {}
 _G.node_to_rigid_body_map = {}
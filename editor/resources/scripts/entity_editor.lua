@@ -51,6 +51,8 @@ function Update()
     entity_editor_scroll_bar_node:set_height("Factor",
 		math.min(1.0, entity_editor_area_node.size.y / editor_height) --- visible pixels / total pixels
 	)
+
+    update_custom_component_rows()
 end
 function click_entity()
     Engine.renderer.scene_renderer.queue_hovered_component_read = true --- TEMP
@@ -69,6 +71,130 @@ local function get_next_render_component_editor_node_index()
 	return owned_render_component_editor_node_indices[current_used_render_component_editor_node_count]
 end
 
+--- custom component editor rows, a header per component followed by a row per field, or per axis of vector fields
+local owned_custom_component_row_node_indices = {}
+local owned_custom_component_row_text_indices = {}
+local current_used_custom_component_row_count = 0
+local custom_component_rows = {} --- row node index -> { entity, component, field, axis, text }
+local function get_next_custom_component_row(text_string, quad_index)
+	local current_owned_row_count = #owned_custom_component_row_node_indices
+	if current_used_custom_component_row_count >= current_owned_row_count then
+		owned_custom_component_row_node_indices[current_owned_row_count + 1] = {
+			gui:add_node(entity_editor_root_node.index),
+			gui:add_node(entity_editor_root_node.index)
+		}
+		owned_custom_component_row_text_indices[current_owned_row_count + 1] = gui:add_text(text_string)
+	end
+	current_used_custom_component_row_count = current_used_custom_component_row_count + 1
+	local node_indices = owned_custom_component_row_node_indices[current_used_custom_component_row_count]
+	local text_index = owned_custom_component_row_text_indices[current_used_custom_component_row_count]
+
+	local node = gui:get_node(node_indices[1])
+	node:reset()
+	node:set_width("Factor", 1.0)
+	node:set_height("Absolute", 20.0)
+	node:add_element_index(quad_index)
+	--- text node
+	local text_node = gui:get_node(node_indices[2])
+	text_node:reset()
+	text_node:set_x("Pixels", 5)
+	text_node:set_width("Factor", 1.0)
+	text_node:set_height("Factor", 1.0)
+	node:add_child_index(node_indices[2])
+
+	local text = gui:get_text(text_index)
+	text:update_text(text_string)
+	text.font_size = 15.0
+	text.auto_wrap_distance = 1000.0
+	text_node:add_element_index(text_index)
+
+	entity_editor_root_node:add_child_index(node_indices[1])
+	return node, text
+end
+
+local function format_custom_field(value, axis)
+	if axis ~= nil then
+		return string.format("%.3f", value[axis])
+	elseif type(value) == "number" and math.type(value) == "float" then
+		return string.format("%.3f", value)
+	end
+	return tostring(value)
+end
+local function custom_field_row_text(row)
+	local value = row.entity:get_component(row.component):get(row.field)
+	local name = row.field
+	if row.axis ~= nil then name = row.field .. "." .. row.axis end
+	return name .. ": " .. format_custom_field(value, row.axis)
+end
+
+local function add_custom_component_editors(entity)
+	current_used_custom_component_row_count = 0
+	custom_component_rows = {}
+
+	for _, component_name in ipairs(entity.component_names) do
+		get_next_custom_component_row(component_name, 3)
+
+		local component = entity:get_component(component_name)
+		for _, field_name in ipairs(component.field_names) do
+			local value = component:get(field_name)
+			local axes = { false }
+			if type(value) == "userdata" then axes = { "x", "y", "z" } end
+			for _, axis in ipairs(axes) do
+				local row = { entity = entity, component = component_name, field = field_name, axis = axis or nil }
+				local node, text = get_next_custom_component_row(custom_field_row_text(row), 2)
+				row.text = text
+				--- strings can only be set from scripts
+				if type(value) ~= "string" then
+					node:add_left_up_action("increase_custom_field", 3)
+					node:add_right_up_action("decrease_custom_field", 3)
+					node:add_hover_action("hover_cursor", 0)
+				end
+				custom_component_rows[node.index] = row
+			end
+		end
+	end
+end
+local time_since_custom_component_text_update = 0
+function update_custom_component_rows()
+	time_since_custom_component_text_update = time_since_custom_component_text_update + dt
+	if time_since_custom_component_text_update < 0.1 then return end
+	time_since_custom_component_text_update = 0
+
+	for _, row in pairs(custom_component_rows) do
+		--- the component can be removed by scripts while the editor shows it
+		local ok, text_string = pcall(custom_field_row_text, row)
+		if ok then row.text:update_text(text_string) end
+	end
+end
+
+--- left click steps a field up, right click down, booleans are flipped by either
+local function step_custom_field(step)
+	local row = custom_component_rows[gui.ActiveNode.index]
+	if row == nil then return end
+
+	local component = row.entity:get_component(row.component)
+	local value = component:get(row.field)
+	if type(value) == "boolean" then
+		value = not value
+	elseif row.axis ~= nil then
+		local components = { x = value.x, y = value.y, z = value.z }
+		components[row.axis] = components[row.axis] + step * 0.1
+		value = Vector.new3(components.x, components.y, components.z)
+	elseif math.type(value) == "integer" then
+		value = value + step
+	else
+		value = value + step * 0.1
+	end
+	component:set(row.field, value)
+	row.text:update_text(custom_field_row_text(row))
+end
+function increase_custom_field()
+	step_custom_field(1)
+end
+function decrease_custom_field()
+	step_custom_field(-1)
+end
+
 function open_entity_editor()
     Engine.scene:reset_outlines()
     entity_editor_root_node:clear_children()
@@ -76,6 +202,8 @@ function open_entity_editor()
     --- reset mappings and counters
     _G.editor_node_to_render_component_map = {}
     current_used_render_component_editor_node_count = 0
+    current_used_custom_component_row_count = 0
+    custom_component_rows = {}
 
     local entity = Engine.scene:get_entity(_G.node_to_entity_map[gui.ActiveNode.index])
 
@@ -103,6 +231,9 @@ function open_entity_editor()
         entity_editor_root_node:add_child_index(render_component_editor_node_index)
     end
 
+    --- add custom component editors
+    add_custom_component_editors(entity)
+
 end
 function outline_entity(index)
     local entity = Engine.scene:get_entity(index)
@@ -129,6 +260,7 @@ function open_transform_editor()
     _G.Editor.select_entity(Engine.scene:get_transform(_G.selected_transform).owner_index)
 
 	entity_editor_root_node:clear_children()
+	custom_component_rows = {}
 	entity_editor_root_node:add_child_index(transform_editor_ui_node.index)
 end
 function open_render_component_editor()
@@ -149,6 +281,7 @@ function open_rigid_body_editor()
     _G.Editor.select_entity(Engine.scene:get_rigid_body(_G.selected_rigid_body).owner_index)
 
 	entity_editor_root_node:clear_children()
+	custom_component_rows = {}
 	entity_editor_root_node:add_child_index(rigid_body_editor_ui_node.index)
 end
 
@@ -158,6 +291,7 @@ function render_component_editor(component_index)
     Engine.scene:add_outlined_component(selected_render_component);
 
     entity_editor_root_node:clear_children()
+    custom_component_rows = {}
     
     --- reset mappings and counters
     _G.editor_node_to_render_component_map = {}
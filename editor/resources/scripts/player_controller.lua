@@ -18,6 +18,10 @@ camera = nil
 transform = nil
 
 function Awake() 
+    local camera_entity = Engine.scene:entities_with_tag("MainCamera")[1]
+    if camera_entity ~= nil then
+        camera_index = camera_entity.camera_index
+    end
     camera = Engine.scene:get_camera(camera_index)
     transform_index = camera.transform
     transform = Engine.scene:get_transform(transform_index)
@@ -139,6 +139,10 @@ local original_translation
 local original_rotation
 local original_scale
 function begin_drag()
+    if not _G.transform_drag_active then
+        Engine.scene:begin_transaction("Drag transform")
+        _G.transform_drag_active = true
+    end
     accum_delta = Vector.new()
     last_cursor_position = Engine.client.cursor_position
     original_translation = Engine.scene:get_transform(_G.selected_transform).translation
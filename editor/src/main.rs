@@ -25,9 +25,11 @@ fn main() { unsafe {
             transform: 0,
             color: Vector::new3(1.0, 0.0, 1.0),
             light_type: 0,
+            direction: Vector::new3(0.0, 0.0, 1.0),
             quadratic_falloff: 0.1,
             linear_falloff: 0.1,
             constant_falloff: 0.1,
+            range: 0.0,
             inner_cutoff: 0.0,
             outer_cutoff: 0.0,
         }, 0);
@@ -43,8 +45,9 @@ fn main() { unsafe {
             }
             println!("");
 
-            scene.new_entity_from_model(0, "editor/resources/models/ffocks/untitled.gltf");
-            let transform_index = scene.entities[2].transform;
+            let ffocks = scene.new_entity_from_model(0, "editor/resources/models/ffocks/untitled.gltf").expect("failed to load the ffocks model");
+            scene.entities[ffocks].name = String::from("ffocks");
+            let transform_index = scene.entities[ffocks].transform;
             scene.transforms[transform_index].local_scale = Vector::fill(0.01);
             //app.scene.borrow_mut().new_entity_from_model(0, "C:\\Graphics\\assets\\rivals\\luna\\gltf\\luna.gltf");
             
@@ -93,13 +96,13 @@ fn main() { unsafe {
         {
             let scene = &mut *app.scene.borrow_mut();
 
-            let ground = scene.new_entity_from_model(0, "editor/resources/models/collisionTest/collisionTestNoWalls.gltf");
+            let ground = scene.new_entity_from_model(0, "editor/resources/models/collisionTest/collisionTestNoWalls.gltf").expect("failed to load the ground model");
             //let ground = scene.new_entity_from_model(0, "editor/resources/models/sphereScene/scene.gltf");
 
-            let ball = scene.new_entity_from_model(0, "editor/resources/models/demoBall/scene.gltf");
+            let ball = scene.new_entity_from_model(0, "editor/resources/models/demoBall/scene.gltf").expect("failed to load the ball model");
 
             scene.transforms[scene.entities[ball].transform].local_translation = Vector::new3(-0.5, 10.0, -0.5);
-            let ball2 = scene.new_entity_from_model(0, "editor/resources/models/demoBall/scene.gltf");
+            let ball2 = scene.new_entity_from_model(0, "editor/resources/models/demoBall/scene.gltf").expect("failed to load the ball model");
             // let grass_block = scene.new_entity_from_model(0, "editor/resources/models/grassblockGLTF/grassblock.gltf");
 
             let initial_update_command_buffer = base.context.begin_single_time_commands(1);
@@ -166,14 +169,19 @@ fn main() { unsafe {
         let mut gui = renderer.guis[0].borrow_mut();
         gui.load_from_file("editor\\resources\\gui\\editor.gui");
     }
-    app.scene.borrow_mut().add_camera(CameraComponent::new_perspective_rotation(
-        100.0,
-        1.0,
-        0.001,
-        1000.0,
-        true,
-        Vector::new3(0.0, 0.0, 1.0),
-    ), 0);
+    {
+        let mut scene = app.scene.borrow_mut();
+        let camera_index = scene.add_camera(CameraComponent::new_perspective_rotation(
+            100.0,
+            1.0,
+            0.001,
+            1000.0,
+            true,
+            Vector::new3(0.0, 0.0, 1.0),
+        ), 0);
+        let camera_entity = scene.camera_components[camera_index].owner;
+        scene.entities[camera_entity].tags.push(String::from("MainCamera"));
+    }
 
     println!("starting");
 
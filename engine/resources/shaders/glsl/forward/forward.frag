@@ -12,7 +12,7 @@ layout (location = 2) flat in uint material;
 layout (location = 3) in mat3 view_TBN;
 layout (location = 6) flat in uvec2 o_id;
 
-layout(set = 0, binding = 2) uniform sampler2D textures[];
+layout(set = 0, binding = 5) uniform sampler2D textures[];
 
 struct Material {
     int normal_tex;
@@ -55,12 +55,143 @@ struct Material {
 
     vec2 roughness_tex_offset;
     vec2 roughness_tex_scale;
+
+    float clearcoat;
+    int clearcoat_tex;
+    float clearcoat_roughness;
+    int clearcoat_roughness_tex;
+
+    int clearcoat_normal_tex;
+    float transmission;
+    int transmission_tex;
+    uint unlit;
+
+    vec3 sheen_color;
+    int sheen_color_tex;
+
+    float sheen_roughness;
+    int sheen_roughness_tex;
+    float thickness;
+    int thickness_tex;
+
+    vec3 attenuation_color;
+    float attenuation_distance;
+
+    vec2 clearcoat_tex_offset;
+    vec2 clearcoat_tex_scale;
+
+    vec2 clearcoat_roughness_tex_offset;
+    vec2 clearcoat_roughness_tex_scale;
+
+    vec2 clearcoat_normal_tex_offset;
+    vec2 clearcoat_normal_tex_scale;
+
+    vec2 transmission_tex_offset;
+    vec2 transmission_tex_scale;
+
+    vec2 thickness_tex_offset;
+    vec2 thickness_tex_scale;
+
+    vec2 sheen_color_tex_offset;
+    vec2 sheen_color_tex_scale;
+
+    vec2 sheen_roughness_tex_offset;
+    vec2 sheen_roughness_tex_scale;
 };
 
 layout(set = 0, binding = 0, std430) readonly buffer MaterialSSBO {
     Material materials[];
 } materialSSBO;
 
+const float PI = 3.141592;
+
+vec4 sample_texture(int index, vec2 offset, vec2 scale) {
+    return texture(textures[index], o_uv * scale + offset);
+}
+float fresnel_schlick(float cos_theta, float F0) {
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
 void main() {
-    color = vec4(1.0, 0.0, 1.0, 1.0);
+    Material mat = materialSSBO.materials[material];
+
+    vec4 base_color = mat.base_color;
+    if (mat.base_color_tex > -1) {
+        base_color = sample_texture(mat.base_color_tex, mat.base_color_tex_offset, mat.base_color_tex_scale);
+    }
+    if (base_color.a < mat.alpha_cutoff) {
+        discard;
+    }
+    if (mat.unlit != 0u) {
+        color = base_color;
+        return;
+    }
+
+    vec3 view_normal = o_view_normal;
+    if (mat.normal_tex > -1) {
+        vec3 mapped_normal = sample_texture(mat.normal_tex, mat.normal_tex_offset, mat.normal_tex_scale).rgb;
+        view_normal = normalize(view_TBN * normalize(mapped_normal * 2.0 - 1.0));
+    }
+    // without a normal of its own the coat follows the geometry, not the normal map
+    vec3 coat_normal = o_view_normal;
+    if (mat.clearcoat_normal_tex > -1) {
+        vec3 mapped_normal = sample_texture(mat.clearcoat_normal_tex, mat.clearcoat_normal_tex_offset, mat.clearcoat_normal_tex_scale).rgb;
+        coat_normal = normalize(view_TBN * normalize(mapped_normal * 2.0 - 1.0));
+    }
+
+    float metallic = mat.metallic;
+    if (mat.metallic_tex > -1) {
+        metallic *= sample_texture(mat.metallic_tex, mat.metallic_tex_offset, mat.metallic_tex_scale).b;
+    }
+    float roughness = mat.roughness;
+    if (mat.roughness_tex > -1) {
+        roughness *= sample_texture(mat.roughness_tex, mat.roughness_tex_offset, mat.roughness_tex_scale).g;
+    }
+
+    vec3 emission = mat.emissive_color.rgb;
+    if (mat.emissive_texture > -1) {
+        emission = sample_texture(mat.emissive_texture, mat.emissive_tex_offset, mat.emissive_tex_scale).rgb;
+    }
+
+    float clearcoat = mat.clearcoat;
+    if (mat.clearcoat_tex > -1) {
+        clearcoat *= sample_texture(mat.clearcoat_tex, mat.clearcoat_tex_offset, mat.clearcoat_tex_scale).r;
+    }
+    float transmission = mat.transmission;
+    if (mat.transmission_tex > -1) {
+        transmission *= sample_texture(mat.transmission_tex, mat.transmission_tex_offset, mat.transmission_tex_scale).r;
+    }
+    float thickness = mat.thickness;
+    if (mat.thickness_tex > -1) {
+        thickness *= sample_texture(mat.thickness_tex, mat.thickness_tex_offset, mat.thickness_tex_scale).g;
+    }
+    vec3 sheen_color = mat.sheen_color;
+    if (mat.sheen_color_tex > -1) {
+        sheen_color *= sample_texture(mat.sheen_color_tex, mat.sheen_color_tex_offset, mat.sheen_color_tex_scale).rgb;
+    }
+    float sheen_roughness = mat.sheen_roughness;
+    if (mat.sheen_roughness_tex > -1) {
+        sheen_roughness *= sample_texture(mat.sheen_roughness_tex, mat.sheen_roughness_tex_offset, mat.sheen_roughness_tex_scale).a;
+    }
+
+    // the forward pass has no lights bound, so the surface is lit from the camera
+    float cos_theta = max(view_normal.z, 0.0);
+    float coat_cos_theta = max(coat_normal.z, 0.0);
+
+    // light travelling through the volume is absorbed along the way (Beer-Lambert)
+    vec3 absorption = pow(max(mat.attenuation_color, vec3(0.0001)), vec3(thickness / mat.attenuation_distance));
+    vec3 diffuse = base_color.rgb * (1.0 - metallic) * cos_theta;
+    vec3 transmitted = base_color.rgb * absorption;
+    // rougher surfaces spread the highlight, so less of it is reflected straight back
+    vec3 specular = mix(vec3(0.04), base_color.rgb, metallic) * cos_theta * (1.0 - roughness * roughness);
+    // sheen shows up towards grazing angles, and a rougher sheen spreads further towards the centre
+    vec3 sheen = sheen_color * pow(1.0 - cos_theta, mix(5.0, 1.0, sheen_roughness));
+
+    vec3 radiance = mix(diffuse, transmitted, transmission) + specular + sheen;
+    float coat_fresnel = clearcoat * fresnel_schlick(coat_cos_theta, 0.04);
+    radiance = radiance * (1.0 - coat_fresnel) + coat_fresnel;
+    radiance += emission * mat.emissive_strength;
+
+    // whatever is transmitted lets the scene behind show through
+    color = vec4(radiance, base_color.a * (1.0 - transmission * (1.0 - metallic)));
 }
@@ -9,6 +9,7 @@ layout (location = 1) out vec4 frag_metallic_roughness;
 layout (location = 2) out vec4 frag_extra_material_properties;
 layout (location = 3) out vec4 frag_view_normal;
 layout (location = 4) out uvec2 frag_id;
+layout (location = 5) out vec4 frag_sheen;
 
 layout (location = 0) in vec3 o_view_normal;
 layout (location = 1) in vec2 o_uv;
@@ -16,7 +17,7 @@ layout (location = 2) flat in uint material;
 layout (location = 3) in mat3 view_TBN;
 layout (location = 6) flat in uvec2 o_id;
 
-layout(set = 0, binding = 2) uniform sampler2D textures[];
+layout(set = 0, binding = 4) uniform sampler2D textures[];
 
 struct Material {
     int normal_tex;
@@ -59,6 +60,48 @@ struct Material {
 
     vec2 roughness_tex_offset;
     vec2 roughness_tex_scale;
+
+    float clearcoat;
+    int clearcoat_tex;
+    float clearcoat_roughness;
+    int clearcoat_roughness_tex;
+
+    int clearcoat_normal_tex;
+    float transmission;
+    int transmission_tex;
+    uint unlit;
+
+    vec3 sheen_color;
+    int sheen_color_tex;
+
+    float sheen_roughness;
+    int sheen_roughness_tex;
+    float thickness;
+    int thickness_tex;
+
+    vec3 attenuation_color;
+    float attenuation_distance;
+
+    vec2 clearcoat_tex_offset;
+    vec2 clearcoat_tex_scale;
+
+    vec2 clearcoat_roughness_tex_offset;
+    vec2 clearcoat_roughness_tex_scale;
+
+    vec2 clearcoat_normal_tex_offset;
+    vec2 clearcoat_normal_tex_scale;
+
+    vec2 transmission_tex_offset;
+    vec2 transmission_tex_scale;
+
+    vec2 thickness_tex_offset;
+    vec2 thickness_tex_scale;
+
+    vec2 sheen_color_tex_offset;
+    vec2 sheen_color_tex_scale;
+
+    vec2 sheen_roughness_tex_offset;
+    vec2 sheen_roughness_tex_scale;
 };
 
 layout(set = 0, binding = 0, std430) readonly buffer MaterialSSBO {
@@ -96,11 +139,51 @@ void main() {
         base_color = mat.base_color;
     }
 
+    float clearcoat = mat.clearcoat;
+    if (mat.clearcoat_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.clearcoat_tex_scale + mat.clearcoat_tex_offset;
+        clearcoat *= texture(textures[mat.clearcoat_tex], transformed_uv).r;
+    }
+    float clearcoat_roughness = mat.clearcoat_roughness;
+    if (mat.clearcoat_roughness_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.clearcoat_roughness_tex_scale + mat.clearcoat_roughness_tex_offset;
+        clearcoat_roughness *= texture(textures[mat.clearcoat_roughness_tex], transformed_uv).g;
+    }
+
+    float transmission = mat.transmission;
+    if (mat.transmission_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.transmission_tex_scale + mat.transmission_tex_offset;
+        transmission *= texture(textures[mat.transmission_tex], transformed_uv).r;
+    }
+    float thickness = mat.thickness;
+    if (mat.thickness_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.thickness_tex_scale + mat.thickness_tex_offset;
+        thickness *= texture(textures[mat.thickness_tex], transformed_uv).g;
+    }
+    // light travelling through the volume is absorbed along the way (Beer-Lambert), which tints the transmitted part
+    if (transmission > 0.0 && thickness > 0.0) {
+        vec3 absorption = pow(max(mat.attenuation_color, vec3(0.0001)), vec3(thickness / mat.attenuation_distance));
+        base_color.rgb = mix(base_color.rgb, base_color.rgb * absorption, transmission);
+    }
+
+    vec3 sheen_color = mat.sheen_color;
+    if (mat.sheen_color_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.sheen_color_tex_scale + mat.sheen_color_tex_offset;
+        sheen_color *= texture(textures[mat.sheen_color_tex], transformed_uv).rgb;
+    }
+    float sheen_roughness = mat.sheen_roughness;
+    if (mat.sheen_roughness_tex > -1) {
+        vec2 transformed_uv = o_uv * mat.sheen_roughness_tex_scale + mat.sheen_roughness_tex_offset;
+        sheen_roughness *= texture(textures[mat.sheen_roughness_tex], transformed_uv).a;
+    }
+
     // frag_material = ivec4(material);
     frag_albedo = vec4(base_color);
-    frag_metallic_roughness = vec4(mat.metallic, mat.roughness, 1.0, 1.0);
+    frag_metallic_roughness = vec4(mat.metallic, mat.roughness, clearcoat, clearcoat_roughness);
     frag_extra_material_properties = vec4(emission, mat.emissive_strength);
-    frag_view_normal = vec4(view_normal * 0.5 + 0.5, 1.0); // convert normal to 0-1 scale
+    // convert normal to 0-1 scale, alpha holds the transmission or -1 for unlit materials
+    frag_view_normal = vec4(view_normal * 0.5 + 0.5, mat.unlit != 0u ? -1.0 : transmission);
+    frag_sheen = vec4(sheen_color, sheen_roughness);
 
     if (base_color.a < mat.alpha_cutoff) {
         discard;
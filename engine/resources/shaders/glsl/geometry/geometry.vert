@@ -12,6 +12,7 @@ layout (location = 6) in vec4 weights;
 
 layout (location = 7) in mat4 model;
 layout (location = 11) in ivec4 indices;
+layout (location = 12) in ivec4 morph_targets;
 
 layout (location = 0) out vec3 o_view_normal;
 layout (location = 1) out vec2 o_uv;
@@ -24,10 +25,24 @@ layout(push_constant) uniform constants {
     mat4 projection;
 } ubo;
 
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+    vec4 tangent;
+};
+
 layout(set = 0, binding = 1, std430) readonly buffer JointsSSBO {
     mat4 joint_matrices[];
 } joints_SSBO;
 
+layout(set = 0, binding = 2, std430) readonly buffer MorphDeltasSSBO {
+    MorphDelta deltas[];
+} morph_deltas_SSBO;
+
+layout(set = 0, binding = 3, std430) readonly buffer MorphWeightsSSBO {
+    float weights[];
+} morph_weights_SSBO;
+
 void main() {
     int material = indices.x;
     int skin = indices.y;
@@ -42,7 +57,23 @@ void main() {
             weights.w * joints_SSBO.joint_matrices[joint_indices.w + joint_offset];
     }
 
-    vec4 position = model_matrix * vec4(pos, 1.0);
+    // morph targets are applied before skinning, weights are offset per instance by the header at the start of the weights
+    vec3 morphed_pos = pos;
+    vec3 morphed_normal = normal;
+    vec3 morphed_tangent = tangent;
+    int weights_offset = int(morph_weights_SSBO.weights[gl_InstanceIndex]);
+    if (morph_targets.x > -1 && weights_offset > -1) {
+        int first_delta = morph_targets.x + (gl_VertexIndex - morph_targets.z) * morph_targets.y;
+        for (int i = 0; i < morph_targets.y; i++) {
+            float morph_weight = morph_weights_SSBO.weights[weights_offset + i];
+            MorphDelta delta = morph_deltas_SSBO.deltas[first_delta + i];
+            morphed_pos += morph_weight * delta.position.xyz;
+            morphed_normal += morph_weight * delta.normal.xyz;
+            morphed_tangent += morph_weight * delta.tangent.xyz;
+        }
+    }
+
+    vec4 position = model_matrix * vec4(morphed_pos, 1.0);
     gl_Position = ubo.projection * ubo.view * position;
 
     o_uv = vec2(uv.x, uv.y);
@@ -50,9 +81,9 @@ void main() {
 
 
     mat3 view_normal_matrix = transpose(inverse(mat3(ubo.view * model_matrix)));
-    o_view_normal = normalize(view_normal_matrix * normal);
+    o_view_normal = normalize(view_normal_matrix * morphed_normal);
 
-    vec3 view_T = normalize(view_normal_matrix * tangent);
+    vec3 view_T = normalize(view_normal_matrix * morphed_tangent);
     vec3 view_B = normalize(view_normal_matrix * bitangent);
     view_TBN = mat3(view_T, view_B, o_view_normal);
 }
@@ -12,16 +12,31 @@ layout (location = 6) in vec4 weights;
 
 layout (location = 7) in mat4 model;
 layout (location = 11) in ivec4 indices;
+layout (location = 12) in ivec4 morph_targets;
 
 layout(push_constant) uniform constants {
     mat4 view;
     mat4 projection;
 } ubo;
 
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+    vec4 tangent;
+};
+
 layout(set = 0, binding = 1, std430) readonly buffer JointsSSBO {
     mat4 joint_matrices[];
 } joints_SSBO;
 
+layout(set = 0, binding = 3, std430) readonly buffer MorphDeltasSSBO {
+    MorphDelta deltas[];
+} morph_deltas_SSBO;
+
+layout(set = 0, binding = 4, std430) readonly buffer MorphWeightsSSBO {
+    float weights[];
+} morph_weights_SSBO;
+
 void main() {
     int skin = indices.y;
     mat4 model_matrix = model;
@@ -34,6 +49,16 @@ void main() {
         weights.w * joints_SSBO.joint_matrices[joint_indices.w + joint_offset];
     }
 
-    vec4 position = model_matrix * vec4(pos, 1.0);
+    // morph targets are applied before skinning, weights are offset per instance by the header at the start of the weights
+    vec3 morphed_pos = pos;
+    int weights_offset = int(morph_weights_SSBO.weights[gl_InstanceIndex]);
+    if (morph_targets.x > -1 && weights_offset > -1) {
+        int first_delta = morph_targets.x + (gl_VertexIndex - morph_targets.z) * morph_targets.y;
+        for (int i = 0; i < morph_targets.y; i++) {
+            morphed_pos += morph_weights_SSBO.weights[weights_offset + i] * morph_deltas_SSBO.deltas[first_delta + i].position.xyz;
+        }
+    }
+
+    vec4 position = model_matrix * vec4(morphed_pos, 1.0);
     gl_Position = ubo.projection * ubo.view * position;
 }
@@ -16,6 +16,7 @@ layout(set = 0, binding = 4) uniform sampler2D g_view_normal;
 
 layout(set = 0, binding = 5) uniform sampler2DArray shadowmap;
 layout(set = 0, binding = 6) uniform sampler2D ssao_tex;
+layout(set = 0, binding = 7) uniform sampler2D g_sheen;
 
 layout(push_constant) uniform push_constants {
     mat4 inverse_view;
@@ -24,10 +25,13 @@ layout(push_constant) uniform push_constants {
 
 struct Light {
     vec3 position;
+    float range; // 0 = unlimited
     vec3 direction;
     uint type; // 0 = point, 1 = directional, 2 = spotlight
-    vec3 falloffs; // x = quadratic, y = linear, z = constant. For spotlights, x = inner cutoff, y = cutoff
+    vec3 falloffs; // x = quadratic, y = linear, z = constant
+    float inner_cutoff; // cosines of the spotlight's cone angles
     vec3 color;
+    float outer_cutoff;
 };
 
 layout(set = 0, binding = 8, std430) readonly buffer LightsSSBO {
@@ -57,26 +61,29 @@ vec3 get_position_from_depth() {
     return view_space_position.xyz / view_space_position.w;
 }
 
-float attenuation(vec3 l_pos, vec3 pos, float constant, float linear, float quadratic) {
+float attenuation(vec3 l_pos, vec3 pos, float constant, float linear, float quadratic, float range) {
     float distance = length(l_pos - pos);
-    return 1 / (constant + linear*distance + quadratic*(distance*distance));
+    float falloff = 1 / max(constant + linear*distance + quadratic*(distance*distance), 0.0001);
+    if (range > 0) {
+        // fades out smoothly towards the range, as KHR_lights_punctual recommends
+        falloff *= clamp(1 - pow(distance / range, 4), 0, 1);
+    }
+    return falloff;
 }
 vec4 get_lighting(Light l, vec3 pos) {
-    vec4 ret;
+    vec4 ret = vec4(0);
     if (l.type == 0) {
-        ret.w = attenuation(l.position, pos, l.falloffs.z, l.falloffs.y, l.falloffs.x);
+        ret.w = attenuation(l.position, pos, l.falloffs.z, l.falloffs.y, l.falloffs.x, l.range);
         ret.xyz = normalize(l.position - pos);
     } else if (l.type == 1) {
         ret.w = 1;
         ret.xyz = -normalize(l.direction);
     } else if (l.type == 2) {
         float theta = dot(normalize(pos - l.position), normalize(l.direction));
-        float epsilon = l.falloffs.y - l.falloffs.x;
-        float intensity = clamp((l.falloffs.x - theta) / epsilon, 0, 1);
-        if (theta > l.falloffs.y) {
-            ret.w = intensity;
-            ret.xyz = normalize(l.position - pos);
-        }
+        float epsilon = max(l.inner_cutoff - l.outer_cutoff, 0.0001);
+        float intensity = clamp((theta - l.outer_cutoff) / epsilon, 0, 1);
+        ret.w = intensity * attenuation(l.position, pos, l.falloffs.z, l.falloffs.y, l.falloffs.x, l.range);
+        ret.xyz = normalize(l.position - pos);
     }
     return ret;
 }
@@ -148,6 +155,65 @@ float geometry_smith(vec3 N, vec3 V, vec3 L, float roughness) {
 
     return ggx1 * ggx2;
 }
+float distribution_charlie(float NdotH, float roughness) {
+    float inv_a = 1.0 / max(roughness * roughness, 0.0001);
+    float sin2 = max(1.0 - NdotH * NdotH, 0.0);
+    return (2.0 + inv_a) * pow(sin2, inv_a * 0.5) / (2.0 * PI);
+}
+float visibility_neubelt(float NdotV, float NdotL) {
+    return clamp(1.0 / (4.0 * (NdotL + NdotV - NdotL * NdotV)), 0.0, 1.0);
+}
+
+struct Surface {
+    vec3 albedo;
+    float metallic;
+    vec3 F0;
+    float roughness;
+    float clearcoat;
+    float clearcoat_roughness;
+    float transmission;
+    vec3 sheen_color;
+    float sheen_roughness;
+};
+
+// outgoing radiance towards V for unit radiance arriving from Wi, cosine included
+vec3 evaluate_light(Surface s, vec3 N, vec3 V, vec3 Wi) {
+    float cos_theta = max(dot(N, V), 0.001);
+    float cos_theta_light = max(dot(N, Wi), 0.001);
+
+    vec3 H = normalize(V + Wi);
+    float cos_theta_halfway = max(dot(H, V), 0.001);
+    vec3 F = fresnel_schlick_roughness(cos_theta_halfway, s.F0, s.roughness);
+    float NDF = distribution_GGX(N, H, s.roughness);
+    float G = geometry_smith(N, V, Wi, s.roughness);
+    vec3 numerator = NDF * G * F;
+    float denominator = 4.0 * cos_theta * cos_theta_light;
+    vec3 specular = numerator / denominator;
+    vec3 k_d_light = (1.0 - F) * (1.0 - s.metallic);
+
+    // transmission is approximated as diffuse backlighting: transmitted light is taken out of the diffuse lobe, and light from
+    // behind the surface comes through instead. What is behind the surface is not refracted or seen through.
+    vec3 diffuse = k_d_light * s.albedo / PI;
+    vec3 radiance = (diffuse * (1.0 - s.transmission) + specular) * cos_theta_light
+        + diffuse * s.transmission * max(dot(-N, Wi), 0.0);
+
+    if (dot(s.sheen_color, s.sheen_color) > 0.0) {
+        float sheen_D = distribution_charlie(max(dot(N, H), 0.0), s.sheen_roughness);
+        radiance += s.sheen_color * sheen_D * visibility_neubelt(cos_theta, cos_theta_light) * cos_theta_light;
+    }
+
+    // the coat sits on top of everything else and reflects some of the light before it reaches the base
+    // the deferred path has no room for the coat's own normal, so it shares the surface normal
+    if (s.clearcoat > 0.0) {
+        vec3 coat_F = fresnel_schlick(cos_theta_halfway, vec3(0.04));
+        float coat_NDF = distribution_GGX(N, H, s.clearcoat_roughness);
+        float coat_G = geometry_smith(N, V, Wi, s.clearcoat_roughness);
+        vec3 coat_specular = coat_NDF * coat_G * coat_F / denominator;
+        radiance = radiance * (1.0 - s.clearcoat * fresnel_schlick(cos_theta, vec3(0.04)))
+            + s.clearcoat * coat_specular * cos_theta_light;
+    }
+    return radiance;
+}
 
 
 void main() {
@@ -163,7 +229,11 @@ void main() {
     vec3 view_position = get_position_from_depth();
     vec3 world_position = (inverse_view * vec4(view_position, 1.0)).xyz * vec3(1.0, 1.0, 1.0);
 
-    vec3 view_normal = (texture(g_view_normal, uv).xyz * 2.0) - 1.0;
+    vec4 sampled_view_normal = texture(g_view_normal, uv);
+    // unlit materials show their base color as is
+    if (sampled_view_normal.a < 0.0) { uFragColor = vec4(albedo, 1.0); return; }
+    float transmission = sampled_view_normal.a;
+    vec3 view_normal = (sampled_view_normal.xyz * 2.0) - 1.0;
     vec3 N = mat3(inverse_view) * view_normal;
     vec3 V = normalize(-view_position);
     vec3 R = reflect(-V, N);
@@ -178,6 +248,11 @@ void main() {
     vec4 metallic_roughness = texture(g_metallic_roughness, uv);
     float metallic = metallic_roughness.r;
     float roughness = metallic_roughness.g;
+    float clearcoat = metallic_roughness.b;
+    float clearcoat_roughness = max(metallic_roughness.a, 0.03);
+
+    vec4 sheen = texture(g_sheen, uv);
+    float sheen_roughness = max(sheen.a, 0.07);
 
     // uFragColor = vec4(albedo, 1.0); return;
 
@@ -198,22 +273,12 @@ void main() {
 
     vec3 frag_radiance = ambient;
 
+    Surface surface = Surface(albedo, metallic, F0, roughness, clearcoat, clearcoat_roughness, transmission, sheen.rgb, sheen_roughness);
+
     // sunlight
     {
         vec3 Wi = sun.vector;
-
-        float cos_theta_light = max(dot(N, Wi), 0.001);
-
-        vec3 H = normalize(V + Wi);
-        float cos_theta_halfway = max(dot(H, V), 0.001);
-        vec3 F = fresnel_schlick_roughness(cos_theta_halfway, F0, roughness);
-        float NDF = distribution_GGX(N, H, roughness);
-        float G = geometry_smith(N, V, Wi, roughness);
-        vec3 numerator = NDF * G * F;
-        float denominator = 4.0 * cos_theta * cos_theta_light;
-        vec3 specular = numerator / denominator;
-        vec3 k_d_light = (1.0 - F) * (1.0 - metallic);
-        frag_radiance += (k_d_light * albedo / PI + specular) * sun.color * cos_theta_light * get_shadow(world_position, N, -view_position.z);
+        frag_radiance += evaluate_light(surface, N, V, Wi) * sun.color * get_shadow(world_position, N, -view_position.z);
     }
     for (int i = 0; i < ubo.num_lights; i++) {
         Light l = lights_SSBO.lights[i];
@@ -222,20 +287,9 @@ void main() {
         float atten = lighting_info.w;
         if (atten < 0.001) continue;
         vec3 Wi = lighting_info.xyz;
-        float cos_theta_light = max(dot(N, Wi), 0.001);
         vec3 radiance = l.color * atten;
 
-        vec3 H = normalize(V + Wi);
-        float cos_theta_halfway = max(dot(H, V), 0.001);
-        vec3 F = fresnel_schlick_roughness(cos_theta_halfway, F0, roughness);
-        float NDF = distribution_GGX(N, H, roughness);
-        float G = geometry_smith(N, V, Wi, roughness);
-        vec3 numerator = NDF * G * F;
-        float denominator = 4.0 * cos_theta * cos_theta_light;
-        vec3 specular = numerator / denominator;
-        vec3 k_d_light = (1.0 - F) * (1.0 - metallic);
-
-        frag_radiance += (k_d_light * albedo / PI + specular) * radiance * cos_theta_light;
+        frag_radiance += evaluate_light(surface, N, V, Wi) * radiance;
     }
 
     float gamma = 1.0;
@@ -12,12 +12,19 @@ layout (location = 6) in vec4 weights;
 
 layout (location = 7) in mat4 model;
 layout (location = 11) in ivec4 indices;
+layout (location = 12) in ivec4 morph_targets;
 
 layout(push_constant) uniform constants {
     mat4 view;
     mat4 projection;
 } ubo;
 
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+    vec4 tangent;
+};
+
 layout(set = 0, binding = 1, std430) readonly buffer JointsSSBO {
     mat4 joint_matrices[];
 } joints_SSBO;
@@ -26,6 +33,14 @@ layout(binding = 2) uniform ViewportUBO {
     vec4 data;
 } viewport;
 
+layout(set = 0, binding = 3, std430) readonly buffer MorphDeltasSSBO {
+    MorphDelta deltas[];
+} morph_deltas_SSBO;
+
+layout(set = 0, binding = 4, std430) readonly buffer MorphWeightsSSBO {
+    float weights[];
+} morph_weights_SSBO;
+
 void main() {
     int skin = indices.y;
     mat4 model_matrix = model;
@@ -38,8 +53,22 @@ void main() {
         weights.w * joints_SSBO.joint_matrices[joint_indices.w + joint_offset];
     }
 
+    // morph targets are applied before skinning, weights are offset per instance by the header at the start of the weights
+    vec3 morphed_pos = pos;
+    vec3 morphed_normal = normal;
+    int weights_offset = int(morph_weights_SSBO.weights[gl_InstanceIndex]);
+    if (morph_targets.x > -1 && weights_offset > -1) {
+        int first_delta = morph_targets.x + (gl_VertexIndex - morph_targets.z) * morph_targets.y;
+        for (int i = 0; i < morph_targets.y; i++) {
+            float morph_weight = morph_weights_SSBO.weights[weights_offset + i];
+            MorphDelta delta = morph_deltas_SSBO.deltas[first_delta + i];
+            morphed_pos += morph_weight * delta.position.xyz;
+            morphed_normal += morph_weight * delta.normal.xyz;
+        }
+    }
+
     mat3 view_normal_matrix = transpose(inverse(mat3(ubo.view * model_matrix)));
-    vec3 view_normal = normalize(view_normal_matrix * normal);
+    vec3 view_normal = normalize(view_normal_matrix * morphed_normal);
 
     vec2 ndc_offset = normalize((ubo.projection * vec4(view_normal, 0.0)).xy);
 
@@ -48,7 +77,7 @@ void main() {
         2.0 / viewport.data.y
     );
 
-    vec4 position = ubo.projection * ubo.view * model_matrix * vec4(pos, 1.0);
+    vec4 position = ubo.projection * ubo.view * model_matrix * vec4(morphed_pos, 1.0);
     position.xy += ndc_offset * pixel_ndc * 5.0 * position.w;
 
     gl_Position = position;
@@ -6,7 +6,7 @@
 layout (location = 0) in vec2 o_uv;
 layout (location = 1) flat in uint material;
 
-layout(set = 0, binding = 3) uniform sampler2D textures[];
+layout(set = 0, binding = 5) uniform sampler2D textures[];
 
 struct Material {
     int normal_tex;      // 0
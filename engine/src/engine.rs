@@ -215,7 +215,7 @@ impl Engine {
                             |_device, frame_command_buffer| {
                                 {
                                     self.world.borrow_mut().update_buffers(base, frame_command_buffer);
-                                    self.scene.borrow_mut().update_scene(frame_command_buffer, current_frame, delta_time, false);
+                                    self.scene.borrow_mut().update(current_frame, delta_time, false);
                                 }
 
                                 let flags = self.client.borrow().flags.clone();
@@ -291,6 +291,7 @@ impl Renderer {
         let frame_command_buffer = self.draw_command_buffers[current_frame];
 
         let mut scene = &mut scene.borrow_mut();
+        scene.sync_gpu(frame_command_buffer);
         //println!("camera data {:?}", camera);
 
         self.scene_renderer.borrow().render_world(current_frame, &scene);
@@ -288,7 +288,7 @@ impl SceneRenderer {
                 }, // extra properties
                 vk::DescriptorImageInfo {
                     sampler,
-                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().image_view,
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][6].device_texture.borrow().image_view,
                     image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
                 }, // depth
                 vk::DescriptorImageInfo {
@@ -306,6 +306,11 @@ impl SceneRenderer {
                     image_view: ssao_upsample_renderpass.pass.borrow().textures[current_frame][0].device_texture.borrow().image_view,
                     image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
                 }, // ssao tex (final)
+                vk::DescriptorImageInfo {
+                    sampler,
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().image_view,
+                    image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                }, // sheen
             ];
             let lighting_descriptor_writes: Vec<vk::WriteDescriptorSet> = image_infos.iter().enumerate().map(|(i, info)| {
                 vk::WriteDescriptorSet::default()
@@ -320,7 +325,7 @@ impl SceneRenderer {
             let image_infos = [
                 vk::DescriptorImageInfo {
                     sampler,
-                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().image_view,
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][6].device_texture.borrow().image_view,
                     image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
                 }, // geometry depth
                 vk::DescriptorImageInfo {
@@ -421,7 +426,7 @@ impl SceneRenderer {
                 }, // g_normal
                 vk::DescriptorImageInfo {
                     sampler,
-                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().image_view,
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][6].device_texture.borrow().image_view,
                     image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
                 }, // geometry depth
             ];
@@ -438,7 +443,7 @@ impl SceneRenderer {
             let image_infos = [
                 vk::DescriptorImageInfo {
                     sampler,
-                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().stencil_image_view.unwrap(),
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][6].device_texture.borrow().stencil_image_view.unwrap(),
                     image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
                 }, // geometry stencil
             ];
@@ -475,7 +480,7 @@ impl SceneRenderer {
             let image_infos = [
                 vk::DescriptorImageInfo {
                     sampler,
-                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][5].device_texture.borrow().image_view,
+                    image_view: geometry_renderpass.pass.borrow().textures[current_frame][6].device_texture.borrow().image_view,
                     image_layout: ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
                 }, // geometry depth
                 vk::DescriptorImageInfo {
@@ -687,6 +692,8 @@ impl SceneRenderer {
             .add_color_attachment_info(TextureCreateInfo::new(context).format(Format::R16G16_UINT)
                 .usage_flags(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC)
                 .width(resolution.width).height(resolution.height)) // id buffer
+            .add_color_attachment_info(TextureCreateInfo::new(context).format(Format::R8G8B8A8_UNORM)
+                .width(resolution.width).height(resolution.height)) // sheen
             .depth_attachment_info(TextureCreateInfo::new(context).format(Format::D32_SFLOAT_S8_UINT)
                 .width(resolution.width).height(resolution.height)
                 .is_depth(true).clear_value([0.0, 0.0, 0.0, 0.0])
@@ -726,6 +733,14 @@ impl SceneRenderer {
             .descriptor_type(DescriptorType::STORAGE_BUFFER)
             .shader_stages(ShaderStageFlags::VERTEX)
             .buffers(world.joints_buffers.iter().map(|b| {b.0.clone()}).collect());
+        let morph_deltas_ssbo_create_info = DescriptorCreateInfo::new(context)
+            .descriptor_type(DescriptorType::STORAGE_BUFFER)
+            .shader_stages(ShaderStageFlags::VERTEX)
+            .buffers(world.morph_delta_buffers.iter().map(|b| {b.0.clone()}).collect());
+        let morph_weights_ssbo_create_info = DescriptorCreateInfo::new(context)
+            .descriptor_type(DescriptorType::STORAGE_BUFFER)
+            .shader_stages(ShaderStageFlags::VERTEX)
+            .buffers(world.morph_weights_buffers.iter().map(|b| {b.0.clone()}).collect());
         let world_texture_samplers_create_info = DescriptorCreateInfo::new(context)
             .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
             .shader_stages(ShaderStageFlags::FRAGMENT)
@@ -735,12 +750,16 @@ impl SceneRenderer {
         let geometry_descriptor_set_create_info = DescriptorSetCreateInfo::new(context)
             .add_descriptor(Descriptor::new(&material_ssbo_create_info))
             .add_descriptor(Descriptor::new(&joints_ssbo_create_info))
+            .add_descriptor(Descriptor::new(&morph_deltas_ssbo_create_info))
+            .add_descriptor(Descriptor::new(&morph_weights_ssbo_create_info))
             .add_descriptor(Descriptor::new(&world_texture_samplers_create_info));
 
         let shadow_descriptor_set_create_info = DescriptorSetCreateInfo::new(context)
             .add_descriptor(Descriptor::new(&material_ssbo_create_info))
             .add_descriptor(Descriptor::new(&joints_ssbo_create_info))
             .add_descriptor(Descriptor::new(&sun_ubo_create_info))
+            .add_descriptor(Descriptor::new(&morph_deltas_ssbo_create_info))
+            .add_descriptor(Descriptor::new(&morph_weights_ssbo_create_info))
             .add_descriptor(Descriptor::new(&world_texture_samplers_create_info));
         //</editor-fold>]
         // <editor-fold desc = "SSAO descriptor sets">
@@ -903,6 +922,12 @@ impl SceneRenderer {
                 format: Format::R32G32B32A32_SINT,
                 offset: offset_of!(Instance, indices) as u32,
             }, // indices (material + skin)
+            vk::VertexInputAttributeDescription {
+                location: 12,
+                binding: 1,
+                format: Format::R32G32B32A32_SINT,
+                offset: offset_of!(Instance, morph_targets) as u32,
+            }, // morph targets
         ];
         let shadow_vertex_input_attribute_descriptions = [
             // vertex
@@ -962,6 +987,12 @@ impl SceneRenderer {
                 format: Format::R32G32B32_SINT,
                 offset: offset_of!(Instance, indices) as u32,
             }, // indices (material + skin)
+            vk::VertexInputAttributeDescription {
+                location: 12,
+                binding: 1,
+                format: Format::R32G32B32A32_SINT,
+                offset: offset_of!(Instance, morph_targets) as u32,
+            }, // morph targets
         ];
         let geometry_vertex_input_state_info = vk::PipelineVertexInputStateCreateInfo::default()
             .vertex_attribute_descriptions(&geometry_vertex_input_attribute_descriptions)
@@ -1018,7 +1049,7 @@ impl SceneRenderer {
             alpha_blend_op: vk::BlendOp::ADD,
             color_write_mask: vk::ColorComponentFlags::RGBA,
         };
-        let null_blend_states = vec![null_blend_attachment; 5];
+        let null_blend_states = vec![null_blend_attachment; 6];
         let null_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
             .attachments(&null_blend_states);
         //</editor-fold>
@@ -1144,7 +1175,7 @@ impl SceneRenderer {
             let geometry_pass = geometry_renderpass.pass.borrow();
             let forward_pass_create_info = PassCreateInfo::new(context)
                 .grab_attachment(&light_pass, 0, vk::AttachmentLoadOp::LOAD, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                .grab_depth_attachment(&geometry_pass, 5, vk::AttachmentLoadOp::LOAD, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+                .grab_depth_attachment(&geometry_pass, 6, vk::AttachmentLoadOp::LOAD, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
             let forward_descriptor_set_create_info = DescriptorSetCreateInfo::new(context)
                 .add_descriptor(Descriptor::new(&material_ssbo_create_info))
                 .add_descriptor(Descriptor::new(&joints_ssbo_create_info))
@@ -1152,6 +1183,8 @@ impl SceneRenderer {
                     .descriptor_type(DescriptorType::UNIFORM_BUFFER)
                     .size(16u64)
                     .shader_stages(ShaderStageFlags::VERTEX)))
+                .add_descriptor(Descriptor::new(&morph_deltas_ssbo_create_info))
+                .add_descriptor(Descriptor::new(&morph_weights_ssbo_create_info))
                 .add_descriptor(Descriptor::new(&world_texture_samplers_create_info));
             let color_blend_attachment_states = [vk::PipelineColorBlendAttachmentState {
                 blend_enable: vk::TRUE,
@@ -1396,7 +1429,7 @@ impl SceneRenderer {
         let descriptor_write = vk::WriteDescriptorSet {
             s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
             dst_set: self.geometry_renderpass.descriptor_set.borrow().descriptor_sets[frame],
-            dst_binding: 2,
+            dst_binding: 4,
             dst_array_element: 0,
             descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
             descriptor_count: 1024,
@@ -1406,7 +1439,7 @@ impl SceneRenderer {
         let forward_descriptor_write = vk::WriteDescriptorSet {
             s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
             dst_set: self.opaque_forward_renderpass.descriptor_set.borrow().descriptor_sets[frame],
-            dst_binding: 3,
+            dst_binding: 5,
             dst_array_element: 0,
             descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
             descriptor_count: 1024,
@@ -126,6 +126,13 @@ pub fn find_memorytype_index(
         .map(|(index, _memory_type)| index as _)
 }
 
+/// Where the pixels of a texture come from
+#[derive(Clone)]
+pub enum ImageSource {
+    File(PathBuf),
+    Memory { bytes: Arc<Vec<u8>>, mime_type: String }, // encoded image embedded in a model
+}
+
 pub struct Context {
     pub device: Device,
     pub window: Arc<winit::window::Window>,
@@ -287,8 +294,11 @@ impl Context {
     } }
     pub fn load_image_fast(uri: &PathBuf) -> (Vec<u8>, u32, u32) {
         let bytes = fs::read(uri).expect(uri.to_string_lossy().as_ref());
-
-        if uri.extension().and_then(|s| s.to_str()) == Some("png") {
+        Context::decode_image(&bytes, uri.extension().and_then(|s| s.to_str()))
+    }
+    /// Decodes to RGBA8, the extension picks the fast path and anything else goes through the image crate
+    pub fn decode_image(bytes: &[u8], extension: Option<&str>) -> (Vec<u8>, u32, u32) {
+        if extension == Some("png") {
             let decoder = png::Decoder::new(Cursor::new(&bytes));
             let mut reader = decoder.read_info().expect("Failed to read PNG info");
             let mut buf = vec![0; reader.output_buffer_size().expect("Failed to read buffer size")];
@@ -323,7 +333,7 @@ impl Context {
                     rgba
                 }
                 _ => {
-                    let img = image::load_from_memory(&bytes)
+                    let img = image::load_from_memory(bytes)
                         .expect("Failed to load image")
                         .to_rgba8();
                     let width = img.width();
@@ -335,9 +345,9 @@ impl Context {
             return (rgba_data, width, height);
         }
 
-        if let Some(ext) = uri.extension().and_then(|s| s.to_str()) {
+        if let Some(ext) = extension {
             if ext == "jpg" || ext == "jpeg" {
-                let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(&bytes));
+                let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(bytes));
                 let pixels = decoder.decode().expect("Failed to decode JPEG");
                 let info = decoder.info().expect("Failed to get JPEG info");
 
@@ -357,7 +367,7 @@ impl Context {
                         }
                     }
                     _ => {
-                        let img = image::load_from_memory(&bytes)
+                        let img = image::load_from_memory(bytes)
                             .expect("Failed to load image")
                             .to_rgba8();
                         let width = img.width();
@@ -370,19 +380,26 @@ impl Context {
             }
         }
 
-        let img = image::load_from_memory(&bytes)
+        let img = image::load_from_memory(bytes)
             .expect("Failed to load image")
             .to_rgba8();
         let width = img.width();
         let height = img.height();
         (img.into_raw(), width, height)
     }
-    fn load_images_parallel(uris: &[PathBuf]) -> Vec<(Vec<u8>, u32, u32)> {
+    fn load_images_parallel(sources: &[ImageSource]) -> Vec<(Vec<u8>, u32, u32)> {
         use rayon::prelude::*;
-        uris.par_iter()
-            .map(|uri| {
-                let result = Context::load_image_fast(uri);
-                result
+        sources.par_iter()
+            .map(|source| match source {
+                ImageSource::File(uri) => Context::load_image_fast(uri),
+                ImageSource::Memory { bytes, mime_type } => {
+                    let extension = match mime_type.as_str() {
+                        "image/png" => Some("png"),
+                        "image/jpeg" => Some("jpg"),
+                        _ => None,
+                    };
+                    Context::decode_image(bytes, extension)
+                }
             })
             .collect()
     }
@@ -590,14 +607,22 @@ impl Context {
         uris: &[PathBuf],
         generate_mipmaps: bool,
     ) -> Vec<((ImageView, Sampler), (Image, DeviceMemory), u32)> { unsafe {
-        if uris.is_empty() {
+        let sources: Vec<ImageSource> = uris.iter().cloned().map(ImageSource::File).collect();
+        self.load_image_sources_batched(&sources, generate_mipmaps)
+    } }
+    pub unsafe fn load_image_sources_batched(
+        &self,
+        sources: &[ImageSource],
+        generate_mipmaps: bool,
+    ) -> Vec<((ImageView, Sampler), (Image, DeviceMemory), u32)> { unsafe {
+        if sources.is_empty() {
             return Vec::new();
         }
 
         // println!("starting parallel image decode...");
         let decode_start = Instant::now();
 
-        let decoded_images = Context::load_images_parallel(uris);
+        let decoded_images = Context::load_images_parallel(sources);
 
         // println!("all images decoded in {:?}", decode_start.elapsed());
         // println!("starting gpu upload...");
@@ -606,8 +631,8 @@ impl Context {
         let command_buffers = self.begin_single_time_commands(1);
         let command_buffer = command_buffers[0];
 
-        let mut results = Vec::with_capacity(uris.len());
-        let mut staging_buffers = Vec::with_capacity(uris.len());
+        let mut results = Vec::with_capacity(sources.len());
+        let mut staging_buffers = Vec::with_capacity(sources.len());
 
         for (image_data, img_width, img_height) in decoded_images {
             let image_extent = vk::Extent2D { width: img_width, height: img_height };
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use crate::math::Vector;
+use crate::scene::scene::Scene;
+
+/// Channel values of one entity from one animation, None for the paths the animation does not target
+#[derive(Clone, Default)]
+struct Pose {
+    translation: Option<Vector>,
+    rotation: Option<Vector>,
+    scale: Option<Vector>,
+    weights: Option<Vec<f32>>,
+}
+impl Pose {
+    /// Values of the wrong size for their path, and unsupported paths, are ignored
+    fn set(&mut self, path: &str, value: Vec<f32>) {
+        match (path, value.len()) {
+            ("translation", 3) => self.translation = Some(Vector::from_array(&value)),
+            ("rotation", 4) => self.rotation = Some(Vector::from_array(&value)),
+            ("scale", 3) => self.scale = Some(Vector::from_array(&value)),
+            ("weights", _) => self.weights = Some(value),
+            _ => {}
+        }
+    }
+}
+
+struct LayerSample {
+    layer: u32,
+    additive: bool,
+    weight: f32,
+    pose: Pose,
+    reference: Pose, // first keyframe values, for additive animations
+}
+
+impl Scene {
+    /// Fades `from` out and `to` in over duration seconds, starting `to` if it is not running. `from` stops once faded out.
+    pub fn crossfade(&mut self, from: usize, to: usize, duration: f32) {
+        let target = &mut self.animation_components[to];
+        if !target.running {
+            target.start();
+            target.weight = 0.0;
+        }
+        target.fade_to(1.0, duration, false);
+        self.animation_components[from].fade_to(0.0, duration, true);
+    }
+
+    ///* Limits an animation to the entity, and with recursive to its descendants, eg. to play a clip on the upper body by masking
+    ///* it to the spine. Masking again replaces the previous mask.
+    pub fn set_animation_mask(&mut self, animation_index: usize, entity_index: usize, recursive: bool) {
+        let mut mask = vec![entity_index];
+        if recursive {
+            let mut i = 0;
+            while i < mask.len() {
+                mask.extend(self.entities[mask[i]].children_indices.iter().copied());
+                i += 1;
+            }
+        }
+        self.animation_components[animation_index].mask = Some(mask);
+    }
+    pub fn clear_animation_mask(&mut self, animation_index: usize) {
+        self.animation_components[animation_index].mask = None;
+    }
+
+    ///* Blends the poses of all running animations, and of the held ones that stopped this frame without snapping back, into
+    ///* the animated transforms and morph weights of the entities they target.
+    ///* Each entity starts from its own transform and weights. Layers are applied in ascending order: the animations of a layer are
+    ///* averaged by weight and blended over the pose so far by their combined weight (up to 1), then the layer's additive
+    ///* animations add their weighted difference from their first keyframe.
+    pub(crate) fn apply_animation_poses(&mut self, held_animations: &[usize]) {
+        let mut entity_samples: HashMap<usize, Vec<LayerSample>> = HashMap::new();
+        for (animation_index, animation) in self.animation_components.iter().enumerate() {
+            if !animation.running && !held_animations.contains(&animation_index) { continue }
+            self.unupdated_entities.push(animation.owner_entity);
+
+            let mut poses: HashMap<usize, (Pose, Pose)> = HashMap::new();
+            for (channel_index, channel) in animation.channels.iter().enumerate() {
+                if animation.mask.as_ref().is_some_and(|mask| !mask.contains(&channel.1)) { continue }
+                let (Some(value), Some(reference)) = (animation.sample(channel_index), animation.reference(channel_index)) else { continue };
+                let (pose, reference_pose) = poses.entry(channel.1).or_default();
+                pose.set(channel.2.as_str(), value);
+                reference_pose.set(channel.2.as_str(), reference);
+            }
+            for (entity_index, (pose, reference)) in poses {
+                entity_samples.entry(entity_index).or_default().push(LayerSample {
+                    layer: animation.layer,
+                    additive: animation.additive,
+                    weight: animation.weight.max(0.0),
+                    pose,
+                    reference,
+                });
+            }
+        }
+
+        for (entity_index, mut samples) in entity_samples {
+            samples.sort_by_key(|sample| (sample.layer, sample.additive));
+            let entity = &self.entities[entity_index];
+            let rest = &self.transforms[entity.transform];
+            let (mut translation, mut rotation, mut scale) = (rest.local_translation, rest.local_rotation, rest.local_scale);
+            let animates_transform = samples.iter().any(|sample| sample.pose.translation.is_some() || sample.pose.rotation.is_some() || sample.pose.scale.is_some());
+            let animates_weights = samples.iter().any(|sample| sample.pose.weights.is_some());
+            let mut weights = entity.morph_weights.clone();
+
+            for group in samples.chunk_by(|a, b| a.layer == b.layer && a.additive == b.additive) {
+                if group[0].additive {
+                    for sample in group {
+                        if let (Some(value), Some(reference)) = (sample.pose.translation, sample.reference.translation) {
+                            translation += (value - reference) * sample.weight;
+                        }
+                        if let (Some(value), Some(reference)) = (sample.pose.rotation, sample.reference.rotation) {
+                            let difference = reference.inverse_quat().combine(&value);
+                            rotation = rotation.combine(&Vector::spherical_lerp(&Vector::new(), &difference, sample.weight)).normalize4();
+                        }
+                        if let (Some(value), Some(reference)) = (sample.pose.scale, sample.reference.scale) {
+                            scale += (value - reference) * sample.weight;
+                        }
+                        if let (Some(value), Some(reference)) = (&sample.pose.weights, &sample.reference.weights) {
+                            resize_weights(&mut weights, value.len());
+                            for ((weight, value), reference) in weights.iter_mut().zip(value).zip(reference) {
+                                *weight += (value - reference) * sample.weight;
+                            }
+                        }
+                    }
+                    continue
+                }
+
+                if let Some((value, weight)) = average_linear(group.iter().filter_map(|sample| sample.pose.translation.map(|v| (v, sample.weight)))) {
+                    translation = Vector::mix(&translation, &value, weight.min(1.0));
+                }
+                if let Some((value, weight)) = average_rotation(group.iter().filter_map(|sample| sample.pose.rotation.map(|v| (v, sample.weight)))) {
+                    rotation = Vector::spherical_lerp(&rotation, &value, weight.min(1.0));
+                }
+                if let Some((value, weight)) = average_linear(group.iter().filter_map(|sample| sample.pose.scale.map(|v| (v, sample.weight)))) {
+                    scale = Vector::mix(&scale, &value, weight.min(1.0));
+                }
+                if let Some((value, weight)) = average_weights(group.iter().filter_map(|sample| sample.pose.weights.as_ref().map(|v| (v, sample.weight)))) {
+                    resize_weights(&mut weights, value.len());
+                    for (current, value) in weights.iter_mut().zip(value) {
+                        *current += (value - *current) * weight.min(1.0);
+                    }
+                }
+            }
+
+            let entity = &mut self.entities[entity_index];
+            if animates_weights {
+                entity.animated_morph_weights = Some(weights);
+            }
+            if animates_transform {
+                entity.animated_transform.1 = true;
+                let animated_transform = &mut self.transforms[entity.animated_transform.0];
+                animated_transform.local_translation = translation;
+                animated_transform.local_rotation = rotation;
+                animated_transform.local_scale = scale;
+            }
+        }
+    }
+}
+
+/// Weighted average and total weight, None if the total weight is 0
+fn average_linear(values: impl Iterator<Item = (Vector, f32)>) -> Option<(Vector, f32)> {
+    let (sum, total_weight) = values.fold((Vector::empty(), 0.0), |(sum, total_weight), (value, weight)| (sum + value * weight, total_weight + weight));
+    (total_weight > 0.0).then(|| (sum / total_weight, total_weight))
+}
+/// Per weight average of morph target weight lists, which can differ in length, and total weight, None if the total weight is 0
+fn average_weights<'a>(values: impl Iterator<Item = (&'a Vec<f32>, f32)>) -> Option<(Vec<f32>, f32)> {
+    let mut sum: Vec<f32> = Vec::new();
+    let mut total_weight = 0.0;
+    for (value, weight) in values {
+        resize_weights(&mut sum, value.len());
+        for (sum, value) in sum.iter_mut().zip(value) {
+            *sum += value * weight;
+        }
+        total_weight += weight;
+    }
+    (total_weight > 0.0).then(|| (sum.into_iter().map(|sum| sum / total_weight).collect(), total_weight))
+}
+/// Pads weights with zeros, the default weight of morph targets
+fn resize_weights(weights: &mut Vec<f32>, len: usize) {
+    if weights.len() < len {
+        weights.resize(len, 0.0);
+    }
+}
+/// Weighted average of quaternions by successive slerps, and total weight, None if the total weight is 0
+fn average_rotation(values: impl Iterator<Item = (Vector, f32)>) -> Option<(Vector, f32)> {
+    let mut average: Option<(Vector, f32)> = None;
+    for (value, weight) in values {
+        average = Some(match average {
+            None => (value, weight),
+            Some((average, total_weight)) if total_weight + weight > 0.0 => {
+                (Vector::spherical_lerp(&average, &value, weight / (total_weight + weight)), total_weight + weight)
+            }
+            Some(average) => average,
+        });
+    }
+    average.filter(|(_, total_weight)| *total_weight > 0.0)
+}
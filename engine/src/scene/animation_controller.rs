@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use json::JsonValue;
+use crate::scene::scene::Scene;
+
+/// A state machine that plays the animations of its owner entity, loaded from a JSON file like
+/// ```json
+/// {
+///     "parameters": { "speed": 0.0, "grounded": true, "jump": "trigger" },
+///     "default_state": "idle",
+///     "states": [ { "name": "idle", "clip": "Idle", "repeat": true }, { "name": "run", "clip": "Run", "repeat": true, "speed": 1.5 }, ... ],
+///     "transitions": [
+///         { "from": "idle", "to": "run", "duration": 0.2, "conditions": [ { "parameter": "speed", "greater": 0.1 } ] },
+///         { "to": "jump", "duration": 0.1, "conditions": [ { "parameter": "jump" } ] },
+///         { "from": "jump", "to": "idle", "duration": 0.2, "exit_time": 0.9 }
+///     ]
+/// }
+/// ```
+/// Clips are the names of the owner's animations. Transitions without "from" can be taken from any state.
+/// A condition without a comparison holds while a bool is true or a trigger is set. Triggers stay set until a transition uses them.
+#[derive(Clone)]
+pub struct AnimationController {
+    pub owner: usize,
+    pub uri: String,
+    pub parameters: HashMap<String, AnimationParameter>,
+    pub states: Vec<ControllerState>,
+    pub transitions: Vec<ControllerTransition>,
+    pub current_state: usize,
+    pub(crate) entered: bool, // whether the current state's clip has been started
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationParameter {
+    Float(f32),
+    Bool(bool),
+    Trigger(bool),
+}
+
+#[derive(Clone)]
+pub struct ControllerState {
+    pub name: String,
+    pub clip: String,
+    pub speed: f32,
+    pub repeat: bool,
+}
+
+#[derive(Clone)]
+pub struct ControllerTransition {
+    pub from: Option<usize>, // None for any state
+    pub to: usize,
+    pub duration: f32,
+    pub exit_time: Option<f32>, // normalized time the current clip has to reach first
+    pub conditions: Vec<(String, Comparison)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    IsSet, // bools that are true and triggers that are set
+    Greater(f32),
+    Less(f32),
+    Equals(AnimationParameter),
+}
+
+impl AnimationController {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = json::parse(fs::read_to_string(path)?.as_str())?;
+
+        let mut parameters = HashMap::new();
+        for (name, value) in json["parameters"].entries() {
+            let parameter = match value {
+                JsonValue::Boolean(b) => AnimationParameter::Bool(*b),
+                value if value.is_number() => AnimationParameter::Float(value.as_f32().unwrap_or(0.0)),
+                value if value.as_str() == Some("trigger") => AnimationParameter::Trigger(false),
+                _ => return Err(format!("{}: parameter '{}' must be a number, a bool or \"trigger\"", path, name).into()),
+            };
+            parameters.insert(String::from(name), parameter);
+        }
+
+        let states = json["states"].members().map(|state_json| {
+            Ok(ControllerState {
+                name: state_json["name"].as_str().ok_or_else(|| format!("{}: a state has no name", path))?.to_string(),
+                clip: state_json["clip"].as_str().ok_or_else(|| format!("{}: a state has no clip", path))?.to_string(),
+                speed: state_json["speed"].as_f32().unwrap_or(1.0),
+                repeat: state_json["repeat"].as_bool().unwrap_or(false),
+            })
+        }).collect::<Result<Vec<ControllerState>, Box<dyn Error>>>()?;
+        if states.is_empty() {
+            return Err(format!("{}: animation controller has no states", path).into())
+        }
+        let state_index = |name: &str| -> Result<usize, Box<dyn Error>> {
+            states.iter().position(|state| state.name == name).ok_or_else(|| format!("{}: no state named '{}'", path, name).into())
+        };
+
+        let mut transitions = Vec::new();
+        for transition_json in json["transitions"].members() {
+            let from = match transition_json["from"].as_str() {
+                Some(name) => Some(state_index(name)?),
+                None => None,
+            };
+            let to = state_index(transition_json["to"].as_str().ok_or_else(|| format!("{}: a transition has no target state", path))?)?;
+
+            let mut conditions = Vec::new();
+            for condition_json in transition_json["conditions"].members() {
+                let name = condition_json["parameter"].as_str().ok_or_else(|| format!("{}: a condition has no parameter", path))?;
+                let parameter = *parameters.get(name).ok_or_else(|| format!("{}: condition on undeclared parameter '{}'", path, name))?;
+                let comparison = if let Some(value) = condition_json["greater"].as_f32() {
+                    Comparison::Greater(value)
+                } else if let Some(value) = condition_json["less"].as_f32() {
+                    Comparison::Less(value)
+                } else if let Some(value) = condition_json["equals"].as_bool() {
+                    Comparison::Equals(AnimationParameter::Bool(value))
+                } else if let Some(value) = condition_json["equals"].as_f32() {
+                    Comparison::Equals(AnimationParameter::Float(value))
+                } else {
+                    Comparison::IsSet
+                };
+                let valid = matches!(
+                    (parameter, comparison),
+                    (AnimationParameter::Float(_), Comparison::Greater(_) | Comparison::Less(_) | Comparison::Equals(AnimationParameter::Float(_)))
+                    | (AnimationParameter::Bool(_), Comparison::IsSet | Comparison::Equals(AnimationParameter::Bool(_)))
+                    | (AnimationParameter::Trigger(_), Comparison::IsSet)
+                );
+                if !valid {
+                    return Err(format!("{}: condition {:?} does not apply to parameter '{}'", path, comparison, name).into())
+                }
+                conditions.push((String::from(name), comparison));
+            }
+
+            transitions.push(ControllerTransition {
+                from,
+                to,
+                duration: transition_json["duration"].as_f32().unwrap_or(0.0),
+                exit_time: transition_json["exit_time"].as_f32(),
+                conditions,
+            });
+        }
+
+        let current_state = match json["default_state"].as_str() {
+            Some(name) => state_index(name)?,
+            None => 0,
+        };
+
+        Ok(Self {
+            owner: 0,
+            uri: String::from(path),
+            parameters,
+            states,
+            transitions,
+            current_state,
+            entered: false,
+        })
+    }
+
+    /// Fails if the parameter is not declared, or is declared with a different type
+    pub fn set_parameter(&mut self, name: &str, value: AnimationParameter) -> Result<(), Box<dyn Error>> {
+        let parameter = self.parameters.get_mut(name).ok_or_else(|| format!("animation controller has no parameter '{}'", name))?;
+        if std::mem::discriminant(parameter) != std::mem::discriminant(&value) {
+            return Err(format!("parameter '{}' is a {:?}, it cannot be set to {:?}", name, parameter, value).into())
+        }
+        *parameter = value;
+        Ok(())
+    }
+
+    fn condition_holds(&self, name: &str, comparison: Comparison) -> bool {
+        match (self.parameters.get(name), comparison) {
+            (Some(AnimationParameter::Float(value)), Comparison::Greater(threshold)) => *value > threshold,
+            (Some(AnimationParameter::Float(value)), Comparison::Less(threshold)) => *value < threshold,
+            (Some(&value), Comparison::Equals(expected)) => value == expected,
+            (Some(AnimationParameter::Bool(value) | AnimationParameter::Trigger(value)), Comparison::IsSet) => *value,
+            _ => false,
+        }
+    }
+}
+
+impl Scene {
+    /// Loads an animation controller for the entity, whose states' clips have to be among the entity's animations
+    pub fn attach_animation_controller(&mut self, entity_index: usize, path: &str) -> Result<usize, Box<dyn Error>> {
+        if self.get_animation_controller(entity_index).is_some() {
+            return Err(format!("entity {} already has an animation controller", entity_index).into())
+        }
+        let mut controller = AnimationController::load(path)?;
+        controller.owner = entity_index;
+        for state in controller.states.iter() {
+            if self.find_animation(entity_index, state.clip.as_str()).is_none() {
+                return Err(format!("{}: entity {} has no animation named '{}'", path, entity_index, state.clip).into())
+            }
+        }
+        self.animation_controllers.push(controller);
+        Ok(self.animation_controllers.len() - 1)
+    }
+    pub fn get_animation_controller(&self, entity_index: usize) -> Option<usize> {
+        self.animation_controllers.iter().position(|controller| controller.owner == entity_index)
+    }
+    pub fn remove_animation_controller(&mut self, controller_index: usize) {
+        self.animation_controllers.swap_remove(controller_index);
+    }
+
+    /// The first of the entity's animations with the name
+    pub fn find_animation(&self, entity_index: usize, name: &str) -> Option<usize> {
+        self.entities[entity_index].animation_objects.iter().copied()
+            .find(|&animation_index| self.animation_components[animation_index].name == name)
+    }
+
+    ///* Enters each controller's state if it has not been yet, then takes the first transition whose conditions hold,
+    ///* any-state transitions first. Called from the scene update before animations advance.
+    pub(crate) fn update_animation_controllers(&mut self) {
+        for controller_index in 0..self.animation_controllers.len() {
+            let controller = &self.animation_controllers[controller_index];
+            let owner = controller.owner;
+            let current_animation = self.find_animation(owner, controller.states[controller.current_state].clip.as_str());
+
+            if !controller.entered {
+                let state = controller.current_state;
+                self.enter_controller_state(controller_index, None, state, 0.0);
+                continue
+            }
+
+            let normalized_time = current_animation.map_or(1.0, |animation_index| self.animation_components[animation_index].normalized_time());
+            let transition = controller.transitions.iter()
+                .filter(|transition| transition.from.is_none() && transition.to != controller.current_state)
+                .chain(controller.transitions.iter().filter(|transition| transition.from == Some(controller.current_state)))
+                .find(|transition| {
+                    transition.exit_time.is_none_or(|exit_time| normalized_time >= exit_time)
+                        && transition.conditions.iter().all(|(name, comparison)| controller.condition_holds(name, *comparison))
+                })
+                .cloned();
+
+            if let Some(transition) = transition {
+                let controller = &mut self.animation_controllers[controller_index];
+                for (name, _) in transition.conditions.iter() {
+                    if let Some(AnimationParameter::Trigger(set)) = controller.parameters.get_mut(name) {
+                        *set = false;
+                    }
+                }
+                self.enter_controller_state(controller_index, current_animation, transition.to, transition.duration);
+            }
+        }
+    }
+
+    /// Switches the controller to the state, restarting its clip and crossfading to it from the current clip
+    pub(crate) fn enter_controller_state(&mut self, controller_index: usize, current_animation: Option<usize>, state_index: usize, duration: f32) {
+        let controller = &mut self.animation_controllers[controller_index];
+        controller.current_state = state_index;
+        controller.entered = true;
+        let owner = controller.owner;
+        let state = controller.states[state_index].clone();
+
+        let Some(animation_index) = self.find_animation(owner, state.clip.as_str()) else { return };
+        let animation = &mut self.animation_components[animation_index];
+        animation.speed = state.speed;
+        animation.repeat = state.repeat;
+        animation.start();
+        match current_animation {
+            Some(current_animation) if current_animation != animation_index && duration > 0.0 => {
+                animation.weight = 0.0;
+                self.crossfade(current_animation, animation_index, duration);
+            }
+            Some(current_animation) if current_animation != animation_index => {
+                animation.weight = 1.0;
+                self.animation_components[current_animation].stop(&mut self.entities);
+            }
+            Some(_) => animation.weight = 1.0,
+            None => {
+                // the controller takes over the entity's animations when it first enters a state
+                animation.weight = 1.0;
+                for other_animation in self.entities[owner].animation_objects.clone() {
+                    if other_animation != animation_index && self.animation_components[other_animation].running {
+                        self.animation_components[other_animation].stop(&mut self.entities);
+                    }
+                }
+            }
+        }
+    }
+}
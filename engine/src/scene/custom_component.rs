@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::mem::discriminant;
+use crate::scene::scene::{Field, Scene};
+
+///* A component type defined by game code, from Lua or Rust, as a list of named fields with their default values.
+///* Schemas are kept when the scene is cleared, so that they only have to be registered once.
+pub struct ComponentSchema {
+    pub name: String,
+    pub fields: Vec<(String, Field)>,
+}
+
+/// An instance of a ComponentSchema on an entity, at most one per schema per entity
+#[derive(Clone)]
+pub struct CustomComponent {
+    pub owner: usize,
+    pub schema: usize,
+    pub fields: HashMap<String, Field>,
+}
+
+impl Scene {
+    ///* Registers a component type, returning its schema index.
+    ///* Registering a name again replaces its fields. Existing components keep the values of fields that are still there with
+    ///* the same type, and get defaults for the rest.
+    pub fn register_component(&mut self, name: &str, fields: Vec<(String, Field)>) -> usize {
+        let Some(schema_index) = self.component_schema(name) else {
+            self.component_schemas.push(ComponentSchema { name: String::from(name), fields });
+            return self.component_schemas.len() - 1
+        };
+
+        for component in self.custom_components.iter_mut().filter(|component| component.schema == schema_index) {
+            let old_fields = std::mem::take(&mut component.fields);
+            component.fields = fields.iter().map(|(field_name, default)| {
+                let value = old_fields.get(field_name)
+                    .filter(|old| discriminant(*old) == discriminant(default))
+                    .unwrap_or(default);
+                (field_name.clone(), value.clone())
+            }).collect();
+        }
+        self.component_schemas[schema_index].fields = fields;
+        schema_index
+    }
+    pub fn component_schema(&self, name: &str) -> Option<usize> {
+        self.component_schemas.iter().position(|schema| schema.name == name)
+    }
+
+    /// Adds a component of a registered type to the entity, with its fields set to their defaults
+    pub fn attach_custom_component(&mut self, entity_index: usize, name: &str) -> Result<usize, Box<dyn Error>> {
+        let schema_index = self.component_schema(name).ok_or_else(|| format!("component '{}' is not registered", name))?;
+        if self.get_custom_component(entity_index, name).is_some() {
+            return Err(format!("entity {} already has a '{}' component", entity_index, name).into())
+        }
+
+        self.custom_components.push(CustomComponent {
+            owner: entity_index,
+            schema: schema_index,
+            fields: self.component_schemas[schema_index].fields.iter().cloned().collect(),
+        });
+        Ok(self.custom_components.len() - 1)
+    }
+    pub fn get_custom_component(&self, entity_index: usize, name: &str) -> Option<usize> {
+        let schema_index = self.component_schema(name)?;
+        self.custom_components.iter().position(|component| component.owner == entity_index && component.schema == schema_index)
+    }
+    /// Returns whether the entity had the component
+    pub fn detach_custom_component(&mut self, entity_index: usize, name: &str) -> bool {
+        if let Some(component_index) = self.get_custom_component(entity_index, name) {
+            self.remove_custom_component(component_index);
+            true
+        } else {
+            false
+        }
+    }
+    pub fn remove_custom_component(&mut self, component_index: usize) {
+        self.custom_components.swap_remove(component_index);
+    }
+
+    /// Fails if the schema has no field with the name, or if the value has a different type. Integers are accepted for Float fields.
+    pub fn set_custom_field(&mut self, component_index: usize, field_name: &str, value: Field) -> Result<(), Box<dyn Error>> {
+        let component = &mut self.custom_components[component_index];
+        let schema = &self.component_schemas[component.schema];
+        let current = component.fields.get_mut(field_name).ok_or_else(|| format!("component '{}' has no field '{}'", schema.name, field_name))?;
+
+        let value = current.coerce(value.clone())
+            .ok_or_else(|| format!("field '{}' of component '{}' cannot be set to {:?}", field_name, schema.name, value))?;
+        *current = value;
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::scene::scene::Scene;
+
+///* Changes to the scene that other systems can react to instead of polling.
+///* Events are queued as they happen and handed out together by Scene::take_events, which the script engine calls once per frame,
+///* before the Update methods run. Indices in events are the ones at the time of the event, and are queued with the generation
+///* the event's entity had, so that a reused index is not mistaken for the entity.
+#[derive(Clone, Debug)]
+pub enum SceneEvent {
+    EntityAdded { entity: usize },
+    EntityRemoved { entity: usize },
+    EntityReparented { entity: usize, old_parent: usize, new_parent: usize },
+    RigidBodyAttached { entity: usize, rigid_body: usize },
+    TransformChanged { entity: usize, transform: usize },
+    AnimationStarted { entity: usize, animation: usize },
+    AnimationStopped { entity: usize, animation: usize },
+    AnimationMarker { entity: usize, animation: usize, marker: String },
+}
+impl SceneEvent {
+    pub const NAMES: [&'static str; 8] = [
+        "entity_added",
+        "entity_removed",
+        "entity_reparented",
+        "rigid_body_attached",
+        "transform_changed",
+        "animation_started",
+        "animation_stopped",
+        "animation_marker",
+    ];
+
+    /// The name scripts subscribe to the event with
+    pub fn name(&self) -> &'static str {
+        match self {
+            SceneEvent::EntityAdded { .. } => "entity_added",
+            SceneEvent::EntityRemoved { .. } => "entity_removed",
+            SceneEvent::EntityReparented { .. } => "entity_reparented",
+            SceneEvent::RigidBodyAttached { .. } => "rigid_body_attached",
+            SceneEvent::TransformChanged { .. } => "transform_changed",
+            SceneEvent::AnimationStarted { .. } => "animation_started",
+            SceneEvent::AnimationStopped { .. } => "animation_stopped",
+            SceneEvent::AnimationMarker { .. } => "animation_marker",
+        }
+    }
+    /// The entity the event is about
+    pub fn entity(&self) -> usize {
+        match *self {
+            SceneEvent::EntityAdded { entity }
+            | SceneEvent::EntityRemoved { entity }
+            | SceneEvent::EntityReparented { entity, .. }
+            | SceneEvent::RigidBodyAttached { entity, .. }
+            | SceneEvent::TransformChanged { entity, .. }
+            | SceneEvent::AnimationStarted { entity, .. }
+            | SceneEvent::AnimationStopped { entity, .. }
+            | SceneEvent::AnimationMarker { entity, .. } => entity,
+        }
+    }
+}
+
+/// Events kept while nothing takes them, eg. for headless scenes, past which the oldest are dropped
+pub const MAX_PENDING_EVENTS: usize = 4096;
+
+impl Scene {
+    pub fn emit(&mut self, event: SceneEvent) {
+        if self.pending_events.len() >= MAX_PENDING_EVENTS {
+            // half at once, so that a full queue is not shifted for every event
+            self.pending_events.drain(..MAX_PENDING_EVENTS / 2);
+        }
+        let generation = self.entities.get(event.entity()).map_or(0, |entity| entity.generation);
+        self.pending_events.push((event, generation));
+    }
+    /// Every event since the last call, in the order they happened, with the generation of its entity at the time
+    pub fn take_events(&mut self) -> Vec<(SceneEvent, u32)> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// Queues start and stop events for animations whose running state changed since the last call, from the scene update
+    pub(crate) fn emit_animation_events(&mut self) {
+        let mut events = Vec::new();
+        for (animation_index, animation) in self.animation_components.iter_mut().enumerate() {
+            if animation.running != animation.reported_running {
+                animation.reported_running = animation.running;
+                let entity = animation.owner_entity;
+                events.push(if animation.running {
+                    SceneEvent::AnimationStarted { entity, animation: animation_index }
+                } else {
+                    SceneEvent::AnimationStopped { entity, animation: animation_index }
+                });
+            }
+        }
+        for event in events {
+            self.emit(event);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use json::JsonValue;
+use crate::math::Vector;
+use crate::scene::events::SceneEvent;
+use crate::scene::scene::{world_transform_to_local, Scene};
+
+const HISTORY_LIMIT: usize = 200; // undoable entries kept, the oldest are dropped past this
+const MERGE_WINDOW: Duration = Duration::from_secs(1); // consecutive edits to the same field closer than this become one entry
+
+/// An entity index with its generation, edits to an entity that has since been removed are skipped
+pub type EntityHandle = (usize, u32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformField {
+    Translation,
+    Rotation,
+    Scale,
+}
+impl TransformField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransformField::Translation => "translation",
+            TransformField::Rotation => "rotation",
+            TransformField::Scale => "scale",
+        }
+    }
+}
+
+/// Where an entity is in the hierarchy, with the local transform it has there
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub parent: EntityHandle,
+    pub sibling_position: usize,
+    pub translation: Vector,
+    pub rotation: Vector,
+    pub scale: Vector,
+}
+
+///* An entity, and its descendants for recursive edits, saved in the scene file layout.
+///* Respawning it gives the entities new handles, which are remapped throughout the history.
+#[derive(Clone, Debug)]
+pub struct SavedSubtree {
+    entities: Vec<EntityHandle>, // matches the saved entities, the root first
+    parent: EntityHandle,
+    sibling_position: usize,
+    json: JsonValue,
+}
+
+///* A recorded change to the scene, holding the values from both before and after it so it can be applied either way.
+#[derive(Clone, Debug)]
+pub enum SceneEdit {
+    Transform { entity: EntityHandle, render_object: Option<usize>, field: TransformField, before: Vector, after: Vector }, // render_object is the position in the entity's render_objects, None for the entity's own transform
+    Name { entity: EntityHandle, before: String, after: String },
+    Layers { entity: EntityHandle, before: u32, after: u32 },
+    Static { entity: EntityHandle, before: bool, after: bool },
+    Parent { entity: EntityHandle, before: Placement, after: Placement },
+    Spawn(SavedSubtree),
+    Remove(SavedSubtree),
+}
+impl SceneEdit {
+    /// Folds a following edit of the same field into this one, keeping this one's before value
+    fn merge(&mut self, next: &SceneEdit) -> bool {
+        match (self, next) {
+            (
+                SceneEdit::Transform { entity, render_object, field, after, .. },
+                SceneEdit::Transform { entity: next_entity, render_object: next_render_object, field: next_field, after: next_after, .. },
+            ) if entity == next_entity && render_object == next_render_object && field == next_field => {
+                *after = *next_after;
+                true
+            }
+            (SceneEdit::Name { entity, after, .. }, SceneEdit::Name { entity: next_entity, after: next_after, .. }) if entity == next_entity => {
+                *after = next_after.clone();
+                true
+            }
+            (SceneEdit::Layers { entity, after, .. }, SceneEdit::Layers { entity: next_entity, after: next_after, .. }) if entity == next_entity => {
+                *after = *next_after;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn remap(&mut self, remapped: &HashMap<EntityHandle, EntityHandle>) {
+        let remap = |handle: &mut EntityHandle| if let Some(&new_handle) = remapped.get(handle) { *handle = new_handle };
+        match self {
+            SceneEdit::Transform { entity, .. }
+            | SceneEdit::Name { entity, .. }
+            | SceneEdit::Layers { entity, .. }
+            | SceneEdit::Static { entity, .. } => remap(entity),
+            SceneEdit::Parent { entity, before, after } => {
+                remap(entity);
+                remap(&mut before.parent);
+                remap(&mut after.parent);
+            }
+            SceneEdit::Spawn(subtree) | SceneEdit::Remove(subtree) => {
+                subtree.entities.iter_mut().for_each(remap);
+                remap(&mut subtree.parent);
+            }
+        }
+    }
+    fn describe(&self, scene: &Scene) -> String {
+        let name = |handle: &EntityHandle| scene.resolve(*handle).map_or("entity", |index| scene.entities[index].name.as_str()).to_string();
+        let subtree_name = |subtree: &SavedSubtree| subtree.json[0]["name"].as_str().unwrap_or("entity").to_string();
+        match self {
+            SceneEdit::Transform { entity, field, .. } => format!("Set {} of {}", field.name(), name(entity)),
+            SceneEdit::Name { before, after, .. } => format!("Rename {} to {}", before, after),
+            SceneEdit::Layers { entity, .. } => format!("Set layers of {}", name(entity)),
+            SceneEdit::Static { entity, after, .. } => format!("Make {} {}", name(entity), if *after { "static" } else { "dynamic" }),
+            SceneEdit::Parent { entity, after, .. } => format!("Move {} to {}", name(entity), name(&after.parent)),
+            SceneEdit::Spawn(subtree) => format!("Add {}", subtree_name(subtree)),
+            SceneEdit::Remove(subtree) => format!("Remove {}", subtree_name(subtree)),
+        }
+    }
+}
+
+struct HistoryEntry {
+    name: String,
+    edits: Vec<SceneEdit>,
+}
+
+///* Undo and redo stacks of the edits made through the scene API.
+///* Edits are recorded by the callers that make them, undoing and redoing applies them without recording anything.
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    transaction: Option<HistoryEntry>, // edits grouped into one entry until the transaction is committed
+    transaction_depth: usize, // nested transactions are folded into the outermost one
+    pub(crate) last_edit: Option<Instant>, // of the top undo entry, while it is open to merging
+}
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.transaction.as_ref().is_some_and(|transaction| !transaction.edits.is_empty())
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+    fn push(&mut self, entry: HistoryEntry) {
+        self.undo_stack.push(entry);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+    fn remap(&mut self, remapped: &HashMap<EntityHandle, EntityHandle>) {
+        self.undo_stack.iter_mut()
+            .chain(self.redo_stack.iter_mut())
+            .chain(self.transaction.iter_mut())
+            .flat_map(|entry| entry.edits.iter_mut())
+            .for_each(|edit| edit.remap(remapped));
+    }
+}
+
+impl Scene {
+    pub fn entity_handle(&self, entity_index: usize) -> EntityHandle {
+        (entity_index, self.entities[entity_index].generation)
+    }
+    /// The index of the entity if it has not been removed since the handle was taken
+    pub fn resolve(&self, handle: EntityHandle) -> Option<usize> {
+        self.get_entity(handle.0, handle.1).map(|_| handle.0)
+    }
+    pub fn placement(&self, entity_index: usize) -> Placement {
+        let entity = &self.entities[entity_index];
+        let transform = &self.transforms[entity.transform];
+        Placement {
+            parent: self.entity_handle(entity.parent),
+            sibling_position: self.entities[entity.parent].children_indices.iter().position(|&i| i == entity_index).unwrap_or(0),
+            translation: transform.local_translation,
+            rotation: transform.local_rotation,
+            scale: transform.local_scale,
+        }
+    }
+    pub fn saved_subtree(&self, entity_index: usize, recursive: bool) -> SavedSubtree {
+        let (indices, json) = self.subtree_to_json(entity_index, recursive);
+        let placement = self.placement(entity_index);
+        SavedSubtree {
+            entities: indices.iter().map(|&index| self.entity_handle(index)).collect(),
+            parent: placement.parent,
+            sibling_position: placement.sibling_position,
+            json,
+        }
+    }
+
+    pub fn transform_field(&self, transform_index: usize, field: TransformField) -> Vector {
+        let transform = &self.transforms[transform_index];
+        match field {
+            TransformField::Translation => transform.local_translation,
+            TransformField::Rotation => transform.local_rotation,
+            TransformField::Scale => transform.local_scale,
+        }
+    }
+    /// Sets a local transform field of an entity or render component, without recording it
+    pub fn set_transform_field(&mut self, transform_index: usize, field: TransformField, value: Vector) {
+        // one level of the hierarchy up, for safety and to make this work properly for editing transforms of render components (non-entity components)
+        let entity = self.transforms[transform_index].owner;
+        let parent = self.entities[entity].parent;
+        self.unupdated_entities.push(parent);
+        self.emit(SceneEvent::TransformChanged { entity, transform: transform_index });
+        let transform = &mut self.transforms[transform_index];
+        match field {
+            TransformField::Translation => transform.local_translation = value,
+            TransformField::Rotation => transform.local_rotation = value,
+            TransformField::Scale => transform.local_scale = value,
+        }
+    }
+    /// The edit setting a transform field from before to after, for record
+    pub fn transform_edit(&self, transform_index: usize, field: TransformField, before: Vector, after: Vector) -> SceneEdit {
+        let entity = self.transforms[transform_index].owner;
+        let render_object = if self.entities[entity].transform == transform_index {
+            None
+        } else {
+            self.entities[entity].render_objects.iter().position(|&r| self.render_components[r].transform() == transform_index)
+        };
+        SceneEdit::Transform { entity: self.entity_handle(entity), render_object, field, before, after }
+    }
+    pub fn set_rigid_body_static(&mut self, body_index: usize, is_static: bool) {
+        let hitbox = &self.hitbox_components[self.rigid_body_components[body_index].hitbox].hitbox;
+        self.rigid_body_components[body_index].set_static(hitbox, &self.transforms, is_static);
+    }
+
+    ///* Adds an edit that has already been made to the history, clearing what could be redone.
+    ///* Edits made while the scene is running are not recorded, they are reverted on stop instead.
+    pub fn record(&mut self, edit: SceneEdit) {
+        if self.running { return }
+        self.history.redo_stack.clear();
+
+        if let Some(transaction) = &mut self.history.transaction {
+            if !transaction.edits.last_mut().is_some_and(|last| last.merge(&edit)) {
+                transaction.edits.push(edit);
+            }
+            return
+        }
+
+        let now = Instant::now();
+        let mergeable = self.history.last_edit.is_some_and(|last_edit| now.duration_since(last_edit) < MERGE_WINDOW);
+        self.history.last_edit = Some(now);
+        let merged = mergeable && self.history.undo_stack.last_mut()
+            .filter(|entry| entry.edits.len() == 1)
+            .is_some_and(|entry| entry.edits[0].merge(&edit));
+        if merged {
+            let name = self.history.undo_stack.last().unwrap().edits[0].describe(self);
+            self.history.undo_stack.last_mut().unwrap().name = name;
+            return
+        }
+        let name = edit.describe(self);
+        self.history.push(HistoryEntry { name, edits: vec![edit] });
+    }
+    /// Records an added entity and its descendants
+    pub fn record_spawn(&mut self, root_index: usize) {
+        let subtree = self.saved_subtree(root_index, true);
+        self.record(SceneEdit::Spawn(subtree));
+    }
+    /// Records the removal of an entity before it is removed, non-recursive removals also record their children moving up a level
+    pub fn record_removal(&mut self, entity_index: usize, recursive: bool) {
+        let name = format!("Remove {}", self.entities[entity_index].name);
+        self.begin_transaction(name.as_str());
+        if !recursive {
+            let parent_index = self.entities[entity_index].parent;
+            // the children are appended to the parent's, after the removed entity is taken out of them
+            let sibling_count = self.entities[parent_index].children_indices.len() - 1;
+            for (i, child_index) in self.entities[entity_index].children_indices.clone().into_iter().enumerate() {
+                let before = self.placement(child_index);
+                // the children keep their world transform when they move up
+                let (translation, rotation, scale) = world_transform_to_local(
+                    &self.transforms[self.entities[child_index].transform], &self.transforms[self.entities[parent_index].transform]
+                );
+                let after = Placement { parent: self.entity_handle(parent_index), sibling_position: sibling_count + i, translation, rotation, scale };
+                self.record(SceneEdit::Parent { entity: self.entity_handle(child_index), before, after });
+            }
+        }
+        let subtree = self.saved_subtree(entity_index, recursive);
+        self.record(SceneEdit::Remove(subtree));
+        self.commit_transaction();
+    }
+
+    /// Groups the edits recorded until the matching commit_transaction into one entry, like the steps of a drag
+    pub fn begin_transaction(&mut self, name: &str) {
+        self.history.transaction_depth += 1;
+        if self.history.transaction.is_none() {
+            self.history.transaction = Some(HistoryEntry { name: String::from(name), edits: Vec::new() });
+        }
+    }
+    pub fn commit_transaction(&mut self) {
+        self.history.transaction_depth = self.history.transaction_depth.saturating_sub(1);
+        if self.history.transaction_depth > 0 { return }
+        if let Some(transaction) = self.history.transaction.take() && !transaction.edits.is_empty() {
+            self.history.push(transaction);
+        }
+        self.history.last_edit = None;
+    }
+    /// Reverts the edits of the open transaction, including those of the transactions nested in it, and discards them
+    pub fn cancel_transaction(&mut self) -> Result<(), Box<dyn Error>> {
+        self.history.transaction_depth = 0;
+        match self.history.transaction.take() {
+            Some(mut transaction) => self.apply_entry(&mut transaction, false),
+            None => Ok(()),
+        }
+    }
+
+    ///* Reverts the last entry, committing an open transaction first, and returns its name.
+    ///* Edits of entities that have been removed since are skipped, the entry is moved to the redo stack even if some edits fail.
+    pub fn undo(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        if self.history.transaction.is_some() {
+            self.history.transaction_depth = 1;
+            self.commit_transaction();
+        }
+        self.history.last_edit = None;
+        let Some(mut entry) = self.history.undo_stack.pop() else { return Ok(None) };
+        let result = self.apply_entry(&mut entry, false);
+        let name = entry.name.clone();
+        self.history.redo_stack.push(entry);
+        result.map(|_| Some(name))
+    }
+    /// Reapplies the last undone entry and returns its name
+    pub fn redo(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        if self.history.transaction.is_some() { return Ok(None) }
+        self.history.last_edit = None;
+        let Some(mut entry) = self.history.redo_stack.pop() else { return Ok(None) };
+        let result = self.apply_entry(&mut entry, true);
+        let name = entry.name.clone();
+        self.history.undo_stack.push(entry);
+        result.map(|_| Some(name))
+    }
+    /// The names of the entries oldest first, with whether they are done (undoable) or undone (redoable)
+    pub fn history_entries(&self) -> Vec<(&str, bool)> {
+        self.history.undo_stack.iter().map(|entry| (entry.name.as_str(), true))
+            .chain(self.history.redo_stack.iter().rev().map(|entry| (entry.name.as_str(), false)))
+            .collect()
+    }
+    pub fn clear_history(&mut self) {
+        self.history = History::default();
+    }
+
+    /// Applies the edits of an entry in order, or reverts them in reverse order, returning the first error
+    fn apply_entry(&mut self, entry: &mut HistoryEntry, forward: bool) -> Result<(), Box<dyn Error>> {
+        let mut result = Ok(());
+        let order: Vec<usize> = if forward { (0..entry.edits.len()).collect() } else { (0..entry.edits.len()).rev().collect() };
+        for i in order {
+            match self.apply_edit(&entry.edits[i], forward) {
+                Ok(remapped) => if !remapped.is_empty() {
+                    entry.edits.iter_mut().for_each(|edit| edit.remap(&remapped));
+                    self.history.remap(&remapped);
+                }
+                Err(e) => if result.is_ok() { result = Err(e) }
+            }
+        }
+        result
+    }
+    /// Returns the old handles of respawned entities mapped to their new ones
+    fn apply_edit(&mut self, edit: &SceneEdit, forward: bool) -> Result<HashMap<EntityHandle, EntityHandle>, Box<dyn Error>> {
+        match edit {
+            SceneEdit::Transform { entity, render_object, field, before, after } => {
+                let Some(entity_index) = self.resolve(*entity) else { return Ok(HashMap::new()) };
+                let transform_index = match render_object {
+                    None => Some(self.entities[entity_index].transform),
+                    Some(position) => self.entities[entity_index].render_objects.get(*position).map(|&r| self.render_components[r].transform()),
+                };
+                if let Some(transform_index) = transform_index {
+                    self.set_transform_field(transform_index, *field, if forward { *after } else { *before });
+                }
+            }
+            SceneEdit::Name { entity, before, after } => {
+                if let Some(entity_index) = self.resolve(*entity) {
+                    self.entities[entity_index].name = if forward { after.clone() } else { before.clone() };
+                }
+            }
+            SceneEdit::Layers { entity, before, after } => {
+                if let Some(entity_index) = self.resolve(*entity) {
+                    self.entities[entity_index].layers = if forward { *after } else { *before };
+                }
+            }
+            SceneEdit::Static { entity, before, after } => {
+                if let Some(body_index) = self.resolve(*entity).and_then(|entity_index| self.entities[entity_index].rigid_body) {
+                    self.set_rigid_body_static(body_index, if forward { *after } else { *before });
+                }
+            }
+            SceneEdit::Parent { entity, before, after } => {
+                if let Some(entity_index) = self.resolve(*entity) {
+                    self.apply_placement(entity_index, if forward { after } else { before })?;
+                }
+            }
+            SceneEdit::Spawn(subtree) | SceneEdit::Remove(subtree) => {
+                let spawn = matches!(edit, SceneEdit::Spawn(_)) == forward;
+                if spawn {
+                    return self.respawn(subtree)
+                }
+                if let Some(root_index) = self.resolve(subtree.entities[0]) {
+                    self.remove_entity(root_index, true);
+                }
+            }
+        }
+        Ok(HashMap::new())
+    }
+    fn apply_placement(&mut self, entity_index: usize, placement: &Placement) -> Result<(), Box<dyn Error>> {
+        let parent_index = self.resolve(placement.parent).ok_or("the parent entity has been removed")?;
+        self.set_parent(entity_index, parent_index, false)?;
+        let siblings = &mut self.entities[parent_index].children_indices;
+        siblings.retain(|&i| i != entity_index);
+        siblings.insert(placement.sibling_position.min(siblings.len()), entity_index);
+
+        let transform_index = self.entities[entity_index].transform;
+        self.set_transform_field(transform_index, TransformField::Translation, placement.translation);
+        self.set_transform_field(transform_index, TransformField::Rotation, placement.rotation);
+        self.set_transform_field(transform_index, TransformField::Scale, placement.scale);
+        Ok(())
+    }
+    fn respawn(&mut self, subtree: &SavedSubtree) -> Result<HashMap<EntityHandle, EntityHandle>, Box<dyn Error>> {
+        let parent_index = self.resolve(subtree.parent).ok_or("the parent entity has been removed")?;
+        let saved_entities: Vec<&JsonValue> = subtree.json.members().collect();
+        let mapped_entities = self.spawn_saved_entities_mapped(&saved_entities, Some(parent_index))?;
+
+        let root_index = mapped_entities[0].unwrap();
+        let siblings = &mut self.entities[parent_index].children_indices;
+        siblings.retain(|&i| i != root_index);
+        siblings.insert(subtree.sibling_position.min(siblings.len()), root_index);
+
+        Ok(subtree.entities.iter().zip(mapped_entities)
+            .filter_map(|(&old_handle, new_index)| new_index.map(|new_index| (old_handle, self.entity_handle(new_index))))
+            .collect())
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::f32::consts::PI;
+use crate::math::Vector;
+use crate::scene::scene::{Instance, Scene, Transform};
+
+#[derive(Clone, Copy, Debug)]
+pub enum IkTarget {
+    Entity(usize), // followed through the entity's world translation
+    Position(Vector),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IkSolver {
+    TwoBone, // analytic, for a chain of exactly three joints like shoulder, elbow and hand
+    Fabrik,
+    Ccd,
+    LookAt, // turns the joints' aim axis towards the target, shared out over the chain
+}
+impl IkSolver {
+    pub const NAMES: [&'static str; 4] = ["two_bone", "fabrik", "ccd", "look_at"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "two_bone" => Some(IkSolver::TwoBone),
+            "fabrik" => Some(IkSolver::Fabrik),
+            "ccd" => Some(IkSolver::Ccd),
+            "look_at" => Some(IkSolver::LookAt),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            IkSolver::TwoBone => "two_bone",
+            IkSolver::Fabrik => "fabrik",
+            IkSolver::Ccd => "ccd",
+            IkSolver::LookAt => "look_at",
+        }
+    }
+}
+
+///* Poses a chain of joint entities after animations are applied and before skins read the joints, eg. to plant feet on the
+///* ground or make a head track a target. Each joint has to be a child of the previous one, the last joint is the one that
+///* reaches for the target. The solved rotations are blended over the animated pose by weight.
+#[derive(Clone)]
+pub struct IkConstraint {
+    pub solver: IkSolver,
+    pub chain: Vec<usize>,
+    pub target: IkTarget,
+    pub pole: Option<IkTarget>, // two bone: where the middle joint bends towards, by default the way it is already bent
+    pub aim_axis: Vector, // look at: the local axis of the joints that is turned towards the target
+    pub weight: f32,
+    pub enabled: bool,
+    pub iterations: usize, // fabrik and ccd
+    pub tolerance: f32, // fabrik and ccd stop once the last joint is this close to the target
+    pub(crate) applied: Vec<AppliedRotation>,
+}
+
+/// A rotation written to a joint's animated transform, kept to restore the joint's pose before solving again
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AppliedRotation {
+    joint: usize,
+    base: Option<Vector>, // the animated rotation that was replaced, None if the joint was not animated
+    solved: Vector,
+}
+
+impl Scene {
+    pub fn add_ik_constraint(&mut self, solver: IkSolver, chain: Vec<usize>, target: IkTarget) -> Result<usize, Box<dyn Error>> {
+        let valid_length = match solver {
+            IkSolver::TwoBone => chain.len() == 3,
+            IkSolver::Fabrik | IkSolver::Ccd => chain.len() >= 2,
+            IkSolver::LookAt => !chain.is_empty(),
+        };
+        if !valid_length {
+            return Err(format!("a {} chain cannot have {} joints", solver.name(), chain.len()).into())
+        }
+        for (i, &joint) in chain.iter().enumerate() {
+            if joint == 0 || !self.entities.get(joint).is_some_and(|entity| entity.alive) {
+                return Err(format!("entity {} cannot be an IK joint", joint).into())
+            }
+            if i > 0 && self.entities[joint].parent != chain[i - 1] {
+                return Err(format!("IK joint {} is not a child of joint {}", joint, chain[i - 1]).into())
+            }
+        }
+        self.validate_ik_target(&target)?;
+
+        for &joint in chain.iter() {
+            self.ensure_animated_transform(joint);
+        }
+        self.ik_constraints.push(IkConstraint {
+            solver,
+            chain,
+            target,
+            pole: None,
+            aim_axis: Vector::new3(0.0, 0.0, 1.0),
+            weight: 1.0,
+            enabled: true,
+            iterations: 10,
+            tolerance: 0.001,
+            applied: Vec::new(),
+        });
+        Ok(self.ik_constraints.len() - 1)
+    }
+    /// The joints are returned to their animated pose
+    pub fn remove_ik_constraint(&mut self, constraint_index: usize) {
+        self.restore_ik_pose(constraint_index);
+        self.ik_constraints.swap_remove(constraint_index);
+    }
+
+    pub fn validate_ik_target(&self, target: &IkTarget) -> Result<(), Box<dyn Error>> {
+        match *target {
+            IkTarget::Entity(entity_index) if !self.entities.get(entity_index).is_some_and(|entity| entity.alive) => {
+                Err(format!("IK target entity {} does not exist", entity_index).into())
+            }
+            _ => Ok(()),
+        }
+    }
+    fn ik_target_position(&self, target: &IkTarget) -> Vector {
+        match *target {
+            IkTarget::Entity(entity_index) => self.transforms[self.entities[entity_index].transform].world_translation,
+            IkTarget::Position(position) => position,
+        }
+    }
+
+    /// Entities created outside of model imports have no animated transform for IK to write to
+    fn ensure_animated_transform(&mut self, entity_index: usize) {
+        let entity = &self.entities[entity_index];
+        if entity.animated_transform.0 != 0 && entity.animated_transform.0 != entity.transform { return }
+        let transform = &self.transforms[entity.transform];
+        let mut animated_transform = Transform::default();
+        animated_transform.owner = entity_index;
+        animated_transform.local_translation = transform.local_translation;
+        animated_transform.local_rotation = transform.local_rotation;
+        animated_transform.local_scale = transform.local_scale;
+        self.entities[entity_index].animated_transform = (self.transforms.len(), false);
+        self.transforms.push(animated_transform);
+    }
+
+    /// Constraints of a removed entity's chain are removed, targets and poles on it stay where the entity last was
+    pub(crate) fn forget_ik_entity(&mut self, entity_index: usize) {
+        while let Some(constraint_index) = self.ik_constraints.iter().position(|constraint| constraint.chain.contains(&entity_index)) {
+            self.remove_ik_constraint(constraint_index);
+        }
+        let position = self.transforms[self.entities[entity_index].transform].world_translation;
+        for constraint in self.ik_constraints.iter_mut() {
+            for target in std::iter::once(&mut constraint.target).chain(constraint.pole.iter_mut()) {
+                if matches!(target, IkTarget::Entity(target_entity) if *target_entity == entity_index) {
+                    *target = IkTarget::Position(position);
+                }
+            }
+        }
+    }
+
+    /// Copies of the constraints whose whole chain was duplicated, with targets inside the subtree following the copy
+    pub(crate) fn duplicate_ik_constraints(&mut self, mapped_entities: &HashMap<usize, usize>) {
+        let map_target = |target: IkTarget| match target {
+            IkTarget::Entity(entity_index) => IkTarget::Entity(mapped_entities.get(&entity_index).copied().unwrap_or(entity_index)),
+            position => position,
+        };
+        let copies: Vec<IkConstraint> = self.ik_constraints.iter()
+            .filter(|constraint| constraint.chain.iter().all(|joint| mapped_entities.contains_key(joint)))
+            .map(|constraint| IkConstraint {
+                chain: constraint.chain.iter().map(|joint| mapped_entities[joint]).collect(),
+                target: map_target(constraint.target),
+                pole: constraint.pole.map(map_target),
+                applied: Vec::new(),
+                ..constraint.clone()
+            })
+            .collect();
+        for copy in copies {
+            for &joint in copy.chain.iter() {
+                self.ensure_animated_transform(joint);
+            }
+            self.ik_constraints.push(copy);
+        }
+    }
+
+    ///* Puts back the rotations that were replaced by the last solve, so that solving starts from the animated pose again.
+    ///* Joints whose animated rotation has been changed since, eg. by a running animation, are left as they are.
+    pub(crate) fn restore_ik_poses(&mut self) {
+        for constraint_index in 0..self.ik_constraints.len() {
+            self.restore_ik_pose(constraint_index);
+        }
+    }
+    fn restore_ik_pose(&mut self, constraint_index: usize) {
+        for applied in std::mem::take(&mut self.ik_constraints[constraint_index].applied) {
+            let entity = &mut self.entities[applied.joint];
+            let animated_transform = &mut self.transforms[entity.animated_transform.0];
+            if !animated_transform.local_rotation.equals(&applied.solved, 0.0) { continue }
+            match applied.base {
+                Some(rotation) => animated_transform.local_rotation = rotation,
+                None => entity.animated_transform.1 = false,
+            }
+            self.unupdated_entities.push(applied.joint);
+        }
+    }
+
+    ///* Solves every enabled constraint in order and updates the world transforms below each chain, so later constraints
+    ///* and the skins see the solved pose. Called from the scene update once world transforms are up to date.
+    pub(crate) fn solve_ik_constraints(&mut self, frame: usize, dirty_primitive_instance_data: &mut Vec<Instance>) {
+        for constraint_index in 0..self.ik_constraints.len() {
+            let constraint = &self.ik_constraints[constraint_index];
+            if !constraint.enabled || constraint.weight <= 0.0 { continue }
+
+            let target = self.ik_target_position(&constraint.target);
+            let pole = constraint.pole.map(|pole| self.ik_target_position(&pole));
+            let transforms: Vec<&Transform> = constraint.chain.iter().map(|&joint| &self.transforms[self.entities[joint].transform]).collect();
+            let positions: Vec<Vector> = transforms.iter().map(|transform| transform.world_translation).collect();
+            let rotations: Vec<Vector> = transforms.iter().map(|transform| transform.world_rotation).collect();
+
+            let solved_rotations = match constraint.solver {
+                IkSolver::TwoBone => rotations_towards(&positions, &rotations, &two_bone(&positions, target, pole)),
+                IkSolver::Fabrik => rotations_towards(&positions, &rotations, &fabrik(&positions, target, constraint.iterations, constraint.tolerance)),
+                IkSolver::Ccd => rotations_towards(&positions, &rotations, &ccd(&positions, target, constraint.iterations, constraint.tolerance)),
+                IkSolver::LookAt => look_at(&positions, &rotations, target, constraint.aim_axis),
+            };
+
+            let root = constraint.chain[0];
+            let root_parent = self.entities[root].parent;
+            let mut parent_rotation = self.transforms[self.entities[root_parent].transform].world_rotation;
+            let (chain, weight) = (constraint.chain.clone(), constraint.weight.min(1.0));
+            let mut applied = Vec::new();
+            for (&joint, solved_rotation) in chain.iter().zip(solved_rotations) {
+                let local_rotation = parent_rotation.inverse_quat().combine(&solved_rotation).normalize4();
+                parent_rotation = solved_rotation;
+
+                let entity = &mut self.entities[joint];
+                let was_animated = entity.animated_transform.1;
+                if !was_animated {
+                    let (translation, rotation, scale) = {
+                        let transform = &self.transforms[entity.transform];
+                        (transform.local_translation, transform.local_rotation, transform.local_scale)
+                    };
+                    let animated_transform = &mut self.transforms[entity.animated_transform.0];
+                    animated_transform.local_translation = translation;
+                    animated_transform.local_rotation = rotation;
+                    animated_transform.local_scale = scale;
+                    entity.animated_transform.1 = true;
+                }
+                let animated_transform = &mut self.transforms[entity.animated_transform.0];
+                let base = animated_transform.local_rotation;
+                animated_transform.local_rotation = Vector::spherical_lerp(&base, &local_rotation, weight).normalize4();
+                applied.push(AppliedRotation { joint, base: was_animated.then_some(base), solved: animated_transform.local_rotation });
+            }
+            self.ik_constraints[constraint_index].applied = applied;
+
+            let parent_transform = self.entities[root_parent].transform;
+            self.update_entity(frame, parent_transform, root, dirty_primitive_instance_data);
+        }
+    }
+}
+
+/// The shortest rotation that turns `from` to point along `to`
+fn rotation_between(from: &Vector, to: &Vector) -> Vector {
+    if from.magnitude3() < 1e-6 || to.magnitude3() < 1e-6 {
+        return Vector::new()
+    }
+    let (from, to) = (from.normalize3(), to.normalize3());
+    let cosine = from.dot3(&to).clamp(-1.0, 1.0);
+    let axis = from.cross(&to);
+    if axis.magnitude3() < 1e-6 {
+        return if cosine > 0.0 { Vector::new() } else { Vector::axis_angle_quat(&perpendicular(&from), PI) }
+    }
+    Vector::axis_angle_quat(&axis, cosine.acos())
+}
+fn perpendicular(direction: &Vector) -> Vector {
+    let axis = if direction.x.abs() < 0.9 { Vector::new3(1.0, 0.0, 0.0) } else { Vector::new3(0.0, 1.0, 0.0) };
+    axis.cross(direction).normalize3()
+}
+fn direction_or(vector: Vector, fallback: Vector) -> Vector {
+    if vector.magnitude3() < 1e-6 { fallback } else { vector.normalize3() }
+}
+
+///* World rotations that turn each joint of the chain towards the solved position of the next one, starting from the root.
+///* The last joint keeps its rotation relative to its parent.
+fn rotations_towards(positions: &[Vector], rotations: &[Vector], solved: &[Vector]) -> Vec<Vector> {
+    let mut positions = positions.to_vec();
+    let mut rotations = rotations.to_vec();
+    for i in 0..positions.len() - 1 {
+        let delta = rotation_between(&(positions[i + 1] - positions[i]), &(solved[i + 1] - positions[i]));
+        for j in i..positions.len() {
+            if j > i {
+                positions[j] = positions[i] + (positions[j] - positions[i]).rotate_by_quat(&delta);
+            }
+            rotations[j] = delta.combine(&rotations[j]).normalize4();
+        }
+    }
+    rotations
+}
+
+/// Solved positions of a three joint chain, bending in the plane of the target and the pole, or of the current bend
+fn two_bone(positions: &[Vector], target: Vector, pole: Option<Vector>) -> Vec<Vector> {
+    let (root, middle, end) = (positions[0], positions[1], positions[2]);
+    let (upper, lower) = ((middle - root).magnitude3(), (end - middle).magnitude3());
+    let to_target = target - root;
+    if upper < 1e-6 || lower < 1e-6 || to_target.magnitude3() < 1e-6 {
+        return positions.to_vec()
+    }
+    let distance = to_target.magnitude3().clamp((upper - lower).abs() + 1e-4, (upper + lower - 1e-4).max((upper - lower).abs() + 1e-4));
+    let direction = to_target.normalize3();
+
+    let bend_hint = pole.map_or(middle - root, |pole| pole - root);
+    let bend = direction_or(bend_hint - direction * bend_hint.dot3(&direction), perpendicular(&direction));
+    let cosine = ((upper * upper + distance * distance - lower * lower) / (2.0 * upper * distance)).clamp(-1.0, 1.0);
+    let sine = (1.0 - cosine * cosine).sqrt();
+
+    vec![root, root + direction * (upper * cosine) + bend * (upper * sine), root + direction * distance]
+}
+
+/// Forward and backward reaching: moves the end to the target and back to the fixed root until the end is close enough
+fn fabrik(positions: &[Vector], target: Vector, iterations: usize, tolerance: f32) -> Vec<Vector> {
+    let lengths: Vec<f32> = positions.windows(2).map(|pair| (pair[1] - pair[0]).magnitude3()).collect();
+    let (root, end) = (positions[0], positions.len() - 1);
+    let mut solved = positions.to_vec();
+
+    // out of reach, the chain is stretched straight towards the target
+    if (target - root).magnitude3() >= lengths.iter().sum::<f32>() {
+        let direction = direction_or(target - root, Vector::new3(0.0, 1.0, 0.0));
+        for i in 1..solved.len() {
+            solved[i] = solved[i - 1] + direction * lengths[i - 1];
+        }
+        return solved
+    }
+
+    for _ in 0..iterations {
+        if (solved[end] - target).magnitude3() <= tolerance { break }
+        solved[end] = target;
+        for i in (0..end).rev() {
+            let direction = direction_or(solved[i] - solved[i + 1], positions[i] - positions[i + 1]);
+            solved[i] = solved[i + 1] + direction * lengths[i];
+        }
+        solved[0] = root;
+        for i in 1..solved.len() {
+            let direction = direction_or(solved[i] - solved[i - 1], positions[i] - positions[i - 1]);
+            solved[i] = solved[i - 1] + direction * lengths[i - 1];
+        }
+    }
+    solved
+}
+
+/// Cyclic coordinate descent: rotates each joint from the end towards the root so the end points at the target
+fn ccd(positions: &[Vector], target: Vector, iterations: usize, tolerance: f32) -> Vec<Vector> {
+    let end = positions.len() - 1;
+    let mut solved = positions.to_vec();
+    for _ in 0..iterations {
+        if (solved[end] - target).magnitude3() <= tolerance { break }
+        for i in (0..end).rev() {
+            let delta = rotation_between(&(solved[end] - solved[i]), &(target - solved[i]));
+            for j in i + 1..solved.len() {
+                solved[j] = solved[i] + (solved[j] - solved[i]).rotate_by_quat(&delta);
+            }
+        }
+    }
+    solved
+}
+
+/// World rotations that aim the last joint's axis at the target, each joint turning an equal share of what is left
+fn look_at(positions: &[Vector], rotations: &[Vector], target: Vector, aim_axis: Vector) -> Vec<Vector> {
+    let mut positions = positions.to_vec();
+    let mut rotations = rotations.to_vec();
+    let end = positions.len() - 1;
+    for i in 0..positions.len() {
+        let aim = aim_axis.rotate_by_quat(&rotations[end]);
+        let delta = rotation_between(&aim, &(target - positions[end]));
+        let share = Vector::spherical_lerp(&Vector::new(), &delta, 1.0 / (positions.len() - i) as f32).normalize4();
+        for j in i..positions.len() {
+            if j > i {
+                positions[j] = positions[i] + (positions[j] - positions[i]).rotate_by_quat(&share);
+            }
+            rotations[j] = share.combine(&rotations[j]).normalize4();
+        }
+    }
+    rotations
+}
@@ -1,3 +1,16 @@
 pub mod world;
 pub mod physics;
-pub mod scene;
\ No newline at end of file
+pub mod scene;
+pub mod scene_file;
+pub mod snapshot;
+pub mod prefab;
+pub mod query;
+pub mod custom_component;
+pub mod events;
+pub mod animation;
+pub mod animation_controller;
+pub mod ik;
+pub mod spatial_index;
+pub mod history;
+#[cfg(test)]
+mod tests;
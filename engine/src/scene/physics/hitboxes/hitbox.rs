@@ -70,6 +70,23 @@ impl Hitbox {
         None
     }
 
+    /// The box around the hitbox in the body's space
+    pub fn local_min_max(&self) -> (Vector, Vector) {
+        match self {
+            Hitbox::OBB(bounds, _) => (bounds.center - bounds.half_extents, bounds.center + bounds.half_extents),
+            Hitbox::Mesh(mesh) => {
+                let bounds = mesh.bvh.borrow().bounds;
+                (bounds.center - bounds.half_extents, bounds.center + bounds.half_extents)
+            }
+            Hitbox::Capsule(capsule) => (
+                Vector::min(&capsule.a, &capsule.b) - Vector::fill(capsule.radius),
+                Vector::max(&capsule.a, &capsule.b) + Vector::fill(capsule.radius)
+            ),
+            Hitbox::Sphere(sphere) => (sphere.center - Vector::fill(sphere.radius), sphere.center + Vector::fill(sphere.radius)),
+            Hitbox::ConvexHull(convex) => convex.min_max,
+        }
+    }
+
     pub fn get_type(&self) -> HitboxType {
         match self {
             Hitbox::OBB(_, _) => HitboxType::OBB,
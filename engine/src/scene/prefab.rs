@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use json::JsonValue;
+use crate::scene::ik::{IkConstraint, IkTarget};
+use crate::scene::scene::Scene;
+use crate::scene::scene_file::{ik_constraint_to_json, SCENE_FORMAT_VERSION};
+
+///* A saved entity subtree that can be instantiated any number of times.
+///* Stored in the scene file layout, with the root of the subtree as entity 0.
+pub struct Prefab {
+    pub uri: String,
+    entities: JsonValue,
+}
+
+impl Scene {
+    ///* Writes the entity and its descendants to a prefab file, and makes the entity an instance of it.
+    ///* If the prefab was already loaded its cached contents are replaced, existing instances are only updated by resync_prefab.
+    pub fn save_prefab(&mut self, entity_index: usize, path: &str) -> Result<usize, Box<dyn Error>> {
+        if entity_index == 0 {
+            return Err("the root entity cannot be saved as a prefab".into())
+        }
+        if !self.entities.get(entity_index).is_some_and(|e| e.alive) {
+            return Err(format!("entity {} does not exist", entity_index).into())
+        }
+
+        let (_, mut entities) = self.subtree_to_json(entity_index, true);
+        entities[0].remove("prefab");
+
+        let json = json::object! {
+            "version": SCENE_FORMAT_VERSION,
+            "entities": entities.clone(),
+        };
+        fs::write(path, json.pretty(4))?;
+
+        let prefab_index = if let Some(prefab_index) = self.prefabs.iter().position(|prefab| prefab.uri == path) {
+            self.prefabs[prefab_index].entities = entities;
+            prefab_index
+        } else {
+            self.prefabs.push(Prefab { uri: String::from(path), entities });
+            self.prefabs.len() - 1
+        };
+        self.entities[entity_index].prefab = Some(prefab_index);
+        Ok(prefab_index)
+    }
+
+    ///* Saves the entity, and its descendants if recursive, in the scene file layout with parents and children as indices
+    ///* within the returned list. Also returns the scene index of each saved entity, the root first.
+    pub(crate) fn subtree_to_json(&self, entity_index: usize, recursive: bool) -> (Vec<usize>, JsonValue) {
+        let mut subtree = vec![entity_index];
+        let mut i = 0;
+        while recursive && i < subtree.len() {
+            subtree.extend(self.entities[subtree[i]].children_indices.iter().copied());
+            i += 1;
+        }
+        // scene entity index -> index within the subtree
+        let local_indices: HashMap<usize, usize> = subtree.iter().enumerate().map(|(local, &index)| (index, local)).collect();
+
+        let mut entities = JsonValue::new_array();
+        for &index in subtree.iter() {
+            let entity = &self.entities[index];
+            let mut json = self.entity_to_json(index);
+            json["parent"] = local_indices.get(&entity.parent).map_or(JsonValue::Null, |&parent| parent.into());
+            json["children"] = entity.children_indices.iter().filter_map(|child| local_indices.get(child).copied()).collect::<Vec<usize>>().into();
+            match entity.model_node.and_then(|(root, node)| local_indices.get(&root).map(|&root| (root, node))) {
+                Some((root, node)) => json["model_node"] = json::object! { "root": root, "node": node },
+                // the model was imported outside of the subtree, so the node is saved as a plain entity
+                None => { json.remove("model_node"); }
+            }
+            // chains reaching outside of the subtree are dropped, targets outside of it are saved at their current position
+            let localize = |target: &IkTarget| match *target {
+                IkTarget::Entity(target_index) => local_indices.get(&target_index).map_or_else(
+                    || IkTarget::Position(self.transforms[self.entities[target_index].transform].world_translation),
+                    |&local| IkTarget::Entity(local),
+                ),
+                position => position,
+            };
+            let ik_constraints: Vec<JsonValue> = self.ik_constraints.iter()
+                .filter(|constraint| constraint.chain.first() == Some(&index))
+                .filter_map(|constraint| Some(ik_constraint_to_json(&IkConstraint {
+                    chain: constraint.chain.iter().map(|joint| local_indices.get(joint).copied()).collect::<Option<Vec<usize>>>()?,
+                    target: localize(&constraint.target),
+                    pole: constraint.pole.as_ref().map(localize),
+                    ..constraint.clone()
+                })))
+                .collect();
+            if ik_constraints.is_empty() {
+                json.remove("ik_constraints");
+            } else {
+                json["ik_constraints"] = ik_constraints.into();
+            }
+            entities.push(json).unwrap();
+        }
+        (subtree, entities)
+    }
+
+    /// Returns the index of the prefab, reading it from disk the first time it is used
+    pub fn load_prefab(&mut self, uri: &str) -> Result<usize, Box<dyn Error>> {
+        if let Some(prefab_index) = self.prefabs.iter().position(|prefab| prefab.uri == uri) {
+            return Ok(prefab_index)
+        }
+        let entities = read_prefab_file(uri)?;
+        self.prefabs.push(Prefab { uri: String::from(uri), entities });
+        Ok(self.prefabs.len() - 1)
+    }
+
+    /// Creates a new instance of the prefab as a child of the parent, and returns its root entity
+    pub fn instantiate_prefab(&mut self, uri: &str, parent_index: usize) -> Result<usize, Box<dyn Error>> {
+        if !self.entities.get(parent_index).is_some_and(|e| e.alive) {
+            return Err(format!("parent entity {} does not exist", parent_index).into())
+        }
+        let prefab_index = self.load_prefab(uri)?;
+        self.spawn_prefab(prefab_index, parent_index)
+    }
+    fn spawn_prefab(&mut self, prefab_index: usize, parent_index: usize) -> Result<usize, Box<dyn Error>> {
+        let entities = self.prefabs[prefab_index].entities.clone();
+        let saved_entities: Vec<&JsonValue> = entities.members().collect();
+
+        let root_index = self.spawn_saved_entities(&saved_entities, Some(parent_index))?;
+        self.entities[root_index].prefab = Some(prefab_index);
+        Ok(root_index)
+    }
+
+    ///* Re-reads the prefab file and rebuilds every instance of it from the new contents, returning the new root entities.
+    ///* Instances keep their parent, name and local transform, but are recreated, so handles to their entities become invalid.
+    pub fn resync_prefab(&mut self, uri: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+        let entities = read_prefab_file(uri)?;
+        let prefab_index = self.load_prefab(uri)?;
+        self.prefabs[prefab_index].entities = entities;
+
+        let instances: Vec<(usize, u32)> = self.entities.iter().enumerate()
+            .filter(|(_, entity)| entity.alive && entity.prefab == Some(prefab_index))
+            .map(|(index, entity)| (index, entity.generation))
+            .collect();
+
+        let mut new_instances = Vec::new();
+        for (instance_index, generation) in instances {
+            // instances nested in an instance that was already rebuilt are gone
+            if self.get_entity(instance_index, generation).is_none() { continue }
+
+            let instance = &self.entities[instance_index];
+            let parent_index = instance.parent;
+            let name = instance.name.clone();
+            let sibling_position = self.entities[parent_index].children_indices.iter().position(|&i| i == instance_index).unwrap_or(0);
+            let transform = &self.transforms[instance.transform];
+            let (translation, rotation, scale) = (transform.local_translation, transform.local_rotation, transform.local_scale);
+
+            self.remove_entity(instance_index, true);
+            let new_instance_index = self.spawn_prefab(prefab_index, parent_index)?;
+
+            let new_instance = &mut self.entities[new_instance_index];
+            new_instance.name = name;
+            let transform = &mut self.transforms[new_instance.transform];
+            transform.local_translation = translation;
+            transform.local_rotation = rotation;
+            transform.local_scale = scale;
+
+            let siblings = &mut self.entities[parent_index].children_indices;
+            siblings.retain(|&i| i != new_instance_index);
+            siblings.insert(sibling_position.min(siblings.len()), new_instance_index);
+
+            new_instances.push(new_instance_index);
+        }
+        Ok(new_instances)
+    }
+}
+
+fn read_prefab_file(path: &str) -> Result<JsonValue, Box<dyn Error>> {
+    let mut json = json::parse(fs::read_to_string(path)?.as_str())?;
+
+    let version = json["version"].as_u32().ok_or("prefab file has no version")?;
+    if version > SCENE_FORMAT_VERSION {
+        return Err(format!(
+            "prefab file version {} is newer than the supported version {}", version, SCENE_FORMAT_VERSION
+        ).into())
+    }
+    if json["entities"].is_empty() {
+        return Err(format!("prefab file {} has no entities", path).into())
+    }
+
+    Ok(json["entities"].take())
+}
@@ -0,0 +1,195 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use crate::scene::scene::{CameraComponent, Entity, LightComponent, RenderComponent, RigidBodyComponent, Scene, SunComponent, Transform};
+
+///* A component type that entities reference by index, and that can be accessed through Scene::query.
+///* Entities with several components of a type, like render components, are matched with their first one.
+pub trait Component: Sized + 'static {
+    /// The name the component is queried by from Lua
+    const NAME: &'static str;
+
+    fn index_of(entity: &Entity) -> Option<usize>;
+    /// Raw pointer to the component's storage, so different component types can be borrowed mutably at once.
+    /// # Safety
+    /// scene must point to a live scene, and the storage must not be resized while references into it are held.
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self>;
+    /// Called for each component a query handed out mutably, so the change reaches the GPU buffers on the next scene update
+    fn mark_changed(_scene: &mut Scene, _entity_index: usize, _index: usize) {}
+}
+
+impl Component for Transform {
+    const NAME: &'static str = "transform";
+    fn index_of(entity: &Entity) -> Option<usize> { Some(entity.transform) }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).transforms } }
+    fn mark_changed(scene: &mut Scene, entity_index: usize, _index: usize) {
+        scene.unupdated_entities.push(entity_index);
+    }
+}
+impl Component for RenderComponent {
+    const NAME: &'static str = "render_component";
+    fn index_of(entity: &Entity) -> Option<usize> { entity.render_objects.first().copied() }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).render_components } }
+    fn mark_changed(scene: &mut Scene, entity_index: usize, _index: usize) {
+        // the entity's instance data is written for all of its render components when it is updated,
+        // which keeps working if the component is removed before then
+        scene.unupdated_entities.push(entity_index);
+    }
+}
+impl Component for RigidBodyComponent {
+    const NAME: &'static str = "rigid_body";
+    fn index_of(entity: &Entity) -> Option<usize> { entity.rigid_body }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).rigid_body_components } }
+}
+impl Component for CameraComponent {
+    const NAME: &'static str = "camera";
+    fn index_of(entity: &Entity) -> Option<usize> { entity.camera }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).camera_components } }
+    fn mark_changed(scene: &mut Scene, _entity_index: usize, index: usize) {
+        scene.dirty_camera_components.push(index);
+    }
+}
+impl Component for LightComponent {
+    const NAME: &'static str = "light";
+    fn index_of(entity: &Entity) -> Option<usize> { entity.light }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).light_components } }
+    fn mark_changed(scene: &mut Scene, _entity_index: usize, index: usize) {
+        scene.dirty_light_components.push(index);
+    }
+}
+impl Component for SunComponent {
+    const NAME: &'static str = "sun";
+    fn index_of(entity: &Entity) -> Option<usize> { entity.sun }
+    unsafe fn storage(scene: *mut Scene) -> *mut Vec<Self> { unsafe { &raw mut (*scene).sun_components } }
+    fn mark_changed(scene: &mut Scene, _entity_index: usize, index: usize) {
+        scene.dirty_sun_components.push(index);
+    }
+}
+
+/// Component::index_of of a component type
+type IndexOf = fn(&Entity) -> Option<usize>;
+/// Entity index with the indices of its queried components
+pub type QueryMatch = (usize, Vec<usize>);
+
+/// Component names that can be queried without knowing their type, eg. from Lua
+const COMPONENT_LOOKUPS: [(&str, IndexOf); 6] = [
+    (Transform::NAME, Transform::index_of),
+    (RenderComponent::NAME, RenderComponent::index_of),
+    (RigidBodyComponent::NAME, RigidBodyComponent::index_of),
+    (CameraComponent::NAME, CameraComponent::index_of),
+    (LightComponent::NAME, LightComponent::index_of),
+    (SunComponent::NAME, SunComponent::index_of),
+];
+
+/// `&T` or `&mut T` for a component type T
+pub trait QueryParam {
+    type Component: Component;
+    type Item<'a>;
+    const MUTABLE: bool;
+
+    /// # Safety
+    /// storage must be valid for 'a with index in bounds, and a component fetched mutably must not be fetched again while borrowed.
+    unsafe fn fetch<'a>(storage: *mut Vec<Self::Component>, index: usize) -> Self::Item<'a>;
+}
+impl<T: Component> QueryParam for &T {
+    type Component = T;
+    type Item<'a> = &'a T;
+    const MUTABLE: bool = false;
+
+    unsafe fn fetch<'a>(storage: *mut Vec<T>, index: usize) -> &'a T {
+        unsafe { &(&*storage)[index] }
+    }
+}
+impl<T: Component> QueryParam for &mut T {
+    type Component = T;
+    type Item<'a> = &'a mut T;
+    const MUTABLE: bool = true;
+
+    unsafe fn fetch<'a>(storage: *mut Vec<T>, index: usize) -> &'a mut T {
+        unsafe { &mut (&mut *storage)[index] }
+    }
+}
+
+/// A tuple of up to 4 QueryParams, each with a different component type
+pub trait Query {
+    type Item<'a>;
+
+    fn fetch_all(scene: &mut Scene) -> Vec<(usize, Self::Item<'_>)>;
+}
+
+macro_rules! index_type {
+    ($param:ident) => { usize };
+}
+macro_rules! impl_query {
+    ($(($param:ident, $index:ident, $storage:ident)),+) => {
+        impl<$($param: QueryParam),+> Query for ($($param,)+) {
+            type Item<'a> = ($($param::Item<'a>,)+);
+
+            fn fetch_all(scene: &mut Scene) -> Vec<(usize, Self::Item<'_>)> {
+                let types = [$(TypeId::of::<$param::Component>()),+];
+                for (i, component_type) in types.iter().enumerate() {
+                    assert!(!types[..i].contains(component_type), "a query can only access each component type once");
+                }
+
+                let matches: Vec<(usize, ($(index_type!($param),)+))> = scene.entities.iter().enumerate()
+                    .filter(|(_, entity)| entity.alive)
+                    .filter_map(|(entity_index, entity)| Some((entity_index, ($($param::Component::index_of(entity)?,)+))))
+                    .collect();
+
+                for &(entity_index, ($($index,)+)) in matches.iter() {
+                    $(if $param::MUTABLE { $param::Component::mark_changed(scene, entity_index, $index) })+
+                }
+
+                // each entity owns its components, and every param has a different storage, so no component is handed out twice
+                let scene: *mut Scene = scene;
+                $(let $storage = unsafe { $param::Component::storage(scene) };)+
+                matches.into_iter()
+                    .map(|(entity_index, ($($index,)+))| (entity_index, ($(unsafe { $param::fetch($storage, $index) },)+)))
+                    .collect()
+            }
+        }
+    };
+}
+impl_query!((A, a, a_storage));
+impl_query!((A, a, a_storage), (B, b, b_storage));
+impl_query!((A, a, a_storage), (B, b, b_storage), (C, c, c_storage));
+impl_query!((A, a, a_storage), (B, b, b_storage), (C, c, c_storage), (D, d, d_storage));
+
+impl Scene {
+    ///* Every living entity that has all of the queried components, with the components, eg.
+    ///* `for (entity_index, (transform, body)) in scene.query::<(&Transform, &mut RigidBodyComponent)>() { ... }`
+    ///* Components taken by &mut are marked as changed up front, whether or not they are modified.
+    pub fn query<Q: Query>(&mut self) -> Vec<(usize, Q::Item<'_>)> {
+        Q::fetch_all(self)
+    }
+
+    ///* Like query, with components named by their Component::NAME or custom component name, returning component indices
+    ///* in the order of the names.
+    pub fn query_indices(&self, component_names: &[&str]) -> Result<Vec<QueryMatch>, Box<dyn Error>> {
+        let lookups = component_names.iter().map(|&name| {
+            if let Some((_, index_of)) = COMPONENT_LOOKUPS.iter().find(|(lookup_name, _)| *lookup_name == name) {
+                return Ok(Lookup::Builtin(*index_of))
+            }
+            let schema_index = self.component_schema(name).ok_or_else(|| format!("unknown component '{}'", name))?;
+            Ok(Lookup::Custom(self.custom_components.iter().enumerate()
+                .filter(|(_, component)| component.schema == schema_index)
+                .map(|(component_index, component)| (component.owner, component_index))
+                .collect()))
+        }).collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        Ok(self.entities.iter().enumerate()
+            .filter(|(_, entity)| entity.alive)
+            .filter_map(|(entity_index, entity)| {
+                lookups.iter().map(|lookup| match lookup {
+                    Lookup::Builtin(index_of) => index_of(entity),
+                    Lookup::Custom(owned_components) => owned_components.get(&entity_index).copied(),
+                }).collect::<Option<Vec<usize>>>().map(|indices| (entity_index, indices))
+            })
+            .collect())
+    }
+}
+
+enum Lookup {
+    Builtin(IndexOf),
+    Custom(HashMap<usize, usize>), // owner entity -> custom component index
+}
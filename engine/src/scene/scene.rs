@@ -1,9 +1,10 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::error::Error;
+use std::mem::discriminant;
 use std::f32::consts::PI;
 use std::slice;
 use std::sync::Arc;
-use std::time::SystemTime;
 use ash::{vk, Device};
 use ash::vk::{CommandBuffer, ShaderStageFlags};
 use crate::engine::get_command_buffer;
@@ -15,23 +16,45 @@ use crate::render::vulkan_base::{copy_buffer_synchronous, copy_data_to_memory, C
 use crate::scene::physics::hitboxes::bounding_box::BoundingBox;
 use crate::scene::physics::hitboxes::convex_hull::ConvexHull;
 use crate::scene::physics::hitboxes::hitbox::{Hitbox, HitboxType};
+use crate::scene::physics::hitboxes::mesh::MeshCollider;
 use crate::scene::physics::hitboxes::sphere::Sphere;
 use crate::scene::physics::physics_engine::{AxisType, ContactInformation, ContactPoint, PhysicsEngine};
-use crate::scene::world::world::{LightSendable, SunSendable, World};
+use crate::scene::animation_controller::AnimationController;
+use crate::scene::custom_component::{ComponentSchema, CustomComponent};
+use crate::scene::events::SceneEvent;
+use crate::scene::ik::IkConstraint;
+use crate::scene::spatial_index::SpatialIndex;
+use crate::scene::history::History;
+use crate::scene::prefab::Prefab;
+use crate::scene::snapshot::SceneSnapshot;
+use crate::scene::world::gltf_error::GltfError;
+use crate::scene::world::world::{AnimationSampler, GltfCamera, GltfLight, GltfLightType, GltfProjection, LightSendable, SunSendable, World, MAX_MORPH_WEIGHTS};
 
 
 //TODO
 // - EVERYTHING is contained by an entity and is a type of component, including gui elements and rendering pipelines
 
 
+/// Layer 0, which new entities are in
+pub const DEFAULT_LAYERS: u32 = 1;
+
 pub struct Scene {
-    context: Arc<Context>,
+    context: Option<Arc<Context>>, // None for headless scenes
 
     pub runtime: f32,
 
     pub running: bool,
+    pub revert_on_stop: bool,
+    pub(crate) play_snapshot: Option<SceneSnapshot>,
+    pub history: History, // undo and redo of the edits made through the scene API
+
+    pub physics_rate: f32, // fixed physics steps per second
+    pub max_physics_steps: usize, // per update, time past this is dropped so a slow frame cannot snowball
+    pub physics_interpolation: PhysicsInterpolation,
+    pub(crate) physics_accumulator: f32, // simulated time not yet stepped
 
     pub entities: Vec<Entity>, // will always have a root node with sun
+    free_entity_indices: Vec<usize>,
 
     pub unupdated_entities: Vec<usize>,
 
@@ -45,28 +68,62 @@ pub struct Scene {
     pub light_components: Vec<LightComponent>,
     pub sun_components: Vec<SunComponent>,
     pub script_components: Vec<ScriptComponent>,
+    pub custom_components: Vec<CustomComponent>,
+    pub animation_controllers: Vec<AnimationController>,
+    pub ik_constraints: Vec<IkConstraint>,
+
+    pub spatial_index: SpatialIndex, // world bounds of the entities with render components or rigid bodies
+    pub(crate) dirty_bounds: Vec<usize>, // entities to refresh in the spatial index at the end of the update
+
+    pub prefabs: Vec<Prefab>, // loaded prefab assets, kept when the scene is cleared
+    pub component_schemas: Vec<ComponentSchema>, // kept when the scene is cleared
+
+    pub(crate) pending_events: Vec<(SceneEvent, u32)>, // event, generation of its entity
 
     pub outlined_components: Vec<usize>,
     pub outlined_bodies: Vec<usize>,
 
-    pub renderer: Arc<RefCell<Renderer>>,
+    pub renderer: Option<Arc<RefCell<Renderer>>>,
     pub world: Arc<RefCell<World>>,
     pub physics_engine: Arc<RefCell<PhysicsEngine>>,
 
     dirty_render_components: Vec<usize>,
-    dirty_light_components: Vec<usize>,
-    dirty_sun_components: Vec<usize>,
-    dirty_camera_components: Vec<usize>,
+    pub(crate) dirty_light_components: Vec<usize>,
+    pub(crate) dirty_sun_components: Vec<usize>,
+    pub(crate) dirty_camera_components: Vec<usize>,
+    dirty_instance_data: Vec<Instance>, // matches dirty_render_components, waiting for sync_gpu
+    joint_matrices: Vec<Matrix>,
+    morph_weight_data: Vec<f32>, // an offset per render component, -1 without morph targets, followed by the weights
+    dirty_morph_weights: bool, // whether morph_weight_data changed since sync_gpu last uploaded it
 }
 impl Scene {
     pub fn new(context: &Arc<Context>, renderer: Arc<RefCell<Renderer>>, world: Arc<RefCell<World>>, physics_engine: Arc<RefCell<PhysicsEngine>>) -> Self {
+        Self {
+            context: Some(context.clone()),
+            renderer: Some(renderer),
+            ..Self::new_headless(world, physics_engine)
+        }
+    }
+    ///* A scene without a renderer or Vulkan context, for using the scene model without a GPU, eg. in tests.
+    ///* It can be built, edited and stepped with update as usual, but not drawn or synced. The world should be headless too.
+    pub fn new_headless(world: Arc<RefCell<World>>, physics_engine: Arc<RefCell<PhysicsEngine>>) -> Self {
         let mut scene = Self {
-            context: context.clone(),
+            context: None,
+            renderer: None,
 
             runtime: 0.0,
             running: false,
+            revert_on_stop: false,
+            play_snapshot: None,
+            history: History::default(),
+
+            physics_rate: 300.0,
+            max_physics_steps: 20,
+            physics_interpolation: PhysicsInterpolation::Interpolate,
+            physics_accumulator: 0.0,
 
             entities: Vec::new(),
+            free_entity_indices: Vec::new(),
             unupdated_entities: Vec::new(),
             transforms: Vec::new(),
             render_components: Vec::new(),
@@ -78,23 +135,42 @@ impl Scene {
             light_components: Vec::new(),
             sun_components: Vec::new(),
             script_components: Vec::new(),
+            custom_components: Vec::new(),
+            animation_controllers: Vec::new(),
+            ik_constraints: Vec::new(),
+
+            spatial_index: SpatialIndex::new(),
+            dirty_bounds: Vec::new(),
+
+            prefabs: Vec::new(),
+            component_schemas: Vec::new(),
+
+            pending_events: Vec::new(),
 
             outlined_components: Vec::new(),
             outlined_bodies: Vec::new(),
-            renderer,
             world,
             physics_engine,
             dirty_render_components: Vec::new(),
             dirty_light_components: Vec::new(),
             dirty_sun_components: Vec::new(),
             dirty_camera_components: Vec::new(),
+            dirty_instance_data: Vec::new(),
+            joint_matrices: Vec::new(),
+            morph_weight_data: Vec::new(),
+            dirty_morph_weights: false,
         };
-        scene.transforms.push(Transform::default());
-        scene.sun_components.push(SunComponent {
+        scene.add_root_entity();
+
+        scene
+    }
+    fn add_root_entity(&mut self) {
+        self.transforms.push(Transform::default());
+        self.sun_components.push(SunComponent {
             direction: Vector::new3(0.5, 0.0, 1.0).normalize3(),
             color: Vector::fill(1.0)
         });
-        scene.entities.push(Entity {
+        self.entities.push(Entity {
             name: String::from("Scene"),
             transform: 0,
             children_indices: Vec::new(),
@@ -103,76 +179,176 @@ impl Scene {
             sun: Some(0),
             ..Default::default()
         });
+    }
 
-        scene
+    /// Removes every entity and component and the world's models, leaving only the root entity with a default sun
+    pub fn clear(&mut self) {
+        // the world's nodes map to the entities being removed, models are parsed again by whatever is loaded next
+        self.world.borrow_mut().clear();
+        self.clear_entities();
     }
+    /// Like clear, but keeps the world's models so that the same models can be imported again without parsing them
+    pub(crate) fn clear_entities(&mut self) {
+        self.runtime = 0.0;
+        self.play_snapshot = None;
+        self.history = History::default();
+        self.physics_accumulator = 0.0;
 
-    pub fn new_entity_from_model(&mut self, parent_index: usize, uri: &str) -> usize {
-        let model_entity_index = self.entities.len();
-        self.unupdated_entities.push(model_entity_index);
+        self.entities.clear();
+        self.free_entity_indices.clear();
+        self.unupdated_entities.clear();
+        self.transforms.clear();
+        self.render_components.clear();
+        self.skin_components.clear();
+        self.animation_components.clear();
+        self.rigid_body_components.clear();
+        self.hitbox_components.clear();
+        self.camera_components.clear();
+        self.light_components.clear();
+        self.sun_components.clear();
+        self.script_components.clear();
+        self.custom_components.clear();
+        self.animation_controllers.clear();
+        self.ik_constraints.clear();
+        self.spatial_index.clear();
+        self.dirty_bounds.clear();
+
+        // events about the old entities would refer to indices that are about to be reused
+        self.pending_events.clear();
+
+        self.outlined_components.clear();
+        self.outlined_bodies.clear();
+
+        self.dirty_render_components.clear();
+        self.dirty_light_components.clear();
+        self.dirty_sun_components.clear();
+        self.dirty_camera_components.clear();
+        self.dirty_instance_data.clear();
+
+        self.add_root_entity();
+        self.unupdated_entities.push(0);
+    }
+
+    ///* Puts the entity in the slot of a removed entity if there is one, bumping nothing but reusing its generation.
+    ///* Every entity is created through here, so this is where EntityAdded is emitted.
+    fn allocate_entity(&mut self, mut entity: Entity) -> usize {
+        let index = if let Some(index) = self.free_entity_indices.pop() {
+            entity.generation = self.entities[index].generation;
+            self.entities[index] = entity;
+            index
+        } else {
+            self.entities.push(entity);
+            self.entities.len() - 1
+        };
+        self.emit(SceneEvent::EntityAdded { entity: index });
+        index
+    }
+
+    pub fn new_entity(&mut self, parent_index: usize, name: &str) -> usize {
+        let transform_index = self.transforms.len();
+        let entity_index = self.allocate_entity(Entity {
+            name: String::from(name),
+            transform: transform_index,
+            parent: parent_index,
+            ..Default::default()
+        });
+        self.entities[parent_index].children_indices.push(entity_index);
+
+        self.transforms.push(Transform {
+            owner: entity_index,
+            ..Default::default()
+        });
+        self.unupdated_entities.push(entity_index);
+
+        entity_index
+    }
 
-        let (new_nodes, new_skins, new_animations) = {
-            let world = &mut self.world.borrow_mut();
+    ///* Imports a .gltf or .glb model as an entity under parent_index, with an entity for each of its nodes.
+    ///* Nothing is added to the scene or world if the model fails to load.
+    pub fn new_entity_from_model(&mut self, parent_index: usize, uri: &str) -> Result<usize, GltfError> {
+        let world_ref = self.world.clone();
+        let (model_entity_index, new_nodes, new_skins, new_animations, first_node_index, skin_offset) = {
+            let world = &mut world_ref.borrow_mut();
 
-            let model_index = unsafe { world.add_model(uri) };
+            let model_index = unsafe { world.add_model(uri)? };
 
             let new_model = &world.models[model_index];
 
             let entity_transform_index = self.transforms.len();
-            let mut model_transform = Transform::default();
-            model_transform.owner = model_entity_index;
-            self.transforms.push(model_transform);
-            self.entities[parent_index].children_indices.push(model_entity_index);
-            self.entities.push(Entity {
+            let model_entity_index = self.allocate_entity(Entity {
                 name: String::from(uri),
                 transform: entity_transform_index,
                 parent: parent_index,
-                animation_objects: new_model.animations.iter().map(|&i| i + self.animation_components.len()).collect(),
+                model_uri: Some(String::from(uri)),
+                animation_objects: (0..new_model.animations.len()).map(|i| i + self.animation_components.len()).collect(),
                 ..Default::default()
             });
+            self.entities[parent_index].children_indices.push(model_entity_index);
+            let mut model_transform = Transform::default();
+            model_transform.owner = model_entity_index;
+            self.transforms.push(model_transform);
+
+            // render components index the scene's skins, which are offset from the world's
+            let skin_offset = self.skin_components.len() as i32 - new_model.skins.first().copied().unwrap_or(0) as i32;
 
-            (world.scenes[new_model.scene].nodes.clone(), new_model.skins.clone(), new_model.animations.clone())
+            (model_entity_index, world.scenes[new_model.scene].nodes.clone(), new_model.skins.clone(), new_model.animations.clone(), new_model.nodes.first().copied().unwrap_or(0), skin_offset)
         };
+        self.unupdated_entities.push(model_entity_index);
 
         for node_index in new_nodes {
-            self.implement_world_node(node_index, model_entity_index);
+            self.implement_world_node(node_index, model_entity_index, (model_entity_index, first_node_index), skin_offset);
         }
         let world = &self.world.borrow();
         for skin_index in new_skins {
             let skin = &world.skins[skin_index];
             let mapped_joint_indices = skin.joint_indices.iter().map(|&i| world.nodes[i].mapped_entity_index).collect::<Vec<usize>>();
+            let owner = world.nodes.iter()
+                .find(|node| node.skin == Some(skin_index as i32))
+                .map_or(model_entity_index, |node| node.mapped_entity_index);
             self.skin_components.push(SkinComponent {
+                owner,
                 joints: mapped_joint_indices,
                 inverse_bind_matrices: skin.inverse_bind_matrices.clone(),
             });
         }
         for animation_index in new_animations {
             let animation = &world.animations[animation_index];
-            self.animation_components.push(AnimationComponent {
-                owner_entity: model_entity_index,
-                channels: animation.channels.iter().map(|c| (c.0, world.nodes[c.1].mapped_entity_index, c.2.clone())).collect(),
-                samplers: animation.samplers.clone(),
-                start_time: SystemTime::now(),
-                duration: animation.duration,
-                running: animation.running,
-                repeat: animation.snap_back,
-                snap_back: animation.snap_back,
-            });
+            let mut animation_component = AnimationComponent::new(
+                model_entity_index,
+                animation.name.clone(),
+                animation.channels.iter().map(|c| (c.0, world.nodes[c.1].mapped_entity_index, c.2.clone())).collect(),
+                animation.samplers.clone(),
+                animation.duration,
+            );
+            animation_component.running = animation.running;
+            animation_component.repeat = animation.snap_back;
+            animation_component.snap_back = animation.snap_back;
+            self.animation_components.push(animation_component);
         }
 
-        model_entity_index
+        Ok(model_entity_index)
     }
-    fn implement_world_node(&mut self, node_index: usize, parent_index: usize) {
-        let node_entity_index = self.entities.len();
-        self.entities[parent_index].children_indices.push(node_entity_index);
-
-        let child_nodes = {
+    /// model is the entity the model was imported as, and the index of the model's first world node
+    fn implement_world_node(&mut self, node_index: usize, parent_index: usize, model: (usize, usize), skin_offset: i32) {
+        let world_ref = self.world.clone();
+        let (node_entity_index, child_nodes) = {
 
-            let world = &mut self.world.borrow_mut();
-            world.nodes[node_index].mapped_entity_index = self.entities.len();
+            let world = &mut world_ref.borrow_mut();
             let node = &world.nodes[node_index];
 
             let node_transform_index = self.transforms.len();
+            let node_anim_transform_index = node_transform_index + 1;
+            let node_entity_index = self.allocate_entity(Entity {
+                name: node.name.clone(),
+                transform: node_transform_index,
+                animated_transform: (node_anim_transform_index, false),
+                parent: parent_index,
+                model_node: Some((model.0, node_index - model.1)),
+                morph_weights: node.weights.clone(),
+                ..Default::default()
+            });
+            self.entities[parent_index].children_indices.push(node_entity_index);
+
             self.transforms.push(Transform {
                 owner: node_entity_index,
                 local_translation: node.translation,
@@ -180,8 +356,6 @@ impl Scene {
                 local_scale: node.scale,
                 ..Default::default()
             });
-
-            let node_anim_transform_index = self.transforms.len();
             self.transforms.push(Transform {
                 owner: node_entity_index,
                 local_translation: node.translation,
@@ -190,14 +364,6 @@ impl Scene {
                 ..Default::default()
             });
 
-            self.entities.push(Entity {
-                name: node.name.clone(),
-                transform: node_transform_index,
-                animated_transform: (node_anim_transform_index, false),
-                parent: parent_index,
-                ..Default::default()
-            });
-
             if let Some(mesh_index) = node.mesh {
                 let entity = &mut self.entities[node_entity_index];
                 for (i, primitive) in world.meshes[mesh_index].primitives.iter().enumerate() {
@@ -210,57 +376,58 @@ impl Scene {
                     let render_component_index = self.render_components.len();
                     self.render_components.push(RenderComponent {
                         mesh_primitive_index: (mesh_index, i),
-                        skin_index: node.skin,
+                        skin_index: node.skin.map(|skin| skin + skin_offset),
                         material_index: primitive.material_index as usize,
                         transform: render_component_transform_index,
+                        morph_weights: node.weights.clone(),
                     });
 
                     entity.render_objects.push(render_component_index);
                 }
             }
-            node.children_indices.clone()
+            if let Some(light_index) = node.light {
+                self.attach_light(node_entity_index, LightComponent::from_gltf(&world.lights[light_index]));
+            }
+            if let Some(camera_index) = node.camera {
+                self.attach_camera(node_entity_index, CameraComponent::from_gltf(&world.cameras[camera_index]));
+            }
+            let child_nodes = node.children_indices.clone();
+            world.nodes[node_index].mapped_entity_index = node_entity_index;
+            (node_entity_index, child_nodes)
         };
         for child_node_index in child_nodes {
-            self.implement_world_node(child_node_index, node_entity_index);
+            self.implement_world_node(child_node_index, node_entity_index, model, skin_offset);
         }
     }
 
-    pub fn add_light(&mut self, mut light: LightComponent, parent_index: usize) -> usize {
+    pub fn add_light(&mut self, light: LightComponent, parent_index: usize) -> usize {
+        let owner_index = self.new_entity(parent_index, &format!("LightEntity {}", self.light_components.len()));
+        self.attach_light(owner_index, light)
+    }
+    pub fn attach_light(&mut self, entity_index: usize, mut light: LightComponent) -> usize {
         let index = self.light_components.len();
 
-        let owner_index = self.entities.len();
-        self.entities[parent_index].children_indices.push(owner_index);
-        let mut entity = Entity::default();
-        entity.name = String::from(format!("LightEntity {}", index));
-        entity.transform = self.transforms.len();
-        entity.camera = Some(index);
-        self.entities.push(entity);
-        light.owner = owner_index;
-
-        let transform_index = self.transforms.len();
-        self.transforms.push(Transform::default());
-        light.transform = transform_index;
+        let entity = &mut self.entities[entity_index];
+        entity.light = Some(index);
+        light.owner = entity_index;
+        light.transform = entity.transform;
 
         self.dirty_light_components.push(index);
         self.light_components.push(light);
         index
     }
 
-    pub fn add_camera(&mut self, mut camera: CameraComponent, parent_index: usize) -> usize {
+    pub fn add_camera(&mut self, camera: CameraComponent, parent_index: usize) -> usize {
+        let owner_index = self.new_entity(parent_index, &format!("CameraEntity {}", self.camera_components.len()));
+        self.attach_camera(owner_index, camera)
+    }
+    pub fn attach_camera(&mut self, entity_index: usize, mut camera: CameraComponent) -> usize {
         let index = self.camera_components.len();
 
-        let owner_index = self.entities.len();
-        self.entities[parent_index].children_indices.push(owner_index);
-        let mut entity = Entity::default();
-        entity.name = String::from(format!("CameraEntity {}", index));
-        entity.transform = self.transforms.len();
+        let entity = &mut self.entities[entity_index];
         entity.camera = Some(index);
-        self.entities.push(entity);
-        camera.owner = owner_index;
-
-        let transform_index = self.transforms.len();
-        self.transforms.push(Transform::default());
-        camera.transform = transform_index;
+        camera.owner = entity_index;
+        camera.transform = entity.transform;
 
         self.dirty_camera_components.push(index);
         self.camera_components.push(camera);
@@ -270,32 +437,15 @@ impl Scene {
     pub fn add_rigid_body_from_entity(&mut self, entity_index: usize, hitbox_type: usize, is_static: bool) {
         assert!(hitbox_type < 5);
 
-        let entity = &mut self.entities[entity_index];
-
-        if !entity.render_objects.is_empty() {
-            let render_component = &self.render_components[entity.render_objects[0]];
-            let mesh = &self.world.borrow().meshes[render_component.mesh_primitive_index.0];
-
-            entity.rigid_body = Some(self.rigid_body_components.len());
+        if !self.entities[entity_index].render_objects.is_empty() {
+            let render_component = &self.render_components[self.entities[entity_index].render_objects[0]];
+            let (min, max) = self.world.borrow().meshes[render_component.mesh_primitive_index.0].get_min_max();
 
             let transform = &self.transforms[render_component.transform].world;
             let scale = transform.extract_scale();
-            let (min, max) = mesh.get_min_max();
             let half_extent = (max - min) * 0.5 * scale;
 
-            let mut body = RigidBodyComponent::default();
-            body.owner = entity_index;
-            body.transform = entity.transform;
-            let transform = &self.transforms[entity.transform];
-            body.x_f = transform.world_translation;
-            body.q_f = transform.world_rotation;
-            body.hitbox = self.hitbox_components.len();
-            body.stored_hitbox_scale = scale;
-
-            self.rigid_body_components.push(body);
-            let body = &mut self.rigid_body_components[entity.rigid_body.unwrap()];
-
-            self.hitbox_components.push(HitboxComponent { hitbox: match hitbox_type {
+            let hitbox = match hitbox_type {
                 0 => {
                     let bounds = BoundingBox {
                         center: (min + max) * scale * 0.5,
@@ -310,75 +460,643 @@ impl Scene {
                     })
                 }
                 _ => unreachable!()
-            }});
+            };
 
-            body.set_static(&self.hitbox_components[body.hitbox].hitbox, &self.transforms, is_static);
-            body.set_mass(&self.hitbox_components[body.hitbox].hitbox, &self.transforms, 1.0);
+            let body_index = self.attach_rigid_body(entity_index, hitbox, is_static);
+            self.rigid_body_components[body_index].stored_hitbox_scale = scale;
             //body.angular_velocity = Vector::new3(0.0, 1.0, 0.0);
         }
-        for child_index in entity.children_indices.clone() {
+        for child_index in self.entities[entity_index].children_indices.clone() {
             self.add_rigid_body_from_entity(child_index, hitbox_type, is_static);
         }
     }
+    pub fn attach_rigid_body(&mut self, entity_index: usize, hitbox: Hitbox, is_static: bool) -> usize {
+        let index = self.rigid_body_components.len();
 
-    pub fn update_physics_objects(&mut self, delta_time: f32) {
-        let gravity = Vector::new3(0.0, -9.8, 0.0);
-        let iter = 5;
-        let dt = delta_time / iter as f32;
+        let entity = &mut self.entities[entity_index];
+        entity.rigid_body = Some(index);
+
+        let mut body = RigidBodyComponent::default();
+        body.owner = entity_index;
+        body.transform = entity.transform;
+        let transform = &self.transforms[entity.transform];
+        body.x_f = transform.world_translation;
+        body.q_f = transform.world_rotation;
+        body.x_i = body.x_f;
+        body.q_i = body.q_f;
+        body.hitbox = self.hitbox_components.len();
+
+        self.hitbox_components.push(HitboxComponent { hitbox });
+
+        body.set_static(&self.hitbox_components[body.hitbox].hitbox, &self.transforms, is_static);
+        body.set_mass(&self.hitbox_components[body.hitbox].hitbox, &self.transforms, 1.0);
+
+        self.rigid_body_components.push(body);
+        self.dirty_bounds.push(entity_index);
+        self.emit(SceneEvent::RigidBodyAttached { entity: entity_index, rigid_body: index });
+        index
+    }
+
+    /// Returns the entity if it is alive and has not been removed since a handle with this generation was made
+    pub fn get_entity(&self, entity_index: usize, generation: u32) -> Option<&Entity> {
+        self.entities.get(entity_index).filter(|entity| entity.alive && entity.generation == generation)
+    }
+
+    /// The first living entity with the name, in index order
+    pub fn find_by_name(&self, name: &str) -> Option<usize> {
+        self.entities.iter().position(|entity| entity.alive && entity.name == name)
+    }
+    ///* Finds an entity by the names along its hierarchy, starting with the root entity, eg. "Scene/ffocks/Armature/Head".
+    ///* Names that contain slashes themselves, like those of imported models, are matched whole.
+    pub fn find_by_path(&self, path: &str) -> Option<usize> {
+        let root_name = self.entities[0].name.as_str();
+        if path == root_name {
+            return Some(0)
+        }
+        self.find_child_by_path(0, path.strip_prefix(root_name)?.strip_prefix('/')?)
+    }
+    /// Like find_by_path, with the path relative to the entity
+    pub fn find_child_by_path(&self, entity_index: usize, path: &str) -> Option<usize> {
+        for &child_index in self.entities[entity_index].children_indices.iter() {
+            let child_name = self.entities[child_index].name.as_str();
+            if path == child_name {
+                return Some(child_index)
+            }
+            if let Some(rest) = path.strip_prefix(child_name).and_then(|rest| rest.strip_prefix('/'))
+                && let Some(found) = self.find_child_by_path(child_index, rest)
+            {
+                return Some(found)
+            }
+        }
+        None
+    }
+    pub fn entities_with_tag(&self, tag: &str) -> Vec<usize> {
+        (0..self.entities.len()).filter(|&i| self.entities[i].alive && self.entities[i].has_tag(tag)).collect()
+    }
+    /// Entities in any of the layers of the mask
+    pub fn entities_in_layers(&self, layer_mask: u32) -> Vec<usize> {
+        (0..self.entities.len()).filter(|&i| self.entities[i].alive && self.entities[i].in_layers(layer_mask)).collect()
+    }
 
-        for _ in 0..iter {
-            // integrate
-            for body in &mut self.rigid_body_components {
-                body.integrate(dt, &gravity)
+    ///* Moves the entity under a new parent.
+    ///* If keep_world_transform its local transform is recomputed so that it stays where it is in the world,
+    ///* otherwise the local transform is kept and the entity moves with its new parent.
+    pub fn set_parent(&mut self, entity_index: usize, new_parent_index: usize, keep_world_transform: bool) -> Result<(), Box<dyn Error>> {
+        if entity_index == 0 {
+            return Err("the root entity cannot be reparented".into())
+        }
+        if !self.entities.get(entity_index).is_some_and(|e| e.alive) {
+            return Err(format!("entity {} does not exist", entity_index).into())
+        }
+        if !self.entities.get(new_parent_index).is_some_and(|e| e.alive) {
+            return Err(format!("parent entity {} does not exist", new_parent_index).into())
+        }
+        let mut ancestor_index = new_parent_index;
+        loop {
+            if ancestor_index == entity_index {
+                return Err(format!(
+                    "cannot parent entity {} to {}, it is one of its own descendants", entity_index, new_parent_index
+                ).into())
             }
+            if ancestor_index == 0 { break }
+            ancestor_index = self.entities[ancestor_index].parent;
+        }
 
-            // collision constraints
-            let num_bodies = self.rigid_body_components.len();
-            let mut collision_constraints = Vec::new();
-            for i in 0..num_bodies {
-                let body_a = &self.rigid_body_components[i];
-                for j in i + 1..num_bodies {
-                    let body_b = &self.rigid_body_components[j];
+        let old_parent_index = self.entities[entity_index].parent;
+        if old_parent_index == new_parent_index { return Ok(()) }
+        self.entities[old_parent_index].children_indices.retain(|&i| i != entity_index);
+        self.entities[new_parent_index].children_indices.push(entity_index);
+        self.entities[entity_index].parent = new_parent_index;
+
+        let transform_index = self.entities[entity_index].transform;
+        let parent_transform_index = self.entities[new_parent_index].transform;
+        let [transform, parent_transform] = self.transforms.get_disjoint_mut([transform_index, parent_transform_index]).unwrap();
+        if keep_world_transform {
+            (transform.local_translation, transform.local_rotation, transform.local_scale) = world_transform_to_local(transform, parent_transform);
+        } else if let Some(body_index) = self.entities[entity_index].rigid_body {
+            // bodies are simulated in world space, so they have to be moved along with the entity
+            let body = &mut self.rigid_body_components[body_index];
+            body.x_f = parent_transform.world_translation +
+                (transform.local_translation * parent_transform.world_scale).rotate_by_quat(&parent_transform.world_rotation);
+            body.q_f = parent_transform.world_rotation.combine(&transform.local_rotation);
+            body.x_i = body.x_f;
+            body.q_i = body.q_f;
+        }
 
-                    if (body_a.is_static && body_b.is_static) || body_a.owned_by_player || body_b.owned_by_player {
-                        continue;
-                    }
-                    if let Some(collision) = body_a.will_collide_with(&self.hitbox_components, body_b, 0.0) {
-                        if collision.contact_points.is_empty() { continue }
-                        let normal = collision.normal;
-                        let depth = collision.time_of_impact;
-                        let pt_on_a = collision.contact_points[0].point_on_a;
-                        let pt_on_b = collision.contact_points[0].point_on_b;
-
-                        collision_constraints.push(CollisionConstraint {
-                            body_a: i,
-                            body_b: j,
-                            penetration: depth,
-                            normal,
-                            pt_on_a,
-                            pt_on_b,
-                        })
+        self.unupdated_entities.push(entity_index);
+        self.emit(SceneEvent::EntityReparented { entity: entity_index, old_parent: old_parent_index, new_parent: new_parent_index });
+        Ok(())
+    }
+
+    ///* Deep-copies the entity and its descendants with all of their components, the copy is added next to the original.
+    ///* Render components share the original's world meshes, skins and animations are remapped to the copied entities.
+    pub fn duplicate_entity(&mut self, entity_index: usize) -> usize {
+        assert_ne!(entity_index, 0, "The root entity cannot be duplicated");
+        assert!(self.entities[entity_index].alive, "Entity {} has been removed", entity_index);
+
+        // original entity index -> copy
+        let mut mapped_entities = HashMap::new();
+        let parent_index = self.entities[entity_index].parent;
+        let copy_index = self.duplicate_entity_recursive(entity_index, parent_index, &mut mapped_entities);
+
+        let mut copied_entities: Vec<(usize, usize)> = mapped_entities.iter().map(|(&original, &copy)| (original, copy)).collect();
+        copied_entities.sort();
+
+        // skins, animations and IK constraints can reference any entity of the subtree, so they are copied once all entities exist
+        let mut mapped_skins: HashMap<i32, i32> = HashMap::new();
+        for &(_, copy) in copied_entities.iter() {
+            for render_component_index in self.entities[copy].render_objects.clone() {
+                let Some(skin_index) = self.render_components[render_component_index].skin_index else { continue };
+                let copied_skin_index = if let Some(&copied_skin_index) = mapped_skins.get(&skin_index) {
+                    copied_skin_index
+                } else {
+                    let mut skin = self.skin_components[skin_index as usize].clone();
+                    skin.owner = mapped_entities.get(&skin.owner).copied().unwrap_or(copy);
+                    for joint in skin.joints.iter_mut() {
+                        *joint = mapped_entities.get(joint).copied().unwrap_or(*joint);
                     }
+                    self.skin_components.push(skin);
+                    let copied_skin_index = self.skin_components.len() as i32 - 1;
+                    mapped_skins.insert(skin_index, copied_skin_index);
+                    copied_skin_index
+                };
+                self.render_components[render_component_index].skin_index = Some(copied_skin_index);
+            }
+        }
+        for &(original, copy) in copied_entities.iter() {
+            for animation_index in self.entities[original].animation_objects.clone() {
+                let mut animation = self.animation_components[animation_index].clone();
+                animation.owner_entity = copy;
+                // channels targeting entities outside of the subtree would move the original
+                animation.channels = animation.channels.into_iter()
+                    .filter_map(|channel| mapped_entities.get(&channel.1).map(|&target| (channel.0, target, channel.2)))
+                    .collect();
+                animation.mask = animation.mask.map(|mask| mask.iter().filter_map(|masked| mapped_entities.get(masked).copied()).collect());
+                self.entities[copy].animation_objects.push(self.animation_components.len());
+                self.animation_components.push(animation);
+            }
+            if let Some((root, node)) = self.entities[original].model_node {
+                self.entities[copy].model_node = mapped_entities.get(&root).map(|&copied_root| (copied_root, node));
+            }
+        }
+        self.duplicate_ik_constraints(&mapped_entities);
+
+        copy_index
+    }
+    fn duplicate_entity_recursive(&mut self, entity_index: usize, parent_index: usize, mapped_entities: &mut HashMap<usize, usize>) -> usize {
+        let name = self.entities[entity_index].name.clone();
+        let copy_index = self.new_entity(parent_index, &name);
+        mapped_entities.insert(entity_index, copy_index);
+
+        let original = &self.entities[entity_index];
+        let (transform_index, animated_transform) = (original.transform, original.animated_transform);
+        let copy_transform_index = self.entities[copy_index].transform;
+        self.copy_local_transform(transform_index, copy_transform_index);
+        if animated_transform.0 != 0 && animated_transform.0 != transform_index {
+            let copy_animated_transform_index = self.transforms.len();
+            self.transforms.push(Transform {
+                owner: copy_index,
+                ..Default::default()
+            });
+            self.copy_local_transform(animated_transform.0, copy_animated_transform_index);
+            self.entities[copy_index].animated_transform = (copy_animated_transform_index, animated_transform.1);
+        }
+        let original = &self.entities[entity_index];
+        let (model_uri, prefab, tags, layers) = (original.model_uri.clone(), original.prefab, original.tags.clone(), original.layers);
+        let (morph_weights, animated_morph_weights) = (original.morph_weights.clone(), original.animated_morph_weights.clone());
+        let copy = &mut self.entities[copy_index];
+        copy.model_uri = model_uri;
+        copy.prefab = prefab;
+        copy.tags = tags;
+        copy.layers = layers;
+        copy.morph_weights = morph_weights;
+        copy.animated_morph_weights = animated_morph_weights;
+
+        for render_component_index in self.entities[entity_index].render_objects.clone() {
+            let render_component = &self.render_components[render_component_index];
+            let (original_transform_index, copy_transform_index) = (render_component.transform, self.transforms.len());
+            let copy = RenderComponent {
+                mesh_primitive_index: render_component.mesh_primitive_index,
+                skin_index: render_component.skin_index,
+                material_index: render_component.material_index,
+                transform: copy_transform_index,
+                morph_weights: render_component.morph_weights.clone(),
+            };
+            self.transforms.push(Transform {
+                owner: copy_index,
+                ..Default::default()
+            });
+            self.copy_local_transform(original_transform_index, copy_transform_index);
+            self.entities[copy_index].render_objects.push(self.render_components.len());
+            self.render_components.push(copy);
+        }
+
+        if let Some(body_index) = self.entities[entity_index].rigid_body {
+            let body = &self.rigid_body_components[body_index];
+            let hitbox = match &self.hitbox_components[body.hitbox].hitbox {
+                Hitbox::OBB(bounds, convex) => Hitbox::OBB(*bounds, convex.clone()),
+                Hitbox::Sphere(sphere) => Hitbox::Sphere(*sphere),
+                Hitbox::Capsule(capsule) => Hitbox::Capsule(*capsule),
+                Hitbox::ConvexHull(convex) => Hitbox::ConvexHull(convex.clone()),
+                // a fresh bvh, since rescaling one mesh collider would otherwise rescale the other
+                Hitbox::Mesh(collider) => {
+                    let mesh_index = self.render_components[self.entities[entity_index].render_objects[0]].mesh_primitive_index.0;
+                    Hitbox::Mesh(MeshCollider::new(&self.world.borrow().meshes[mesh_index], collider.current_scale_factor))
                 }
+            };
+            let (is_static, owned_by_player, mass, restitution_coefficient, friction_coefficient, velocity, angular_velocity, stored_hitbox_scale) = (
+                body.is_static, body.owned_by_player, body.mass, body.restitution_coefficient, body.friction_coefficient,
+                body.velocity, body.angular_velocity, body.stored_hitbox_scale
+            );
+            let copy_body_index = self.attach_rigid_body(copy_index, hitbox, is_static);
+            let copy_body = &mut self.rigid_body_components[copy_body_index];
+            copy_body.owned_by_player = owned_by_player;
+            copy_body.restitution_coefficient = restitution_coefficient;
+            copy_body.friction_coefficient = friction_coefficient;
+            copy_body.velocity = velocity;
+            copy_body.angular_velocity = angular_velocity;
+            copy_body.stored_hitbox_scale = stored_hitbox_scale;
+            copy_body.set_mass(&self.hitbox_components[copy_body.hitbox].hitbox, &self.transforms, mass);
+        }
+        if let Some(camera_index) = self.entities[entity_index].camera {
+            self.attach_camera(copy_index, self.camera_components[camera_index].clone());
+        }
+        if let Some(light_index) = self.entities[entity_index].light {
+            self.attach_light(copy_index, self.light_components[light_index].clone());
+        }
+        let scripts: Vec<ScriptComponent> = self.script_components.iter()
+            .filter(|script| script.owner == entity_index)
+            .map(|script| ScriptComponent { owner: copy_index, instance: None, ..script.clone() })
+            .collect();
+        self.script_components.extend(scripts);
+        let custom_components: Vec<CustomComponent> = self.custom_components.iter()
+            .filter(|component| component.owner == entity_index)
+            .map(|component| CustomComponent { owner: copy_index, ..component.clone() })
+            .collect();
+        self.custom_components.extend(custom_components);
+        if let Some(controller_index) = self.get_animation_controller(entity_index) {
+            let controller = AnimationController { owner: copy_index, ..self.animation_controllers[controller_index].clone() };
+            self.animation_controllers.push(controller);
+        }
+
+        for child_index in self.entities[entity_index].children_indices.clone() {
+            self.duplicate_entity_recursive(child_index, copy_index, mapped_entities);
+        }
+
+        copy_index
+    }
+    fn copy_local_transform(&mut self, from: usize, to: usize) {
+        let (translation, rotation, scale) = {
+            let from = &self.transforms[from];
+            (from.local_translation, from.local_rotation, from.local_scale)
+        };
+        let to = &mut self.transforms[to];
+        to.local_translation = translation;
+        to.local_rotation = rotation;
+        to.local_scale = scale;
+    }
+
+    ///* Removes the entity and all of its components.
+    ///* If recursive its descendants are removed too, otherwise they are moved to the entity's parent.
+    ///* The index is reused by the next created entity, with a new generation.
+    pub fn remove_entity(&mut self, entity_index: usize, recursive: bool) {
+        assert_ne!(entity_index, 0, "The root entity cannot be removed");
+        assert!(self.entities[entity_index].alive, "Entity {} has already been removed", entity_index);
+
+        let parent_index = self.entities[entity_index].parent;
+        for child_index in self.entities[entity_index].children_indices.clone() {
+            if recursive {
+                self.remove_entity(child_index, true);
+            } else {
+                // the children keep their place in the world instead of jumping by the removed entity's transform
+                self.set_parent(child_index, parent_index, true).expect("a child can always be moved to its grandparent");
             }
-            for constraint in collision_constraints {
-                constraint.solve(dt, &mut self.rigid_body_components)
+        }
+        self.entities[parent_index].children_indices.retain(|&i| i != entity_index);
+
+        while let Some(&render_component_index) = self.entities[entity_index].render_objects.last() {
+            self.remove_render_component(render_component_index);
+        }
+        while let Some(skin_index) = self.skin_components.iter().position(|skin| skin.owner == entity_index) {
+            self.remove_skin(skin_index);
+        }
+        while let Some(&animation_index) = self.entities[entity_index].animation_objects.last() {
+            self.remove_animation(animation_index);
+        }
+        if let Some(body_index) = self.entities[entity_index].rigid_body {
+            self.remove_rigid_body(body_index);
+        }
+        if let Some(camera_index) = self.entities[entity_index].camera {
+            self.remove_camera(camera_index);
+        }
+        if let Some(light_index) = self.entities[entity_index].light {
+            self.remove_light(light_index);
+        }
+        while let Some(script_index) = self.script_components.iter().position(|script| script.owner == entity_index) {
+            self.remove_script_component(script_index);
+        }
+        while let Some(component_index) = self.custom_components.iter().position(|component| component.owner == entity_index) {
+            self.remove_custom_component(component_index);
+        }
+        if let Some(controller_index) = self.get_animation_controller(entity_index) {
+            self.remove_animation_controller(controller_index);
+        }
+        self.forget_ik_entity(entity_index);
+        self.spatial_index.remove(entity_index);
+
+        // removing one transform can move the other, so the indices are read after each removal
+        let entity = &self.entities[entity_index];
+        if entity.animated_transform.0 != 0 && entity.animated_transform.0 != entity.transform {
+            self.remove_transform(entity.animated_transform.0);
+        }
+        self.remove_transform(self.entities[entity_index].transform);
+
+        // references from outside of the entity
+        for skin in self.skin_components.iter_mut() {
+            for joint in skin.joints.iter_mut() {
+                if *joint == entity_index { *joint = parent_index }
             }
+        }
+        for animation in self.animation_components.iter_mut() {
+            animation.channels.retain(|channel| channel.1 != entity_index);
+            if let Some(mask) = animation.mask.as_mut() {
+                mask.retain(|&masked| masked != entity_index);
+            }
+        }
+        for entity in self.entities.iter_mut() {
+            if entity.model_node.is_some_and(|node| node.0 == entity_index) {
+                entity.model_node = None;
+            }
+        }
+        for node in self.world.borrow_mut().nodes.iter_mut() {
+            if node.mapped_entity_index == entity_index { node.mapped_entity_index = 0 }
+        }
+        self.unupdated_entities.retain(|&i| i != entity_index);
+        self.unupdated_entities.push(parent_index);
+        self.forget_hovered_entity(entity_index);
+        // before the slot is reset, so the event has the removed entity's generation
+        self.emit(SceneEvent::EntityRemoved { entity: entity_index });
+
+        let generation = self.entities[entity_index].generation + 1;
+        self.entities[entity_index] = Entity {
+            generation,
+            alive: false,
+            name: String::new(),
+            ..Default::default()
+        };
+        self.free_entity_indices.push(entity_index);
+    }
+    fn forget_hovered_entity(&self, entity_index: usize) {
+        let Some(renderer) = &self.renderer else { return };
+        if let Ok(renderer) = renderer.try_borrow()
+            && let Ok(mut scene_renderer) = renderer.scene_renderer.try_borrow_mut()
+            && scene_renderer.hovered_ids.0 == entity_index
+        {
+            scene_renderer.hovered_ids = (0, 0);
+        }
+    }
+
+    // Components are removed by swapping the last component into the freed slot, then pointing everything that referenced the last component at its new index
+    fn remove_transform(&mut self, transform_index: usize) {
+        let last = self.transforms.len() - 1;
+        self.transforms.swap_remove(transform_index);
+        if transform_index == last { return }
 
-            // update velocity
-            for body in &mut self.rigid_body_components {
-                body.update_velocity(dt)
+        let remap = |index: &mut usize| if *index == last { *index = transform_index };
+        let entity = &mut self.entities[self.transforms[transform_index].owner];
+        remap(&mut entity.transform);
+        remap(&mut entity.animated_transform.0);
+        for &render_component_index in entity.render_objects.iter() {
+            remap(&mut self.render_components[render_component_index].transform);
+        }
+        if let Some(body_index) = entity.rigid_body {
+            remap(&mut self.rigid_body_components[body_index].transform);
+        }
+        if let Some(camera_index) = entity.camera {
+            remap(&mut self.camera_components[camera_index].transform);
+        }
+        if let Some(light_index) = entity.light {
+            remap(&mut self.light_components[light_index].transform);
+        }
+    }
+    pub fn remove_render_component(&mut self, render_component_index: usize) {
+        let transform_index = self.render_components[render_component_index].transform;
+        let owner = self.transforms[transform_index].owner;
+        self.entities[owner].render_objects.retain(|&i| i != render_component_index);
+        self.outlined_components.retain(|&i| i != render_component_index);
+        self.unupdated_entities.push(owner);
+        self.forget_hovered_entity(owner);
+        // instance data written since the last sync is dropped with its component
+        let mut i = 0;
+        while i < self.dirty_render_components.len() {
+            if self.dirty_render_components[i] == render_component_index {
+                self.dirty_render_components.remove(i);
+                self.dirty_instance_data.remove(i);
+            } else {
+                i += 1;
             }
         }
+
+        let last = self.render_components.len() - 1;
+        self.render_components.swap_remove(render_component_index);
+        if render_component_index != last {
+            let moved_owner = self.transforms[self.render_components[render_component_index].transform].owner;
+            let remap = |index: &mut usize| if *index == last { *index = render_component_index };
+            self.entities[moved_owner].render_objects.iter_mut().for_each(remap);
+            self.outlined_components.iter_mut().for_each(remap);
+            self.dirty_render_components.iter_mut().for_each(remap);
+            // the moved component's instance has to be written to its new slot
+            self.unupdated_entities.push(moved_owner);
+        }
+
+        self.remove_transform(transform_index);
+    }
+    ///* Removes the skin, render components still using it are drawn unskinned.
+    ///* The last skin takes its index.
+    pub fn remove_skin(&mut self, skin_index: usize) {
+        let last = self.skin_components.len() - 1;
+        self.skin_components.swap_remove(skin_index);
+        for render_component in self.render_components.iter_mut() {
+            let Some(index) = render_component.skin_index else { continue };
+            if index as usize == skin_index {
+                render_component.skin_index = None;
+            } else if index as usize == last {
+                render_component.skin_index = Some(skin_index as i32);
+            } else {
+                continue
+            }
+            self.unupdated_entities.push(self.transforms[render_component.transform].owner);
+        }
+    }
+    pub fn remove_rigid_body(&mut self, body_index: usize) {
+        let (owner, hitbox_index) = {
+            let body = &self.rigid_body_components[body_index];
+            (body.owner, body.hitbox)
+        };
+        self.entities[owner].rigid_body = None;
+        self.outlined_bodies.retain(|&i| i != body_index);
+        self.dirty_bounds.push(owner);
+
+        let last = self.rigid_body_components.len() - 1;
+        self.rigid_body_components.swap_remove(body_index);
+        if body_index != last {
+            let moved_owner = self.rigid_body_components[body_index].owner;
+            self.entities[moved_owner].rigid_body = Some(body_index);
+            self.outlined_bodies.iter_mut().for_each(|index| if *index == last { *index = body_index });
+        }
+
+        let last = self.hitbox_components.len() - 1;
+        self.hitbox_components.swap_remove(hitbox_index);
+        if hitbox_index != last {
+            for body in self.rigid_body_components.iter_mut() {
+                if body.hitbox == last { body.hitbox = hitbox_index }
+            }
+        }
+    }
+    pub fn remove_camera(&mut self, camera_index: usize) {
+        self.entities[self.camera_components[camera_index].owner].camera = None;
+        self.dirty_camera_components.retain(|&i| i != camera_index);
+
+        let last = self.camera_components.len() - 1;
+        self.camera_components.swap_remove(camera_index);
+        if camera_index != last {
+            self.entities[self.camera_components[camera_index].owner].camera = Some(camera_index);
+            self.dirty_camera_components.iter_mut().for_each(|index| if *index == last { *index = camera_index });
+        }
+    }
+    pub fn remove_light(&mut self, light_index: usize) {
+        self.entities[self.light_components[light_index].owner].light = None;
+        self.dirty_light_components.retain(|&i| i != light_index);
+
+        let last = self.light_components.len() - 1;
+        self.light_components.swap_remove(light_index);
+        if light_index != last {
+            self.entities[self.light_components[light_index].owner].light = Some(light_index);
+            self.dirty_light_components.retain(|&i| i != last);
+            self.dirty_light_components.push(light_index);
+        }
+    }
+    pub fn remove_animation(&mut self, animation_index: usize) {
+        self.animation_components[animation_index].stop(&mut self.entities);
+        let owner = self.animation_components[animation_index].owner_entity;
+        self.entities[owner].animation_objects.retain(|&i| i != animation_index);
+        self.unupdated_entities.push(owner);
+
+        let last = self.animation_components.len() - 1;
+        self.animation_components.swap_remove(animation_index);
+        if animation_index != last {
+            let moved_owner = self.animation_components[animation_index].owner_entity;
+            self.entities[moved_owner].animation_objects.iter_mut().for_each(|index| if *index == last { *index = animation_index });
+        }
+    }
+    pub fn attach_script(&mut self, entity_index: usize, uri: &str) -> usize {
+        self.script_components.push(ScriptComponent {
+            owner: entity_index,
+            uri: String::from(uri),
+            instance: None,
+            fields: HashMap::new(),
+        });
+        self.script_components.len() - 1
+    }
+    ///* Sets a field the script exports, which the script engine applies to the component's instance as well.
+    ///* Fails if the script does not export the field or the value has a different type than its declaration.
+    pub fn set_script_field(&mut self, script_index: usize, field_name: &str, value: Field) -> Result<(), Box<dyn Error>> {
+        let component = &mut self.script_components[script_index];
+        let current = component.fields.get_mut(field_name).ok_or_else(|| format!("script '{}' has no field '{}'", component.uri, field_name))?;
+        *current = current.coerce(value.clone()).ok_or_else(|| format!("field '{}' of script '{}' cannot be set to {:?}", field_name, component.uri, value))?;
+        Ok(())
+    }
+    pub fn remove_script_component(&mut self, script_index: usize) {
+        self.script_components.swap_remove(script_index);
+    }
+
+    ///* Steps the simulation at physics_rate for as many whole steps as fit in the accumulated time, then poses the bodies'
+    ///* transforms between or past the last two steps by the leftover time, following physics_interpolation.
+    pub fn update_physics_objects(&mut self, delta_time: f32) {
+        let step = 1.0 / self.physics_rate;
+        self.physics_accumulator += delta_time;
+
+        let mut steps = 0;
+        while self.physics_accumulator >= step {
+            if steps == self.max_physics_steps {
+                self.physics_accumulator %= step;
+                break
+            }
+            self.step_physics(step);
+            self.physics_accumulator -= step;
+            steps += 1;
+        }
+
+        let alpha = self.physics_accumulator / step;
         for body in &mut self.rigid_body_components {
             if !body.is_static {
                 let owner = &self.entities[body.owner];
                 let parent = &self.entities[owner.parent];
-                body.update(&mut self.transforms, parent.transform);
+                let pose = body.pose(self.physics_interpolation, alpha, self.physics_accumulator);
+                body.update(&mut self.transforms, parent.transform, pose);
                 let entity_index = body.owner;
                 self.unupdated_entities.push(entity_index);
             }
         }
+    }
+    fn step_physics(&mut self, dt: f32) {
+        let gravity = Vector::new3(0.0, -9.8, 0.0);
+
+        // integrate
+        for body in &mut self.rigid_body_components {
+            body.integrate(dt, &gravity)
+        }
+
+        // the moving bodies' bounds are kept current for the broadphase, static ones only once they have bounds
+        for body_index in 0..self.rigid_body_components.len() {
+            let body = &self.rigid_body_components[body_index];
+            if !body.is_static || self.spatial_index.bounds(body.owner).is_none() {
+                self.refresh_entity_bounds(body.owner);
+            }
+        }
+
+        // collision constraints
+        let num_bodies = self.rigid_body_components.len();
+        let mut collision_constraints = Vec::new();
+        for i in 0..num_bodies {
+            let body_a = &self.rigid_body_components[i];
+            let mut candidates: Vec<usize> = self.spatial_index.query_aabb(&self.rigid_body_bounds(i)).into_iter()
+                .filter_map(|entity_index| self.entities[entity_index].rigid_body)
+                .filter(|&j| j > i)
+                .collect();
+            candidates.sort_unstable();
+            for j in candidates {
+                let body_b = &self.rigid_body_components[j];
+
+                if (body_a.is_static && body_b.is_static) || body_a.owned_by_player || body_b.owned_by_player {
+                    continue;
+                }
+                if let Some(collision) = body_a.will_collide_with(&self.hitbox_components, body_b, 0.0) {
+                    if collision.contact_points.is_empty() { continue }
+                    let normal = collision.normal;
+                    let depth = collision.time_of_impact;
+                    let pt_on_a = collision.contact_points[0].point_on_a;
+                    let pt_on_b = collision.contact_points[0].point_on_b;
+
+                    collision_constraints.push(CollisionConstraint {
+                        body_a: i,
+                        body_b: j,
+                        penetration: depth,
+                        normal,
+                        pt_on_a,
+                        pt_on_b,
+                    })
+                }
+            }
+        }
+        for constraint in collision_constraints {
+            constraint.solve(dt, &mut self.rigid_body_components)
+        }
+
+        // update velocity
+        for body in &mut self.rigid_body_components {
+            body.update_velocity(dt)
+        }
         /*
         let gravity = Vector::new3(0.0, -9.8, 0.0);
         // apply gravity
@@ -467,7 +1185,9 @@ impl Scene {
          */
     }
 
-    pub unsafe fn update_scene(&mut self, command_buffer: CommandBuffer, frame: usize, delta_time: f32, force_run: bool) {
+    ///* Advances the simulation when the scene is running or force_run is set, then brings world transforms, cameras and joint
+    ///* matrices up to date. Only CPU-side data is touched, the changes are uploaded by sync_gpu.
+    pub fn update(&mut self, frame: usize, delta_time: f32, force_run: bool) {
         if self.running || force_run {
             self.update_physics_objects(delta_time);
 
@@ -476,13 +1196,38 @@ impl Scene {
             // self.world.borrow_mut().sun.vector = Vector::new3(0.55, f32::sin(self.runtime * 0.05), f32::cos(-self.runtime * 0.05));
             self.sun_components[0].direction = Vector::new3(0.55, f32::sin(self.runtime * 0.05), -f32::cos(self.runtime * 0.05)).normalize3();
 
-            for animation in self.animation_components.iter_mut() {
-                animation.update(&mut self.entities, &mut self.transforms, &mut self.unupdated_entities)
+            self.update_animation_controllers();
+            let mut finished_animations = Vec::new();
+            let mut marker_events = Vec::new();
+            for (animation_index, animation) in self.animation_components.iter_mut().enumerate() {
+                let (passed_markers, finished) = animation.update(delta_time);
+                for marker in passed_markers {
+                    marker_events.push(SceneEvent::AnimationMarker { entity: animation.owner_entity, animation: animation_index, marker });
+                }
+                if finished {
+                    finished_animations.push(animation_index);
+                }
+            }
+            for event in marker_events {
+                self.emit(event);
             }
+            // finished animations are stopped first so that snapping back does not undo poses of the animations still running
+            let mut held_animations = Vec::new();
+            for animation_index in finished_animations {
+                let animation = &mut self.animation_components[animation_index];
+                animation.stop(&mut self.entities);
+                if !animation.snap_back {
+                    held_animations.push(animation_index);
+                }
+            }
+            self.apply_animation_poses(&held_animations);
+            self.restore_ik_poses();
         }
+        self.emit_animation_events();
 
-        let mut dirty_primitive_instance_data: Vec<Instance> = Vec::new();
+        let mut dirty_primitive_instance_data = std::mem::take(&mut self.dirty_instance_data);
         for entity_index in self.unupdated_entities.clone().iter() {
+            if !self.entities[*entity_index].alive { continue }
             //for entity_index in &vec![1usize] {
             let parent_index = self.entities[*entity_index].parent;
             let parent_transform = if *entity_index == 0 {
@@ -498,6 +1243,12 @@ impl Scene {
             );
         }
         self.unupdated_entities.clear();
+        if self.running || force_run {
+            // after the joints' world transforms are known, and before the skins read them
+            self.solve_ik_constraints(frame, &mut dirty_primitive_instance_data);
+        }
+        self.update_spatial_index();
+        self.dirty_instance_data = dirty_primitive_instance_data;
 
         for dirty_camera_index in &self.dirty_camera_components {
             let camera = &mut self.camera_components[*dirty_camera_index];
@@ -516,6 +1267,39 @@ impl Scene {
         for skin in self.skin_components.iter() {
             skin.update(&self, &mut joints);
         }
+        self.joint_matrices = joints;
+
+        let world = self.world.borrow();
+        // render components past the buffer's size can't have an offset, the geometry buffers hold fewer instances than that anyway
+        let header_len = self.render_components.len().min(MAX_MORPH_WEIGHTS as usize);
+        let mut morph_weights = vec![-1.0; header_len];
+        for (render_component_index, render_component) in self.render_components.iter().take(header_len).enumerate() {
+            let (mesh_index, primitive_index) = render_component.mesh_primitive_index;
+            let target_count = world.meshes[mesh_index].primitives[primitive_index].morph_targets.len();
+            if target_count == 0 || morph_weights.len() + target_count > MAX_MORPH_WEIGHTS as usize { continue }
+            morph_weights[render_component_index] = morph_weights.len() as f32;
+            morph_weights.extend((0..target_count).map(|i| render_component.morph_weights.get(i).copied().unwrap_or(0.0)));
+        }
+        drop(world);
+        if morph_weights != self.morph_weight_data {
+            self.morph_weight_data = morph_weights;
+            self.dirty_morph_weights = true;
+        }
+
+        if self.context.is_none() {
+            // nothing will upload these for a headless scene
+            self.dirty_render_components.clear();
+            self.dirty_instance_data.clear();
+            self.dirty_light_components.clear();
+        }
+    }
+
+    /// Uploads the instance, joint and light data changed by update, called by the renderer each frame
+    pub unsafe fn sync_gpu(&mut self, command_buffer: CommandBuffer) {
+        let context = self.context.clone().expect("headless scenes cannot be synced to the GPU");
+        let dirty_primitive_instance_data = std::mem::take(&mut self.dirty_instance_data);
+        let joints = &self.joint_matrices;
+        let morph_weights = &self.morph_weight_data;
 
         let world = &self.world.borrow();
         unsafe {
@@ -534,11 +1318,11 @@ impl Scene {
                 });
             }
             self.dirty_render_components.clear();
-            copy_data_to_memory(world.joints_staging_buffer.2, &joints);
+            copy_data_to_memory(world.joints_staging_buffer.2, joints);
             if !copy_regions.is_empty() {
                 for frame in 0..MAX_FRAMES_IN_FLIGHT {
                     copy_buffer_synchronous(
-                        &self.context.device,
+                        &context.device,
                         command_buffer,
                         &world.instance_staging_buffer.0,
                         &world.instance_buffers[frame].0,
@@ -546,11 +1330,22 @@ impl Scene {
                         &0u64
                     );
 
-                    copy_buffer_synchronous(&self.context.device, command_buffer, &world.joints_staging_buffer.0, &world.joints_buffers[frame].0, None, &world.joints_buffers_size);
+                    copy_buffer_synchronous(&context.device, command_buffer, &world.joints_staging_buffer.0, &world.joints_buffers[frame].0, None, &world.joints_buffers_size);
                 }
                 copy_regions.clear();
             }
 
+            // weights change without the instances changing, eg. when only a weights animation is playing
+            if self.dirty_morph_weights && !morph_weights.is_empty() {
+                assert!(morph_weights.len() <= MAX_MORPH_WEIGHTS as usize, "morph weights exceed the staging buffer");
+                copy_data_to_memory(world.morph_weights_staging_buffer.2, morph_weights);
+                let morph_weights_size = (morph_weights.len() * size_of::<f32>()) as u64;
+                for frame in 0..MAX_FRAMES_IN_FLIGHT {
+                    copy_buffer_synchronous(&context.device, command_buffer, &world.morph_weights_staging_buffer.0, &world.morph_weights_buffers[frame].0, None, &morph_weights_size);
+                }
+            }
+            self.dirty_morph_weights = false;
+
             let mut new_light_data = Vec::new();
             for (i, &light_id) in self.dirty_light_components.iter().enumerate() {
                 copy_regions.push(vk::BufferCopy {
@@ -567,7 +1362,7 @@ impl Scene {
             if !copy_regions.is_empty() {
                 for frame in 0..MAX_FRAMES_IN_FLIGHT {
                     copy_buffer_synchronous(
-                        &self.context.device,
+                        &context.device,
                         command_buffer,
                         &world.lights_staging_buffer.0,
                         &world.lights_buffers[frame].0,
@@ -578,6 +1373,12 @@ impl Scene {
             }
         }
     }
+
+    /// update followed by sync_gpu, for stepping the scene outside of the frame loop
+    pub unsafe fn update_scene(&mut self, command_buffer: CommandBuffer, frame: usize, delta_time: f32, force_run: bool) {
+        self.update(frame, delta_time, force_run);
+        unsafe { self.sync_gpu(command_buffer) }
+    }
     pub fn update_entity(
         &mut self,
         frame: usize,
@@ -618,8 +1419,11 @@ impl Scene {
                 }
             };
 
+            let world = self.world.borrow();
             for (i, render_object_index) in entity.render_objects.iter().enumerate() {
+                self.render_components[*render_object_index].morph_weights = entity.current_morph_weights().to_vec();
                 let render_component = &self.render_components[*render_object_index];
+                let primitive = &world.meshes[render_component.mesh_primitive_index.0].primitives[render_component.mesh_primitive_index.1];
 
                 self.transforms[render_component.transform].update_local_matrix();
 
@@ -635,15 +1439,22 @@ impl Scene {
                             render_component.skin_index.map_or(-1, |i| i),
                             entity_index as i32,
                             i as i32
-                        ]
+                        ],
+                        morph_targets: if primitive.morph_targets.is_empty() {
+                            [-1, 0, 0, 0]
+                        } else {
+                            [primitive.morph_delta_offset as i32, primitive.morph_targets.len() as i32, primitive.vertex_buffer_offset as i32, 0]
+                        },
                     }
                 );
             }
+            drop(world);
 
             if let Some(body_index) = entity.rigid_body {
                 let body = &mut self.rigid_body_components[body_index];
-                body.initialize(&mut self.transforms);
+                body.sync_with_transform(&self.transforms);
             }
+            self.dirty_bounds.push(entity_index);
 
             if let Some(light_index) = entity.light {
                 self.dirty_light_components.push(light_index);
@@ -661,6 +1472,10 @@ impl Scene {
         }
     }
 
+    fn context(&self) -> &Arc<Context> {
+        self.context.as_ref().expect("headless scenes cannot be drawn")
+    }
+
     pub unsafe fn draw(&self, scene_renderer: &SceneRenderer, frame: usize, camera: Option<usize>, draw_mode: DrawMode) {
         let command_buffer = get_command_buffer();
         let world = &self.world.borrow();
@@ -675,20 +1490,20 @@ impl Scene {
             };
 
             if do_hitboxes {
-                self.context.device.cmd_bind_vertex_buffers(
+                self.context().device.cmd_bind_vertex_buffers(
                     command_buffer,
                     0,
                     &[scene_renderer.editor_primitives_vertices_buffer.0],
                     &[0],
                 );
-                self.context.device.cmd_bind_index_buffer(
+                self.context().device.cmd_bind_index_buffer(
                     command_buffer,
                     scene_renderer.editor_primitives_indices_buffer.0,
                     0,
                     vk::IndexType::UINT32,
                 );
 
-                self.context.device.cmd_bind_pipeline(
+                self.context().device.cmd_bind_pipeline(
                     command_buffer,
                     vk::PipelineBindPoint::GRAPHICS,
                     scene_renderer.opaque_forward_renderpass.pipelines[2].vulkan_pipeline,
@@ -715,7 +1530,7 @@ impl Scene {
                         _ => ((0, 0), Matrix::new())
                     };
 
-                    self.context.device.cmd_push_constants(
+                    self.context().device.cmd_push_constants(
                         command_buffer,
                         scene_renderer.opaque_forward_renderpass.pipeline_layout,
                         ShaderStageFlags::ALL_GRAPHICS,
@@ -729,22 +1544,22 @@ impl Scene {
                         )
                     );
 
-                    self.context.device.cmd_draw_indexed(command_buffer, index_count, 1, first_index, 0 ,0);
+                    self.context().device.cmd_draw_indexed(command_buffer, index_count, 1, first_index, 0 ,0);
                 }
             } else {
-                self.context.device.cmd_bind_vertex_buffers(
+                self.context().device.cmd_bind_vertex_buffers(
                     command_buffer,
                     1,
                     &[world.instance_buffers[frame].0],
                     &[0],
                 );
-                self.context.device.cmd_bind_vertex_buffers(
+                self.context().device.cmd_bind_vertex_buffers(
                     command_buffer,
                     0,
                     &[world.vertex_buffer.0],
                     &[0],
                 );
-                self.context.device.cmd_bind_index_buffer(
+                self.context().device.cmd_bind_index_buffer(
                     command_buffer,
                     world.index_buffer.0,
                     0,
@@ -752,7 +1567,7 @@ impl Scene {
                 );
 
                 if do_outline {
-                    self.context.device.cmd_bind_pipeline(
+                    self.context().device.cmd_bind_pipeline(
                         command_buffer,
                         vk::PipelineBindPoint::GRAPHICS,
                         scene_renderer.opaque_forward_renderpass.pipelines[0].vulkan_pipeline,
@@ -762,8 +1577,20 @@ impl Scene {
                     }
                 } else {
                     if do_deferred {
-                        for (i, render_component) in self.render_components.iter().enumerate() {
-                            render_component.draw(&self, scene_renderer, &command_buffer, world, i, camera);
+                        if let Some(camera) = camera {
+                            let mut visible: Vec<usize> = self.spatial_index.query_frustum(&camera.frustum).into_iter()
+                                .flat_map(|entity_index| self.entities[entity_index].render_objects.iter().copied())
+                                .chain((0..self.render_components.len()).filter(|&i| self.render_components[i].is_deformed(world)))
+                                .collect();
+                            visible.sort_unstable();
+                            visible.dedup();
+                            for i in visible {
+                                self.render_components[i].draw(&self, scene_renderer, &command_buffer, world, i, Some(camera));
+                            }
+                        } else {
+                            for (i, render_component) in self.render_components.iter().enumerate() {
+                                render_component.draw(&self, scene_renderer, &command_buffer, world, i, camera);
+                            }
                         }
                     }
                     if do_forward {
@@ -783,6 +1610,8 @@ pub enum DrawMode {
 }
 
 pub struct Entity {
+    pub generation: u32, // bumped every time the entity is removed, so handles to a reused index can be told apart
+    pub alive: bool,
     pub name: String,
     pub transform: usize,
     pub animated_transform: (usize, bool),
@@ -796,10 +1625,34 @@ pub struct Entity {
     pub rigid_body: Option<usize>,
     pub camera: Option<usize>,
     pub light: Option<usize>,
+
+    pub model_uri: Option<String>, // set on the entity a gltf model was imported as
+    pub model_node: Option<(usize, usize)>, // imported model entity, gltf node index within that model
+    pub prefab: Option<usize>, // set on the root entity of a prefab instance
+
+    pub tags: Vec<String>,
+    pub layers: u32, // bitmask of the layers the entity is in, for the renderer and physics to filter by
+
+    pub morph_weights: Vec<f32>, // weights of the morph targets of the entity's mesh
+    pub animated_morph_weights: Option<Vec<f32>>, // set while animations drive the weights, used instead of morph_weights
+}
+impl Entity {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+    pub fn in_layers(&self, layer_mask: u32) -> bool {
+        self.layers & layer_mask != 0
+    }
+    /// The morph target weights to render with
+    pub fn current_morph_weights(&self) -> &[f32] {
+        self.animated_morph_weights.as_deref().unwrap_or(&self.morph_weights)
+    }
 }
 impl Default for Entity {
     fn default() -> Self {
         Self {
+            generation: 0,
+            alive: true,
             name: String::from("entity"),
             transform: 0,
             animated_transform: (0, false),
@@ -812,6 +1665,13 @@ impl Default for Entity {
             rigid_body: None,
             camera: None,
             light: None,
+            model_uri: None,
+            model_node: None,
+            prefab: None,
+            tags: Vec::new(),
+            layers: DEFAULT_LAYERS,
+            morph_weights: Vec::new(),
+            animated_morph_weights: None,
         }
     }
 }
@@ -832,6 +1692,9 @@ pub struct Transform {
     world: Matrix
 }
 impl Transform {
+    pub fn world(&self) -> Matrix {
+        self.world
+    }
     fn update_local_matrix(&mut self) {
         let rotate = Matrix::new_rotate_quaternion_vec4(&self.local_rotation);
         let scale = Matrix::new_scale_vec3(&self.local_scale);
@@ -894,91 +1757,192 @@ impl Default for Transform {
 
 
 
+/// A named time in an animation, reported with an animation_marker event whenever playback passes it
+#[derive(Clone)]
+pub struct AnimationMarker {
+    pub name: String,
+    pub time: f32,
+}
+
+#[derive(Clone)]
 pub struct AnimationComponent {
-    owner_entity: usize,
-    pub channels: Vec<(usize, usize, String)>, // sampler index, impacted node, target transform component
-    pub samplers: Vec<(Vec<f32>, String, Vec<Vector>)>, // input times, interpolation method, output vectors
-    pub start_time: SystemTime,
+    pub(crate) owner_entity: usize,
+    pub name: String,
+    pub channels: Vec<(usize, usize, String)>, // sampler index, impacted node, target path (translation, rotation, scale or weights)
+    pub samplers: Vec<AnimationSampler>,
+    pub time: f32, // seconds into the animation, advanced by the scene's delta time
     pub duration: f32,
+    pub speed: f32,
     pub running: bool,
+    pub paused: bool, // holds the current pose without stopping
+    pub(crate) reported_running: bool, // running state of the last animation event
     pub repeat: bool,
+    pub ping_pong: bool, // plays backwards from the end instead of restarting, once or with repeat indefinitely
     pub snap_back: bool,
+    pub markers: Vec<AnimationMarker>,
+    pub(crate) direction: f32, // -1 while a ping pong animation plays backwards
+    pub(crate) bounced: bool, // whether a ping pong animation without repeat has turned around
+
+    pub weight: f32, // influence relative to other animations of the same entities
+    pub layer: u32, // layers are applied in ascending order, each over the pose of the ones below
+    pub additive: bool, // adds the difference from the first keyframe to the pose instead of blending towards its own
+    pub mask: Option<Vec<usize>>, // entities the animation may affect, all targeted entities if None
+    pub(crate) fade: Option<(f32, f32, bool)>, // target weight, weight change per second, whether to stop at the target
 }
 impl AnimationComponent {
+    pub fn new(owner_entity: usize, name: String, channels: Vec<(usize, usize, String)>, samplers: Vec<AnimationSampler>, duration: f32) -> Self {
+        Self {
+            owner_entity,
+            name,
+            channels,
+            samplers,
+            time: 0.0,
+            duration,
+            speed: 1.0,
+            running: false,
+            paused: false,
+            reported_running: false,
+            repeat: false,
+            ping_pong: false,
+            snap_back: false,
+            markers: Vec::new(),
+            direction: 1.0,
+            bounced: false,
+            weight: 1.0,
+            layer: 0,
+            additive: false,
+            mask: None,
+            fade: None,
+        }
+    }
+
+    /// Plays from the start, or from the end if speed is negative
     pub fn start(&mut self) {
-        self.start_time = SystemTime::now();
+        self.time = if self.speed < 0.0 { self.duration } else { 0.0 };
+        self.direction = 1.0;
+        self.bounced = false;
         self.running = true;
+        self.paused = false;
+        self.fade = None;
     }
 
     pub fn stop(&mut self, entities: &mut Vec<Entity>) {
         self.running = false;
+        self.fade = None;
         if self.snap_back {
             for channel in self.channels.iter() {
-                entities[channel.1].animated_transform.1 = false;
+                let entity = &mut entities[channel.1];
+                if channel.2 == "weights" {
+                    entity.animated_morph_weights = None;
+                } else {
+                    entity.animated_transform.1 = false;
+                }
             }
         }
     }
 
-    pub fn update(&mut self, entities: &mut Vec<Entity>, transforms: &mut Vec<Transform>, unnupdated_entities: &mut Vec<usize>) {
+    /// Jumps to a time without passing markers on the way. The pose is applied on the next scene update if the animation is running.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.duration.max(0.0));
+    }
+    pub fn normalized_time(&self) -> f32 {
+        if self.duration > 0.0 { self.time / self.duration } else { 0.0 }
+    }
+    pub fn set_normalized_time(&mut self, normalized_time: f32) {
+        self.seek(normalized_time * self.duration);
+    }
+
+    ///* Moves the weight linearly to the target over duration seconds of scene time, regardless of speed and pausing.
+    ///* With stop set the animation stops once there, eg. when fading out.
+    pub fn fade_to(&mut self, weight: f32, duration: f32, stop: bool) {
+        let rate = if duration > 0.0 { (weight - self.weight).abs() / duration } else { f32::INFINITY };
+        self.fade = Some((weight, rate, stop));
+    }
+
+    ///* Advances the animation's time and weight by delta_time, returning the names of the markers that were passed in the order
+    ///* they were passed, and whether the animation has finished and should be stopped. Poses are applied separately, by
+    ///* Scene::apply_animation_poses, so that animations of the same entities can be blended.
+    pub fn update(&mut self, delta_time: f32) -> (Vec<String>, bool) {
+        let mut passed_markers = Vec::new();
         if !self.running {
-            return
+            return (passed_markers, false)
         }
-        unnupdated_entities.push(self.owner_entity);
-        let current_time = SystemTime::now();
-        let elapsed_time = current_time.duration_since(self.start_time).unwrap().as_secs_f32();
-        let mut repeat = false;
-        if elapsed_time > self.duration {
-            if self.repeat {
-                repeat = true
+
+        let mut finished = false;
+        if let Some((target, rate, stop)) = self.fade {
+            if rate.is_infinite() || (target - self.weight).abs() <= rate * delta_time {
+                self.weight = target;
+                self.fade = None;
+                finished = stop;
             } else {
-                self.stop(entities);
-                return
+                self.weight += (rate * delta_time).copysign(target - self.weight);
             }
         }
-        for channel in self.channels.iter() {
-            let sampler = &self.samplers[channel.0];
-            let mut current_time_index = 0;
-            for i in 0..sampler.0.len() - 1 {
-                if elapsed_time >= sampler.0[i] && elapsed_time < sampler.0[i + 1] {
-                    current_time_index = i;
-                    break
-                }
-            }
-            let current_time_index = current_time_index.min(sampler.0.len() - 1);
-            let interpolation_factor = ((elapsed_time - sampler.0[current_time_index]) / (sampler.0[current_time_index + 1] - sampler.0[current_time_index])).min(1.0).max(0.0);
-            let vector1 = &sampler.2[current_time_index];
-            let vector2 = &sampler.2[current_time_index + 1];
-            let new_vector;
-            if channel.2.eq("translation") || channel.2.eq("scale") {
-                new_vector = Vector::new3(
-                    vector1.x + interpolation_factor * (vector2.x - vector1.x),
-                    vector1.y + interpolation_factor * (vector2.y - vector1.y),
-                    vector1.z + interpolation_factor * (vector2.z - vector1.z),
-                )
-            } else {
-                new_vector = Vector::spherical_lerp(vector1, vector2, interpolation_factor)
-            }
 
-            let entity = &mut entities[channel.1];
-            entity.animated_transform.1 = true;
-            let animated_transform = &mut transforms[entity.animated_transform.0];
-
-            if channel.2.eq("translation") {
-                animated_transform.local_translation = new_vector
-            } else if channel.2.eq("rotation") {
-                animated_transform.local_rotation = new_vector
-            } else if channel.2.eq("scale") {
-                animated_transform.local_scale = new_vector
+        let mut remaining = if self.paused || self.duration <= 0.0 { 0.0 } else { delta_time * self.speed * self.direction };
+        // a frame can pass the ends several times when the animation is short compared to the step
+        // markers at the start of a step are passed, except at a ping pong turn where the previous step already passed them
+        let mut include_start = true;
+        for _ in 0..16 {
+            if remaining == 0.0 { break }
+            let forward = remaining > 0.0;
+            let end = if forward { self.duration } else { 0.0 };
+            let target = self.time + remaining;
+            if (forward && target < end) || (!forward && target > end) {
+                self.pass_markers(target, include_start, false, &mut passed_markers);
+                self.time = target;
+                break
+            }
+            self.pass_markers(end, include_start, true, &mut passed_markers);
+            self.time = end;
+            let overshoot = target - end;
+
+            if self.ping_pong && (self.repeat || !self.bounced) {
+                self.bounced = true;
+                self.direction = -self.direction;
+                remaining = -overshoot;
+                include_start = false;
+            } else if self.repeat {
+                self.time = self.duration - end;
+                remaining = overshoot;
+                include_start = true;
             } else {
-                panic!("Illogical animation channel target! Should be translation, rotation or scale");
+                finished = true;
+                break
             }
         }
-        if repeat {
-            self.start()
-        }
+        (passed_markers, finished)
+    }
+
+    ///* Markers from the current time up to the target in playback direction. Steps within the animation pass [time, target),
+    ///* steps reaching an end pass [time, end] so that markers on the ends are passed once per loop.
+    fn pass_markers(&self, target: f32, include_start: bool, include_target: bool, passed_markers: &mut Vec<String>) {
+        let forward = target >= self.time;
+        let mut passed: Vec<&AnimationMarker> = self.markers.iter()
+            .filter(|marker| {
+                let after_start = if forward { marker.time > self.time } else { marker.time < self.time };
+                let before_target = if forward { marker.time < target } else { marker.time > target };
+                (after_start || (include_start && marker.time == self.time)) && (before_target || (include_target && marker.time == target))
+            })
+            .collect();
+        passed.sort_by(|a, b| if forward { a.time.total_cmp(&b.time) } else { b.time.total_cmp(&a.time) });
+        passed_markers.extend(passed.into_iter().map(|marker| marker.name.clone()));
+    }
+
+    /// The value of a channel at the current time, None if its sampler has no keyframes
+    pub fn sample(&self, channel_index: usize) -> Option<Vec<f32>> {
+        let channel = &self.channels[channel_index];
+        self.samplers.get(channel.0)?.sample(self.time, channel.2 == "rotation")
+    }
+    /// The value of a channel at its first keyframe, which additive animations are relative to
+    pub fn reference(&self, channel_index: usize) -> Option<Vec<f32>> {
+        let sampler = self.samplers.get(self.channels[channel_index].0)?;
+        (!sampler.times.is_empty()).then(|| sampler.value(0).to_vec())
     }
 }
+#[derive(Clone)]
 pub struct SkinComponent {
+    owner: usize, // the entity whose mesh is skinned, removing it removes the skin
     joints: Vec<usize>, // entity indices
     inverse_bind_matrices: Vec<Matrix>
 }
@@ -990,6 +1954,32 @@ impl SkinComponent {
         }
     }
 }
+/// How bodies' transforms are posed between fixed physics steps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhysicsInterpolation {
+    None, // the last step's pose, visibly stepping when the frame rate is not a multiple of the physics rate
+    Interpolate, // between the last two steps, smooth but a step behind
+    Extrapolate, // past the last step by the body's velocity, current but overshooting on impacts
+}
+impl PhysicsInterpolation {
+    pub const NAMES: [&'static str; 3] = ["none", "interpolate", "extrapolate"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(PhysicsInterpolation::None),
+            "interpolate" => Some(PhysicsInterpolation::Interpolate),
+            "extrapolate" => Some(PhysicsInterpolation::Extrapolate),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            PhysicsInterpolation::None => "none",
+            PhysicsInterpolation::Interpolate => "interpolate",
+            PhysicsInterpolation::Extrapolate => "extrapolate",
+        }
+    }
+}
 pub struct RigidBodyComponent {
     pub owner: usize,
     pub transform: usize,
@@ -1006,6 +1996,7 @@ pub struct RigidBodyComponent {
     pub x_f: Vector,
     pub q_i: Vector,
     pub q_f: Vector,
+    pub(crate) rendered_pose: Option<(Vector, Vector)>, // the world translation and rotation last written to the transform
 
     pub velocity: Vector,
     pub angular_velocity: Vector, // axis angle
@@ -1116,10 +2107,24 @@ impl RigidBodyComponent {
         w
     }
 
+    /// Moves the body to its transform, without interpolating from where it was
     pub fn initialize(&mut self, transforms: &Vec<Transform>) {
         let transform = &transforms[self.transform];
         self.x_f = transform.world_translation;
         self.q_f = transform.world_rotation;
+        self.x_i = self.x_f;
+        self.q_i = self.q_f;
+        self.rendered_pose = Some((self.x_f, self.q_f));
+    }
+    /// Initializes the body if its transform was moved by something other than the physics update, eg. a script or its parent
+    pub fn sync_with_transform(&mut self, transforms: &Vec<Transform>) {
+        let transform = &transforms[self.transform];
+        if let Some((translation, rotation)) = self.rendered_pose
+            && transform.world_translation.equals(&translation, 1e-4) && transform.world_rotation.equals(&rotation, 1e-4)
+        {
+            return
+        }
+        self.initialize(transforms);
     }
     pub fn integrate(&mut self, dt: f32, g: &Vector) {
         if self.is_static { return }
@@ -1143,13 +2148,28 @@ impl RigidBodyComponent {
         self.angular_velocity = self.differential_rotation.with('w', 0.0) * 2.0 / dt;
         if self.differential_rotation.w < 0.0 { self.angular_velocity = -self.angular_velocity }
     }
-    pub fn update(&mut self, transforms: &mut Vec<Transform>, parent_transform: usize) {
+    /// The world translation and rotation to render, remainder seconds and alpha steps past the last step
+    pub fn pose(&self, interpolation: PhysicsInterpolation, alpha: f32, remainder: f32) -> (Vector, Vector) {
+        match interpolation {
+            PhysicsInterpolation::None => (self.x_f, self.q_f),
+            PhysicsInterpolation::Interpolate => (
+                self.x_i + (self.x_f - self.x_i) * alpha,
+                Vector::spherical_lerp(&self.q_i, &self.q_f, alpha)
+            ),
+            PhysicsInterpolation::Extrapolate => {
+                let differential_rotation = self.angular_velocity.with('w', 0.0).combine(&self.q_f);
+                (self.x_f + self.velocity * remainder, (self.q_f + differential_rotation * (0.5 * remainder)).normalize4())
+            }
+        }
+    }
+    pub fn update(&mut self, transforms: &mut Vec<Transform>, parent_transform: usize, pose: (Vector, Vector)) {
         if self.is_static { return }
 
         let [transform, parent_transform] = transforms.get_disjoint_mut([self.transform, parent_transform]).unwrap();
 
-        transform.local_translation = transform.world_to_local_position(self.x_f, parent_transform);
-        transform.local_rotation = transform.world_to_local_rotation(self.q_f, parent_transform);
+        transform.local_translation = transform.world_to_local_position(pose.0, parent_transform);
+        transform.local_rotation = transform.world_to_local_rotation(pose.1, parent_transform);
+        self.rendered_pose = Some(pose);
     }
     pub fn apply_correction(
         &mut self,
@@ -1569,6 +2589,7 @@ impl Default for RigidBodyComponent {
             x_f: Vector::new(),
             q_i: Vector::new(),
             q_f: Vector::new(),
+            rendered_pose: None,
             velocity: Default::default(),
             angular_velocity: Default::default(),
             differential_rotation: Default::default(),
@@ -1603,8 +2624,20 @@ pub struct RenderComponent {
     transform: usize, // independent from parent
     skin_index: Option<i32>,
     material_index: usize,
+    pub morph_weights: Vec<f32>, // weights of the primitive's morph targets for this instance, copied from the entity on update
 }
 impl RenderComponent {
+    pub fn mesh_primitive_index(&self) -> (usize, usize) {
+        self.mesh_primitive_index
+    }
+    pub fn transform(&self) -> usize {
+        self.transform
+    }
+    /// Skinned and morphed vertices can leave the primitive's bind pose bounds, so these components are never culled
+    pub fn is_deformed(&self, world: &World) -> bool {
+        self.skin_index.is_some() || !world.meshes[self.mesh_primitive_index.0].primitives[self.mesh_primitive_index.1].morph_targets.is_empty()
+    }
+
     unsafe fn draw(
         &self,
         scene:
@@ -1619,7 +2652,7 @@ impl RenderComponent {
 
         let primitive = &world.meshes[self.mesh_primitive_index.0].primitives[self.mesh_primitive_index.1];
 
-        if camera.is_some() {
+        if camera.is_some() && !self.is_deformed(world) {
             for plane_idx in 0..6 {
                 let mut all_outside_this_plane = true;
 
@@ -1639,7 +2672,7 @@ impl RenderComponent {
         }
         if !all_points_outside_of_same_plane || camera.is_none() {
             unsafe {
-                scene.context.device.cmd_draw_indexed(
+                scene.context().device.cmd_draw_indexed(
                     *command_buffer,
                     world.accessors[primitive.indices].count as u32,
                     1,
@@ -1651,6 +2684,7 @@ impl RenderComponent {
         }
     }
 }
+#[derive(Clone)]
 pub struct CameraComponent {
     pub owner: usize,
     pub transform: usize,
@@ -1663,6 +2697,7 @@ pub struct CameraComponent {
     pub far: f32,
     pub frustum: Frustum,
     pub infinite_reverse: bool,
+    pub orthographic: Option<(f32, f32)>, // half width and height of the view, instead of fov_y and aspect_ratio
 
     pub third_person: bool,
     pub third_person_vector: Vector, // to be rotated by the cameras rotation and added to the position when creating the view matrix, if in third person
@@ -1688,10 +2723,54 @@ impl CameraComponent {
             far,
             frustum: Frustum::null(),
             infinite_reverse,
+            orthographic: None,
             third_person: false,
             third_person_vector
         }
     }
+    /// Takes half the width and height of the view box
+    pub fn new_orthographic_rotation(
+        half_width: f32,
+        half_height: f32,
+        near: f32,
+        far: f32,
+        third_person_vector: Vector
+    ) -> Self {
+        Self {
+            orthographic: Some((half_width, half_height)),
+            ..Self::new_perspective_rotation(0.0, half_width / half_height, near, far, false, third_person_vector)
+        }
+    }
+    /// A glTF camera, perspective ones without a far plane get an infinite reversed projection
+    pub fn from_gltf(camera: &GltfCamera) -> Self {
+        match camera.projection {
+            GltfProjection::Perspective { y_fov, aspect_ratio, z_near, z_far } => Self::new_perspective_rotation(
+                y_fov.to_degrees(),
+                aspect_ratio.unwrap_or(1.0),
+                z_near,
+                z_far.unwrap_or(1000.0),
+                z_far.is_none(),
+                Vector::new3(0.0, 0.0, 1.0),
+            ),
+            GltfProjection::Orthographic { x_mag, y_mag, z_near, z_far } => Self::new_orthographic_rotation(
+                x_mag,
+                y_mag,
+                z_near,
+                z_far,
+                Vector::new3(0.0, 0.0, 1.0),
+            ),
+        }
+    }
+
+    fn projection_with_near_far(&self, near: f32, far: f32) -> Matrix {
+        if let Some((half_width, half_height)) = self.orthographic {
+            let mut projection = Matrix::new_ortho(-half_width, half_width, -half_height, half_height, near, far);
+            projection.data[10] *= -1.0; // looking down -z, like the perspective projection
+            projection
+        } else {
+            Matrix::new_projection(self.fov_y.to_radians(), self.aspect_ratio, near, far)
+        }
+    }
 
     pub fn update_matrices(&mut self, transform: &Transform) {
         self.view_matrix = Matrix::new_view(
@@ -1705,12 +2784,7 @@ impl CameraComponent {
             self.fov_y.to_radians(),
             self.aspect_ratio,
             self.near,
-        ) } else { Matrix::new_projection(
-            self.fov_y.to_radians(),
-            self.aspect_ratio,
-            self.near,
-            self.far,
-        ) }
+        ) } else { self.projection_with_near_far(self.near, self.far) }
     }
 
     pub fn update_frustum(&mut self, transform: &Transform) {
@@ -1720,13 +2794,28 @@ impl CameraComponent {
         let cam_up = Vector::new3(0.0, 1.0, 0.0).rotate_by_euler(&rotation);
         let cam_right = cam_up.cross(&cam_front).normalize3();
 
+        let position = transform.world_translation;
+
+        if let Some((half_width, half_height)) = self.orthographic {
+            // the sides of the view box face inwards
+            self.frustum = Frustum {
+                planes: [
+                    Plane { normal: cam_front, point: position + (cam_front * self.near) },
+                    Plane { normal: cam_front * -1.0, point: position + (cam_front * self.far) },
+                    Plane { normal: cam_right, point: position - (cam_right * half_width) },
+                    Plane { normal: cam_right * -1.0, point: position + (cam_right * half_width) },
+                    Plane { normal: cam_up * -1.0, point: position + (cam_up * half_height) },
+                    Plane { normal: cam_up, point: position - (cam_up * half_height) },
+                ],
+            };
+            return
+        }
+
         let half_v = self.far * (self.fov_y*0.5).to_radians().tan();
         let half_h = half_v*self.aspect_ratio;
 
         let front_by_far = cam_front * self.far;
 
-        let position = transform.world_translation;
-
         self.frustum = Frustum {
             planes: [
                 Plane {
@@ -1758,7 +2847,7 @@ impl CameraComponent {
     }
 
     pub fn get_frustum_corners_with_near_far(&self, near: f32, far: f32) -> [Vector; 8] {
-        let inverse_view_projection = (Matrix::new_projection(self.fov_y.to_radians(), self.aspect_ratio, near, far) * self.view_matrix).inverse4();
+        let inverse_view_projection = (self.projection_with_near_far(near, far) * self.view_matrix).inverse4();
         let mut corners = [
             Vector::new4(-1.0, 1.0, 0.0, 1.0),
             Vector::new4(1.0, 1.0, 0.0, 1.0),
@@ -1778,16 +2867,19 @@ impl CameraComponent {
     }
 }
 
+#[derive(Clone)]
 pub struct LightComponent {
     pub owner: usize,
     pub transform: usize,
 
     pub color: Vector,
-    pub light_type: u32,
+    pub light_type: u32, // 0 = point, 1 = directional, 2 = spotlight
+    pub direction: Vector, // in the space of the owner, +z unless imported
     pub quadratic_falloff: f32,
     pub linear_falloff: f32,
     pub constant_falloff: f32,
-    pub inner_cutoff: f32,
+    pub range: f32, // distance the light fades out at, 0 for unlimited
+    pub inner_cutoff: f32, // cosines of the spotlight's cone angles
     pub outer_cutoff: f32,
 }
 impl LightComponent {
@@ -1797,25 +2889,42 @@ impl LightComponent {
             transform: 0,
             color,
             light_type: 0,
+            direction,
             quadratic_falloff: 0.1,
             linear_falloff: 0.1,
             constant_falloff: 0.1,
+            range: 0.0,
             inner_cutoff: 0.0,
             outer_cutoff: 0.0,
         }
     }
+    ///* A KHR_lights_punctual light, its intensity is folded into the color. Point and spot lights fall off with the
+    ///* inverse square of the distance, as the extension specifies.
+    pub fn from_gltf(light: &GltfLight) -> LightComponent {
+        let mut component = LightComponent::new(Vector::new(), Vector::new3(0.0, 0.0, -1.0), light.color * light.intensity);
+        (component.quadratic_falloff, component.linear_falloff, component.constant_falloff) = (1.0, 0.0, 0.0);
+        component.range = light.range.unwrap_or(0.0);
+        match light.light_type {
+            GltfLightType::Point => component.light_type = 0,
+            GltfLightType::Directional => component.light_type = 1,
+            GltfLightType::Spot { inner_cone_angle, outer_cone_angle } => {
+                component.light_type = 2;
+                component.inner_cutoff = inner_cone_angle.cos();
+                component.outer_cutoff = outer_cone_angle.cos();
+            }
+        }
+        component
+    }
     pub fn to_sendable(&self, transform: &Transform) -> LightSendable {
         LightSendable {
             position: transform.world_translation.to_array3(),
-            _pad0: 0u32,
-            direction: Vector::new3(0.0, 0.0, 1.0).rotate_by_quat(&transform.world_rotation).to_array3(),
+            range: self.range,
+            direction: self.direction.rotate_by_quat(&transform.world_rotation).to_array3(),
             light_type: self.light_type,
-            attenuation_values:
-            if self.light_type == 0 { [self.quadratic_falloff, self.linear_falloff, self.constant_falloff] }
-            else { [self.inner_cutoff, self.outer_cutoff, 0.0] },
-            _pad1: 0u32,
+            attenuation_values: [self.quadratic_falloff, self.linear_falloff, self.constant_falloff],
+            inner_cutoff: self.inner_cutoff,
             color: self.color.to_array3(),
-            _pad2: 0u32,
+            outer_cutoff: self.outer_cutoff,
         }
     }
 }
@@ -1918,20 +3027,17 @@ impl SunComponent {
     }
 }
 
-/*
-TODO()
-    - Scripts exist as ScriptAssets in the LuaEngine
-    - ScriptComponents have ScriptInstances, which are instances of ScriptAssets
-    - ScriptInstances are called with a "self" being set in their environment
-    - Entities should have a HashMap of FieldComponents, which are per-entity fields that can be accessed by scripts
-    - eg. Lua: local health = self:get_field("health")
-    - eg. Lua: self:set_field("health", 10.0)
-*/
+///* A script attached to an entity. While the scene is running, the script engine gives each component its own instance
+///* of the script, with its own environment and "self" set to the owning entity.
+///* fields holds the values of the script's exported top-level declarations for this component, and is what gets saved.
+#[derive(Clone)]
 pub struct ScriptComponent {
     pub owner: usize,
-    pub script: usize,
+    pub uri: String,
+    pub instance: Option<usize>, // id of the script engine's instance, None until the script engine has created it
     pub fields: HashMap<String, Field>,
 }
+#[derive(Clone, Debug)]
 pub enum Field {
     Float(f32),
     Int(i32),
@@ -1939,18 +3045,30 @@ pub enum Field {
     Bool(bool),
     String(String),
 }
+impl Field {
+    /// The value converted to this field's type, None if it has a different type. Float fields take integers, which Lua does not tell apart from floats.
+    pub fn coerce(&self, value: Field) -> Option<Field> {
+        let value = match (self, value) {
+            (Field::Float(_), Field::Int(v)) => Field::Float(v as f32),
+            (_, value) => value,
+        };
+        (discriminant(self) == discriminant(&value)).then_some(value)
+    }
+}
 
 #[derive(Clone, Debug, Copy)]
 #[repr(C)]
 pub struct Instance {
     pub matrix: [f32; 16],
     pub indices: [i32; 4], // material id, skin id, owner entity id, component child id
+    pub morph_targets: [i32; 4], // first morph delta (-1 for none), morph target count, first vertex, unused
 }
 impl Instance {
     pub fn new(matrix: Matrix, material: u32, skin: i32, owner_entity: u32, component_number: u32) -> Self {
         Self {
             matrix: matrix.data,
             indices: [material as i32, skin, owner_entity as i32, component_number as i32],
+            morph_targets: [-1, 0, 0, 0],
         }
     }
 }
@@ -2015,6 +3133,16 @@ pub fn linear_displacement_to_local(dx: Vector, parent: &Transform) -> Vector {
 pub fn angular_displacement_to_local(dq: Vector, parent: &Transform) -> Vector {
     parent.world_rotation.inverse_quat().combine(&dq.combine(&parent.world_rotation))
 }
+/// The local translation, rotation and scale that keep the world transform the same under another parent
+pub fn world_transform_to_local(transform: &Transform, parent: &Transform) -> (Vector, Vector, Vector) {
+    let translation = world_position_to_local(transform.world_translation, parent);
+    // parent^-1 * (world * parent^-1) * parent = parent^-1 * world
+    let rotation = angular_displacement_to_local(
+        transform.world_rotation.combine(&parent.world_rotation.inverse_quat()),
+        parent
+    ).normalize4();
+    (translation, rotation, transform.world_scale / parent.world_scale)
+}
 
 fn test_axis(
     axis: &Vector,
@@ -0,0 +1,659 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use json::JsonValue;
+use crate::math::Vector;
+use crate::scene::animation_controller::AnimationParameter;
+use crate::scene::ik::{IkConstraint, IkSolver, IkTarget};
+use crate::scene::physics::hitboxes::bounding_box::BoundingBox;
+use crate::scene::physics::hitboxes::capsule::Capsule;
+use crate::scene::physics::hitboxes::convex_hull::ConvexHull;
+use crate::scene::physics::hitboxes::hitbox::Hitbox;
+use crate::scene::physics::hitboxes::mesh::MeshCollider;
+use crate::scene::physics::hitboxes::sphere::Sphere;
+use crate::scene::scene::{AnimationMarker, CameraComponent, Field, LightComponent, Scene, ScriptComponent, SunComponent, DEFAULT_LAYERS};
+
+///* Written to every saved scene and prefab. Bump when the layout changes, and migrate older versions in Scene::load.
+///* 1: entities with their transforms, models, suns, lights, cameras, animations, rigid bodies and scripts
+///* 2: prefab links
+///* 3: tags and layers
+///* 4: custom components
+///* 5: animation time, paused, speed, ping pong and markers
+///* 6: animation weight, layer, additive and mask
+///* 7: animation controllers
+///* 8: morph weights
+///* 9: light range and orthographic cameras
+///* 10: IK constraints
+pub const SCENE_FORMAT_VERSION: u32 = 10;
+
+impl Scene {
+    ///* Writes the scene to a json file.
+    ///* Imported models are stored by their gltf uri, and re-imported on load with the saved overrides applied to their nodes.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut entities = JsonValue::new_array();
+        for entity_index in 0..self.entities.len() {
+            // removed entities are kept as null so that saved indices line up
+            entities.push(if self.entities[entity_index].alive {
+                self.entity_to_json(entity_index)
+            } else {
+                JsonValue::Null
+            })?;
+        }
+
+        let json = json::object! {
+            "version": SCENE_FORMAT_VERSION,
+            "runtime": self.runtime,
+            "entities": entities,
+        };
+        fs::write(path, json.pretty(4))?;
+        Ok(())
+    }
+
+    ///* Replaces the contents of the scene with a scene file written by Scene::save.
+    pub fn load(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let json = json::parse(fs::read_to_string(path)?.as_str())?;
+
+        let version = json["version"].as_u32().ok_or("scene file has no version")?;
+        if version > SCENE_FORMAT_VERSION {
+            return Err(format!(
+                "scene file version {} is newer than the supported version {}", version, SCENE_FORMAT_VERSION
+            ).into())
+        }
+        // every version so far only added fields, which older files lack and which load with their defaults: no prefab link,
+        // no tags and the default layers, no custom components, animations at their start with speed 1 and no markers, full
+        // weight on layer 0 without being additive or masked, no animation controller, no morph weights, lights without a
+        // range, perspective cameras and no IK constraints
+
+        let saved_entities: Vec<&JsonValue> = json["entities"].members().collect();
+        if saved_entities.is_empty() {
+            return Err("scene file has no root entity".into())
+        }
+
+        self.clear();
+        self.runtime = json["runtime"].as_f32().unwrap_or(0.0);
+        self.spawn_saved_entities(&saved_entities, None)?;
+
+        Ok(())
+    }
+
+    ///* Creates saved entities with their components, in the layout written by Scene::save, and returns the index of the first one.
+    ///* With a parent the first saved entity is created as its child, otherwise it is applied to the scene's root entity.
+    pub(crate) fn spawn_saved_entities(&mut self, saved_entities: &[&JsonValue], parent_index: Option<usize>) -> Result<usize, Box<dyn Error>> {
+        let mapped_entities = self.spawn_saved_entities_mapped(saved_entities, parent_index)?;
+        Ok(mapped_entities[0].unwrap())
+    }
+    /// Like spawn_saved_entities, but returns the new index of every saved entity, None for null entries
+    pub(crate) fn spawn_saved_entities_mapped(&mut self, saved_entities: &[&JsonValue], parent_index: Option<usize>) -> Result<Vec<Option<usize>>, Box<dyn Error>> {
+        let spawn_parent_index = parent_index.unwrap_or(0);
+        let first_spawned = if parent_index.is_some() { 0 } else { 1 };
+
+        // saved entity index -> new entity index
+        let mut mapped_entities: Vec<Option<usize>> = vec![None; saved_entities.len()];
+        if parent_index.is_none() {
+            mapped_entities[0] = Some(0);
+        }
+
+        // import models first, since they create the entities of all of their nodes
+        let mut model_nodes: HashMap<usize, HashMap<usize, usize>> = HashMap::new();
+        for (saved_index, entity_json) in saved_entities.iter().enumerate().skip(first_spawned) {
+            if let Some(uri) = entity_json["model"].as_str() {
+                let entity_index = self.new_entity_from_model(spawn_parent_index, uri)?;
+                mapped_entities[saved_index] = Some(entity_index);
+                model_nodes.insert(saved_index, (entity_index..self.entities.len())
+                    .filter_map(|i| self.entities[i].model_node
+                        .filter(|node| node.0 == entity_index)
+                        .map(|node| (node.1, i))
+                    ).collect());
+            }
+        }
+        for (saved_index, entity_json) in saved_entities.iter().enumerate().skip(first_spawned) {
+            if mapped_entities[saved_index].is_some() || entity_json.is_null() { continue }
+
+            let model_node = &entity_json["model_node"];
+            mapped_entities[saved_index] = Some(if model_node.is_null() {
+                self.new_entity(spawn_parent_index, entity_json["name"].as_str().unwrap_or("entity"))
+            } else {
+                let (root, node) = (json_usize(&model_node["root"])?, json_usize(&model_node["node"])?);
+                *model_nodes.get(&root).and_then(|nodes| nodes.get(&node)).ok_or_else(|| format!(
+                    "entity {} references node {} of model entity {}, which does not exist", saved_index, node, root
+                ))?
+            });
+        }
+        let map = |saved_index: usize| -> Result<usize, Box<dyn Error>> {
+            mapped_entities.get(saved_index).copied().flatten()
+                .ok_or_else(|| format!("scene file references missing entity {}", saved_index).into())
+        };
+
+        // model nodes that were removed before saving
+        let mut unsaved_nodes: Vec<usize> = model_nodes.values()
+            .flat_map(|nodes| nodes.values().copied())
+            .filter(|entity_index| !mapped_entities.contains(&Some(*entity_index)))
+            .collect();
+        unsaved_nodes.sort();
+        for entity_index in unsaved_nodes {
+            self.remove_entity(entity_index, false);
+        }
+
+        // hierarchy, everything was created under the spawn parent and is moved to its saved parent
+        self.entities[spawn_parent_index].children_indices.retain(|i| !mapped_entities.contains(&Some(*i)));
+        for (saved_index, entity_json) in saved_entities.iter().enumerate() {
+            if entity_json.is_null() { continue }
+            let entity_index = map(saved_index)?;
+            let parent = if saved_index == 0 { spawn_parent_index } else { map(json_usize(&entity_json["parent"])?)? };
+            let children = entity_json["children"].members()
+                .map(|child| json_usize(child).and_then(map))
+                .collect::<Result<Vec<usize>, _>>()?;
+
+            let entity = &mut self.entities[entity_index];
+            if let Some(name) = entity_json["name"].as_str() {
+                entity.name = String::from(name);
+            }
+            entity.tags = entity_json["tags"].members().filter_map(|tag| tag.as_str().map(String::from)).collect();
+            entity.layers = entity_json["layers"].as_u32().unwrap_or(DEFAULT_LAYERS);
+            if entity_json["morph_weights"].is_array() {
+                entity.morph_weights = entity_json["morph_weights"].members().map(json_f32).collect::<Result<Vec<f32>, _>>()?;
+            }
+            entity.parent = parent;
+            entity.children_indices = children;
+        }
+        if parent_index.is_some() {
+            self.entities[spawn_parent_index].children_indices.push(map(0)?);
+        }
+
+        // components
+        for (saved_index, entity_json) in saved_entities.iter().enumerate() {
+            if entity_json.is_null() { continue }
+            let entity_index = map(saved_index)?;
+
+            let transform_json = &entity_json["transform"];
+            if !transform_json.is_null() {
+                let transform = &mut self.transforms[self.entities[entity_index].transform];
+                transform.local_translation = json_vector(&transform_json["translation"])?;
+                transform.local_rotation = json_vector(&transform_json["rotation"])?;
+                transform.local_scale = json_vector(&transform_json["scale"])?;
+            }
+
+            let sun_json = &entity_json["sun"];
+            if !sun_json.is_null() {
+                let sun = SunComponent::new_sun(json_vector(&sun_json["direction"])?, json_vector(&sun_json["color"])?);
+                if let Some(sun_index) = self.entities[entity_index].sun {
+                    self.sun_components[sun_index] = sun;
+                } else {
+                    self.entities[entity_index].sun = Some(self.sun_components.len());
+                    self.sun_components.push(sun);
+                }
+            }
+
+            let light_json = &entity_json["light"];
+            if !light_json.is_null() {
+                let direction = if light_json["direction"].is_null() { Vector::new3(0.0, 0.0, 1.0) } else { json_vector(&light_json["direction"])? };
+                let mut light = LightComponent::new(Vector::new(), direction, json_vector(&light_json["color"])?);
+                light.light_type = light_json["light_type"].as_u32().unwrap_or(0);
+                light.range = light_json["range"].as_f32().unwrap_or(0.0);
+                light.quadratic_falloff = json_f32(&light_json["quadratic_falloff"])?;
+                light.linear_falloff = json_f32(&light_json["linear_falloff"])?;
+                light.constant_falloff = json_f32(&light_json["constant_falloff"])?;
+                light.inner_cutoff = json_f32(&light_json["inner_cutoff"])?;
+                light.outer_cutoff = json_f32(&light_json["outer_cutoff"])?;
+                // model nodes already have the light they were imported with
+                if let Some(light_index) = self.entities[entity_index].light {
+                    light.owner = entity_index;
+                    light.transform = self.entities[entity_index].transform;
+                    self.light_components[light_index] = light;
+                    self.dirty_light_components.push(light_index);
+                } else {
+                    self.attach_light(entity_index, light);
+                }
+            }
+
+            let camera_json = &entity_json["camera"];
+            if !camera_json.is_null() {
+                let mut camera = if camera_json["orthographic"].is_array() {
+                    CameraComponent::new_orthographic_rotation(
+                        json_f32(&camera_json["orthographic"][0])?,
+                        json_f32(&camera_json["orthographic"][1])?,
+                        json_f32(&camera_json["near"])?,
+                        json_f32(&camera_json["far"])?,
+                        json_vector(&camera_json["third_person_vector"])?,
+                    )
+                } else {
+                    CameraComponent::new_perspective_rotation(
+                        json_f32(&camera_json["fov_y"])?,
+                        json_f32(&camera_json["aspect_ratio"])?,
+                        json_f32(&camera_json["near"])?,
+                        json_f32(&camera_json["far"])?,
+                        camera_json["infinite_reverse"].as_bool().unwrap_or(false),
+                        json_vector(&camera_json["third_person_vector"])?,
+                    )
+                };
+                camera.third_person = camera_json["third_person"].as_bool().unwrap_or(false);
+                if let Some(camera_index) = self.entities[entity_index].camera {
+                    camera.owner = entity_index;
+                    camera.transform = self.entities[entity_index].transform;
+                    self.camera_components[camera_index] = camera;
+                    self.dirty_camera_components.push(camera_index);
+                } else {
+                    self.attach_camera(entity_index, camera);
+                }
+            }
+
+            for (animation_json, animation_index) in entity_json["animations"].members()
+                .zip(self.entities[entity_index].animation_objects.clone())
+            {
+                let animation = &mut self.animation_components[animation_index];
+                animation.repeat = animation_json["repeat"].as_bool().unwrap_or(animation.repeat);
+                animation.snap_back = animation_json["snap_back"].as_bool().unwrap_or(animation.snap_back);
+                animation.ping_pong = animation_json["ping_pong"].as_bool().unwrap_or(false);
+                animation.speed = animation_json["speed"].as_f32().unwrap_or(1.0);
+                animation.markers = animation_json["markers"].members().map(|marker_json| AnimationMarker {
+                    name: marker_json["name"].as_str().unwrap_or("").to_string(),
+                    time: marker_json["time"].as_f32().unwrap_or(0.0),
+                }).collect();
+                animation.layer = animation_json["layer"].as_u32().unwrap_or(0);
+                animation.additive = animation_json["additive"].as_bool().unwrap_or(false);
+                animation.mask = if animation_json["mask"].is_null() { None } else {
+                    Some(animation_json["mask"].members().map(|masked| json_usize(masked).and_then(map)).collect::<Result<Vec<usize>, _>>()?)
+                };
+                if animation_json["running"].as_bool().unwrap_or(false) {
+                    animation.start();
+                    animation.weight = animation_json["weight"].as_f32().unwrap_or(1.0);
+                    animation.seek(animation_json["time"].as_f32().unwrap_or(animation.time));
+                    animation.paused = animation_json["paused"].as_bool().unwrap_or(false);
+                }
+            }
+
+            let body_json = &entity_json["rigid_body"];
+            if !body_json.is_null() {
+                let hitbox = self.hitbox_from_json(entity_index, &body_json["hitbox"])?;
+                let body_index = self.attach_rigid_body(entity_index, hitbox, body_json["static"].as_bool().unwrap_or(true));
+
+                let body = &mut self.rigid_body_components[body_index];
+                body.owned_by_player = body_json["owned_by_player"].as_bool().unwrap_or(false);
+                body.restitution_coefficient = json_f32(&body_json["restitution_coefficient"])?;
+                body.friction_coefficient = json_f32(&body_json["friction_coefficient"])?;
+                body.velocity = json_vector(&body_json["velocity"])?;
+                body.angular_velocity = json_vector(&body_json["angular_velocity"])?;
+                body.set_mass(&self.hitbox_components[body.hitbox].hitbox, &self.transforms, json_f32(&body_json["mass"])?);
+            }
+
+            for script_json in entity_json["scripts"].members() {
+                let mut fields = HashMap::new();
+                for (name, field_json) in script_json["fields"].entries() {
+                    fields.insert(String::from(name), field_from_json(field_json)?);
+                }
+                self.script_components.push(ScriptComponent {
+                    owner: entity_index,
+                    uri: String::from(script_json["uri"].as_str().ok_or("script component has no uri")?),
+                    instance: None,
+                    fields,
+                });
+            }
+
+            for component_json in entity_json["components"].members() {
+                let name = component_json["type"].as_str().ok_or("custom component has no type")?;
+                let mut fields = Vec::new();
+                for (field_name, field_json) in component_json["fields"].entries() {
+                    fields.push((String::from(field_name), field_from_json(field_json)?));
+                }
+                // types that have not been registered yet, eg. by a script that has not run, get a schema from the saved fields
+                if self.component_schema(name).is_none() {
+                    self.register_component(name, fields.clone());
+                }
+                let component_index = self.attach_custom_component(entity_index, name)?;
+                for (field_name, value) in fields {
+                    // values that no longer fit the registered schema keep their defaults
+                    self.set_custom_field(component_index, field_name.as_str(), value).ok();
+                }
+            }
+
+            let controller_json = &entity_json["animation_controller"];
+            if !controller_json.is_null() {
+                let uri = controller_json["uri"].as_str().ok_or("animation controller has no uri")?;
+                let controller_index = self.attach_animation_controller(entity_index, uri)?;
+                let controller = &mut self.animation_controllers[controller_index];
+                if let Some(state) = controller_json["state"].as_str().and_then(|name| controller.states.iter().position(|state| state.name == name)) {
+                    controller.current_state = state;
+                    // the clips were restored with the animations, so the state is not entered again
+                    controller.entered = controller_json["entered"].as_bool().unwrap_or(false);
+                }
+                for (name, value_json) in controller_json["parameters"].entries() {
+                    let value = match controller.parameters.get(name) {
+                        Some(AnimationParameter::Float(_)) => value_json.as_f32().map(AnimationParameter::Float),
+                        Some(AnimationParameter::Bool(_)) => value_json.as_bool().map(AnimationParameter::Bool),
+                        Some(AnimationParameter::Trigger(_)) => value_json.as_bool().map(AnimationParameter::Trigger),
+                        None => None,
+                    };
+                    // parameters that were removed from the controller, or changed type, keep their defaults
+                    if let Some(value) = value {
+                        controller.set_parameter(name, value).ok();
+                    }
+                }
+            }
+
+            // the hierarchy is already restored, so the chains can be validated
+            for constraint_json in entity_json["ik_constraints"].members() {
+                let solver_name = constraint_json["solver"].as_str().ok_or("IK constraint has no solver")?;
+                let solver = IkSolver::from_name(solver_name).ok_or_else(|| format!("unknown IK solver {}", solver_name))?;
+                let chain = constraint_json["chain"].members().map(|joint| json_usize(joint).and_then(map)).collect::<Result<Vec<usize>, _>>()?;
+                let target = ik_target_from_json(&constraint_json["target"], &map)?;
+                let pole = if constraint_json["pole"].is_null() { None } else { Some(ik_target_from_json(&constraint_json["pole"], &map)?) };
+
+                let constraint_index = self.add_ik_constraint(solver, chain, target)?;
+                let constraint = &mut self.ik_constraints[constraint_index];
+                constraint.pole = pole;
+                if !constraint_json["aim_axis"].is_null() {
+                    constraint.aim_axis = json_vector(&constraint_json["aim_axis"])?;
+                }
+                constraint.weight = constraint_json["weight"].as_f32().unwrap_or(1.0);
+                constraint.enabled = constraint_json["enabled"].as_bool().unwrap_or(true);
+                constraint.iterations = constraint_json["iterations"].as_usize().unwrap_or(constraint.iterations);
+                constraint.tolerance = constraint_json["tolerance"].as_f32().unwrap_or(constraint.tolerance);
+            }
+
+            if let Some(uri) = entity_json["prefab"].as_str() {
+                self.entities[entity_index].prefab = Some(self.load_prefab(uri)?);
+            }
+        }
+
+        map(0)?;
+        Ok(mapped_entities)
+    }
+
+    pub(crate) fn entity_to_json(&self, entity_index: usize) -> JsonValue {
+        let entity = &self.entities[entity_index];
+        let transform = &self.transforms[entity.transform];
+
+        let mut json = json::object! {
+            "name": entity.name.as_str(),
+            "parent": entity.parent,
+            "children": entity.children_indices.clone(),
+            "transform": {
+                "translation": vector_to_json(&transform.local_translation, 3),
+                "rotation": vector_to_json(&transform.local_rotation, 4),
+                "scale": vector_to_json(&transform.local_scale, 3),
+            },
+        };
+
+        if let Some(uri) = &entity.model_uri {
+            json["model"] = uri.as_str().into();
+        }
+        if let Some((root, node)) = entity.model_node {
+            json["model_node"] = json::object! { "root": root, "node": node };
+        }
+        if let Some(prefab_index) = entity.prefab {
+            json["prefab"] = self.prefabs[prefab_index].uri.as_str().into();
+        }
+        if !entity.tags.is_empty() {
+            json["tags"] = entity.tags.clone().into();
+        }
+        if entity.layers != DEFAULT_LAYERS {
+            json["layers"] = entity.layers.into();
+        }
+        if !entity.morph_weights.is_empty() {
+            json["morph_weights"] = entity.morph_weights.clone().into();
+        }
+
+        if let Some(sun_index) = entity.sun {
+            let sun = &self.sun_components[sun_index];
+            json["sun"] = json::object! {
+                "direction": vector_to_json(&sun.direction, 3),
+                "color": vector_to_json(&sun.color, 3),
+            };
+        }
+
+        if let Some(light_index) = entity.light {
+            let light = &self.light_components[light_index];
+            json["light"] = json::object! {
+                "color": vector_to_json(&light.color, 3),
+                "light_type": light.light_type,
+                "direction": vector_to_json(&light.direction, 3),
+                "quadratic_falloff": light.quadratic_falloff,
+                "linear_falloff": light.linear_falloff,
+                "constant_falloff": light.constant_falloff,
+                "range": light.range,
+                "inner_cutoff": light.inner_cutoff,
+                "outer_cutoff": light.outer_cutoff,
+            };
+        }
+
+        if let Some(camera_index) = entity.camera {
+            let camera = &self.camera_components[camera_index];
+            json["camera"] = json::object! {
+                "fov_y": camera.fov_y,
+                "aspect_ratio": camera.aspect_ratio,
+                "near": camera.near,
+                "far": camera.far,
+                "infinite_reverse": camera.infinite_reverse,
+                "third_person": camera.third_person,
+                "third_person_vector": vector_to_json(&camera.third_person_vector, 3),
+            };
+            if let Some((half_width, half_height)) = camera.orthographic {
+                json["camera"]["orthographic"] = json::array![half_width, half_height];
+            }
+        }
+
+        if !entity.animation_objects.is_empty() {
+            json["animations"] = entity.animation_objects.iter().map(|&animation_index| {
+                let animation = &self.animation_components[animation_index];
+                json::object! {
+                    "running": animation.running,
+                    "paused": animation.paused,
+                    "time": animation.time,
+                    "speed": animation.speed,
+                    "repeat": animation.repeat,
+                    "ping_pong": animation.ping_pong,
+                    "snap_back": animation.snap_back,
+                    "weight": animation.weight,
+                    "layer": animation.layer,
+                    "additive": animation.additive,
+                    "mask": animation.mask.clone(),
+                    "markers": animation.markers.iter().map(|marker| json::object! {
+                        "name": marker.name.clone(),
+                        "time": marker.time,
+                    }).collect::<Vec<JsonValue>>(),
+                }
+            }).collect::<Vec<JsonValue>>().into();
+        }
+
+        if let Some(body_index) = entity.rigid_body {
+            let body = &self.rigid_body_components[body_index];
+            json["rigid_body"] = json::object! {
+                "hitbox": hitbox_to_json(&self.hitbox_components[body.hitbox].hitbox),
+                "static": body.is_static,
+                "owned_by_player": body.owned_by_player,
+                "mass": body.mass,
+                "restitution_coefficient": body.restitution_coefficient,
+                "friction_coefficient": body.friction_coefficient,
+                "velocity": vector_to_json(&body.velocity, 3),
+                "angular_velocity": vector_to_json(&body.angular_velocity, 3),
+            };
+        }
+
+        let scripts: Vec<JsonValue> = self.script_components.iter()
+            .filter(|script| script.owner == entity_index)
+            .map(|script| {
+                let mut fields = JsonValue::new_object();
+                for (name, field) in script.fields.iter() {
+                    fields[name.as_str()] = field_to_json(field);
+                }
+                json::object! { "uri": script.uri.as_str(), "fields": fields }
+            }).collect();
+        if !scripts.is_empty() {
+            json["scripts"] = scripts.into();
+        }
+
+        let components: Vec<JsonValue> = self.custom_components.iter()
+            .filter(|component| component.owner == entity_index)
+            .map(|component| {
+                let schema = &self.component_schemas[component.schema];
+                let mut fields = JsonValue::new_object();
+                // in schema order, so saved files stay stable
+                for (name, _) in schema.fields.iter() {
+                    fields[name.as_str()] = field_to_json(&component.fields[name]);
+                }
+                json::object! { "type": schema.name.as_str(), "fields": fields }
+            }).collect();
+        if !components.is_empty() {
+            json["components"] = components.into();
+        }
+
+        if let Some(controller_index) = self.get_animation_controller(entity_index) {
+            let controller = &self.animation_controllers[controller_index];
+            let mut parameters = JsonValue::new_object();
+            let mut names: Vec<&String> = controller.parameters.keys().collect();
+            names.sort();
+            for name in names {
+                parameters[name.as_str()] = match controller.parameters[name] {
+                    AnimationParameter::Float(value) => value.into(),
+                    AnimationParameter::Bool(value) | AnimationParameter::Trigger(value) => value.into(),
+                };
+            }
+            json["animation_controller"] = json::object! {
+                "uri": controller.uri.as_str(),
+                "state": controller.states[controller.current_state].name.as_str(),
+                "entered": controller.entered,
+                "parameters": parameters,
+            };
+        }
+
+        // constraints are saved with the first joint of their chain
+        let ik_constraints: Vec<JsonValue> = self.ik_constraints.iter()
+            .filter(|constraint| constraint.chain.first() == Some(&entity_index))
+            .map(ik_constraint_to_json)
+            .collect();
+        if !ik_constraints.is_empty() {
+            json["ik_constraints"] = ik_constraints.into();
+        }
+
+        json
+    }
+
+    fn hitbox_from_json(&self, entity_index: usize, json: &JsonValue) -> Result<Hitbox, Box<dyn Error>> {
+        Ok(match json["type"].as_str().ok_or("hitbox has no type")? {
+            "OBB" => {
+                let bounds = BoundingBox {
+                    center: json_vector(&json["center"])?,
+                    half_extents: json_vector(&json["half_extents"])?,
+                };
+                Hitbox::OBB(bounds, ConvexHull::from_bounds(&bounds))
+            }
+            "Sphere" => Hitbox::Sphere(Sphere {
+                center: json_vector(&json["center"])?,
+                radius: json_f32(&json["radius"])?,
+            }),
+            "Capsule" => Hitbox::Capsule(Capsule {
+                a: json_vector(&json["a"])?,
+                b: json_vector(&json["b"])?,
+                radius: json_f32(&json["radius"])?,
+            }),
+            "ConvexHull" => Hitbox::ConvexHull(ConvexHull::new(
+                json["points"].members().map(json_vector).collect::<Result<Vec<Vector>, _>>()?
+            )),
+            // mesh colliders are rebuilt from the mesh of the owning entity
+            "Mesh" => {
+                let render_object = *self.entities[entity_index].render_objects.first()
+                    .ok_or("mesh hitbox saved on an entity without a mesh")?;
+                let mesh_index = self.render_components[render_object].mesh_primitive_index().0;
+                Hitbox::Mesh(MeshCollider::new(&self.world.borrow().meshes[mesh_index], json_vector(&json["scale"])?))
+            }
+            other => return Err(format!("unknown hitbox type {}", other).into())
+        })
+    }
+}
+
+fn hitbox_to_json(hitbox: &Hitbox) -> JsonValue {
+    match hitbox {
+        Hitbox::OBB(bounds, _) => json::object! {
+            "type": "OBB",
+            "center": vector_to_json(&bounds.center, 3),
+            "half_extents": vector_to_json(&bounds.half_extents, 3),
+        },
+        Hitbox::Sphere(sphere) => json::object! {
+            "type": "Sphere",
+            "center": vector_to_json(&sphere.center, 3),
+            "radius": sphere.radius,
+        },
+        Hitbox::Capsule(capsule) => json::object! {
+            "type": "Capsule",
+            "a": vector_to_json(&capsule.a, 3),
+            "b": vector_to_json(&capsule.b, 3),
+            "radius": capsule.radius,
+        },
+        Hitbox::ConvexHull(convex) => json::object! {
+            "type": "ConvexHull",
+            "points": convex.points.iter().map(|p| vector_to_json(p, 3)).collect::<Vec<JsonValue>>(),
+        },
+        Hitbox::Mesh(mesh) => json::object! {
+            "type": "Mesh",
+            "scale": vector_to_json(&mesh.current_scale_factor, 3),
+        },
+    }
+}
+
+pub(crate) fn ik_constraint_to_json(constraint: &IkConstraint) -> JsonValue {
+    json::object! {
+        "solver": constraint.solver.name(),
+        "chain": constraint.chain.clone(),
+        "target": ik_target_to_json(&constraint.target),
+        "pole": constraint.pole.as_ref().map_or(JsonValue::Null, ik_target_to_json),
+        "aim_axis": vector_to_json(&constraint.aim_axis, 3),
+        "weight": constraint.weight,
+        "enabled": constraint.enabled,
+        "iterations": constraint.iterations,
+        "tolerance": constraint.tolerance,
+    }
+}
+fn ik_target_to_json(target: &IkTarget) -> JsonValue {
+    match target {
+        IkTarget::Entity(entity_index) => json::object! { "entity": *entity_index },
+        IkTarget::Position(position) => json::object! { "position": vector_to_json(position, 3) },
+    }
+}
+fn ik_target_from_json(json: &JsonValue, map: &dyn Fn(usize) -> Result<usize, Box<dyn Error>>) -> Result<IkTarget, Box<dyn Error>> {
+    if json["entity"].is_null() {
+        Ok(IkTarget::Position(json_vector(&json["position"])?))
+    } else {
+        Ok(IkTarget::Entity(map(json_usize(&json["entity"])?)?))
+    }
+}
+
+pub(crate) fn field_to_json(field: &Field) -> JsonValue {
+    match field {
+        Field::Float(v) => json::object! { "type": "Float", "value": *v },
+        Field::Int(v) => json::object! { "type": "Int", "value": *v },
+        Field::Vec3(v) => json::object! { "type": "Vec3", "value": vector_to_json(v, 3) },
+        Field::Bool(v) => json::object! { "type": "Bool", "value": *v },
+        Field::String(v) => json::object! { "type": "String", "value": v.as_str() },
+    }
+}
+pub(crate) fn field_from_json(json: &JsonValue) -> Result<Field, Box<dyn Error>> {
+    let value = &json["value"];
+    Ok(match json["type"].as_str().ok_or("field has no type")? {
+        "Float" => Field::Float(json_f32(value)?),
+        "Int" => Field::Int(value.as_i32().ok_or("Int field is not an integer")?),
+        "Vec3" => Field::Vec3(json_vector(value)?),
+        "Bool" => Field::Bool(value.as_bool().ok_or("Bool field is not a boolean")?),
+        "String" => Field::String(String::from(value.as_str().ok_or("String field is not a string")?)),
+        other => return Err(format!("unknown field type {}", other).into())
+    })
+}
+
+pub(crate) fn vector_to_json(vector: &Vector, components: usize) -> JsonValue {
+    vector.to_array4()[..components].to_vec().into()
+}
+/// Reads a 3 or 4 component array, w defaults to 1
+pub(crate) fn json_vector(json: &JsonValue) -> Result<Vector, Box<dyn Error>> {
+    let components = json.members().map(|c| c.as_f32()).collect::<Option<Vec<f32>>>()
+        .ok_or("vector has non-numeric components")?;
+    match components.len() {
+        3 => Ok(Vector::new3(components[0], components[1], components[2])),
+        4 => Ok(Vector::new4(components[0], components[1], components[2], components[3])),
+        n => Err(format!("expected a vector of 3 or 4 components, found {}", n).into())
+    }
+}
+pub(crate) fn json_f32(json: &JsonValue) -> Result<f32, Box<dyn Error>> {
+    json.as_f32().ok_or_else(|| format!("expected a number, found {}", json.dump()).into())
+}
+pub(crate) fn json_usize(json: &JsonValue) -> Result<usize, Box<dyn Error>> {
+    json.as_usize().ok_or_else(|| format!("expected an index, found {}", json.dump()).into())
+}
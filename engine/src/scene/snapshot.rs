@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::error::Error;
+use json::JsonValue;
+use crate::math::Vector;
+use crate::scene::animation_controller::AnimationParameter;
+use crate::scene::ik::AppliedRotation;
+use crate::scene::scene::{CameraComponent, Field, LightComponent, Scene};
+
+///* The simulated state of a scene, used to return to the pre-play state when the scene stops running.
+///* Restored in place while the scene has the same entities and components, otherwise the scene is rebuilt from the entities saved with it.
+pub struct SceneSnapshot {
+    entities: Vec<JsonValue>, // in the layout of Scene::save, null for removed entities
+    runtime: f32,
+    physics_accumulator: f32,
+    entity_generations: Vec<Option<u32>>, // None for removed entities
+    animated_flags: Vec<bool>,
+    morph_weights: Vec<(Vec<f32>, Option<Vec<f32>>)>, // weights, animated weights
+    transforms: Vec<(Vector, Vector, Vector)>, // local translation, rotation, scale
+    sun_directions: Vec<Vector>,
+    lights: Vec<LightComponent>,
+    cameras: Vec<CameraComponent>,
+    rigid_bodies: Vec<RigidBodyState>,
+    animations: Vec<AnimationState>,
+    script_fields: Vec<HashMap<String, Field>>,
+    custom_fields: Vec<HashMap<String, Field>>,
+    animation_controllers: Vec<(usize, bool, HashMap<String, AnimationParameter>)>, // current state, entered, parameters
+    ik_applied: Vec<Vec<AppliedRotation>>,
+}
+struct RigidBodyState {
+    x_i: Vector,
+    x_f: Vector,
+    q_i: Vector,
+    q_f: Vector,
+    rendered_pose: Option<(Vector, Vector)>,
+    velocity: Vector,
+    angular_velocity: Vector,
+}
+struct AnimationState {
+    time: f32,
+    speed: f32,
+    direction: f32,
+    bounced: bool,
+    running: bool,
+    paused: bool,
+    weight: f32,
+    fade: Option<(f32, f32, bool)>,
+    repeat: bool,
+    snap_back: bool,
+}
+
+impl Scene {
+    pub fn snapshot(&self) -> SceneSnapshot {
+        SceneSnapshot {
+            entities: (0..self.entities.len())
+                .map(|entity_index| if self.entities[entity_index].alive { self.entity_to_json(entity_index) } else { JsonValue::Null })
+                .collect(),
+            runtime: self.runtime,
+            physics_accumulator: self.physics_accumulator,
+            entity_generations: self.entities.iter().map(|e| e.alive.then_some(e.generation)).collect(),
+            animated_flags: self.entities.iter().map(|e| e.animated_transform.1).collect(),
+            morph_weights: self.entities.iter().map(|e| (e.morph_weights.clone(), e.animated_morph_weights.clone())).collect(),
+            transforms: self.transforms.iter().map(|t| (t.local_translation, t.local_rotation, t.local_scale)).collect(),
+            sun_directions: self.sun_components.iter().map(|s| s.direction).collect(),
+            lights: self.light_components.clone(),
+            cameras: self.camera_components.clone(),
+            rigid_bodies: self.rigid_body_components.iter().map(|body| RigidBodyState {
+                x_i: body.x_i,
+                x_f: body.x_f,
+                q_i: body.q_i,
+                q_f: body.q_f,
+                rendered_pose: body.rendered_pose,
+                velocity: body.velocity,
+                angular_velocity: body.angular_velocity,
+            }).collect(),
+            animations: self.animation_components.iter().map(|animation| AnimationState {
+                time: animation.time,
+                speed: animation.speed,
+                direction: animation.direction,
+                bounced: animation.bounced,
+                running: animation.running,
+                paused: animation.paused,
+                weight: animation.weight,
+                fade: animation.fade,
+                repeat: animation.repeat,
+                snap_back: animation.snap_back,
+            }).collect(),
+            script_fields: self.script_components.iter().map(|script| script.fields.clone()).collect(),
+            custom_fields: self.custom_components.iter().map(|component| component.fields.clone()).collect(),
+            animation_controllers: self.animation_controllers.iter()
+                .map(|controller| (controller.current_state, controller.entered, controller.parameters.clone()))
+                .collect(),
+            ik_applied: self.ik_constraints.iter().map(|constraint| constraint.applied.clone()).collect(),
+        }
+    }
+
+    ///* Returns the scene to the state it was in when the snapshot was taken.
+    ///* If entities or components were added or removed since, the scene's entities are rebuilt from the snapshot, which renumbers them.
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) -> Result<(), Box<dyn Error>> {
+        let generations: Vec<Option<u32>> = self.entities.iter().map(|e| e.alive.then_some(e.generation)).collect();
+        if generations != snapshot.entity_generations
+            || self.transforms.len() != snapshot.transforms.len()
+            || self.sun_components.len() != snapshot.sun_directions.len()
+            || self.light_components.len() != snapshot.lights.len()
+            || self.camera_components.len() != snapshot.cameras.len()
+            || self.rigid_body_components.len() != snapshot.rigid_bodies.len()
+            || self.animation_components.len() != snapshot.animations.len()
+            || self.script_components.len() != snapshot.script_fields.len()
+            || self.custom_components.len() != snapshot.custom_fields.len()
+            || self.animation_controllers.len() != snapshot.animation_controllers.len()
+            || self.ik_constraints.len() != snapshot.ik_applied.len()
+        {
+            return self.rebuild(snapshot)
+        }
+
+        self.runtime = snapshot.runtime;
+        self.physics_accumulator = snapshot.physics_accumulator;
+        for (entity, &animated) in self.entities.iter_mut().zip(snapshot.animated_flags.iter()) {
+            entity.animated_transform.1 = animated;
+        }
+        for (entity, (weights, animated_weights)) in self.entities.iter_mut().zip(snapshot.morph_weights.iter()) {
+            entity.morph_weights = weights.clone();
+            entity.animated_morph_weights = animated_weights.clone();
+        }
+        for (transform, &(translation, rotation, scale)) in self.transforms.iter_mut().zip(snapshot.transforms.iter()) {
+            transform.local_translation = translation;
+            transform.local_rotation = rotation;
+            transform.local_scale = scale;
+        }
+        for (sun, &direction) in self.sun_components.iter_mut().zip(snapshot.sun_directions.iter()) {
+            sun.direction = direction;
+        }
+        self.light_components.clone_from(&snapshot.lights);
+        self.dirty_light_components.extend(0..self.light_components.len());
+        self.camera_components.clone_from(&snapshot.cameras);
+        self.dirty_camera_components.extend(0..self.camera_components.len());
+        for (body, state) in self.rigid_body_components.iter_mut().zip(snapshot.rigid_bodies.iter()) {
+            body.x_i = state.x_i;
+            body.x_f = state.x_f;
+            body.q_i = state.q_i;
+            body.q_f = state.q_f;
+            body.rendered_pose = state.rendered_pose;
+            body.velocity = state.velocity;
+            body.angular_velocity = state.angular_velocity;
+        }
+        for (animation, state) in self.animation_components.iter_mut().zip(snapshot.animations.iter()) {
+            animation.time = state.time;
+            animation.speed = state.speed;
+            animation.direction = state.direction;
+            animation.bounced = state.bounced;
+            animation.running = state.running;
+            animation.paused = state.paused;
+            animation.weight = state.weight;
+            animation.fade = state.fade;
+            animation.repeat = state.repeat;
+            animation.snap_back = state.snap_back;
+        }
+        for (script, fields) in self.script_components.iter_mut().zip(snapshot.script_fields.iter()) {
+            script.fields = fields.clone();
+            // the script engine recreates instances from the restored fields the next time the scene runs
+            script.instance = None;
+        }
+        for (component, fields) in self.custom_components.iter_mut().zip(snapshot.custom_fields.iter()) {
+            component.fields = fields.clone();
+        }
+        for (controller, (current_state, entered, parameters)) in self.animation_controllers.iter_mut().zip(snapshot.animation_controllers.iter()) {
+            controller.current_state = *current_state;
+            controller.entered = *entered;
+            controller.parameters = parameters.clone();
+        }
+        for (constraint, applied) in self.ik_constraints.iter_mut().zip(snapshot.ik_applied.iter()) {
+            constraint.applied = applied.clone();
+        }
+
+        self.unupdated_entities.push(0);
+        Ok(())
+    }
+    ///* Replaces the scene's entities with the ones saved in the snapshot, keeping the world's models so they are not parsed again.
+    ///* Simulation state that scene files do not store, like interpolated physics poses, starts over.
+    ///* The undo history is dropped with the old entity indices it refers to.
+    fn rebuild(&mut self, snapshot: &SceneSnapshot) -> Result<(), Box<dyn Error>> {
+        let saved_entities: Vec<&JsonValue> = snapshot.entities.iter().collect();
+        self.clear_entities();
+        self.runtime = snapshot.runtime;
+        self.physics_accumulator = snapshot.physics_accumulator;
+        self.spawn_saved_entities(&saved_entities, None)?;
+        Ok(())
+    }
+
+    ///* Starts or stops the simulation.
+    ///* With revert_on_stop set, a snapshot is taken when the scene starts running and restored when it stops.
+    ///* The scene stops even if it could not be restored.
+    pub fn set_running(&mut self, running: bool) -> Result<(), Box<dyn Error>> {
+        if running == self.running { return Ok(()) }
+        self.running = running;
+
+        if running && self.revert_on_stop {
+            self.play_snapshot = Some(self.snapshot());
+        } else if !running && let Some(snapshot) = self.play_snapshot.take() {
+            self.restore(&snapshot).map_err(|e| format!("could not revert the scene to its state before running: {}", e))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+use crate::math::matrix::Matrix;
+use crate::math::Vector;
+use crate::scene::physics::hitboxes::hitbox::Hitbox;
+use crate::scene::scene::{Frustum, Scene};
+
+const FAT_MARGIN: f32 = 0.1; // leaves are stored this much larger, so small movements don't restructure the tree
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vector,
+    pub max: Vector,
+}
+impl Aabb {
+    pub fn new(min: Vector, max: Vector) -> Self {
+        Self { min: Vector::min(&min, &max).with('w', 1.0), max: Vector::max(&min, &max).with('w', 1.0) }
+    }
+    pub fn from_points(points: &[Vector]) -> Option<Self> {
+        let (first, rest) = points.split_first()?;
+        let (min, max) = rest.iter().fold((*first, *first), |(min, max), point| (Vector::min(&min, point), Vector::max(&max, point)));
+        Some(Self::new(min, max))
+    }
+    pub fn from_sphere(center: Vector, radius: f32) -> Self {
+        Self::new(center - Vector::fill(radius), center + Vector::fill(radius))
+    }
+
+    pub fn corners(&self) -> [Vector; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vector::new3(a.x, a.y, a.z), Vector::new3(b.x, a.y, a.z), Vector::new3(a.x, b.y, a.z), Vector::new3(b.x, b.y, a.z),
+            Vector::new3(a.x, a.y, b.z), Vector::new3(b.x, a.y, b.z), Vector::new3(a.x, b.y, b.z), Vector::new3(b.x, b.y, b.z),
+        ]
+    }
+    /// The box around this box's corners after the transform
+    pub fn transformed(&self, matrix: &Matrix) -> Self {
+        Self::from_points(&self.corners().map(|corner| *matrix * corner)).unwrap()
+    }
+    /// The box around this box's corners rotated by the quaternion, then translated
+    pub fn rotated(&self, translation: &Vector, rotation: &Vector) -> Self {
+        Self::from_points(&self.corners().map(|corner| corner.rotate_by_quat(rotation) + translation)).unwrap()
+    }
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self { min: Vector::min(&self.min, &other.min), max: Vector::max(&self.max, &other.max) }
+    }
+    pub fn expanded(&self, margin: f32) -> Self {
+        Self::new(self.min - Vector::fill(margin), self.max + Vector::fill(margin))
+    }
+    fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.x <= other.min.x && self.min.y <= other.min.y && self.min.z <= other.min.z &&
+        self.max.x >= other.max.x && self.max.y >= other.max.y && self.max.z >= other.max.z
+    }
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.min.y <= other.max.y && self.min.z <= other.max.z &&
+        self.max.x >= other.min.x && self.max.y >= other.min.y && self.max.z >= other.min.z
+    }
+    pub fn overlaps_sphere(&self, center: &Vector, radius: f32) -> bool {
+        let closest = center.clamp3(&self.min, &self.max);
+        (closest - center).magnitude3_sq() <= radius * radius
+    }
+    /// False only if the box is entirely behind one of the frustum's planes, so boxes near the corners can pass
+    pub fn in_frustum(&self, frustum: &Frustum) -> bool {
+        frustum.planes.iter().all(|plane| {
+            let n = plane.normal;
+            // the corner furthest along the plane's normal
+            let corner = Vector::new3(
+                if n.x >= 0.0 { self.max.x } else { self.min.x },
+                if n.y >= 0.0 { self.max.y } else { self.min.y },
+                if n.z >= 0.0 { self.max.z } else { self.min.z },
+            );
+            plane.test_point_within(&corner)
+        })
+    }
+    /// The distance along the normalized direction at which the ray enters the box, 0 if it starts inside
+    pub fn ray_distance(&self, origin: &Vector, direction: &Vector, max_distance: f32) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = max_distance;
+        for axis in 0..3 {
+            let (o, d, min, max) = match axis {
+                0 => (origin.x, direction.x, self.min.x, self.max.x),
+                1 => (origin.y, direction.y, self.min.y, self.max.y),
+                _ => (origin.z, direction.z, self.min.z, self.max.z),
+            };
+            if d.abs() < 1e-8 {
+                if o < min || o > max { return None }
+                continue
+            }
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_min > t_max { return None }
+        }
+        Some(t_min)
+    }
+}
+
+struct TreeNode {
+    bounds: Aabb, // fattened for leaves
+    parent: Option<usize>,
+    children: Option<[usize; 2]>, // None for leaves
+    item: usize,
+    item_bounds: Aabb, // the exact bounds the leaf was given
+}
+
+///* A dynamic AABB tree over items given by index, eg. entities. Leaves are kept fattened, so an item is only reinserted
+///* once it moves out of its leaf, and queries test the exact bounds of the leaves they reach.
+pub struct SpatialIndex {
+    nodes: Vec<TreeNode>,
+    free_nodes: Vec<usize>,
+    root: Option<usize>,
+    leaves: Vec<Option<usize>>, // node of each item
+}
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: None,
+            leaves: Vec::new(),
+        }
+    }
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free_nodes.clear();
+        self.root = None;
+        self.leaves.clear();
+    }
+
+    pub fn bounds(&self, item: usize) -> Option<Aabb> {
+        self.leaves.get(item).copied().flatten().map(|leaf| self.nodes[leaf].item_bounds)
+    }
+    /// Inserts the item, or moves it to its new bounds
+    pub fn update(&mut self, item: usize, bounds: Aabb) {
+        if let Some(leaf) = self.leaves.get(item).copied().flatten() {
+            self.nodes[leaf].item_bounds = bounds;
+            if self.nodes[leaf].bounds.contains(&bounds) { return }
+            self.detach_leaf(leaf);
+            self.nodes[leaf].bounds = bounds.expanded(FAT_MARGIN);
+            self.attach_leaf(leaf);
+            return
+        }
+
+        let leaf = self.allocate_node(TreeNode {
+            bounds: bounds.expanded(FAT_MARGIN),
+            parent: None,
+            children: None,
+            item,
+            item_bounds: bounds,
+        });
+        if self.leaves.len() <= item {
+            self.leaves.resize(item + 1, None);
+        }
+        self.leaves[item] = Some(leaf);
+        self.attach_leaf(leaf);
+    }
+    pub fn remove(&mut self, item: usize) {
+        let Some(leaf) = self.leaves.get_mut(item).and_then(|leaf| leaf.take()) else { return };
+        self.detach_leaf(leaf);
+        self.free_nodes.push(leaf);
+    }
+
+    /// Items whose bounds overlap the box
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        self.query(|bounds| bounds.overlaps(aabb))
+    }
+    /// Items whose bounds overlap the sphere
+    pub fn query_sphere(&self, center: &Vector, radius: f32) -> Vec<usize> {
+        self.query(|bounds| bounds.overlaps_sphere(center, radius))
+    }
+    /// Items whose bounds are not entirely outside of the frustum
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<usize> {
+        self.query(|bounds| bounds.in_frustum(frustum))
+    }
+    /// Items whose bounds the ray enters within max_distance, nearest first, with the distance it enters at
+    pub fn query_ray(&self, origin: &Vector, direction: &Vector, max_distance: f32) -> Vec<(usize, f32)> {
+        let direction = direction.normalize3();
+        let mut hits: Vec<(usize, f32)> = self.query(|bounds| bounds.ray_distance(origin, &direction, max_distance).is_some())
+            .into_iter()
+            .map(|item| (item, self.bounds(item).unwrap().ray_distance(origin, &direction, max_distance).unwrap()))
+            .collect();
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+    fn query(&self, test: impl Fn(&Aabb) -> bool) -> Vec<usize> {
+        let mut items = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !test(&node.bounds) { continue }
+            match node.children {
+                Some(children) => stack.extend(children),
+                None => if test(&node.item_bounds) { items.push(node.item) },
+            }
+        }
+        items
+    }
+
+    fn allocate_node(&mut self, node: TreeNode) -> usize {
+        if let Some(index) = self.free_nodes.pop() {
+            self.nodes[index] = node;
+            index
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        }
+    }
+    /// Pairs the leaf with the node it adds the least surface area to
+    fn attach_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.nodes[leaf].parent = None;
+            self.root = Some(leaf);
+            return
+        };
+        let leaf_bounds = self.nodes[leaf].bounds;
+
+        let mut sibling = root;
+        while let Some(children) = self.nodes[sibling].children {
+            let area = self.nodes[sibling].bounds.surface_area();
+            let combined_area = self.nodes[sibling].bounds.union(&leaf_bounds).surface_area();
+            // cost of making a new parent for this node and the leaf, or of pushing the leaf further down
+            let cost = 2.0 * combined_area;
+            let inheritance_cost = 2.0 * (combined_area - area);
+            let child_cost = |child: usize| {
+                let child_node = &self.nodes[child];
+                let union_area = child_node.bounds.union(&leaf_bounds).surface_area();
+                match child_node.children {
+                    Some(_) => union_area - child_node.bounds.surface_area() + inheritance_cost,
+                    None => union_area + inheritance_cost,
+                }
+            };
+            let (cost_a, cost_b) = (child_cost(children[0]), child_cost(children[1]));
+            if cost < cost_a && cost < cost_b { break }
+            sibling = if cost_a < cost_b { children[0] } else { children[1] };
+        }
+
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node(TreeNode {
+            bounds: self.nodes[sibling].bounds.union(&leaf_bounds),
+            parent: old_parent,
+            children: Some([sibling, leaf]),
+            item: 0,
+            item_bounds: leaf_bounds,
+        });
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(old_parent) => {
+                let children = self.nodes[old_parent].children.as_mut().unwrap();
+                if children[0] == sibling { children[0] = new_parent } else { children[1] = new_parent }
+                self.refit(Some(old_parent));
+            }
+            None => self.root = Some(new_parent),
+        }
+    }
+    /// Takes the leaf out of the tree, its parent is replaced by its sibling
+    fn detach_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return
+        }
+        let parent = self.nodes[leaf].parent.unwrap();
+        let children = self.nodes[parent].children.unwrap();
+        let sibling = if children[0] == leaf { children[1] } else { children[0] };
+        let grandparent = self.nodes[parent].parent;
+
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => {
+                let children = self.nodes[grandparent].children.as_mut().unwrap();
+                if children[0] == parent { children[0] = sibling } else { children[1] = sibling }
+                self.refit(Some(grandparent));
+            }
+            None => self.root = Some(sibling),
+        }
+        self.free_nodes.push(parent);
+        self.nodes[leaf].parent = None;
+    }
+    fn refit(&mut self, mut node: Option<usize>) {
+        while let Some(index) = node {
+            let [a, b] = self.nodes[index].children.unwrap();
+            self.nodes[index].bounds = self.nodes[a].bounds.union(&self.nodes[b].bounds);
+            node = self.nodes[index].parent;
+        }
+    }
+}
+
+impl Scene {
+    ///* The world space box around the entity's render components and hitbox, None if it has neither.
+    ///* Render components are bounded in their bind pose, so skinned and morphed ones can reach outside of it.
+    pub fn entity_world_bounds(&self, entity_index: usize) -> Option<Aabb> {
+        let entity = &self.entities[entity_index];
+        let world = self.world.borrow();
+        let render_bounds = entity.render_objects.iter().map(|&render_component_index| {
+            let render_component = &self.render_components[render_component_index];
+            let (mesh_index, primitive_index) = render_component.mesh_primitive_index();
+            let primitive = &world.meshes[mesh_index].primitives[primitive_index];
+            Aabb::new(primitive.min, primitive.max).transformed(&self.transforms[render_component.transform()].world())
+        });
+        let body_bounds = entity.rigid_body.map(|body_index| self.rigid_body_bounds(body_index));
+        render_bounds.chain(body_bounds).reduce(|a, b| a.union(&b))
+    }
+    /// The world space box around the body's hitbox at its simulated pose
+    pub fn rigid_body_bounds(&self, body_index: usize) -> Aabb {
+        let body = &self.rigid_body_components[body_index];
+        match &self.hitbox_components[body.hitbox].hitbox {
+            Hitbox::Sphere(sphere) => Aabb::from_sphere(sphere.center.rotate_by_quat(&body.q_f) + body.x_f, sphere.radius),
+            hitbox => {
+                let (min, max) = hitbox.local_min_max();
+                Aabb::new(min, max).rotated(&body.x_f, &body.q_f)
+            }
+        }
+    }
+
+    /// Brings the entity's leaf in the spatial index up to date with its bounds
+    pub fn refresh_entity_bounds(&mut self, entity_index: usize) {
+        let bounds = self.entities[entity_index].alive.then(|| self.entity_world_bounds(entity_index)).flatten();
+        match bounds {
+            Some(bounds) => self.spatial_index.update(entity_index, bounds),
+            None => self.spatial_index.remove(entity_index),
+        }
+    }
+    pub(crate) fn update_spatial_index(&mut self) {
+        let mut dirty_bounds = std::mem::take(&mut self.dirty_bounds);
+        dirty_bounds.sort_unstable();
+        dirty_bounds.dedup();
+        for entity_index in dirty_bounds {
+            self.refresh_entity_bounds(entity_index);
+        }
+    }
+
+    /// Entities whose bounds overlap the sphere, by index
+    pub fn overlap_sphere(&self, center: &Vector, radius: f32) -> Vec<usize> {
+        let mut entities = self.spatial_index.query_sphere(center, radius);
+        entities.sort_unstable();
+        entities
+    }
+    /// Entities whose bounds overlap the box, by index
+    pub fn overlap_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        let mut entities = self.spatial_index.query_aabb(aabb);
+        entities.sort_unstable();
+        entities
+    }
+    /// Entities whose bounds the ray passes through, nearest first
+    pub fn raycast_bounds(&self, origin: &Vector, direction: &Vector, max_distance: f32) -> Vec<(usize, f32)> {
+        self.spatial_index.query_ray(origin, direction, max_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::scene::Plane;
+
+    /// Unit boxes at known places, with the item index they were added with
+    fn index_of_boxes() -> SpatialIndex {
+        let mut index = SpatialIndex::new();
+        for (item, center) in [
+            Vector::new3(0.0, 0.0, 5.0),
+            Vector::new3(5.0, 0.0, 5.0),
+            Vector::new3(0.0, 0.0, -5.0),
+            Vector::new3(1.2, 0.0, 5.0),
+            Vector::new3(0.0, 8.0, 20.0),
+        ].into_iter().enumerate() {
+            index.update(item, Aabb::from_sphere(center, 0.5));
+        }
+        index
+    }
+    fn sorted(mut items: Vec<usize>) -> Vec<usize> {
+        items.sort_unstable();
+        items
+    }
+
+    #[test]
+    fn frustum_query_keeps_boxes_inside_or_crossing_the_planes() {
+        // the box from (-1, -1, 0) to (1, 1, 10), with the planes facing inwards
+        let plane = |normal: Vector, point: Vector| Plane { normal, point };
+        let frustum = Frustum {
+            planes: [
+                plane(Vector::new3(0.0, 0.0, 1.0), Vector::new3(0.0, 0.0, 0.0)),
+                plane(Vector::new3(0.0, 0.0, -1.0), Vector::new3(0.0, 0.0, 10.0)),
+                plane(Vector::new3(1.0, 0.0, 0.0), Vector::new3(-1.0, 0.0, 0.0)),
+                plane(Vector::new3(-1.0, 0.0, 0.0), Vector::new3(1.0, 0.0, 0.0)),
+                plane(Vector::new3(0.0, 1.0, 0.0), Vector::new3(0.0, -1.0, 0.0)),
+                plane(Vector::new3(0.0, -1.0, 0.0), Vector::new3(0.0, 1.0, 0.0)),
+            ],
+        };
+        assert_eq!(sorted(index_of_boxes().query_frustum(&frustum)), vec![0, 3]);
+    }
+
+    #[test]
+    fn sphere_query_finds_the_boxes_it_touches() {
+        let index = index_of_boxes();
+        assert_eq!(sorted(index.query_sphere(&Vector::new3(0.0, 0.0, -5.0), 1.0)), vec![2]);
+        assert!(index.query_sphere(&Vector::new3(3.0, 0.0, 5.0), 1.0).is_empty());
+        assert_eq!(sorted(index.query_sphere(&Vector::new3(3.0, 0.0, 5.0), 1.6)), vec![1, 3]);
+    }
+
+    #[test]
+    fn ray_query_returns_the_boxes_it_enters_nearest_first() {
+        let index = index_of_boxes();
+        let origin = Vector::new3(0.0, 0.0, -10.0);
+        let direction = Vector::new3(0.0, 0.0, 2.0);
+
+        let hits = index.query_ray(&origin, &direction, f32::INFINITY);
+        assert_eq!(hits.iter().map(|hit| hit.0).collect::<Vec<usize>>(), vec![2, 0]);
+        assert!((hits[0].1 - 4.5).abs() < 1e-5 && (hits[1].1 - 14.5).abs() < 1e-5, "got {:?}", hits);
+
+        assert_eq!(index.query_ray(&origin, &direction, 10.0).len(), 1);
+        assert!(index.query_ray(&origin, &Vector::new3(0.0, 0.0, -1.0), f32::INFINITY).is_empty());
+    }
+}
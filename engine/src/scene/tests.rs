@@ -0,0 +1,266 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::math::Vector;
+use crate::scene::history::TransformField;
+use crate::scene::ik::{IkSolver, IkTarget};
+use crate::scene::physics::hitboxes::hitbox::Hitbox;
+use crate::scene::physics::hitboxes::sphere::Sphere;
+use crate::scene::physics::physics_engine::PhysicsEngine;
+use crate::scene::scene::{AnimationComponent, AnimationMarker, PhysicsInterpolation, Scene, Transform, DEFAULT_LAYERS};
+use crate::scene::scene_file::SCENE_FORMAT_VERSION;
+use crate::scene::world::world::{AnimationSampler, Interpolation, World};
+
+fn headless_scene() -> Scene {
+    let world = Arc::new(RefCell::new(World::new_headless()));
+    let physics_engine = Arc::new(RefCell::new(PhysicsEngine::new(Vector::new3(0.0, -9.8, 0.0), 0.0, 0.0)));
+    Scene::new_headless(world, physics_engine)
+}
+fn world_translation(scene: &Scene, entity_index: usize) -> Vector {
+    scene.transforms[scene.entities[entity_index].transform].world_translation
+}
+/// Sets the entity's translation and records it, like the scene API does
+fn record_translation(scene: &mut Scene, entity_index: usize, translation: Vector) {
+    let transform_index = scene.entities[entity_index].transform;
+    let before = scene.transform_field(transform_index, TransformField::Translation);
+    scene.set_transform_field(transform_index, TransformField::Translation, translation);
+    let edit = scene.transform_edit(transform_index, TransformField::Translation, before, translation);
+    scene.record(edit);
+}
+fn local_translation(scene: &Scene, entity_index: usize) -> Vector {
+    scene.transforms[scene.entities[entity_index].transform].local_translation
+}
+fn assert_near(actual: Vector, expected: Vector) {
+    // only xyz is compared, w is left over from the matrix math for positions
+    assert!((actual - expected).magnitude3() < 1e-4, "expected {:?}, got {:?}", expected, actual);
+}
+
+#[test]
+fn children_follow_their_parent() {
+    let mut scene = headless_scene();
+    let parent = scene.new_entity(0, "parent");
+    let child = scene.new_entity(parent, "child");
+    scene.transforms[scene.entities[parent].transform].local_translation = Vector::new3(1.0, 2.0, 3.0);
+    scene.transforms[scene.entities[child].transform].local_translation = Vector::new3(0.0, 1.0, 0.0);
+
+    scene.update(0, 0.0, false);
+
+    assert_eq!(scene.entities[child].parent, parent);
+    assert_eq!(scene.entities[parent].children_indices, vec![child]);
+    assert_near(world_translation(&scene, child), Vector::new3(1.0, 3.0, 3.0));
+}
+
+#[test]
+fn reparenting_keeps_or_resets_the_world_transform() {
+    let mut scene = headless_scene();
+    let first = scene.new_entity(0, "first");
+    let second = scene.new_entity(0, "second");
+    let child = scene.new_entity(first, "child");
+    scene.transforms[scene.entities[first].transform].local_translation = Vector::new3(1.0, 0.0, 0.0);
+    scene.transforms[scene.entities[second].transform].local_translation = Vector::new3(0.0, 0.0, 5.0);
+    scene.update(0, 0.0, false);
+
+    scene.set_parent(child, second, true).unwrap();
+    scene.update(0, 0.0, false);
+    assert!(!scene.entities[first].children_indices.contains(&child));
+    assert_eq!(scene.entities[second].children_indices, vec![child]);
+    assert_near(world_translation(&scene, child), Vector::new3(1.0, 0.0, 0.0));
+
+    scene.set_parent(child, first, false).unwrap();
+    scene.update(0, 0.0, false);
+    assert_near(world_translation(&scene, child), Vector::new3(2.0, 0.0, -5.0));
+
+    assert!(scene.set_parent(first, child, false).is_err(), "an entity cannot be parented to its own descendant");
+}
+
+#[test]
+fn removing_an_entity_keeps_its_children_in_place() {
+    let mut scene = headless_scene();
+    let parent = scene.new_entity(0, "parent");
+    let child = scene.new_entity(parent, "child");
+    scene.transforms[scene.entities[parent].transform].local_translation = Vector::new3(1.0, 2.0, 3.0);
+    scene.transforms[scene.entities[child].transform].local_translation = Vector::new3(0.0, 1.0, 0.0);
+    scene.update(0, 0.0, false);
+
+    scene.remove_entity(parent, false);
+    scene.update(0, 0.0, false);
+    assert_eq!(scene.entities[child].parent, 0);
+    assert!(scene.entities[0].children_indices.contains(&child));
+    assert_near(world_translation(&scene, child), Vector::new3(1.0, 3.0, 3.0));
+}
+
+#[test]
+fn one_physics_step_applies_gravity() {
+    let mut scene = headless_scene();
+    scene.physics_rate = 60.0;
+    scene.physics_interpolation = PhysicsInterpolation::None;
+    let ball = scene.new_entity(0, "ball");
+    scene.attach_rigid_body(ball, Hitbox::Sphere(Sphere { center: Vector::new(), radius: 0.5 }), false);
+
+    let dt = 1.0 / 60.0;
+    scene.set_running(true).unwrap();
+    scene.update(0, dt, false);
+
+    let body = &scene.rigid_body_components[scene.entities[ball].rigid_body.unwrap()];
+    assert_near(body.velocity, Vector::new3(0.0, -9.8 * dt, 0.0));
+    assert_near(world_translation(&scene, ball), Vector::new3(0.0, -9.8 * dt * dt, 0.0));
+}
+
+#[test]
+fn running_animations_are_sampled_into_the_animated_transform() {
+    let mut scene = headless_scene();
+    let entity = scene.new_entity(0, "animated");
+    // imported model nodes get a second transform for animations to write to, this gives one to a plain entity
+    let animated_transform = scene.transforms.len();
+    let mut transform = Transform::default();
+    transform.owner = entity;
+    scene.transforms.push(transform);
+    scene.entities[entity].animated_transform = (animated_transform, false);
+
+    let sampler = AnimationSampler::new(vec![0.0, 1.0], Interpolation::Linear, vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0]);
+    let mut animation = AnimationComponent::new(entity, String::from("slide"), vec![(0, entity, String::from("translation"))], vec![sampler], 1.0);
+    animation.start();
+    scene.entities[entity].animation_objects.push(scene.animation_components.len());
+    scene.animation_components.push(animation);
+
+    scene.set_running(true).unwrap();
+    scene.update(0, 0.25, false);
+    assert_near(world_translation(&scene, entity), Vector::new3(0.5, 0.0, 0.0));
+    scene.update(0, 0.5, false);
+    assert_near(world_translation(&scene, entity), Vector::new3(1.5, 0.0, 0.0));
+    // the rest transform is untouched
+    assert_near(scene.transforms[scene.entities[entity].transform].local_translation, Vector::new());
+}
+
+#[test]
+fn markers_are_passed_once_per_loop() {
+    let mut animation = AnimationComponent::new(0, String::from("loop"), Vec::new(), Vec::new(), 1.0);
+    for (name, time) in [("start", 0.0), ("middle", 0.5), ("end", 1.0)] {
+        animation.markers.push(AnimationMarker { name: String::from(name), time });
+    }
+    animation.repeat = true;
+    animation.start();
+
+    assert_eq!(animation.update(0.5).0, vec!["start"]);
+    assert_eq!(animation.update(0.25).0, vec!["middle"]);
+    // wrapping passes the rest of the loop and the start of the next one
+    assert_eq!(animation.update(0.5).0, vec!["end", "start"]);
+    assert_eq!(animation.update(0.5).0, vec!["middle"]);
+}
+
+#[test]
+fn ik_constraints_are_saved_and_loaded() {
+    let mut scene = headless_scene();
+    let shoulder = scene.new_entity(0, "shoulder");
+    let elbow = scene.new_entity(shoulder, "elbow");
+    let hand = scene.new_entity(elbow, "hand");
+    let goal = scene.new_entity(0, "goal");
+    let constraint_index = scene.add_ik_constraint(IkSolver::TwoBone, vec![shoulder, elbow, hand], IkTarget::Entity(goal)).unwrap();
+    scene.ik_constraints[constraint_index].pole = Some(IkTarget::Position(Vector::new3(0.0, 0.0, -1.0)));
+    scene.ik_constraints[constraint_index].weight = 0.5;
+
+    let path = std::env::temp_dir().join(format!("ffengine_ik_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    scene.save(path).unwrap();
+    let mut loaded = headless_scene();
+    loaded.load(path).unwrap();
+    std::fs::remove_file(path).ok();
+
+    assert_eq!(loaded.ik_constraints.len(), 1);
+    let constraint = &loaded.ik_constraints[0];
+    assert_eq!(constraint.solver, IkSolver::TwoBone);
+    let names: Vec<&str> = constraint.chain.iter().map(|&joint| loaded.entities[joint].name.as_str()).collect();
+    assert_eq!(names, vec!["shoulder", "elbow", "hand"]);
+    assert!(matches!(constraint.target, IkTarget::Entity(target) if loaded.entities[target].name == "goal"));
+    assert!(matches!(constraint.pole, Some(IkTarget::Position(pole)) if pole.equals(&Vector::new3(0.0, 0.0, -1.0), 1e-6)));
+    assert_eq!(constraint.weight, 0.5);
+}
+
+#[test]
+fn older_scene_files_load_with_defaults_and_newer_ones_are_refused() {
+    let mut scene = headless_scene();
+    let tagged = scene.new_entity(0, "tagged");
+    scene.entities[tagged].tags.push(String::from("enemy"));
+    scene.entities[tagged].layers = 0b100;
+
+    let path = std::env::temp_dir().join(format!("ffengine_version_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    scene.save(path).unwrap();
+    let mut json = json::parse(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+    // tags and layers were added in version 3
+    json["version"] = 2.into();
+    for entity_json in json["entities"].members_mut() {
+        entity_json.remove("tags");
+        entity_json.remove("layers");
+    }
+    std::fs::write(path, json.dump()).unwrap();
+    let mut loaded = headless_scene();
+    loaded.load(path).unwrap();
+    let loaded_tagged = loaded.entities.iter().position(|entity| entity.name == "tagged").unwrap();
+    assert!(loaded.entities[loaded_tagged].tags.is_empty());
+    assert_eq!(loaded.entities[loaded_tagged].layers, DEFAULT_LAYERS);
+
+    json["version"] = (SCENE_FORMAT_VERSION + 1).into();
+    std::fs::write(path, json.dump()).unwrap();
+    let result = headless_scene().load(path);
+    std::fs::remove_file(path).ok();
+    assert!(result.is_err());
+}
+
+#[test]
+fn transform_edits_are_undone_and_redone() {
+    let mut scene = headless_scene();
+    let entity = scene.new_entity(0, "entity");
+    record_translation(&mut scene, entity, Vector::new3(1.0, 2.0, 3.0));
+
+    assert_eq!(scene.undo().unwrap().as_deref(), Some("Set translation of entity"));
+    assert_near(local_translation(&scene, entity), Vector::new3(0.0, 0.0, 0.0));
+    assert!(scene.history.can_redo());
+
+    assert_eq!(scene.redo().unwrap().as_deref(), Some("Set translation of entity"));
+    assert_near(local_translation(&scene, entity), Vector::new3(1.0, 2.0, 3.0));
+    assert!(!scene.history.can_redo());
+}
+
+#[test]
+fn edits_are_merged_only_within_the_merge_window() {
+    let mut scene = headless_scene();
+    let entity = scene.new_entity(0, "entity");
+    record_translation(&mut scene, entity, Vector::new3(1.0, 0.0, 0.0));
+    record_translation(&mut scene, entity, Vector::new3(2.0, 0.0, 0.0));
+    assert_eq!(scene.history_entries().len(), 1);
+
+    // as if the last edit was made two seconds ago
+    scene.history.last_edit = Some(Instant::now() - Duration::from_secs(2));
+    record_translation(&mut scene, entity, Vector::new3(3.0, 0.0, 0.0));
+    assert_eq!(scene.history_entries().len(), 2);
+
+    scene.undo().unwrap();
+    assert_near(local_translation(&scene, entity), Vector::new3(2.0, 0.0, 0.0));
+    scene.undo().unwrap();
+    assert_near(local_translation(&scene, entity), Vector::new3(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn undoing_a_removal_restores_the_entity_under_a_new_handle() {
+    let mut scene = headless_scene();
+    let parent = scene.new_entity(0, "parent");
+    let entity = scene.new_entity(parent, "entity");
+    scene.new_entity(entity, "child");
+    scene.transforms[scene.entities[entity].transform].local_translation = Vector::new3(0.0, 4.0, 0.0);
+    let handle = scene.entity_handle(entity);
+
+    scene.record_removal(entity, true);
+    scene.remove_entity(entity, true);
+    assert_eq!(scene.find_by_name("entity"), None);
+
+    assert_eq!(scene.undo().unwrap().as_deref(), Some("Remove entity"));
+    assert_eq!(scene.resolve(handle), None, "the old handle must not reach the restored entity");
+    let restored = scene.find_by_name("entity").unwrap();
+    assert_ne!(scene.entity_handle(restored), handle);
+    assert_eq!(scene.entities[restored].parent, parent);
+    assert_near(local_translation(&scene, restored), Vector::new3(0.0, 4.0, 0.0));
+    let children: Vec<&str> = scene.entities[restored].children_indices.iter().map(|&i| scene.entities[i].name.as_str()).collect();
+    assert_eq!(children, vec!["child"]);
+}
@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt;
+use json::JsonValue;
+
+///* A glTF file that could not be loaded, naming the file and the JSON path of the field at fault, eg. accessors[12].bufferView.
+///* Problems with the file as a whole, like an unreadable file or a malformed glb container, have an empty path.
+#[derive(Debug)]
+pub struct GltfError {
+    pub file: String,
+    pub path: String,
+    pub kind: GltfErrorKind,
+}
+#[derive(Debug)]
+pub enum GltfErrorKind {
+    Io(std::io::Error),
+    Malformed(String), // not valid JSON or not a valid glb container
+    Missing, // a required field is absent
+    Invalid(String), // the field has the wrong type or value, holding what was expected
+    OutOfRange { index: usize, count: usize }, // an index past the end of the array it refers to
+}
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if !self.path.is_empty() {
+            write!(f, ": {}", self.path)?;
+        }
+        match &self.kind {
+            GltfErrorKind::Io(e) => write!(f, ": {}", e),
+            GltfErrorKind::Malformed(message) => write!(f, ": {}", message),
+            GltfErrorKind::Missing => write!(f, " is missing"),
+            GltfErrorKind::Invalid(expected) => write!(f, " is invalid, expected {}", expected),
+            GltfErrorKind::OutOfRange { index, count } => write!(f, " is {}, but there are only {}", index, count),
+        }
+    }
+}
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            GltfErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+///* Reads fields of a glTF file's JSON, turning absent or mistyped fields into errors with their path.
+///* Optional fields are None when absent but still an error when present with the wrong type.
+pub(crate) struct GltfReader<'a> {
+    pub file: &'a str,
+}
+impl GltfReader<'_> {
+    pub fn error(&self, path: impl Into<String>, kind: GltfErrorKind) -> GltfError {
+        GltfError { file: String::from(self.file), path: path.into(), kind }
+    }
+
+    pub fn opt_usize(&self, json: &JsonValue, path: &str) -> Result<Option<usize>, GltfError> {
+        if json.is_null() { return Ok(None) }
+        json.as_usize().map(Some).ok_or_else(|| self.error(path, GltfErrorKind::Invalid(String::from("a non-negative integer"))))
+    }
+    pub fn usize(&self, json: &JsonValue, path: &str) -> Result<usize, GltfError> {
+        self.opt_usize(json, path)?.ok_or_else(|| self.error(path, GltfErrorKind::Missing))
+    }
+    /// An index into an array of count elements
+    pub fn opt_index(&self, json: &JsonValue, path: &str, count: usize) -> Result<Option<usize>, GltfError> {
+        match self.opt_usize(json, path)? {
+            Some(index) if index >= count => Err(self.error(path, GltfErrorKind::OutOfRange { index, count })),
+            index => Ok(index),
+        }
+    }
+    pub fn index(&self, json: &JsonValue, path: &str, count: usize) -> Result<usize, GltfError> {
+        self.opt_index(json, path, count)?.ok_or_else(|| self.error(path, GltfErrorKind::Missing))
+    }
+
+    pub fn opt_f32(&self, json: &JsonValue, path: &str) -> Result<Option<f32>, GltfError> {
+        if json.is_null() { return Ok(None) }
+        json.as_f32().map(Some).ok_or_else(|| self.error(path, GltfErrorKind::Invalid(String::from("a number"))))
+    }
+    pub fn f32(&self, json: &JsonValue, path: &str) -> Result<f32, GltfError> {
+        self.opt_f32(json, path)?.ok_or_else(|| self.error(path, GltfErrorKind::Missing))
+    }
+    pub fn opt_bool(&self, json: &JsonValue, path: &str) -> Result<Option<bool>, GltfError> {
+        if json.is_null() { return Ok(None) }
+        json.as_bool().map(Some).ok_or_else(|| self.error(path, GltfErrorKind::Invalid(String::from("a boolean"))))
+    }
+    pub fn opt_str<'j>(&self, json: &'j JsonValue, path: &str) -> Result<Option<&'j str>, GltfError> {
+        if json.is_null() { return Ok(None) }
+        json.as_str().map(Some).ok_or_else(|| self.error(path, GltfErrorKind::Invalid(String::from("a string"))))
+    }
+    pub fn str<'j>(&self, json: &'j JsonValue, path: &str) -> Result<&'j str, GltfError> {
+        self.opt_str(json, path)?.ok_or_else(|| self.error(path, GltfErrorKind::Missing))
+    }
+    /// The first N numbers of an array, None if it is absent or shorter
+    pub fn opt_floats<const N: usize>(&self, json: &JsonValue, path: &str) -> Result<Option<[f32; N]>, GltfError> {
+        let values = self.array(json, path)?;
+        if values.len() < N { return Ok(None) }
+        let mut floats = [0.0; N];
+        for (i, value) in values[..N].iter().enumerate() {
+            floats[i] = self.f32(value, &format!("{}[{}]", path, i))?;
+        }
+        Ok(Some(floats))
+    }
+    /// The elements of an array, empty if it is absent
+    pub fn array<'j>(&self, json: &'j JsonValue, path: &str) -> Result<&'j [JsonValue], GltfError> {
+        match json {
+            JsonValue::Null => Ok(&[]),
+            JsonValue::Array(values) => Ok(values),
+            _ => Err(self.error(path, GltfErrorKind::Invalid(String::from("an array")))),
+        }
+    }
+}
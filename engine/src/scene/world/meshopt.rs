@@ -0,0 +1,508 @@
+//! Decoders for EXT_meshopt_compression buffer views, which gltfpack -c writes. Decoded data replaces the contents of the
+//! view, which usually sits in a fallback buffer that has no data of its own.
+
+const VERTEX_HEADER: u8 = 0xa0;
+const INDEX_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+const BYTE_GROUP_SIZE: usize = 16;
+const BYTE_GROUP_DECODE_LIMIT: usize = 24; // the most bytes a group can read
+const TAIL_MAX_SIZE: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeshoptMode {
+    Attributes,
+    Triangles,
+    Indices,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeshoptFilter {
+    None,
+    Octahedral,
+    Quaternion,
+    Exponential,
+}
+
+///* Decodes count elements of stride bytes from source into destination, which has to hold count * stride bytes.
+///* Errors describe what is wrong with the compressed data.
+pub fn decode(destination: &mut [u8], count: usize, stride: usize, mode: MeshoptMode, filter: MeshoptFilter, source: &[u8]) -> Result<(), String> {
+    if filter != MeshoptFilter::None && mode != MeshoptMode::Attributes {
+        return Err(String::from("filters only apply to ATTRIBUTES"))
+    }
+    let destination = &mut destination[..count * stride];
+    match mode {
+        MeshoptMode::Attributes => decode_vertex_buffer(destination, count, stride, source)?,
+        MeshoptMode::Triangles => decode_index_buffer(destination, count, stride, source)?,
+        MeshoptMode::Indices => decode_index_sequence(destination, count, stride, source)?,
+    }
+    apply_filter(destination, stride, filter)
+}
+
+fn decode_vertex_buffer(destination: &mut [u8], count: usize, stride: usize, source: &[u8]) -> Result<(), String> {
+    if stride == 0 || stride > 256 || !stride.is_multiple_of(4) {
+        return Err(format!("ATTRIBUTES need a byteStride that is a multiple of 4 up to 256, not {}", stride))
+    }
+    if source.len() < 1 + stride {
+        return Err(String::from("vertex data is truncated"))
+    }
+    if source[0] & 0xf0 != VERTEX_HEADER || source[0] & 0x0f > 0 {
+        return Err(format!("unsupported vertex codec header {:#x}", source[0]))
+    }
+
+    // the tail ends with the first vertex, each channel is delta encoded from the previous vertex
+    let mut last_vertex = source[source.len() - stride..].to_vec();
+    let block_size = ((VERTEX_BLOCK_SIZE_BYTES / stride) & !(BYTE_GROUP_SIZE - 1)).min(VERTEX_BLOCK_MAX_SIZE);
+    let mut buffer = [0u8; VERTEX_BLOCK_MAX_SIZE];
+    let mut position = 1;
+    let mut first_vertex = 0;
+    while first_vertex < count {
+        let block_count = block_size.min(count - first_vertex);
+        let aligned_count = (block_count + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        for k in 0..stride {
+            position = decode_bytes(source, position, &mut buffer[..aligned_count])?;
+            let mut previous = last_vertex[k];
+            for (i, &encoded) in buffer[..block_count].iter().enumerate() {
+                let value = ((encoded >> 1) ^ (encoded & 1).wrapping_neg()).wrapping_add(previous);
+                destination[(first_vertex + i) * stride + k] = value;
+                previous = value;
+            }
+            last_vertex[k] = previous;
+        }
+        first_vertex += block_count;
+    }
+
+    if source.len() - position != stride.max(TAIL_MAX_SIZE) {
+        return Err(String::from("vertex data has trailing bytes"))
+    }
+    Ok(())
+}
+
+/// Decodes one byte of every element into buffer, returning the position after the data read
+fn decode_bytes(source: &[u8], position: usize, buffer: &mut [u8]) -> Result<usize, String> {
+    // two bits per group, saying how many bits its values are packed with
+    let header_size = (buffer.len() / BYTE_GROUP_SIZE).div_ceil(4);
+    if source.len() - position < header_size {
+        return Err(String::from("vertex data is truncated"))
+    }
+    let header = &source[position..position + header_size];
+    let mut data = position + header_size;
+    for (group_index, group) in buffer.chunks_mut(BYTE_GROUP_SIZE).enumerate() {
+        if source.len() - data < BYTE_GROUP_DECODE_LIMIT {
+            return Err(String::from("vertex data is truncated"))
+        }
+        let bits_log2 = (header[group_index / 4] >> ((group_index % 4) * 2)) & 3;
+        data = decode_bytes_group(source, data, group, bits_log2);
+    }
+    Ok(data)
+}
+
+fn decode_bytes_group(source: &[u8], data: usize, group: &mut [u8], bits_log2: u8) -> usize {
+    match bits_log2 {
+        0 => {
+            group.fill(0);
+            data
+        }
+        1 | 2 => {
+            // values are packed most significant bits first, a value of all ones is followed up by a full byte
+            let bits = 1 << bits_log2;
+            let sentinel = (1u8 << bits) - 1;
+            let mut extra = data + BYTE_GROUP_SIZE * bits / 8;
+            for (i, value) in group.iter_mut().enumerate() {
+                let bit = i * bits;
+                let encoded = (source[data + bit / 8] >> (8 - bits - bit % 8)) & sentinel;
+                if encoded == sentinel {
+                    *value = source[extra];
+                    extra += 1;
+                } else {
+                    *value = encoded;
+                }
+            }
+            extra
+        }
+        _ => {
+            group.copy_from_slice(&source[data..data + BYTE_GROUP_SIZE]);
+            data + BYTE_GROUP_SIZE
+        }
+    }
+}
+
+fn write_index(destination: &mut [u8], i: usize, index_size: usize, value: u32) {
+    if index_size == 2 {
+        destination[i * 2..i * 2 + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    } else {
+        destination[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn decode_vbyte(source: &[u8], data: &mut usize) -> u32 {
+    let mut result = 0;
+    for group in 0..5 {
+        let byte = source[*data];
+        *data += 1;
+        result |= ((byte & 127) as u32) << (7 * group);
+        if byte < 128 {
+            break
+        }
+    }
+    result
+}
+
+/// Zigzag encoded delta from the last free index
+fn decode_index(source: &[u8], data: &mut usize, last: u32) -> u32 {
+    let v = decode_vbyte(source, data);
+    last.wrapping_add((v >> 1) ^ (v & 1).wrapping_neg())
+}
+
+fn decode_index_buffer(destination: &mut [u8], count: usize, index_size: usize, source: &[u8]) -> Result<(), String> {
+    if !count.is_multiple_of(3) {
+        return Err(format!("TRIANGLES need a count divisible by 3, not {}", count))
+    }
+    if index_size != 2 && index_size != 4 {
+        return Err(format!("TRIANGLES need a byteStride of 2 or 4, not {}", index_size))
+    }
+    // a header, a code byte per triangle and the 16 byte table of common auxiliary codes
+    if source.len() < 1 + count / 3 + 16 {
+        return Err(String::from("index data is truncated"))
+    }
+    if source[0] & 0xf0 != INDEX_HEADER || source[0] & 0x0f > 1 {
+        return Err(format!("unsupported index codec header {:#x}", source[0]))
+    }
+    let version = source[0] & 0x0f;
+
+    let mut edge_fifo = [[u32::MAX; 2]; 16];
+    let mut vertex_fifo = [u32::MAX; 16];
+    let mut edge_offset = 0usize;
+    let mut vertex_offset = 0usize;
+    let push_edge = |fifo: &mut [[u32; 2]; 16], offset: &mut usize, a: u32, b: u32| {
+        fifo[*offset] = [a, b];
+        *offset = (*offset + 1) & 15;
+    };
+    let push_vertex = |fifo: &mut [u32; 16], offset: &mut usize, v: u32, condition: bool| {
+        fifo[*offset] = v;
+        *offset = (*offset + condition as usize) & 15;
+    };
+
+    let mut next = 0u32;
+    let mut last = 0u32;
+    let fec_max = if version >= 1 { 13 } else { 15 };
+
+    let mut data = 1 + count / 3;
+    let data_safe_end = source.len() - 16;
+    let code_aux_table = &source[data_safe_end..];
+
+    // the codes of the triangles follow the header
+    for (code, i) in (1..).zip((0..count).step_by(3)) {
+        // a triangle reads at most 16 bytes, which the code aux table guarantees are there
+        if data > data_safe_end {
+            return Err(String::from("index data is truncated"))
+        }
+        let code_triangle = source[code];
+
+        let (a, b, c);
+        if code_triangle < 0xf0 {
+            // an edge from the fifo, and a third vertex from the fifo, the next vertex or a free index
+            let fe = (code_triangle >> 4) as usize;
+            [a, b] = edge_fifo[(edge_offset.wrapping_sub(1 + fe)) & 15];
+            let fec = (code_triangle & 15) as usize;
+            if fec < fec_max {
+                c = if fec == 0 { next } else { vertex_fifo[(vertex_offset.wrapping_sub(1 + fec)) & 15] };
+                next += (fec == 0) as u32;
+                push_vertex(&mut vertex_fifo, &mut vertex_offset, c, fec == 0);
+            } else {
+                // 13 and 14 are the last free index -1 and +1
+                c = match fec {
+                    13 => last.wrapping_sub(1),
+                    14 => last.wrapping_add(1),
+                    _ => decode_index(source, &mut data, last),
+                };
+                last = c;
+                push_vertex(&mut vertex_fifo, &mut vertex_offset, c, true);
+            }
+            push_edge(&mut edge_fifo, &mut edge_offset, c, b);
+            push_edge(&mut edge_fifo, &mut edge_offset, a, c);
+        } else {
+            // a triangle of new vertices, with the codes for b and c from the table or a following byte
+            let code_aux = if code_triangle < 0xfe { code_aux_table[(code_triangle & 15) as usize] } else {
+                data += 1;
+                source[data - 1]
+            };
+            let fea = if code_triangle == 0xff { 15 } else { 0 };
+            let feb = (code_aux >> 4) as usize;
+            let fec = (code_aux & 15) as usize;
+            if code_triangle >= 0xfe && code_aux == 0 {
+                next = 0; // restart
+            }
+
+            let mut from_fifo_or_next = |fe: usize| if fe == 0 {
+                next += 1;
+                next - 1
+            } else {
+                vertex_fifo[(vertex_offset.wrapping_sub(fe)) & 15]
+            };
+            let mut decoded_a = if fea == 0 { from_fifo_or_next(0) } else { 0 };
+            let mut decoded_b = if feb == 15 { 0 } else { from_fifo_or_next(feb) };
+            let mut decoded_c = if fec == 15 { 0 } else { from_fifo_or_next(fec) };
+            if fea == 15 {
+                last = decode_index(source, &mut data, last);
+                decoded_a = last;
+            }
+            if feb == 15 {
+                last = decode_index(source, &mut data, last);
+                decoded_b = last;
+            }
+            if fec == 15 {
+                last = decode_index(source, &mut data, last);
+                decoded_c = last;
+            }
+            (a, b, c) = (decoded_a, decoded_b, decoded_c);
+
+            push_vertex(&mut vertex_fifo, &mut vertex_offset, a, true);
+            push_vertex(&mut vertex_fifo, &mut vertex_offset, b, feb == 0 || feb == 15);
+            push_vertex(&mut vertex_fifo, &mut vertex_offset, c, fec == 0 || fec == 15);
+            push_edge(&mut edge_fifo, &mut edge_offset, b, a);
+            push_edge(&mut edge_fifo, &mut edge_offset, c, b);
+            push_edge(&mut edge_fifo, &mut edge_offset, a, c);
+        }
+        write_index(destination, i, index_size, a);
+        write_index(destination, i + 1, index_size, b);
+        write_index(destination, i + 2, index_size, c);
+    }
+
+    if data != data_safe_end {
+        return Err(String::from("index data has trailing bytes"))
+    }
+    Ok(())
+}
+
+fn decode_index_sequence(destination: &mut [u8], count: usize, index_size: usize, source: &[u8]) -> Result<(), String> {
+    if index_size != 2 && index_size != 4 {
+        return Err(format!("INDICES need a byteStride of 2 or 4, not {}", index_size))
+    }
+    // a header, at least a byte per index and a 4 byte tail
+    if source.len() < 1 + count + 4 {
+        return Err(String::from("index data is truncated"))
+    }
+    if source[0] & 0xf0 != SEQUENCE_HEADER || source[0] & 0x0f > 1 {
+        return Err(format!("unsupported index sequence header {:#x}", source[0]))
+    }
+
+    let mut data = 1;
+    let data_safe_end = source.len() - 4;
+    // each index is a delta from one of two baselines, picked by its lowest bit
+    let mut last = [0u32; 2];
+    for i in 0..count {
+        if data >= data_safe_end {
+            return Err(String::from("index data is truncated"))
+        }
+        let v = decode_vbyte(source, &mut data);
+        let baseline = (v & 1) as usize;
+        let v = v >> 1;
+        let index = last[baseline].wrapping_add((v >> 1) ^ (v & 1).wrapping_neg());
+        last[baseline] = index;
+        write_index(destination, i, index_size, index);
+    }
+
+    if data != data_safe_end {
+        return Err(String::from("index data has trailing bytes"))
+    }
+    Ok(())
+}
+
+fn apply_filter(data: &mut [u8], stride: usize, filter: MeshoptFilter) -> Result<(), String> {
+    match filter {
+        MeshoptFilter::None => {}
+        MeshoptFilter::Octahedral => {
+            // x and y of an octahedral encoded unit vector, z holds the value 1 is encoded as
+            let size = match stride {
+                4 => 1,
+                8 => 2,
+                _ => return Err(format!("OCTAHEDRAL needs a byteStride of 4 or 8, not {}", stride)),
+            };
+            let max = if size == 1 { 127.0 } else { 32767.0 };
+            for element in data.chunks_exact_mut(stride) {
+                let read = |i: usize| if size == 1 {
+                    element[i] as i8 as f32
+                } else {
+                    i16::from_le_bytes([element[i * 2], element[i * 2 + 1]]) as f32
+                };
+                let mut x = read(0);
+                let mut y = read(1);
+                let z = read(2) - x.abs() - y.abs();
+
+                let t = z.min(0.0);
+                x += if x >= 0.0 { t } else { -t };
+                y += if y >= 0.0 { t } else { -t };
+
+                let s = max / (x * x + y * y + z * z).sqrt();
+                for (i, value) in [x, y, z].into_iter().enumerate() {
+                    let rounded = (value * s + if value >= 0.0 { 0.5 } else { -0.5 }) as i32;
+                    if size == 1 {
+                        element[i] = rounded as i8 as u8;
+                    } else {
+                        element[i * 2..i * 2 + 2].copy_from_slice(&(rounded as i16).to_le_bytes());
+                    }
+                }
+            }
+        }
+        MeshoptFilter::Quaternion => {
+            // the three smallest components, with the index of the largest one and a scale in the fourth
+            if stride != 8 {
+                return Err(format!("QUATERNION needs a byteStride of 8, not {}", stride))
+            }
+            let scale = 1.0 / 2.0f32.sqrt();
+            for element in data.chunks_exact_mut(8) {
+                let read = |i: usize| i16::from_le_bytes([element[i * 2], element[i * 2 + 1]]);
+                let encoded_scale = read(3);
+                let s = scale / (encoded_scale | 3) as f32;
+                let x = read(0) as f32 * s;
+                let y = read(1) as f32 * s;
+                let z = read(2) as f32 * s;
+                let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+
+                let largest = (encoded_scale & 3) as usize;
+                for (i, value) in [w, x, y, z].into_iter().enumerate() {
+                    let rounded = (value * 32767.0 + if value >= 0.0 { 0.5 } else { -0.5 }) as i16;
+                    let target = (largest + i) & 3;
+                    element[target * 2..target * 2 + 2].copy_from_slice(&rounded.to_le_bytes());
+                }
+            }
+        }
+        MeshoptFilter::Exponential => {
+            // a 24 bit signed mantissa and an 8 bit signed exponent
+            if !stride.is_multiple_of(4) {
+                return Err(format!("EXPONENTIAL needs a byteStride divisible by 4, not {}", stride))
+            }
+            for word in data.chunks_exact_mut(4) {
+                let v = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+                let mantissa = ((v << 8) as i32) >> 8;
+                let exponent = (v as i32) >> 24;
+                let value = f32::from_bits(((exponent + 127) as u32) << 23) * mantissa as f32;
+                word.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // encoded with meshopt_encodeVertexBuffer and meshopt_encodeIndexBuffer, which gltfpack -c uses
+    const ATTRIBUTES: [u8; 97] = [
+        0xa0, 0x02, 0x06, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x00, 0x03, 0x00, 0x4a, 0xde, 0x8d,
+        0x06, 0x9a, 0xd1, 0x3d, 0x56, 0xea, 0x81, 0x12, 0xa6, 0xc5, 0x31, 0x62, 0x02, 0x00, 0x02, 0x22,
+        0x44, 0x44, 0x66, 0x68, 0x88, 0x03, 0x00, 0x5f, 0x60, 0x5f, 0x60, 0x5f, 0x60, 0x5f, 0x60, 0x5f,
+        0x60, 0x5f, 0x60, 0x5f, 0x60, 0x5f, 0x02, 0x0e, 0xde, 0xde, 0xde, 0xde, 0xde, 0xde, 0xde, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0xfe, 0x18, 0xfc, 0x01,
+        0x00,
+    ];
+    const TRIANGLES: [u8; 27] = [
+        0xe0, 0xf0, 0x10, 0xfe, 0xff, 0xf0, 0x0c, 0xff, 0x02, 0x02, 0x02, 0x00, 0x76, 0x87, 0x56, 0x67,
+        0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00,
+    ];
+    // written by hand in the layout of meshopt_encodeIndexSequence, switching baselines on deltas of 30 or more
+    const INDICES: [u8; 13] = [0xd1, 0x00, 0x04, 0xcd, 0x01, 0x04, 0x07, 0x98, 0x1f, 0x00, 0x00, 0x00, 0x00];
+
+    // the filter inputs and outputs of meshoptimizer's own tests, vertex encoded like gltfpack stores them
+    const OCTAHEDRAL_8: [u8; 53] = [
+        0xa0, 0x01, 0x07, 0x00, 0x00, 0x00, 0x1e, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x8b, 0x8c, 0xfd, 0x00,
+        0x01, 0x26, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x01, 0x7f, 0x00,
+    ];
+    const OCTAHEDRAL_12: [u8; 71] = [
+        0xa0, 0x01, 0x0f, 0x00, 0x00, 0x00, 0x3d, 0x5a, 0x01, 0x0f, 0x00, 0x00, 0x00, 0x0e, 0x0d, 0x01,
+        0x3f, 0x00, 0x00, 0x00, 0x9a, 0x99, 0x26, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x0e, 0x0d, 0x0a, 0x00,
+        0x00, 0x01, 0x26, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0xff, 0x07, 0x00, 0x00,
+    ];
+    const QUATERNION_12: [u8; 71] = [
+        0xa0, 0x01, 0x0f, 0x00, 0x00, 0x00, 0x3d, 0x5a, 0x01, 0x0f, 0x00, 0x00, 0x00, 0x0e, 0x0d, 0x01,
+        0x3f, 0x00, 0x00, 0x00, 0x9a, 0x99, 0x26, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x0e, 0x0d, 0x0a, 0x00,
+        0x00, 0x01, 0x2a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0xfc, 0x07,
+    ];
+    const EXPONENTIAL: [u8; 59] = [
+        0xa0, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x06, 0x17, 0x10, 0x01, 0x04, 0x00, 0x00, 0x00, 0x01, 0x07,
+        0x00, 0x00, 0x00, 0xff, 0x01, 0x1f, 0x00, 0x00, 0x00, 0x06, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn decoded(count: usize, stride: usize, mode: MeshoptMode, filter: MeshoptFilter, source: &[u8]) -> Vec<u8> {
+        let mut destination = vec![0; count * stride];
+        decode(&mut destination, count, stride, mode, filter, source).unwrap();
+        destination
+    }
+    fn u16s(bytes: &[u8]) -> Vec<u16> {
+        bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect()
+    }
+    fn u32s(bytes: &[u8]) -> Vec<u32> {
+        bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+    }
+
+    #[test]
+    fn attributes() {
+        let expected: Vec<u16> = (0..16i16)
+            .flat_map(|i| [i * 3, i * i * 37 - 500, if i % 2 == 1 { 1000 } else { -1000 }, 1])
+            .map(|value| value as u16)
+            .collect();
+        assert_eq!(u16s(&decoded(16, 8, MeshoptMode::Attributes, MeshoptFilter::None, &ATTRIBUTES)), expected);
+    }
+
+    #[test]
+    fn triangles() {
+        let expected = [0, 1, 2, 2, 1, 3, 4, 6, 5, 7, 8, 9];
+        assert_eq!(u16s(&decoded(12, 2, MeshoptMode::Triangles, MeshoptFilter::None, &TRIANGLES)), expected.map(|i: u32| i as u16));
+        assert_eq!(u32s(&decoded(12, 4, MeshoptMode::Triangles, MeshoptFilter::None, &TRIANGLES)), expected);
+    }
+
+    #[test]
+    fn indices() {
+        let expected = [0, 1, 51, 2, 49, 1000];
+        assert_eq!(u16s(&decoded(6, 2, MeshoptMode::Indices, MeshoptFilter::None, &INDICES)), expected.map(|i: u32| i as u16));
+        assert_eq!(u32s(&decoded(6, 4, MeshoptMode::Indices, MeshoptFilter::None, &INDICES)), expected);
+    }
+
+    #[test]
+    fn octahedral_filter() {
+        assert_eq!(
+            decoded(4, 4, MeshoptMode::Attributes, MeshoptFilter::Octahedral, &OCTAHEDRAL_8),
+            [0, 1, 127, 0, 0, 159, 82, 1, 255, 1, 127, 0, 1, 130, 241, 1],
+        );
+        assert_eq!(
+            u16s(&decoded(4, 8, MeshoptMode::Attributes, MeshoptFilter::Octahedral, &OCTAHEDRAL_12)),
+            [0, 16, 32767, 0, 0, 32621, 3088, 1, 32764, 16, 471, 0, 307, 28541, 16093, 1],
+        );
+    }
+
+    #[test]
+    fn quaternion_filter() {
+        assert_eq!(
+            u16s(&decoded(4, 8, MeshoptMode::Attributes, MeshoptFilter::Quaternion, &QUATERNION_12)),
+            [32767, 0, 11, 0, 0, 25013, 0, 21166, 11, 0, 23504, 22830, 158, 14715, 0, 29277],
+        );
+    }
+
+    #[test]
+    fn exponential_filter() {
+        assert_eq!(
+            u32s(&decoded(4, 4, MeshoptMode::Attributes, MeshoptFilter::Exponential, &EXPONENTIAL)),
+            [0, 0x3fc00000, 0xc2100000, 0x49fffffe],
+        );
+    }
+
+    #[test]
+    fn malformed_data_is_rejected() {
+        let mut destination = vec![0; 12 * 2];
+        assert!(decode(&mut destination, 12, 2, MeshoptMode::Triangles, MeshoptFilter::None, &TRIANGLES[..20]).is_err());
+        assert!(decode(&mut destination, 12, 2, MeshoptMode::Indices, MeshoptFilter::None, &TRIANGLES).is_err());
+        assert!(decode(&mut destination, 12, 2, MeshoptMode::Triangles, MeshoptFilter::Octahedral, &TRIANGLES).is_err());
+    }
+}
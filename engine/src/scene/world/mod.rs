@@ -1 +1,3 @@
-pub mod world;
\ No newline at end of file
+pub mod world;
+pub mod gltf_error;
+pub mod meshopt;
\ No newline at end of file
@@ -12,8 +12,11 @@ use json::JsonValue;
 use crate::math::matrix::Matrix;
 use crate::math::Vector;
 use crate::render::render::MAX_FRAMES_IN_FLIGHT;
-use crate::render::vulkan_base::{copy_buffer_synchronous, copy_data_to_memory, Context, VkBase};
+use crate::render::vulkan_base::{copy_buffer_synchronous, copy_data_to_memory, Context, ImageSource, VkBase};
 use crate::scene::scene::{Instance, Scene};
+use crate::scene::world::gltf_error::{GltfError, GltfErrorKind, GltfReader};
+use crate::scene::world::meshopt;
+use crate::scene::world::meshopt::{MeshoptFilter, MeshoptMode};
 
 // SHOULD DETECT MATH VS COLOR DATA TEXTURES, LOAD COLOR AS SRGB, MATH AS UNORM
 const MAX_VERTICES: u64 = 3 * 10u64.pow(6); // 7 for bistro
@@ -22,9 +25,11 @@ pub const MAX_INSTANCES: u64 = 10u64 * 10u64.pow(4); // 5 for bistro
 const MAX_MATERIALS: u64 = 10u64 * 10u64.pow(4);
 const MAX_JOINTS: u64 = 10u64 * 10u64.pow(4);
 const MAX_LIGHTS: u64 = 10u64 * 10u64.pow(3);
+const MAX_MORPH_DELTAS: u64 = 5 * 10u64.pow(5);
+pub const MAX_MORPH_WEIGHTS: u64 = 2 * 10u64.pow(5); // including the per instance offsets at the start
 
 pub struct World {
-    context: Arc<Context>,
+    context: Option<Arc<Context>>, // None for headless worlds, which only parse models
 
     pub loaded_files: HashMap<String, usize>,
 
@@ -34,6 +39,7 @@ pub struct World {
     new_indices: Vec<u32>,
     new_joints: Vec<Matrix>,
     new_materials: Vec<MaterialSendable>,
+    new_morph_deltas: Vec<MorphDelta>,
 
     pub nodes: Vec<Node>,
     pub meshes: Vec<Mesh>,
@@ -46,6 +52,8 @@ pub struct World {
     pub buffer_views: Vec<BufferView>,
     pub buffers: Vec<Buffer>,
     pub scenes: Vec<GltfScene>,
+    pub lights: Vec<GltfLight>,
+    pub cameras: Vec<GltfCamera>,
 
     pub texture_count: i32,
 
@@ -76,6 +84,16 @@ pub struct World {
     buffer_joints_count: usize,
     pub joints_buffers_size: u64,
 
+    pub morph_delta_staging_buffer: (vk::Buffer, DeviceMemory, *mut c_void),
+    pub morph_delta_buffers: Vec<(vk::Buffer, DeviceMemory)>,
+    pub morph_deltas_count: usize,
+    buffer_morph_deltas_count: usize,
+    pub morph_delta_buffer_size: u64,
+
+    pub morph_weights_staging_buffer: (vk::Buffer, DeviceMemory, *mut c_void),
+    pub morph_weights_buffers: Vec<(vk::Buffer, DeviceMemory)>,
+    pub morph_weights_buffers_size: u64,
+
     pub lights_staging_buffer: (vk::Buffer, DeviceMemory, *mut c_void),
     pub lights_buffers: Vec<(vk::Buffer, DeviceMemory)>,
     pub lights_buffers_size: u64,
@@ -86,7 +104,15 @@ pub struct World {
 impl World {
     pub fn new(context: &Arc<Context>) -> Self {
         Self {
-            context: context.clone(),
+            context: Some(context.clone()),
+            ..Self::new_headless()
+        }
+    }
+    ///* A world without a Vulkan context, for using scenes without a GPU, eg. in tests.
+    ///* Models are parsed into nodes, meshes, skins and animations as usual, but nothing is prepared for upload.
+    pub fn new_headless() -> Self {
+        Self {
+            context: None,
 
             buffers_need_update: false,
 
@@ -97,6 +123,7 @@ impl World {
             new_vertices: Vec::new(),
             new_joints: Vec::new(),
             new_materials: Vec::new(),
+            new_morph_deltas: Vec::new(),
 
             nodes: Vec::new(),
             meshes: Vec::new(),
@@ -109,6 +136,8 @@ impl World {
             buffer_views: Vec::new(),
             buffers: Vec::new(),
             scenes: Vec::new(),
+            lights: Vec::new(),
+            cameras: Vec::new(),
 
             texture_count: 0,
             index_buffer: (vk::Buffer::null(), DeviceMemory::null()),
@@ -134,6 +163,14 @@ impl World {
             joints_count: 0,
             buffer_joints_count: 0,
             joints_buffers_size: 0,
+            morph_delta_staging_buffer: (vk::Buffer::null(), DeviceMemory::null(), null_mut()),
+            morph_delta_buffers: Vec::new(),
+            morph_deltas_count: 0,
+            buffer_morph_deltas_count: 0,
+            morph_delta_buffer_size: 0,
+            morph_weights_staging_buffer: (vk::Buffer::null(), DeviceMemory::null(), null_mut()),
+            morph_weights_buffers: Vec::new(),
+            morph_weights_buffers_size: 0,
             lights_staging_buffer: (vk::Buffer::null(), DeviceMemory::null(), null_mut()),
             lights_buffers: Vec::new(),
             lights_buffers_size: 0,
@@ -141,50 +178,79 @@ impl World {
             primitive_count: 0,
         }
     }
+    pub fn is_headless(&self) -> bool {
+        self.context.is_none()
+    }
+    fn context(&self) -> &Arc<Context> {
+        self.context.as_ref().expect("headless worlds have no GPU resources")
+    }
+
+    ///* The float components of an accessor's elements, read tightly packed from the start of its buffer view.
+    ///* Empty for accessors of other component types.
+    pub fn accessor_floats(&self, accessor_index: usize) -> Vec<f32> {
+        self.accessors[accessor_index].read_floats(&self.buffer_views, &self.buffers)
+    }
+
     pub unsafe fn initialize(&mut self) { unsafe {
         self.instance_buffer_size = MAX_INSTANCES * size_of::<Instance>() as u64;
         self.material_buffer_size = MAX_MATERIALS * size_of::<MaterialSendable>() as u64;
         self.lights_buffers_size = MAX_LIGHTS * size_of::<LightSendable>() as u64;
+        self.morph_delta_buffer_size = MAX_MORPH_DELTAS * size_of::<MorphDelta>() as u64;
         self.indices_buffer_size = 3 * MAX_INDICES * size_of::<u32>() as u64;
         self.vertex_buffer_size = MAX_VERTICES * size_of::<Vertex>() as u64;
-        (self.vertex_buffer, self.vertex_staging_buffer) = self.context.create_device_and_staging_buffer(self.vertex_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, false, true, false);
-        (self.index_buffer, self.index_staging_buffer) = self.context.create_device_and_staging_buffer(self.indices_buffer_size, &[0], vk::BufferUsageFlags::INDEX_BUFFER, false, true, false);
+        (self.vertex_buffer, self.vertex_staging_buffer) = self.context().create_device_and_staging_buffer(self.vertex_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, false, true, false);
+        (self.index_buffer, self.index_staging_buffer) = self.context().create_device_and_staging_buffer(self.indices_buffer_size, &[0], vk::BufferUsageFlags::INDEX_BUFFER, false, true, false);
         for i in 0..MAX_FRAMES_IN_FLIGHT {
             self.instance_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
             self.material_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
             self.lights_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
             self.joints_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
+            self.morph_delta_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
+            self.morph_weights_buffers.push((vk::Buffer::null(), DeviceMemory::null()));
             if i == 0 {
                 (self.instance_buffers[i], self.instance_staging_buffer) =
-                    self.context.create_device_and_staging_buffer(self.instance_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, false, true, false);
+                    self.context().create_device_and_staging_buffer(self.instance_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, false, true, false);
                 (self.material_buffers[i], self.material_staging_buffer) =
-                    self.context.create_device_and_staging_buffer(self.material_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
+                    self.context().create_device_and_staging_buffer(self.material_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
                 (self.lights_buffers[i], self.lights_staging_buffer) =
-                    self.context.create_device_and_staging_buffer(self.lights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
+                    self.context().create_device_and_staging_buffer(self.lights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
+                (self.morph_delta_buffers[i], self.morph_delta_staging_buffer) =
+                    self.context().create_device_and_staging_buffer(self.morph_delta_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
             } else {
-                self.instance_buffers[i] = self.context.create_device_and_staging_buffer(self.instance_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, true, false, false).0;
-                self.material_buffers[i] = self.context.create_device_and_staging_buffer(self.material_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, false, false).0;
-                self.lights_buffers[i] = self.context.create_device_and_staging_buffer(self.lights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, false, false).0;
+                self.instance_buffers[i] = self.context().create_device_and_staging_buffer(self.instance_buffer_size, &[0], vk::BufferUsageFlags::VERTEX_BUFFER, true, false, false).0;
+                self.material_buffers[i] = self.context().create_device_and_staging_buffer(self.material_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, false, false).0;
+                self.lights_buffers[i] = self.context().create_device_and_staging_buffer(self.lights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, false, false).0;
+                self.morph_delta_buffers[i] = self.context().create_device_and_staging_buffer(self.morph_delta_buffer_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, false, false).0;
             }
         }
         self.joints_buffers_size = MAX_JOINTS * size_of::<Matrix>() as u64;
         for i in 0..MAX_FRAMES_IN_FLIGHT {
             if i == 0 {
                 (self.joints_buffers[i], self.joints_staging_buffer) =
-                    self.context.create_device_and_staging_buffer(self.joints_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
+                    self.context().create_device_and_staging_buffer(self.joints_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
+            } else {
+                self.joints_buffers[i] = self.context().create_device_and_staging_buffer(self.joints_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, true, false).0;
+            }
+        }
+        self.morph_weights_buffers_size = MAX_MORPH_WEIGHTS * size_of::<f32>() as u64;
+        for i in 0..MAX_FRAMES_IN_FLIGHT {
+            if i == 0 {
+                (self.morph_weights_buffers[i], self.morph_weights_staging_buffer) =
+                    self.context().create_device_and_staging_buffer(self.morph_weights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, false, true, false);
             } else {
-                self.joints_buffers[i] = self.context.create_device_and_staging_buffer(self.joints_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, true, false).0;
+                self.morph_weights_buffers[i] = self.context().create_device_and_staging_buffer(self.morph_weights_buffers_size, &[0], vk::BufferUsageFlags::STORAGE_BUFFER, true, true, false).0;
             }
         }
     } }
 
-    pub unsafe fn add_model(&mut self, uri: &str) -> usize {
+    pub unsafe fn add_model(&mut self, uri: &str) -> Result<usize, GltfError> {
         if !self.loaded_files.contains_key(&String::from(uri)) {
+            let model = ModelContainer::new(uri, self)?;
             self.loaded_files.insert(String::from(uri), self.models.len());
-            let model = ModelContainer::new(uri, self);
             let mut new_vertices: Vec<Vertex> = vec![];
             let mut new_indices: Vec<u32> = vec![];
             let mut new_materials_send: Vec<MaterialSendable> = vec![];
+            let mut new_morph_deltas: Vec<MorphDelta> = vec![];
             for mesh in &model.meshes {
                 for primitive in &mut self.meshes[*mesh].primitives {
                     primitive.construct_data(&self.accessors, &self.buffer_views, &self.buffers);
@@ -204,7 +270,11 @@ impl World {
                             primitive.index_data_u32.iter().map(|&i| i + primitive.vertex_buffer_offset as u32)
                         );
                     }
-                    primitive.construct_min_max()
+                    primitive.construct_min_max();
+                    if !primitive.morph_targets.is_empty() {
+                        primitive.morph_delta_offset = self.morph_deltas_count + new_morph_deltas.len();
+                        new_morph_deltas.extend(primitive.interleaved_morph_deltas());
+                    }
                 }
             }
             for material in model.materials.iter() {
@@ -225,34 +295,94 @@ impl World {
             self.materials_count += new_materials_send.len();
             self.texture_count += model.textures.len() as i32;
             self.joints_count += new_joints_send.len();
+            self.morph_deltas_count += new_morph_deltas.len();
 
-            self.new_indices.extend(new_indices);
-            self.new_vertices.extend(new_vertices);
-            self.new_joints.extend(new_joints_send);
-            self.new_materials.extend(new_materials_send);
+            // headless worlds have nowhere to upload to
+            if !self.is_headless() {
+                self.new_indices.extend(new_indices);
+                self.new_vertices.extend(new_vertices);
+                self.new_joints.extend(new_joints_send);
+                self.new_materials.extend(new_materials_send);
+                self.new_morph_deltas.extend(new_morph_deltas);
 
-            self.buffers_need_update = true;
+                self.buffers_need_update = true;
+            }
 
             self.models.push(model);
         }
-        *self.loaded_files.get(&String::from(uri)).unwrap()
+        Ok(*self.loaded_files.get(&String::from(uri)).unwrap())
+    }
+    ///* Drops every loaded model, so that they are parsed and uploaded again when next added.
+    ///* The GPU buffers are kept and refilled from the start. Images and textures of worlds with a GPU are kept too,
+    ///* as frames in flight and the renderer's texture descriptors still use them, so texture indices continue after them.
+    pub fn clear(&mut self) {
+        for model in self.models.drain(..) {
+            self.loaded_files.remove(&model.path);
+        }
+        if self.is_headless() {
+            self.loaded_files.clear();
+            self.textures.clear();
+            self.images.clear();
+            self.texture_count = 0;
+        }
+
+        self.nodes.clear();
+        self.meshes.clear();
+        self.materials.clear();
+        self.skins.clear();
+        self.animations.clear();
+        self.accessors.clear();
+        self.buffer_views.clear();
+        self.buffers.clear();
+        self.scenes.clear();
+        self.lights.clear();
+        self.cameras.clear();
+
+        self.new_vertices.clear();
+        self.new_indices.clear();
+        self.new_joints.clear();
+        self.new_materials.clear();
+        self.new_morph_deltas.clear();
+        // images still waiting to be constructed are picked up by the next update
+        self.buffers_need_update = self.images.iter().any(|image| !image.generated);
+
+        self.vertices_count = 0;
+        self.buffer_vertices_count = 0;
+        self.indices_count = 0;
+        self.buffer_indices_count = 0;
+        self.materials_count = 0;
+        self.buffer_materials_count = 0;
+        self.joints_count = 0;
+        self.buffer_joints_count = 0;
+        self.morph_deltas_count = 0;
+        self.buffer_morph_deltas_count = 0;
+        self.lights_count = 0;
+        self.primitive_count = 0;
     }
     pub unsafe fn add_texture(&mut self, uri: &str, generate_mips: bool) -> usize {
         let sampler = if !self.loaded_files.contains_key(uri) {
             let path = PathBuf::from(uri);
-            let (image_view, image, mips) = unsafe { self.context.create_2d_texture_image(&path, generate_mips) };
-            let image = Image {
-                mime_type: String::new(),
-                name: String::from(uri),
-                uri: path,
-                generated: true,
-                image,
-                image_view: image_view.0,
-                mip_levels: mips,
-            };
-            self.loaded_files.insert(String::from(uri), self.images.len());
-            self.images.push(image);
-            image_view.1
+            if self.is_headless() {
+                // only recorded, the image is never created
+                self.loaded_files.insert(String::from(uri), self.images.len());
+                self.images.push(Image::new(String::new(), String::from(uri), path));
+                vk::Sampler::null()
+            } else {
+                let (image_view, image, mips) = unsafe { self.context().create_2d_texture_image(&path, generate_mips) };
+                let image = Image {
+                    mime_type: String::new(),
+                    name: String::from(uri),
+                    uri: path,
+                    data: None,
+                    generated: true,
+                    image,
+                    image_view: image_view.0,
+                    mip_levels: mips,
+                };
+                self.loaded_files.insert(String::from(uri), self.images.len());
+                self.images.push(image);
+                image_view.1
+            }
         } else {
             self.textures[0].sampler
         };
@@ -268,7 +398,7 @@ impl World {
                 address_mode_v: vk::SamplerAddressMode::REPEAT,
                 address_mode_w: vk::SamplerAddressMode::REPEAT,
             },
-            has_sampler: true
+            has_sampler: !self.is_headless()
         });
 
         self.texture_count += 1;
@@ -298,7 +428,7 @@ impl World {
             // }
             */
 
-            self.context.update_buffer_through_staging(
+            self.context().update_buffer_through_staging(
                 &command_buffer,
                 &self.vertex_buffer,
                 &self.vertex_staging_buffer,
@@ -306,7 +436,7 @@ impl World {
                 size_of::<Vertex>() as u64 * self.buffer_vertices_count as u64,
                 true
             );
-            self.context.update_buffer_through_staging(
+            self.context().update_buffer_through_staging(
                 &command_buffer,
                 &self.index_buffer,
                 &self.index_staging_buffer,
@@ -315,7 +445,7 @@ impl World {
                 true
             );
             for frame in 0..self.material_buffers.len() {
-                self.context.update_buffer_through_staging(
+                self.context().update_buffer_through_staging(
                     &command_buffer,
                     &self.material_buffers[frame],
                     &self.material_staging_buffer,
@@ -324,7 +454,7 @@ impl World {
                     frame == 0
                 );
                 if self.new_joints.len() > 0 {
-                    self.context.update_buffer_through_staging(
+                    self.context().update_buffer_through_staging(
                         &command_buffer,
                         &self.joints_buffers[frame],
                         &self.joints_staging_buffer,
@@ -333,16 +463,28 @@ impl World {
                         frame == 0
                     );
                 }
+                if !self.new_morph_deltas.is_empty() {
+                    self.context().update_buffer_through_staging(
+                        &command_buffer,
+                        &self.morph_delta_buffers[frame],
+                        &self.morph_delta_staging_buffer,
+                        &self.new_morph_deltas,
+                        size_of::<MorphDelta>() as u64 * self.buffer_morph_deltas_count as u64,
+                        frame == 0
+                    );
+                }
             }
             self.buffer_vertices_count += self.new_vertices.len();
             self.buffer_indices_count += self.new_indices.len();
             self.buffer_materials_count += self.new_materials.len();
             self.buffer_joints_count += self.new_joints.len();
+            self.buffer_morph_deltas_count += self.new_morph_deltas.len();
 
             self.new_indices.clear();
             self.new_vertices.clear();
             self.new_joints.clear();
             self.new_materials.clear();
+            self.new_morph_deltas.clear();
         }
     } }
 
@@ -353,11 +495,11 @@ impl World {
             .filter(|(_, img)| !img.generated)
             .map(|(i, _)| i)
             .collect::<Vec<usize>>();
-        let uris: Vec<PathBuf> = ungenerated_indices
+        let sources: Vec<ImageSource> = ungenerated_indices
             .iter()
-            .map(|i| self.images[*i].uri.clone())
+            .map(|i| self.images[*i].source())
             .collect();
-        let image_sources = self.context.load_textures_batched(uris.as_slice(), true);
+        let image_sources = self.context().load_image_sources_batched(sources.as_slice(), true);
         for (i, ungenerated_image_index) in ungenerated_indices.iter().enumerate() {
             let img = &mut self.images[*ungenerated_image_index];
             let (image_view, image, mips) = image_sources[i];
@@ -404,6 +546,21 @@ impl World {
         base.device.destroy_buffer(self.joints_staging_buffer.0, None);
         base.device.free_memory(self.joints_staging_buffer.1, None);
 
+        for morph_delta_buffer in &self.morph_delta_buffers {
+            base.device.destroy_buffer(morph_delta_buffer.0, None);
+            base.device.free_memory(morph_delta_buffer.1, None);
+        }
+        base.device.destroy_buffer(self.morph_delta_staging_buffer.0, None);
+        base.device.free_memory(self.morph_delta_staging_buffer.1, None);
+
+        for morph_weights_buffer in &self.morph_weights_buffers {
+            base.device.destroy_buffer(morph_weights_buffer.0, None);
+            base.device.free_memory(morph_weights_buffer.1, None);
+        }
+        base.device.unmap_memory(self.morph_weights_staging_buffer.1);
+        base.device.destroy_buffer(self.morph_weights_staging_buffer.0, None);
+        base.device.free_memory(self.morph_weights_staging_buffer.1, None);
+
         base.device.destroy_buffer(self.index_buffer.0, None);
         base.device.free_memory(self.index_buffer.1, None);
         base.device.destroy_buffer(self.index_staging_buffer.0, None);
@@ -428,13 +585,13 @@ impl World {
 #[derive(Clone)]
 pub struct LightSendable {
     pub position: [f32; 3],
-    pub _pad0: u32,
+    pub range: f32,
     pub direction: [f32; 3],
     pub light_type: u32,
     pub attenuation_values: [f32; 3],
-    pub _pad1: u32,
+    pub inner_cutoff: f32,
     pub color: [f32; 3],
-    pub _pad2: u32,
+    pub outer_cutoff: f32,
 }
 #[derive(Copy)]
 #[derive(Clone)]
@@ -449,6 +606,7 @@ pub struct SunSendable {
 
 #[derive(Clone)]
 pub struct ModelContainer {
+    pub path: String,
     pub extensions_used: Vec<String>,
     pub scene: usize,
     pub scenes: Vec<usize>,
@@ -464,136 +622,273 @@ pub struct ModelContainer {
     pub buffers: Vec<usize>,
 }
 impl ModelContainer {
-    pub fn new(path: &str, world: &mut World) -> Self {
-        let json = json::parse(fs::read_to_string(path).expect("failed to load json file").as_str()).expect("json parse error");
-
-        let initial_buffer_count = world.buffers.len();
-        let initial_buffer_view_count = world.buffer_views.len();
-        let initial_accessors_count = world.accessors.len();
-        let initial_images_count = world.images.len();
-        let initial_skins_count = world.skins.len();
-        let initial_textures_count = world.textures.len();
-        let initial_materials_count = world.materials.len();
-        let initial_primitive_count = world.primitive_count;
-        let initial_animation_count = world.animations.len();
-        let initial_node_count = world.nodes.len();
-        let initial_mesh_count = world.meshes.len();
-        let initial_scene_count = world.scenes.len();
+    ///* Loads a .gltf or .glb file into the world. If it fails, the world is left as it was and the error names the file
+    ///* and the JSON path of the field at fault.
+    pub fn new(path: &str, world: &mut World) -> Result<Self, GltfError> {
+        let initial = WorldLengths::of(world);
+        let model = Self::load(path, world, &initial);
+        if model.is_err() {
+            initial.truncate(world);
+        }
+        model
+    }
+
+    fn load(path: &str, world: &mut World, initial: &WorldLengths) -> Result<Self, GltfError> {
+        let reader = GltfReader { file: path };
+        let (json, mut glb_binary_chunk) = read_gltf(path)?;
+
+        // lengths of the model's own arrays, which its indices have to be within
+        let buffer_count = reader.array(&json["buffers"], "buffers")?.len();
+        let buffer_view_count = reader.array(&json["bufferViews"], "bufferViews")?.len();
+        let accessor_count = reader.array(&json["accessors"], "accessors")?.len();
+        let image_count = reader.array(&json["images"], "images")?.len();
+        let sampler_count = reader.array(&json["samplers"], "samplers")?.len();
+        let texture_count = reader.array(&json["textures"], "textures")?.len();
+        let material_count = reader.array(&json["materials"], "materials")?.len();
+        let mesh_count = reader.array(&json["meshes"], "meshes")?.len();
+        let node_count = reader.array(&json["nodes"], "nodes")?.len();
+        let skin_count = reader.array(&json["skins"], "skins")?.len();
+        let scene_count = reader.array(&json["scenes"], "scenes")?.len();
+        let camera_count = reader.array(&json["cameras"], "cameras")?.len();
+        let light_count = reader.array(&json["extensions"]["KHR_lights_punctual"]["lights"], "extensions.KHR_lights_punctual.lights")?.len();
 
         let mut extensions_used = Vec::new();
-        for extension in json["extensionsUsed"].members() {
-            extensions_used.push(extension.as_str().unwrap().to_string());
+        for (i, extension) in json["extensionsUsed"].members().enumerate() {
+            extensions_used.push(reader.str(extension, &format!("extensionsUsed[{}]", i))?.to_string());
         }
 
-        let mut buffers = Vec::new();
-        for buffer in json["buffers"].members() {
-            buffers.push(
-                Buffer::new(
-                    resolve_gltf_uri(path, buffer["uri"].as_str().unwrap()),
-                    buffer["byteLength"].as_usize().unwrap()
-                ))
+        for (i, buffer) in json["buffers"].members().enumerate() {
+            let field = |name: &str| format!("buffers[{}].{}", i, name);
+            let byte_length = reader.usize(&buffer["byteLength"], &field("byteLength"))?;
+            let fallback = reader.opt_bool(&buffer["extensions"]["EXT_meshopt_compression"]["fallback"], &field("extensions.EXT_meshopt_compression.fallback"))?.unwrap_or(false);
+            let (uri, data) = match reader.opt_str(&buffer["uri"], &field("uri"))? {
+                // compressed buffer views are decoded into the fallback buffer
+                None if fallback => (PathBuf::from(path), vec![0; byte_length]),
+                // the first buffer of a glb has no uri, its data is the binary chunk
+                None => (PathBuf::from(path), glb_binary_chunk.take().ok_or_else(|| reader.error(field("uri"), GltfErrorKind::Missing))?),
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = decode_data_uri(uri).ok_or_else(|| reader.error(field("uri"), GltfErrorKind::Invalid(String::from("a base64 data uri"))))?;
+                    (PathBuf::from(path), data)
+                }
+                Some(uri) => {
+                    let uri = resolve_gltf_uri(path, uri);
+                    let data = fs::read(&uri).map_err(|e| reader.error(field("uri"), GltfErrorKind::Io(e)))?;
+                    (uri, data)
+                }
+            };
+            if data.len() < byte_length {
+                return Err(reader.error(field("byteLength"), GltfErrorKind::Invalid(format!("at most the {} bytes of data the buffer has", data.len()))))
+            }
+            world.buffers.push(Buffer::new(uri, byte_length, data))
         }
-        world.buffers.extend(buffers);
 
-        let mut buffer_views = Vec::new();
-        for buffer_view in json["bufferViews"].members() {
-            buffer_views.push(
+        for (i, buffer_view) in json["bufferViews"].members().enumerate() {
+            let field = |name: &str| format!("bufferViews[{}].{}", i, name);
+            let buffer = reader.index(&buffer_view["buffer"], &field("buffer"), buffer_count)? + initial.buffers;
+            let byte_length = reader.usize(&buffer_view["byteLength"], &field("byteLength"))?;
+            let byte_offset = reader.opt_usize(&buffer_view["byteOffset"], &field("byteOffset"))?.unwrap_or(0);
+            if byte_offset + byte_length > world.buffers[buffer].byte_length {
+                return Err(reader.error(field("byteLength"), GltfErrorKind::Invalid(format!(
+                    "the view to end within the {} bytes of its buffer", world.buffers[buffer].byte_length
+                ))))
+            }
+            let byte_stride = reader.opt_usize(&buffer_view["byteStride"], &field("byteStride"))?;
+            if let Some(byte_stride) = byte_stride.filter(|&stride| !(4..=252).contains(&stride) || stride % 4 != 0) {
+                return Err(reader.error(field("byteStride"), GltfErrorKind::Invalid(format!("a multiple of 4 from 4 to 252, not {}", byte_stride))))
+            }
+
+            let compression = &buffer_view["extensions"]["EXT_meshopt_compression"];
+            if compression.is_object() {
+                let compression_field = |name: &str| field(&format!("extensions.EXT_meshopt_compression.{}", name));
+                let source_buffer = reader.index(&compression["buffer"], &compression_field("buffer"), buffer_count)? + initial.buffers;
+                let source_offset = reader.opt_usize(&compression["byteOffset"], &compression_field("byteOffset"))?.unwrap_or(0);
+                let source_length = reader.usize(&compression["byteLength"], &compression_field("byteLength"))?;
+                let source = world.buffers[source_buffer].data.get(source_offset..source_offset + source_length)
+                    .ok_or_else(|| reader.error(compression_field("byteLength"), GltfErrorKind::Invalid(format!(
+                        "the compressed data to end within the {} bytes of its buffer", world.buffers[source_buffer].data.len()
+                    ))))?
+                    .to_vec();
+                let count = reader.usize(&compression["count"], &compression_field("count"))?;
+                let stride = reader.usize(&compression["byteStride"], &compression_field("byteStride"))?;
+                if count * stride > byte_length {
+                    return Err(reader.error(compression_field("count"), GltfErrorKind::Invalid(format!("count * byteStride to fit within the {} bytes of the view", byte_length))))
+                }
+                let mode = match reader.str(&compression["mode"], &compression_field("mode"))? {
+                    "ATTRIBUTES" => MeshoptMode::Attributes,
+                    "TRIANGLES" => MeshoptMode::Triangles,
+                    "INDICES" => MeshoptMode::Indices,
+                    other => return Err(reader.error(compression_field("mode"), GltfErrorKind::Invalid(format!("ATTRIBUTES, TRIANGLES or INDICES, not {}", other)))),
+                };
+                let filter = match reader.opt_str(&compression["filter"], &compression_field("filter"))?.unwrap_or("NONE") {
+                    "NONE" => MeshoptFilter::None,
+                    "OCTAHEDRAL" => MeshoptFilter::Octahedral,
+                    "QUATERNION" => MeshoptFilter::Quaternion,
+                    "EXPONENTIAL" => MeshoptFilter::Exponential,
+                    other => return Err(reader.error(compression_field("filter"), GltfErrorKind::Invalid(format!("NONE, OCTAHEDRAL, QUATERNION or EXPONENTIAL, not {}", other)))),
+                };
+                let destination = &mut world.buffers[buffer].data[byte_offset..byte_offset + byte_length];
+                meshopt::decode(destination, count, stride, mode, filter, &source)
+                    .map_err(|e| reader.error(field("extensions.EXT_meshopt_compression"), GltfErrorKind::Malformed(e)))?;
+            }
+
+            world.buffer_views.push(
                 BufferView {
-                    buffer: buffer_view["buffer"].as_usize().unwrap() + initial_buffer_count,
-                    byte_length: buffer_view["byteLength"].as_usize().unwrap(),
-                    byte_offset: buffer_view["byteOffset"].as_usize().unwrap_or(0),
-                    target: buffer_view["target"].as_usize().unwrap_or(0)
+                    buffer,
+                    byte_length,
+                    byte_offset,
+                    byte_stride,
+                    target: reader.opt_usize(&buffer_view["target"], &field("target"))?.unwrap_or(0),
                 })
         }
-        world.buffer_views.extend(buffer_views);
-
-        let mut accessors = Vec::new();
-        for accessor in json["accessors"].members() {
-            let mut min: Option<Vector> = None;
-            let mut max: Option<Vector> = None;
-            if let JsonValue::Array(ref min_data) = accessor["min"] {
-                if min_data.len() >= 3 {
-                    min = Some(Vector::new3(
-                        min_data[0].as_f32().unwrap(),
-                        min_data[1].as_f32().unwrap(),
-                        min_data[2].as_f32().unwrap()));
-                }
+
+        for (i, accessor) in json["accessors"].members().enumerate() {
+            let field = |name: &str| format!("accessors[{}].{}", i, name);
+            let component_type_id = reader.usize(&accessor["componentType"], &field("componentType"))?;
+            let component_type = ComponentType::from_u32(component_type_id as u32)
+                .ok_or_else(|| reader.error(field("componentType"), GltfErrorKind::Invalid(format!("a glTF component type, not {}", component_type_id))))?;
+            let r#type = reader.str(&accessor["type"], &field("type"))?;
+            if !matches!(r#type, "SCALAR" | "VEC2" | "VEC3" | "VEC4" | "MAT2" | "MAT3" | "MAT4") {
+                return Err(reader.error(field("type"), GltfErrorKind::Invalid(format!("SCALAR, VECn or MATn, not {}", r#type))))
             }
-            if let JsonValue::Array(ref max_data) = accessor["max"] {
-                if max_data.len() >= 3 {
-                    max = Some(Vector::new3(
-                        max_data[0].as_f32().unwrap(), 
-                        max_data[1].as_f32().unwrap(), 
-                        max_data[2].as_f32().unwrap()));
-                }
+            let normalized = reader.opt_bool(&accessor["normalized"], &field("normalized"))?.unwrap_or(false);
+            if normalized && matches!(component_type, ComponentType::U32 | ComponentType::F32) {
+                return Err(reader.error(field("normalized"), GltfErrorKind::Invalid(String::from("false for 32 bit component types"))))
             }
-            accessors.push(
-                Accessor {
-                    buffer_view: accessor["bufferView"].as_usize().unwrap() + initial_buffer_view_count,
-                    component_type: ComponentType::from_u32(accessor["componentType"].as_u32().unwrap()).expect("unsupported component type"),
-                    count: accessor["count"].as_usize().unwrap(),
-                    r#type: accessor["type"].as_str().unwrap().parse().unwrap(),
-                    min,
-                    max,
-                    data: Vec::new(),
+
+            // without a buffer view the elements are zeros, which a sparse accessor substitutes some of
+            let sparse_json = &accessor["sparse"];
+            let sparse = if sparse_json.is_object() {
+                let sparse_field = |name: &str| field(&format!("sparse.{}", name));
+                let indices_component_type_id = reader.usize(&sparse_json["indices"]["componentType"], &sparse_field("indices.componentType"))?;
+                let indices_component_type = match ComponentType::from_u32(indices_component_type_id as u32) {
+                    Some(component_type @ (ComponentType::U8 | ComponentType::U16 | ComponentType::U32)) => component_type,
+                    _ => return Err(reader.error(sparse_field("indices.componentType"), GltfErrorKind::Invalid(format!("an unsigned integer component type, not {}", indices_component_type_id)))),
+                };
+                Some(SparseAccessor {
+                    count: reader.usize(&sparse_json["count"], &sparse_field("count"))?,
+                    indices_buffer_view: reader.index(&sparse_json["indices"]["bufferView"], &sparse_field("indices.bufferView"), buffer_view_count)? + initial.buffer_views,
+                    indices_byte_offset: reader.opt_usize(&sparse_json["indices"]["byteOffset"], &sparse_field("indices.byteOffset"))?.unwrap_or(0),
+                    indices_component_type,
+                    values_buffer_view: reader.index(&sparse_json["values"]["bufferView"], &sparse_field("values.bufferView"), buffer_view_count)? + initial.buffer_views,
+                    values_byte_offset: reader.opt_usize(&sparse_json["values"]["byteOffset"], &sparse_field("values.byteOffset"))?.unwrap_or(0),
                 })
-        }
-        world.accessors.extend(accessors);
+            } else {
+                None
+            };
 
-        let mut images = Vec::new();
-        for image in json["images"].members() {
-            let name_maybe: Option<&str> = image["name"].as_str();
-            let mut name = String::from("unnamed image");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
+            let new_accessor = Accessor {
+                buffer_view: reader.opt_index(&accessor["bufferView"], &field("bufferView"), buffer_view_count)?.map(|index| index + initial.buffer_views),
+                byte_offset: reader.opt_usize(&accessor["byteOffset"], &field("byteOffset"))?.unwrap_or(0),
+                component_type,
+                normalized,
+                count: reader.usize(&accessor["count"], &field("count"))?,
+                r#type: String::from(r#type),
+                min: reader.opt_floats::<3>(&accessor["min"], &field("min"))?.map(|min| Vector::new3(min[0], min[1], min[2])),
+                max: reader.opt_floats::<3>(&accessor["max"], &field("max"))?.map(|max| Vector::new3(max[0], max[1], max[2])),
+                sparse,
+                data: Vec::new(),
+            };
+            if new_accessor.buffer_view.is_none() && new_accessor.sparse.is_none() {
+                return Err(reader.error(field("bufferView"), GltfErrorKind::Missing))
             }
 
-            let mime_type_maybe: Option<&str> = image["mimeType"].as_str();
-            let mut mime_type = String::from("no mime type");
-            match mime_type_maybe {
-                Some(mime_type_str) => mime_type = String::from(mime_type_str),
-                None => (),
+            // elements have to end within their views, so reading them never goes out of bounds
+            let element_size = new_accessor.element_size();
+            if let Some(buffer_view) = new_accessor.buffer_view.map(|index| &world.buffer_views[index]) {
+                let stride = buffer_view.byte_stride.unwrap_or(element_size);
+                let end = new_accessor.byte_offset + new_accessor.count.saturating_sub(1) * stride + element_size;
+                if new_accessor.count > 0 && end > buffer_view.byte_length {
+                    return Err(reader.error(field("count"), GltfErrorKind::Invalid(format!(
+                        "the elements to end within the {} bytes of the buffer view, not at byte {}", buffer_view.byte_length, end
+                    ))))
+                }
+            }
+            if let Some(sparse) = &new_accessor.sparse {
+                let indices_end = sparse.indices_byte_offset + sparse.count * sparse.indices_component_type.size();
+                if indices_end > world.buffer_views[sparse.indices_buffer_view].byte_length {
+                    return Err(reader.error(field("sparse.count"), GltfErrorKind::Invalid(String::from("the sparse indices to end within their buffer view"))))
+                }
+                let values_end = sparse.values_byte_offset + sparse.count * element_size;
+                if values_end > world.buffer_views[sparse.values_buffer_view].byte_length {
+                    return Err(reader.error(field("sparse.count"), GltfErrorKind::Invalid(String::from("the sparse values to end within their buffer view"))))
+                }
             }
+            world.accessors.push(new_accessor)
+        }
 
-            let uri = image["uri"].as_str().unwrap();
+        // added to the loaded files once the whole model has loaded
+        let mut image_files = Vec::new();
+        for (i, image) in json["images"].members().enumerate() {
+            let field = |name: &str| format!("images[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&image["name"], &field("name"))?.unwrap_or("unnamed image"));
+            let mut mime_type = String::from(reader.opt_str(&image["mimeType"], &field("mimeType"))?.unwrap_or("no mime type"));
+
+            // embedded images are keyed by the model path and their index, as they have no file of their own
+            let embedded_uri = format!("{}#images/{}", path, i);
+            let (uri, resolved_uri, data) = if let Some(buffer_view_index) = reader.opt_index(&image["bufferView"], &field("bufferView"), buffer_view_count)? {
+                let bytes = world.buffer_views[buffer_view_index + initial.buffer_views].bytes(&world.buffers);
+                (embedded_uri.clone(), PathBuf::from(embedded_uri), Some(bytes.to_vec()))
+            } else {
+                let uri = reader.str(&image["uri"], &field("uri"))?;
+                if uri.starts_with("data:") {
+                    let (data_mime_type, bytes) = decode_data_uri(uri).ok_or_else(|| reader.error(field("uri"), GltfErrorKind::Invalid(String::from("a base64 data uri"))))?;
+                    if image["mimeType"].is_null() {
+                        mime_type = data_mime_type;
+                    }
+                    (embedded_uri.clone(), PathBuf::from(embedded_uri), Some(bytes))
+                } else {
+                    let resolved_uri = resolve_gltf_uri(path, uri);
+                    // the file itself is only read once the textures are constructed
+                    fs::metadata(&resolved_uri).map_err(|e| reader.error(field("uri"), GltfErrorKind::Io(e)))?;
+                    (String::from(uri), resolved_uri, None)
+                }
+            };
 
-            world.loaded_files.insert(String::from(uri), initial_images_count + images.len());
+            image_files.push((uri, world.images.len()));
 
-            images.push(
-                Image::new(
-                    mime_type,
-                    name,
-                    resolve_gltf_uri(path, uri)
-                ))
+            let mut new_image = Image::new(mime_type, name, resolved_uri);
+            new_image.data = data.map(Arc::new);
+            world.images.push(new_image)
         }
-        world.images.extend(images);
 
         let mut samplers = Vec::new();
-        for sampler in json["samplers"].members() {
+        for (i, sampler) in json["samplers"].members().enumerate() {
+            let field = |name: &str| format!("samplers[{}].{}", i, name);
+            let mode = |name: &str| -> Result<i32, GltfError> { Ok(reader.opt_usize(&sampler[name], &field(name))?.unwrap_or(0) as i32) };
             samplers.push(SceneSampler {
-                min_filter: SceneSampler::get_filter_type(sampler["minFilter"].as_i32().unwrap_or(0)),
-                mag_filter: SceneSampler::get_filter_type(sampler["magFilter"].as_i32().unwrap_or(0)),
-                address_mode_u: SceneSampler::get_address_mode(sampler["wrapS"].as_i32().unwrap_or(0)),
-                address_mode_v: SceneSampler::get_address_mode(sampler["wrapT"].as_i32().unwrap_or(0)),
-                address_mode_w: SceneSampler::get_address_mode(sampler["wrapT"].as_i32().unwrap_or(0)), // TODO() Deduce if modern gltf has a w-wrapping field
+                min_filter: SceneSampler::get_filter_type(mode("minFilter")?),
+                mag_filter: SceneSampler::get_filter_type(mode("magFilter")?),
+                address_mode_u: SceneSampler::get_address_mode(mode("wrapS")?),
+                address_mode_v: SceneSampler::get_address_mode(mode("wrapT")?),
+                address_mode_w: SceneSampler::get_address_mode(mode("wrapT")?), // TODO() Deduce if modern gltf has a w-wrapping field
             })
         }
-
-        let mut textures = Vec::new();
-        for texture in json["textures"].members() {
-            textures.push(
+        debug_assert_eq!(samplers.len(), sampler_count);
+
+        for (i, texture) in json["textures"].members().enumerate() {
+            let field = |name: &str| format!("textures[{}].{}", i, name);
+            let sampler_info = match reader.opt_index(&texture["sampler"], &field("sampler"), sampler_count)? {
+                Some(sampler) => samplers[sampler],
+                // without a sampler textures repeat and use auto filtering
+                None => SceneSampler {
+                    min_filter: SceneSampler::get_filter_type(0),
+                    mag_filter: SceneSampler::get_filter_type(0),
+                    address_mode_u: SceneSampler::get_address_mode(0),
+                    address_mode_v: SceneSampler::get_address_mode(0),
+                    address_mode_w: SceneSampler::get_address_mode(0),
+                },
+            };
+            world.textures.push(
                 SceneTexture {
-                    source: texture["source"].as_usize().unwrap() + initial_images_count,
+                    source: reader.index(&texture["source"], &field("source"), image_count)? + initial.images,
                     sampler: Sampler::null(),
-                    sampler_info: samplers[texture["sampler"].as_usize().unwrap().clone()],
+                    sampler_info,
                     has_sampler: false
                 })
         }
-        world.textures.extend(textures);
 
-        let mut materials = Vec::new();
-        materials.push(Material {
+        // the default material, used by primitives without one
+        world.materials.push(Material {
             alpha_mode: String::from("BLEND"),
             alpha_cutoff: 0.5,
             double_sided: false,
@@ -620,431 +915,346 @@ impl ModelContainer {
             emissive_texture_offset: None,
             emissive_texture_scale: None,
             emissive_strength: 1.0,
+            clearcoat_factor: 0.0,
+            clearcoat_texture: None,
+            clearcoat_texture_offset: None,
+            clearcoat_texture_scale: None,
+            clearcoat_roughness_factor: 0.0,
+            clearcoat_roughness_texture: None,
+            clearcoat_roughness_texture_offset: None,
+            clearcoat_roughness_texture_scale: None,
+            clearcoat_normal_texture: None,
+            clearcoat_normal_texture_offset: None,
+            clearcoat_normal_texture_scale: None,
+            transmission_factor: 0.0,
+            transmission_texture: None,
+            transmission_texture_offset: None,
+            transmission_texture_scale: None,
+            thickness_factor: 0.0,
+            thickness_texture: None,
+            thickness_texture_offset: None,
+            thickness_texture_scale: None,
+            attenuation_distance: f32::INFINITY,
+            attenuation_color: [1.0; 3],
+            sheen_color_factor: [0.0; 3],
+            sheen_color_texture: None,
+            sheen_color_texture_offset: None,
+            sheen_color_texture_scale: None,
+            sheen_roughness_factor: 0.0,
+            sheen_roughness_texture: None,
+            sheen_roughness_texture_offset: None,
+            sheen_roughness_texture_scale: None,
+            unlit: false,
         });
-        for material in json["materials"].members() {
-            let name_maybe: Option<&str> = material["name"].as_str();
-            let mut name = String::from("unnamed node");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
-
-            let mut alpha_mode = String::from("BLEND");
-            if let JsonValue::String(ref alpha_mode_json) = material["alphaMode"] {
-                alpha_mode = (*alpha_mode_json).parse().unwrap();
-            }
-
-            let mut alpha_cutoff = 0.5;
-            if let JsonValue::Number(ref alpha_cutoff_json) = material["alphaCutoff"] {
-                if let Ok(f) = alpha_cutoff_json.to_string().parse::<f32>() {
-                    alpha_cutoff = f;
-                }
-            }
-
-            let mut double_sided = false;
-            if let JsonValue::Boolean(ref double_sided_json) = material["doubleSided"] {
-                double_sided = *double_sided_json;
-            }
-
-            let mut normal_texture = None;
-            let mut normal_texture_offset = None;
-            let mut normal_texture_scale = None;
-            if let JsonValue::Object(ref normal_texture_json) = material["normalTexture"] {
-                normal_texture = Some(normal_texture_json["index"].as_i32().expect(""));
-                if let JsonValue::Object(ref extensions_json) = normal_texture_json["extensions"] {
-                    if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                        if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                            if json_value.len() >= 3 {
-                                normal_texture_offset = Some([
-                                    json_value[0].as_f32().unwrap(),
-                                    json_value[1].as_f32().unwrap(),
-                                ]);
-                            }
-                        }
-                        if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                            if json_value.len() >= 3 {
-                                normal_texture_scale = Some([
-                                    json_value[0].as_f32().unwrap(),
-                                    json_value[1].as_f32().unwrap(),
-                                ]);
-                            }
-                        }
-                    }
-                }
-            }
-
-            let mut emissive_factor = [0.0; 3];
-            let mut emissive_texture = None;
-            let mut emissive_texture_offset = None;
-            let mut emissive_texture_scale = None;
-            if let JsonValue::Array(ref json_value) = material["emissiveFactor"] {
-                if json_value.len() >= 3 {
-                    emissive_factor = [
-                        json_value[0].as_f32().unwrap(),
-                        json_value[1].as_f32().unwrap(),
-                        json_value[2].as_f32().unwrap(),
-                    ];
-                }
-            }
-            if let JsonValue::Object(ref emissive_texture_json) = material["emissiveTexture"] {
-                emissive_texture = Some(emissive_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for emissiveTexture"));
-                if let JsonValue::Object(ref extensions_json) = emissive_texture_json["extensions"] {
-                    if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                        if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                            if json_value.len() >= 3 {
-                                emissive_texture_offset = Some([
-                                    json_value[0].as_f32().unwrap(),
-                                    json_value[1].as_f32().unwrap(),
-                                ]);
-                            }
-                        }
-                        if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                            if json_value.len() >= 3 {
-                                emissive_texture_scale = Some([
-                                    json_value[0].as_f32().unwrap(),
-                                    json_value[1].as_f32().unwrap(),
-                                ]);
-                            }
-                        }
-                    }
-                }
-            }
-
-            let mut base_color_factor = [0.5, 0.5, 0.5, 1.0];
-            let mut base_color_texture = None;
-            let mut base_color_texture_offset = None;
-            let mut base_color_texture_scale = None;
-            let mut metallic_factor = 0.1;
-            let mut roughness_factor = 0.5;
-            let mut metallic_texture = None;
-            let mut metallic_texture_offset = None;
-            let mut metallic_texture_scale = None;
-            let mut roughness_texture = None;
-            let mut roughness_texture_offset = None;
-            let mut roughness_texture_scale = None;
-            if let JsonValue::Object(ref pbr_metallic_roughness) = material["pbrMetallicRoughness"] {
-                if let JsonValue::Array(ref json_value) = pbr_metallic_roughness["baseColorFactor"] {
-                    if json_value.len() >= 4 {
-                        base_color_factor = [
-                            json_value[0].as_f32().unwrap(),
-                            json_value[1].as_f32().unwrap(),
-                            json_value[2].as_f32().unwrap(),
-                            json_value[3].as_f32().unwrap(),
-                        ];
-                    }
-                }
-                if let JsonValue::Object(ref base_color_texture_json) = pbr_metallic_roughness["baseColorTexture"] {
-                    base_color_texture = Some(base_color_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for baseColorTexture at pbrMetallicRoughness"));
-                    if let JsonValue::Object(ref extensions_json) = base_color_texture_json["extensions"] {
-                        if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                                if json_value.len() >= 2 {
-                                    base_color_texture_offset = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                                if json_value.len() >= 2 {
-                                    base_color_texture_scale = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if let JsonValue::Number(ref json_value) = pbr_metallic_roughness["metallicFactor"] {
-                    if let Ok(f) = json_value.to_string().parse::<f32>() {
-                        metallic_factor = f;
-                    }
-                }
-                if let JsonValue::Object(ref metallic_texture_json) = pbr_metallic_roughness["metallicTexture"] {
-                    metallic_texture = Some(metallic_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for metallicTexture at pbrMetallicRoughness"));
-                    if let JsonValue::Object(ref extensions_json) = metallic_texture_json["extensions"] {
-                        if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                                if json_value.len() >= 2 {
-                                    metallic_texture_offset = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                                if json_value.len() >= 2 {
-                                    metallic_texture_scale = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if let JsonValue::Number(ref json_value) = pbr_metallic_roughness["roughnessFactor"] {
-                    if let Ok(f) = json_value.to_string().parse::<f32>() {
-                        roughness_factor = f;
-                    }
-                }
-                if let JsonValue::Object(ref roughness_texture_json) = pbr_metallic_roughness["roughnessTexture"] {
-                    roughness_texture = Some(roughness_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for roughnessTexture at pbrMetallicRoughness"));
-                    if let JsonValue::Object(ref extensions_json) = roughness_texture_json["extensions"] {
-                        if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                                if json_value.len() >= 2 {
-                                    roughness_texture_offset = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                                if json_value.len() >= 2 {
-                                    roughness_texture_scale = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if let JsonValue::Object(ref metallic_roughness_texture_json) = pbr_metallic_roughness["metallicRoughnessTexture"] {
-                    roughness_texture = Some(metallic_roughness_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for metallicRoughnessTexture at pbrMetallicRoughness"));
-                    metallic_texture = Some(metallic_roughness_texture_json["index"].as_i32().expect("FAULTY GLTF: \n    Missing index for metallicRoughnessTexture at pbrMetallicRoughness"));
-                    if let JsonValue::Object(ref extensions_json) = metallic_roughness_texture_json["extensions"] {
-                        if let JsonValue::Object(ref texture_transform_json) = extensions_json["KHR_texture_transform"] {
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["offset"] {
-                                if json_value.len() >= 2 {
-                                    metallic_texture_offset = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                    roughness_texture_offset = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                            if let JsonValue::Array(ref json_value) = texture_transform_json["scale"] {
-                                if json_value.len() >= 2 {
-                                    metallic_texture_scale = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                    roughness_texture_scale = Some([
-                                        json_value[0].as_f32().unwrap(),
-                                        json_value[1].as_f32().unwrap(),
-                                    ]);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            let mut specular_color_factor = [0.0; 3];
-            if let JsonValue::Object(ref khr_materials_specular) = material["KHR_materials_specular"] {
-                if let JsonValue::Array(ref json_val) = khr_materials_specular["baseColorFactor"] {
-                    if json_val.len() >= 3 {
-                        specular_color_factor = [
-                            json_val[0].as_f32().unwrap(),
-                            json_val[1].as_f32().unwrap(),
-                            json_val[2].as_f32().unwrap(),
-                        ];
-                    }
-                }
+        for (i, material) in json["materials"].members().enumerate() {
+            let field = |name: &str| format!("materials[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&material["name"], &field("name"))?.unwrap_or("unnamed node"));
+            let alpha_mode = String::from(reader.opt_str(&material["alphaMode"], &field("alphaMode"))?.unwrap_or("BLEND"));
+            let alpha_cutoff = reader.opt_f32(&material["alphaCutoff"], &field("alphaCutoff"))?.unwrap_or(0.5);
+            let double_sided = reader.opt_bool(&material["doubleSided"], &field("doubleSided"))?.unwrap_or(false);
+
+            let normal_texture = read_texture_info(&reader, &material["normalTexture"], &field("normalTexture"), texture_count)?;
+
+            let emissive_factor = reader.opt_floats::<3>(&material["emissiveFactor"], &field("emissiveFactor"))?.unwrap_or([0.0; 3]);
+            let emissive_texture = read_texture_info(&reader, &material["emissiveTexture"], &field("emissiveTexture"), texture_count)?;
+
+            let pbr_metallic_roughness = &material["pbrMetallicRoughness"];
+            let pbr_field = |name: &str| format!("materials[{}].pbrMetallicRoughness.{}", i, name);
+            let base_color_factor = reader.opt_floats::<4>(&pbr_metallic_roughness["baseColorFactor"], &pbr_field("baseColorFactor"))?.unwrap_or([0.5, 0.5, 0.5, 1.0]);
+            let base_color_texture = read_texture_info(&reader, &pbr_metallic_roughness["baseColorTexture"], &pbr_field("baseColorTexture"), texture_count)?;
+            let metallic_factor = reader.opt_f32(&pbr_metallic_roughness["metallicFactor"], &pbr_field("metallicFactor"))?.unwrap_or(0.1);
+            let mut metallic_texture = read_texture_info(&reader, &pbr_metallic_roughness["metallicTexture"], &pbr_field("metallicTexture"), texture_count)?;
+            let roughness_factor = reader.opt_f32(&pbr_metallic_roughness["roughnessFactor"], &pbr_field("roughnessFactor"))?.unwrap_or(0.5);
+            let mut roughness_texture = read_texture_info(&reader, &pbr_metallic_roughness["roughnessTexture"], &pbr_field("roughnessTexture"), texture_count)?;
+            // metalness and roughness packed into one texture
+            if let Some(texture) = read_texture_info(&reader, &pbr_metallic_roughness["metallicRoughnessTexture"], &pbr_field("metallicRoughnessTexture"), texture_count)? {
+                metallic_texture = Some(texture);
+                roughness_texture = Some(texture);
             }
 
-            let mut ior = 1.0;
-            if let JsonValue::Object(ref khr_materials_ior) = material["KHR_materials_ior"] {
-                if let JsonValue::Number(ref json_value) = khr_materials_ior["ior"] {
-                    if let Ok(f) = json_value.to_string().parse::<f32>() {
-                        ior = f;
-                    }
-                }
-            }
+            let specular_color_factor = reader.opt_floats::<3>(&material["KHR_materials_specular"]["baseColorFactor"], &field("KHR_materials_specular.baseColorFactor"))?.unwrap_or([0.0; 3]);
+            let ior = reader.opt_f32(&material["KHR_materials_ior"]["ior"], &field("KHR_materials_ior.ior"))?.unwrap_or(1.0);
 
-            let mut emissive_strength = 1.0;
-            if let JsonValue::Object(ref extensions) = material["extensions"] {
-                if let JsonValue::Object(ref json_value) = extensions["KHR_materials_emissive_strength"] {
-                    emissive_strength = json_value["emissiveStrength"].as_f32().expect("FAULTY GLTF: \n    Missing emissiveStrength for KHR_materials_emissive_strength");
-                }
-            }
+            let emissive_strength_json = &material["extensions"]["KHR_materials_emissive_strength"];
+            let emissive_strength = if emissive_strength_json.is_object() {
+                reader.f32(&emissive_strength_json["emissiveStrength"], &field("extensions.KHR_materials_emissive_strength.emissiveStrength"))?
+            } else {
+                1.0
+            };
 
-            materials.push(
+            let extensions = &material["extensions"];
+            let extension_field = |extension: &str, name: &str| format!("materials[{}].extensions.{}.{}", i, extension, name);
+
+            let clearcoat = &extensions["KHR_materials_clearcoat"];
+            let clearcoat_field = |name: &str| extension_field("KHR_materials_clearcoat", name);
+            let clearcoat_factor = reader.opt_f32(&clearcoat["clearcoatFactor"], &clearcoat_field("clearcoatFactor"))?.unwrap_or(0.0);
+            let clearcoat_texture = read_texture_info(&reader, &clearcoat["clearcoatTexture"], &clearcoat_field("clearcoatTexture"), texture_count)?;
+            let clearcoat_roughness_factor = reader.opt_f32(&clearcoat["clearcoatRoughnessFactor"], &clearcoat_field("clearcoatRoughnessFactor"))?.unwrap_or(0.0);
+            let clearcoat_roughness_texture = read_texture_info(&reader, &clearcoat["clearcoatRoughnessTexture"], &clearcoat_field("clearcoatRoughnessTexture"), texture_count)?;
+            let clearcoat_normal_texture = read_texture_info(&reader, &clearcoat["clearcoatNormalTexture"], &clearcoat_field("clearcoatNormalTexture"), texture_count)?;
+
+            let transmission = &extensions["KHR_materials_transmission"];
+            let transmission_field = |name: &str| extension_field("KHR_materials_transmission", name);
+            let transmission_factor = reader.opt_f32(&transmission["transmissionFactor"], &transmission_field("transmissionFactor"))?.unwrap_or(0.0);
+            let transmission_texture = read_texture_info(&reader, &transmission["transmissionTexture"], &transmission_field("transmissionTexture"), texture_count)?;
+
+            let volume = &extensions["KHR_materials_volume"];
+            let volume_field = |name: &str| extension_field("KHR_materials_volume", name);
+            let thickness_factor = reader.opt_f32(&volume["thicknessFactor"], &volume_field("thicknessFactor"))?.unwrap_or(0.0);
+            let thickness_texture = read_texture_info(&reader, &volume["thicknessTexture"], &volume_field("thicknessTexture"), texture_count)?;
+            let attenuation_distance = reader.opt_f32(&volume["attenuationDistance"], &volume_field("attenuationDistance"))?.unwrap_or(f32::INFINITY);
+            let attenuation_color = reader.opt_floats::<3>(&volume["attenuationColor"], &volume_field("attenuationColor"))?.unwrap_or([1.0; 3]);
+
+            let sheen = &extensions["KHR_materials_sheen"];
+            let sheen_field = |name: &str| extension_field("KHR_materials_sheen", name);
+            let sheen_color_factor = reader.opt_floats::<3>(&sheen["sheenColorFactor"], &sheen_field("sheenColorFactor"))?.unwrap_or([0.0; 3]);
+            let sheen_color_texture = read_texture_info(&reader, &sheen["sheenColorTexture"], &sheen_field("sheenColorTexture"), texture_count)?;
+            let sheen_roughness_factor = reader.opt_f32(&sheen["sheenRoughnessFactor"], &sheen_field("sheenRoughnessFactor"))?.unwrap_or(0.0);
+            let sheen_roughness_texture = read_texture_info(&reader, &sheen["sheenRoughnessTexture"], &sheen_field("sheenRoughnessTexture"), texture_count)?;
+
+            // the extension has no properties, its presence alone makes the material unlit
+            let unlit = extensions["KHR_materials_unlit"].is_object();
+
+            world.materials.push(
                 Material {
                     name,
                     alpha_mode,
                     alpha_cutoff,
                     double_sided,
-                    normal_texture,
+                    normal_texture: normal_texture.map(|t| t.index),
                     // KHR_texture_transform
-                        normal_texture_offset,
-                        normal_texture_scale,
+                        normal_texture_offset: normal_texture.and_then(|t| t.offset),
+                        normal_texture_scale: normal_texture.and_then(|t| t.scale),
                     // KHR_materials_specular
                         specular_color_factor,
                     // KHR_materials_ior
                         ior,
                     // pbrMetallicRoughness
                         base_color_factor,
-                        base_color_texture,
+                        base_color_texture: base_color_texture.map(|t| t.index),
                         // KHR_texture_transform
-                            base_color_texture_offset,
-                            base_color_texture_scale,
+                            base_color_texture_offset: base_color_texture.and_then(|t| t.offset),
+                            base_color_texture_scale: base_color_texture.and_then(|t| t.scale),
                         metallic_factor,
-                        metallic_texture,
+                        metallic_texture: metallic_texture.map(|t| t.index),
                         // KHR_texture_transform
-                            metallic_texture_offset,
-                            metallic_texture_scale,
+                            metallic_texture_offset: metallic_texture.and_then(|t| t.offset),
+                            metallic_texture_scale: metallic_texture.and_then(|t| t.scale),
                         roughness_factor,
-                        roughness_texture,
+                        roughness_texture: roughness_texture.map(|t| t.index),
                         // KHR_texture_transform
-                            roughness_texture_offset,
-                            roughness_texture_scale,
+                            roughness_texture_offset: roughness_texture.and_then(|t| t.offset),
+                            roughness_texture_scale: roughness_texture.and_then(|t| t.scale),
                     emissive_factor,
-                    emissive_texture,
+                    emissive_texture: emissive_texture.map(|t| t.index),
                         // KHR_texture_transform
-                        emissive_texture_offset,
-                        emissive_texture_scale,
+                        emissive_texture_offset: emissive_texture.and_then(|t| t.offset),
+                        emissive_texture_scale: emissive_texture.and_then(|t| t.scale),
                     // extensions
                         emissive_strength,
+                        clearcoat_factor,
+                        clearcoat_texture: clearcoat_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            clearcoat_texture_offset: clearcoat_texture.and_then(|t| t.offset),
+                            clearcoat_texture_scale: clearcoat_texture.and_then(|t| t.scale),
+                        clearcoat_roughness_factor,
+                        clearcoat_roughness_texture: clearcoat_roughness_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            clearcoat_roughness_texture_offset: clearcoat_roughness_texture.and_then(|t| t.offset),
+                            clearcoat_roughness_texture_scale: clearcoat_roughness_texture.and_then(|t| t.scale),
+                        clearcoat_normal_texture: clearcoat_normal_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            clearcoat_normal_texture_offset: clearcoat_normal_texture.and_then(|t| t.offset),
+                            clearcoat_normal_texture_scale: clearcoat_normal_texture.and_then(|t| t.scale),
+                        transmission_factor,
+                        transmission_texture: transmission_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            transmission_texture_offset: transmission_texture.and_then(|t| t.offset),
+                            transmission_texture_scale: transmission_texture.and_then(|t| t.scale),
+                        thickness_factor,
+                        thickness_texture: thickness_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            thickness_texture_offset: thickness_texture.and_then(|t| t.offset),
+                            thickness_texture_scale: thickness_texture.and_then(|t| t.scale),
+                        attenuation_distance,
+                        attenuation_color,
+                        sheen_color_factor,
+                        sheen_color_texture: sheen_color_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            sheen_color_texture_offset: sheen_color_texture.and_then(|t| t.offset),
+                            sheen_color_texture_scale: sheen_color_texture.and_then(|t| t.scale),
+                        sheen_roughness_factor,
+                        sheen_roughness_texture: sheen_roughness_texture.map(|t| t.index),
+                        // KHR_texture_transform
+                            sheen_roughness_texture_offset: sheen_roughness_texture.and_then(|t| t.offset),
+                            sheen_roughness_texture_scale: sheen_roughness_texture.and_then(|t| t.scale),
+                        unlit,
                 })
         }
-        world.materials.extend(materials);
 
-        let mut meshes = Vec::new();
-        for mesh in json["meshes"].members() {
-            let name_maybe: Option<&str> = mesh["name"].as_str();
-            let mut name = String::from("unnamed node");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
+        for (i, mesh) in json["meshes"].members().enumerate() {
+            let name = String::from(reader.opt_str(&mesh["name"], &format!("meshes[{}].name", i))?.unwrap_or("unnamed node"));
 
             let mut primitives = Vec::new();
-            if let JsonValue::Array(ref primitives_json) = mesh["primitives"] {
-                for primitive_json in primitives_json {
-                    let mut attributes: Vec<(String, usize)> = Vec::new();
-                    if let JsonValue::Object(ref attributes_json) = primitive_json["attributes"] {
-                        for (name, accessor) in attributes_json.iter() {
-                            attributes.push((name.to_string(), accessor.as_usize().unwrap() + initial_accessors_count));
-                        }
+            for (j, primitive_json) in reader.array(&mesh["primitives"], &format!("meshes[{}].primitives", i))?.iter().enumerate() {
+                let field = |name: &str| format!("meshes[{}].primitives[{}].{}", i, j, name);
+                let is_unsigned = |accessor: usize| matches!(world.accessors[accessor].component_type, ComponentType::U8 | ComponentType::U16 | ComponentType::U32);
+
+                let mut attributes: Vec<(String, usize)> = Vec::new();
+                for (name, accessor) in primitive_json["attributes"].entries() {
+                    let attribute_field = field(&format!("attributes.{}", name));
+                    let accessor = reader.index(accessor, &attribute_field, accessor_count)? + initial.accessors;
+                    if name == "JOINTS_0" && !is_unsigned(accessor) {
+                        return Err(reader.error(attribute_field, GltfErrorKind::Invalid(String::from("an accessor of unsigned integers"))))
                     }
+                    attributes.push((name.to_string(), accessor));
+                }
+
+                let mut targets: Vec<Vec<(String, usize)>> = Vec::new();
+                for (k, target_json) in reader.array(&primitive_json["targets"], &field("targets"))?.iter().enumerate() {
+                    let mut target = Vec::new();
+                    for (name, accessor) in target_json.entries() {
+                        target.push((name.to_string(), reader.index(accessor, &field(&format!("targets[{}].{}", k, name)), accessor_count)? + initial.accessors));
+                    }
+                    targets.push(target);
+                }
 
-                    let indices = primitive_json["indices"].as_usize().unwrap() + initial_accessors_count;
-
-                    let material_index = if let Some(material_index) = primitive_json["material"].as_u32() {
-                        material_index + initial_materials_count as u32 + 1u32
-                    } else {
-                        0u32
-                    };
-                    primitives.push(Primitive {
-                        attributes,
-                        indices_count: world.accessors[indices].count,
-                        indices,
-                        index_buffer_offset: 0,
-                        vertex_buffer_offset: 0,
-                        index_data_u8: Vec::new(),
-                        index_data_u16: Vec::new(),
-                        index_data_u32: Vec::new(),
-                        vertex_data: Vec::new(),
-                        material_index,
-                        min: Vector::new(),
-                        max: Vector::new(),
-                        corners: [Vector::new(); 8],
-                        id: world.primitive_count,
-                    });
-                    world.primitive_count += 1;
+                let indices = reader.index(&primitive_json["indices"], &field("indices"), accessor_count)? + initial.accessors;
+                if !is_unsigned(indices) {
+                    return Err(reader.error(field("indices"), GltfErrorKind::Invalid(String::from("an accessor of unsigned integers"))))
                 }
+
+                let material_index = if let Some(material_index) = reader.opt_index(&primitive_json["material"], &field("material"), material_count)? {
+                    (material_index + initial.materials + 1) as u32
+                } else {
+                    initial.materials as u32 // the model's default material
+                };
+                primitives.push(Primitive {
+                    attributes,
+                    targets,
+                    indices_count: world.accessors[indices].count,
+                    indices,
+                    index_buffer_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_data_u8: Vec::new(),
+                    index_data_u16: Vec::new(),
+                    index_data_u32: Vec::new(),
+                    vertex_data: Vec::new(),
+                    morph_targets: Vec::new(),
+                    morph_delta_offset: 0,
+                    material_index,
+                    min: Vector::new(),
+                    max: Vector::new(),
+                    corners: [Vector::new(); 8],
+                    id: world.primitive_count,
+                });
+                world.primitive_count += 1;
             }
 
-            meshes.push(
+            world.meshes.push(
                 Mesh {
                     name,
                     primitives,
                 }
             );
         }
-        world.meshes.extend(meshes);
 
-        let mut nodes = Vec::new();
-        for node in json["nodes"].members() {
-            let name_maybe: Option<&str> = node["name"].as_str();
-            let mut name = String::from("unnamed node");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
+        for (i, camera) in json["cameras"].members().enumerate() {
+            let field = |name: &str| format!("cameras[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&camera["name"], &field("name"))?.unwrap_or("unnamed camera"));
+            let projection = match reader.str(&camera["type"], &field("type"))? {
+                "perspective" => {
+                    let perspective = &camera["perspective"];
+                    let perspective_field = |name: &str| field(&format!("perspective.{}", name));
+                    GltfProjection::Perspective {
+                        y_fov: reader.f32(&perspective["yfov"], &perspective_field("yfov"))?,
+                        aspect_ratio: reader.opt_f32(&perspective["aspectRatio"], &perspective_field("aspectRatio"))?,
+                        z_near: reader.f32(&perspective["znear"], &perspective_field("znear"))?,
+                        z_far: reader.opt_f32(&perspective["zfar"], &perspective_field("zfar"))?,
+                    }
+                }
+                "orthographic" => {
+                    let orthographic = &camera["orthographic"];
+                    let orthographic_field = |name: &str| field(&format!("orthographic.{}", name));
+                    GltfProjection::Orthographic {
+                        x_mag: reader.f32(&orthographic["xmag"], &orthographic_field("xmag"))?,
+                        y_mag: reader.f32(&orthographic["ymag"], &orthographic_field("ymag"))?,
+                        z_near: reader.f32(&orthographic["znear"], &orthographic_field("znear"))?,
+                        z_far: reader.f32(&orthographic["zfar"], &orthographic_field("zfar"))?,
+                    }
+                }
+                other => return Err(reader.error(field("type"), GltfErrorKind::Invalid(format!("perspective or orthographic, not {}", other)))),
+            };
+            world.cameras.push(GltfCamera { name, projection })
+        }
 
-            let mut mesh = if let Some(index) = node["mesh"].as_usize() {
-                Some(index + initial_mesh_count)
-            } else {
-                None
+        for (i, light) in json["extensions"]["KHR_lights_punctual"]["lights"].members().enumerate() {
+            let field = |name: &str| format!("extensions.KHR_lights_punctual.lights[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&light["name"], &field("name"))?.unwrap_or("unnamed light"));
+            let light_type = match reader.str(&light["type"], &field("type"))? {
+                "point" => GltfLightType::Point,
+                "directional" => GltfLightType::Directional,
+                "spot" => {
+                    let spot = &light["spot"];
+                    GltfLightType::Spot {
+                        inner_cone_angle: reader.opt_f32(&spot["innerConeAngle"], &field("spot.innerConeAngle"))?.unwrap_or(0.0),
+                        outer_cone_angle: reader.opt_f32(&spot["outerConeAngle"], &field("spot.outerConeAngle"))?.unwrap_or(std::f32::consts::FRAC_PI_4),
+                    }
+                }
+                other => return Err(reader.error(field("type"), GltfErrorKind::Invalid(format!("point, directional or spot, not {}", other)))),
             };
+            world.lights.push(GltfLight {
+                name,
+                light_type,
+                color: reader.opt_floats::<3>(&light["color"], &field("color"))?.map_or(Vector::fill(1.0), |c| Vector::new3(c[0], c[1], c[2])),
+                intensity: reader.opt_f32(&light["intensity"], &field("intensity"))?.unwrap_or(1.0),
+                range: reader.opt_f32(&light["range"], &field("range"))?,
+            })
+        }
 
-            let skin = if let Some(index) = node["skin"].as_usize() {
-                Some((index + initial_skins_count) as i32)
+        for (i, node) in json["nodes"].members().enumerate() {
+            let field = |name: &str| format!("nodes[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&node["name"], &field("name"))?.unwrap_or("unnamed node"));
+
+            let mesh_index = reader.opt_index(&node["mesh"], &field("mesh"), mesh_count)?;
+            let mesh = mesh_index.map(|index| index + initial.meshes);
+            let skin = reader.opt_index(&node["skin"], &field("skin"), skin_count)?.map(|index| (index + initial.skins) as i32);
+            let camera = reader.opt_index(&node["camera"], &field("camera"), camera_count)?.map(|index| index + initial.cameras);
+            let light = reader.opt_index(&node["extensions"]["KHR_lights_punctual"]["light"], &field("extensions.KHR_lights_punctual.light"), light_count)?
+                .map(|index| index + initial.lights);
+
+            let rotation = reader.opt_floats::<4>(&node["rotation"], &field("rotation"))?
+                .map_or(Vector::new(), |r| Vector::new4(r[0], r[1], r[2], r[3]).normalize4());
+            let scale = reader.opt_floats::<3>(&node["scale"], &field("scale"))?
+                .map_or(Vector::fill(1.0), |s| Vector::new3(s[0], s[1], s[2]));
+            let translation = reader.opt_floats::<3>(&node["translation"], &field("translation"))?
+                .map_or(Vector::empty(), |t| Vector::new3(t[0], t[1], t[2]));
+
+            // default morph target weights, which the node can override for its mesh
+            let weights_json = if node["weights"].is_array() {
+                &node["weights"]
             } else {
-                None
+                mesh_index.map_or(&JsonValue::Null, |mesh_index| &json["meshes"][mesh_index]["weights"])
             };
-
-            let mut rotation = Vector::new();
-            if let JsonValue::Array(ref rotation_json) = node["rotation"] {
-                if rotation_json.len() >= 4 {
-                    rotation = Vector::new4(
-                        rotation_json[0].as_f32().unwrap(),
-                        rotation_json[1].as_f32().unwrap(),
-                        rotation_json[2].as_f32().unwrap(),
-                        rotation_json[3].as_f32().unwrap()
-                    ).normalize4();
-                };
-            }
-
-            let mut scale = Vector::fill(1.0);
-            if let JsonValue::Array(ref scale_json) = node["scale"] {
-                if scale_json.len() >= 3 {
-                    scale = Vector::new3(
-                        scale_json[0].as_f32().unwrap(),
-                        scale_json[1].as_f32().unwrap(),
-                        scale_json[2].as_f32().unwrap()
-                    );
-                }
-            }
-
-            let mut translation = Vector::empty();
-            if let JsonValue::Array(ref translation_json) = node["translation"] {
-                if translation_json.len() >= 3 {
-                    translation = Vector::new3(
-                        translation_json[0].as_f32().unwrap(),
-                        translation_json[1].as_f32().unwrap(),
-                        translation_json[2].as_f32().unwrap()
-                    );
-                }
-            }
+            let weights = weights_json.members().map(|weight| weight.as_f32().unwrap_or(0.0)).collect();
 
             let mut children_indices = Vec::new();
-            if let JsonValue::Array(ref children_json) = node["children"] {
-                for child_json in children_json {
-                    children_indices.push(child_json.as_usize().unwrap() + initial_node_count);
-                }
+            for (j, child_json) in reader.array(&node["children"], &field("children"))?.iter().enumerate() {
+                children_indices.push(reader.index(child_json, &field(&format!("children[{}]", j)), node_count)? + initial.nodes);
             }
 
-            nodes.push(
+            world.nodes.push(
                 Node {
                     mapped_entity_index: 0,
                     name,
                     mesh,
                     skin,
+                    camera,
+                    light,
                     rotation,
                     scale,
                     translation,
+                    weights,
                     needs_update: true,
                     user_rotation: Vector::new(),
                     user_scale: Vector::fill(1.0),
@@ -1058,125 +1268,120 @@ impl ModelContainer {
                 }
             )
         }
-        world.nodes.extend(nodes);
 
-        let mut skins = Vec::new();
-        for skin in json["skins"].members() {
-            let name_maybe: Option<&str> = skin["name"].as_str();
-            let mut name = String::from("unnamed skin");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
+        for (i, skin) in json["skins"].members().enumerate() {
+            let field = |name: &str| format!("skins[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&skin["name"], &field("name"))?.unwrap_or("unnamed skin"));
 
             let mut joint_indices = Vec::new();
-            if let JsonValue::Array(ref joint_json) = skin["joints"] {
-                for joint in joint_json.iter() {
-                    joint_indices.push(joint.as_usize().unwrap() + initial_node_count);
-                }
+            for (j, joint) in reader.array(&skin["joints"], &field("joints"))?.iter().enumerate() {
+                joint_indices.push(reader.index(joint, &field(&format!("joints[{}]", j)), node_count)? + initial.nodes);
             }
 
-            let inverse_bind_matrices_accessor = skin["inverseBindMatrices"].as_usize().unwrap() + initial_accessors_count;
-
-            let mut skeleton: Option<usize> = if let Some(skeleton_index) = skin["skeleton"].as_usize() {
-                Some(skeleton_index + initial_node_count)
-            } else {
-                None
-            };
+            let inverse_bind_matrices_accessor = reader.index(&skin["inverseBindMatrices"], &field("inverseBindMatrices"), accessor_count)? + initial.accessors;
+            let accessor = &world.accessors[inverse_bind_matrices_accessor];
+            if accessor.r#type != "MAT4" || accessor.count < joint_indices.len() {
+                return Err(reader.error(field("inverseBindMatrices"), GltfErrorKind::Invalid(format!("an accessor of a MAT4 for each of the {} joints", joint_indices.len()))))
+            }
 
-            skins.push(Skin {
+            world.skins.push(Skin {
                 name,
                 inverse_bind_matrices_accessor,
                 inverse_bind_matrices: Vec::new(),
                 joint_indices,
                 joint_matrices: Vec::new(),
-                skeleton,
+                skeleton: reader.opt_index(&skin["skeleton"], &field("skeleton"), node_count)?.map(|index| index + initial.nodes),
             })
         }
-        world.skins.extend(skins);
 
-        let mut animations = Vec::new();
-        for animation in json["animations"].members() {
-            let name_maybe: Option<&str> = animation["name"].as_str();
-            let mut name = String::from("unnamed animation");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
+        for (i, animation) in json["animations"].members().enumerate() {
+            let field = |name: &str| format!("animations[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&animation["name"], &field("name"))?.unwrap_or("unnamed animation"));
 
-            let mut channels = Vec::new();
-            if let JsonValue::Array(ref channels_json) = animation["channels"] {
-                for channel in channels_json {
-                    channels.push((
-                        channel["sampler"].as_usize().unwrap() + initial_accessors_count,
-                        channel["target"]["node"].as_usize().unwrap() + initial_node_count,
-                        String::from(channel["target"]["path"].as_str().unwrap())
-                    ))
+            let samplers_json = reader.array(&animation["samplers"], &field("samplers"))?;
+            let mut samplers = Vec::new();
+            for (j, sampler_json) in samplers_json.iter().enumerate() {
+                let sampler_field = |name: &str| field(&format!("samplers[{}].{}", j, name));
+                let input = reader.index(&sampler_json["input"], &sampler_field("input"), accessor_count)? + initial.accessors;
+                if !matches!(world.accessors[input].component_type, ComponentType::F32) || world.accessors[input].r#type != "SCALAR" {
+                    return Err(reader.error(sampler_field("input"), GltfErrorKind::Invalid(String::from("an accessor of float scalars"))))
                 }
+                let interpolation = match reader.opt_str(&sampler_json["interpolation"], &sampler_field("interpolation"))?.unwrap_or("LINEAR") {
+                    "LINEAR" => Interpolation::Linear,
+                    "STEP" => Interpolation::Step,
+                    "CUBICSPLINE" => Interpolation::CubicSpline,
+                    other => return Err(reader.error(sampler_field("interpolation"), GltfErrorKind::Invalid(format!("LINEAR, STEP or CUBICSPLINE, not {}", other)))),
+                };
+                samplers.push((
+                    input,
+                    interpolation,
+                    reader.index(&sampler_json["output"], &sampler_field("output"), accessor_count)? + initial.accessors,
+                ))
             }
 
-            let mut samplers = Vec::new();
-            if let JsonValue::Array(ref samplers_json) = animation["samplers"] {
-                for sampler_json in samplers_json {
-                    samplers.push((
-                        sampler_json["input"].as_usize().unwrap() + initial_accessors_count,
-                        String::from(sampler_json["interpolation"].as_str().unwrap()),
-                        sampler_json["output"].as_usize().unwrap() + initial_accessors_count
-                    ))
+            let mut channels = Vec::new();
+            for (j, channel) in reader.array(&animation["channels"], &field("channels"))?.iter().enumerate() {
+                let channel_field = |name: &str| field(&format!("channels[{}].{}", j, name));
+                let target_path = reader.str(&channel["target"]["path"], &channel_field("target.path"))?;
+                if !matches!(target_path, "translation" | "rotation" | "scale" | "weights") {
+                    return Err(reader.error(channel_field("target.path"), GltfErrorKind::Invalid(format!("translation, rotation, scale or weights, not {}", target_path))))
                 }
+                channels.push((
+                    reader.index(&channel["sampler"], &channel_field("sampler"), samplers.len())?, // within the animation's samplers
+                    reader.index(&channel["target"]["node"], &channel_field("target.node"), node_count)? + initial.nodes,
+                    String::from(target_path),
+                ))
             }
 
-            animations.push(Animation::new(
+            let new_animation = Animation::new(
                 name,
                 world,
                 channels,
                 samplers,
-            ))
+            );
+            world.animations.push(new_animation)
         }
-        world.animations.extend(animations);
 
-        let mut scenes = Vec::new();
-        for scene in json["scenes"].members() {
-            let name_maybe: Option<&str> = scene["name"].as_str();
-            let mut name = String::from("unnamed world");
-            match name_maybe {
-                Some(name_str) => name = String::from(name_str),
-                None => (),
-            }
+        if scene_count == 0 {
+            return Err(reader.error("scenes", GltfErrorKind::Missing))
+        }
+        for (i, scene) in json["scenes"].members().enumerate() {
+            let field = |name: &str| format!("scenes[{}].{}", i, name);
+            let name = String::from(reader.opt_str(&scene["name"], &field("name"))?.unwrap_or("unnamed world"));
 
             let mut scene_nodes = Vec::new();
-            if let JsonValue::Array(ref nodes_json) = scene["nodes"] {
-                for node_json in nodes_json {
-                    scene_nodes.push(node_json.as_usize().unwrap() + initial_node_count);
-                }
+            for (j, node_json) in reader.array(&scene["nodes"], &field("nodes"))?.iter().enumerate() {
+                scene_nodes.push(reader.index(node_json, &field(&format!("nodes[{}]", j)), node_count)? + initial.nodes);
             }
 
-            scenes.push(
+            world.scenes.push(
                 GltfScene {
                     name,
                     nodes: scene_nodes,
                 }
             )
         }
-        world.scenes.extend(scenes);
 
-        let scene = json["world"].as_usize().unwrap_or(0) + initial_scene_count;
+        let scene = reader.opt_index(&json["scene"], "scene", scene_count)?.unwrap_or(0) + initial.scenes;
 
-        Self {
+        world.loaded_files.extend(image_files);
+
+        Ok(Self {
+            path: String::from(path),
             extensions_used,
             scene,
-            scenes: (initial_scene_count..world.scenes.len()).collect(),
-            animations: (initial_animation_count..world.animations.len()).collect(),
-            skins: (initial_skins_count..world.skins.len()).collect(),
-            nodes: (initial_node_count..world.nodes.len()).collect(),
-            meshes: (initial_mesh_count..world.meshes.len()).collect(),
-            materials: (initial_materials_count..world.materials.len()).collect(),
-            textures: (initial_textures_count..world.textures.len()).collect(),
-            images: (initial_images_count..world.images.len()).collect(),
-            accessors: (initial_accessors_count..world.accessors.len()).collect(),
-            buffer_views: (initial_buffer_view_count..world.buffer_views.len()).collect(),
-            buffers: (initial_buffer_count..world.buffers.len()).collect(),
-        }
+            scenes: (initial.scenes..world.scenes.len()).collect(),
+            animations: (initial.animations..world.animations.len()).collect(),
+            skins: (initial.skins..world.skins.len()).collect(),
+            nodes: (initial.nodes..world.nodes.len()).collect(),
+            meshes: (initial.meshes..world.meshes.len()).collect(),
+            materials: (initial.materials..world.materials.len()).collect(),
+            textures: (initial.textures..world.textures.len()).collect(),
+            images: (initial.images..world.images.len()).collect(),
+            accessors: (initial.accessors..world.accessors.len()).collect(),
+            buffer_views: (initial.buffer_views..world.buffer_views.len()).collect(),
+            buffers: (initial.buffers..world.buffers.len()).collect(),
+        })
     }
 
     ///* Takes euler for rotation, converts to quaternion
@@ -1198,9 +1403,9 @@ pub struct Buffer {
     pub data: Vec<u8>,
 }
 impl Buffer {
-    fn new(uri: PathBuf, byte_length: usize) -> Self {
+    fn new(uri: PathBuf, byte_length: usize, data: Vec<u8>) -> Self {
         Buffer {
-            data: fs::read(&uri).expect("failed to load buffer").to_vec(),
+            data,
             uri,
             byte_length,
         }
@@ -1211,23 +1416,126 @@ pub struct BufferView {
     pub buffer: usize,
     pub byte_length: usize,
     pub byte_offset: usize,
+    pub byte_stride: Option<usize>, // None for tightly packed elements
     pub target: usize,
 }
+impl BufferView {
+    pub fn bytes<'a>(&self, buffers: &'a [Buffer]) -> &'a [u8] {
+        buffers[self.buffer].data.get(self.byte_offset..self.byte_offset + self.byte_length).unwrap_or(&[])
+    }
+}
 
 pub struct Accessor {
-    pub buffer_view: usize,
+    pub buffer_view: Option<usize>, // None for all zeros
+    pub byte_offset: usize,
     pub component_type: ComponentType,
+    pub normalized: bool,
     pub count: usize,
     pub r#type: String,
     pub min: Option<Vector>,
     pub max: Option<Vector>,
+    pub sparse: Option<SparseAccessor>,
     pub data: Vec<Vec<f32>>,
 }
+impl Accessor {
+    /// Components of each element, eg. 3 for VEC3 and 16 for MAT4
+    pub fn components(&self) -> usize {
+        match self.r#type.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            _ => 0,
+        }
+    }
+    fn matrix_rows(&self) -> Option<usize> {
+        match self.r#type.as_str() {
+            "MAT2" => Some(2),
+            "MAT3" => Some(3),
+            "MAT4" => Some(4),
+            _ => None,
+        }
+    }
+    /// Bytes of each element, matrix columns are padded to start on 4 byte boundaries
+    pub fn element_size(&self) -> usize {
+        let size = self.component_type.size();
+        match self.matrix_rows() {
+            Some(rows) => rows * ((rows * size + 3) & !3),
+            None => self.components() * size,
+        }
+    }
+    fn component_offset(&self, component: usize) -> usize {
+        let size = self.component_type.size();
+        match self.matrix_rows() {
+            Some(rows) => (component / rows) * ((rows * size + 3) & !3) + (component % rows) * size,
+            None => component * size,
+        }
+    }
+
+    ///* The elements as floats, flattened. Normalized integers are mapped to 0..1 or -1..1, others keep their value as
+    ///* KHR_mesh_quantization positions and texture coordinates expect.
+    pub fn read_floats(&self, buffer_views: &[BufferView], buffers: &[Buffer]) -> Vec<f32> {
+        self.read(buffer_views, buffers, |component_type, bytes| component_type.read_f32(bytes, self.normalized))
+    }
+    /// The elements as unsigned integers, flattened, for indices and joints
+    pub fn read_u32s(&self, buffer_views: &[BufferView], buffers: &[Buffer]) -> Vec<u32> {
+        self.read(buffer_views, buffers, |component_type, bytes| component_type.read_u32(bytes))
+    }
+
+    fn read<T: Copy + Default>(&self, buffer_views: &[BufferView], buffers: &[Buffer], convert: impl Fn(ComponentType, &[u8]) -> T) -> Vec<T> {
+        let components = self.components();
+        let size = self.component_type.size();
+        let read_element = |bytes: &[u8], start: usize, element: &mut [T]| {
+            for (component, value) in element.iter_mut().enumerate() {
+                let offset = start + self.component_offset(component);
+                if let Some(component_bytes) = bytes.get(offset..offset + size) {
+                    *value = convert(self.component_type, component_bytes);
+                }
+            }
+        };
+
+        let mut values = vec![T::default(); self.count * components];
+        if let Some(buffer_view) = self.buffer_view.map(|index| &buffer_views[index]) {
+            let stride = buffer_view.byte_stride.unwrap_or(self.element_size());
+            let bytes = buffer_view.bytes(buffers);
+            for (i, element) in values.chunks_mut(components).enumerate() {
+                read_element(bytes, self.byte_offset + i * stride, element);
+            }
+        }
+        if let Some(sparse) = &self.sparse {
+            let index_size = sparse.indices_component_type.size();
+            let indices = buffer_views[sparse.indices_buffer_view].bytes(buffers);
+            let substitutes = buffer_views[sparse.values_buffer_view].bytes(buffers);
+            for i in 0..sparse.count {
+                let index_start = sparse.indices_byte_offset + i * index_size;
+                let Some(index_bytes) = indices.get(index_start..index_start + index_size) else { break };
+                let index = sparse.indices_component_type.read_u32(index_bytes) as usize;
+                if index < self.count {
+                    read_element(substitutes, sparse.values_byte_offset + i * self.element_size(), &mut values[index * components..(index + 1) * components]);
+                }
+            }
+        }
+        values
+    }
+}
+
+/// Elements that replace those of the accessor's buffer view, values are tightly packed and indices increase
+pub struct SparseAccessor {
+    pub count: usize,
+    pub indices_buffer_view: usize,
+    pub indices_byte_offset: usize,
+    pub indices_component_type: ComponentType,
+    pub values_buffer_view: usize,
+    pub values_byte_offset: usize,
+}
 
 pub struct Image {
     pub mime_type: String,
     pub name: String,
     pub uri: PathBuf,
+    pub data: Option<Arc<Vec<u8>>>, // encoded bytes of images embedded in the model, loaded instead of the uri
 
     pub generated: bool,
     pub image: (vk::Image, DeviceMemory),
@@ -1240,6 +1548,7 @@ impl Image {
             mime_type,
             name,
             uri,
+            data: None,
             generated: false,
             image: (vk::Image::null(), DeviceMemory::null()),
             image_view: ImageView::null(),
@@ -1247,8 +1556,15 @@ impl Image {
         }
     }
 
+    fn source(&self) -> ImageSource {
+        match &self.data {
+            Some(bytes) => ImageSource::Memory { bytes: bytes.clone(), mime_type: self.mime_type.clone() },
+            None => ImageSource::File(self.uri.clone()),
+        }
+    }
+
     unsafe fn construct_image_view(&mut self, context: &Arc<Context>) { unsafe {
-        let (image_view, image, mips) = context.create_2d_texture_image(&self.uri, true);
+        let (image_view, image, mips) = context.load_image_sources_batched(&[self.source()], true).pop().expect("Failed to load texture");
         self.image = image;
         self.image_view = image_view.0;
         self.mip_levels = mips;
@@ -1350,6 +1666,48 @@ pub struct Material {
         pub emissive_texture_scale: Option<[f32; 2]>,
     // KHR_materials_emissive_strength
         pub emissive_strength: f32,
+    // KHR_materials_clearcoat
+        pub clearcoat_factor: f32,
+        pub clearcoat_texture: Option<i32>,
+        // KHR_texture_transform
+            pub clearcoat_texture_offset: Option<[f32; 2]>,
+            pub clearcoat_texture_scale: Option<[f32; 2]>,
+        pub clearcoat_roughness_factor: f32,
+        pub clearcoat_roughness_texture: Option<i32>,
+        // KHR_texture_transform
+            pub clearcoat_roughness_texture_offset: Option<[f32; 2]>,
+            pub clearcoat_roughness_texture_scale: Option<[f32; 2]>,
+        pub clearcoat_normal_texture: Option<i32>,
+        // KHR_texture_transform
+            pub clearcoat_normal_texture_offset: Option<[f32; 2]>,
+            pub clearcoat_normal_texture_scale: Option<[f32; 2]>,
+    // KHR_materials_transmission
+        pub transmission_factor: f32,
+        pub transmission_texture: Option<i32>,
+        // KHR_texture_transform
+            pub transmission_texture_offset: Option<[f32; 2]>,
+            pub transmission_texture_scale: Option<[f32; 2]>,
+    // KHR_materials_volume
+        pub thickness_factor: f32,
+        pub thickness_texture: Option<i32>,
+        // KHR_texture_transform
+            pub thickness_texture_offset: Option<[f32; 2]>,
+            pub thickness_texture_scale: Option<[f32; 2]>,
+        pub attenuation_distance: f32, // infinite when the medium absorbs nothing
+        pub attenuation_color: [f32; 3],
+    // KHR_materials_sheen
+        pub sheen_color_factor: [f32; 3],
+        pub sheen_color_texture: Option<i32>,
+        // KHR_texture_transform
+            pub sheen_color_texture_offset: Option<[f32; 2]>,
+            pub sheen_color_texture_scale: Option<[f32; 2]>,
+        pub sheen_roughness_factor: f32,
+        pub sheen_roughness_texture: Option<i32>,
+        // KHR_texture_transform
+            pub sheen_roughness_texture_offset: Option<[f32; 2]>,
+            pub sheen_roughness_texture_scale: Option<[f32; 2]>,
+    // KHR_materials_unlit
+        pub unlit: bool,
 }
 impl Material {
     fn to_sendable(&self, texture_offset: i32) -> MaterialSendable {
@@ -1392,6 +1750,48 @@ impl Material {
 
             roughness_texture_offset: self.roughness_texture_offset.unwrap_or([0.0; 2]),
             roughness_texture_scale: self.roughness_texture_scale.unwrap_or([1.0; 2]),
+
+            clearcoat_factor: self.clearcoat_factor,
+            clearcoat_texture: self.clearcoat_texture.map(|val| val + texture_offset).unwrap_or(-1),
+            clearcoat_roughness_factor: self.clearcoat_roughness_factor,
+            clearcoat_roughness_texture: self.clearcoat_roughness_texture.map(|val| val + texture_offset).unwrap_or(-1),
+
+            clearcoat_normal_texture: self.clearcoat_normal_texture.map(|val| val + texture_offset).unwrap_or(-1),
+            transmission_factor: self.transmission_factor,
+            transmission_texture: self.transmission_texture.map(|val| val + texture_offset).unwrap_or(-1),
+            unlit: self.unlit as u32,
+
+            sheen_color_factor: self.sheen_color_factor,
+            sheen_color_texture: self.sheen_color_texture.map(|val| val + texture_offset).unwrap_or(-1),
+
+            sheen_roughness_factor: self.sheen_roughness_factor,
+            sheen_roughness_texture: self.sheen_roughness_texture.map(|val| val + texture_offset).unwrap_or(-1),
+            thickness_factor: self.thickness_factor,
+            thickness_texture: self.thickness_texture.map(|val| val + texture_offset).unwrap_or(-1),
+
+            attenuation_color: self.attenuation_color,
+            attenuation_distance: self.attenuation_distance,
+
+            clearcoat_texture_offset: self.clearcoat_texture_offset.unwrap_or([0.0; 2]),
+            clearcoat_texture_scale: self.clearcoat_texture_scale.unwrap_or([1.0; 2]),
+
+            clearcoat_roughness_texture_offset: self.clearcoat_roughness_texture_offset.unwrap_or([0.0; 2]),
+            clearcoat_roughness_texture_scale: self.clearcoat_roughness_texture_scale.unwrap_or([1.0; 2]),
+
+            clearcoat_normal_texture_offset: self.clearcoat_normal_texture_offset.unwrap_or([0.0; 2]),
+            clearcoat_normal_texture_scale: self.clearcoat_normal_texture_scale.unwrap_or([1.0; 2]),
+
+            transmission_texture_offset: self.transmission_texture_offset.unwrap_or([0.0; 2]),
+            transmission_texture_scale: self.transmission_texture_scale.unwrap_or([1.0; 2]),
+
+            thickness_texture_offset: self.thickness_texture_offset.unwrap_or([0.0; 2]),
+            thickness_texture_scale: self.thickness_texture_scale.unwrap_or([1.0; 2]),
+
+            sheen_color_texture_offset: self.sheen_color_texture_offset.unwrap_or([0.0; 2]),
+            sheen_color_texture_scale: self.sheen_color_texture_scale.unwrap_or([1.0; 2]),
+
+            sheen_roughness_texture_offset: self.sheen_roughness_texture_offset.unwrap_or([0.0; 2]),
+            sheen_roughness_texture_scale: self.sheen_roughness_texture_scale.unwrap_or([1.0; 2]),
         }
     }
 }
@@ -1436,6 +1836,48 @@ pub struct MaterialSendable { // newlines at each set of 16 bytes
 
     pub roughness_texture_offset: [f32; 2],
     pub roughness_texture_scale: [f32; 2],
+
+    pub clearcoat_factor: f32,
+    pub clearcoat_texture: i32,
+    pub clearcoat_roughness_factor: f32,
+    pub clearcoat_roughness_texture: i32,
+
+    pub clearcoat_normal_texture: i32,
+    pub transmission_factor: f32,
+    pub transmission_texture: i32,
+    pub unlit: u32,
+
+    pub sheen_color_factor: [f32; 3],
+    pub sheen_color_texture: i32,
+
+    pub sheen_roughness_factor: f32,
+    pub sheen_roughness_texture: i32,
+    pub thickness_factor: f32,
+    pub thickness_texture: i32,
+
+    pub attenuation_color: [f32; 3],
+    pub attenuation_distance: f32,
+
+    pub clearcoat_texture_offset: [f32; 2],
+    pub clearcoat_texture_scale: [f32; 2],
+
+    pub clearcoat_roughness_texture_offset: [f32; 2],
+    pub clearcoat_roughness_texture_scale: [f32; 2],
+
+    pub clearcoat_normal_texture_offset: [f32; 2],
+    pub clearcoat_normal_texture_scale: [f32; 2],
+
+    pub transmission_texture_offset: [f32; 2],
+    pub transmission_texture_scale: [f32; 2],
+
+    pub thickness_texture_offset: [f32; 2],
+    pub thickness_texture_scale: [f32; 2],
+
+    pub sheen_color_texture_offset: [f32; 2],
+    pub sheen_color_texture_scale: [f32; 2],
+
+    pub sheen_roughness_texture_offset: [f32; 2],
+    pub sheen_roughness_texture_scale: [f32; 2],
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1448,6 +1890,38 @@ pub enum ComponentType {
     F32,
 }
 impl ComponentType {
+    pub fn size(&self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::U32 | Self::F32 => 4,
+        }
+    }
+    /// Normalized signed values are clamped, as the most negative one would fall below -1
+    fn read_f32(&self, bytes: &[u8], normalized: bool) -> f32 {
+        match (self, normalized) {
+            (Self::I8, false) => bytes[0] as i8 as f32,
+            (Self::I8, true) => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+            (Self::U8, false) => bytes[0] as f32,
+            (Self::U8, true) => bytes[0] as f32 / 255.0,
+            (Self::I16, false) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            (Self::I16, true) => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32767.0).max(-1.0),
+            (Self::U16, false) => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            (Self::U16, true) => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+            (Self::U32, _) => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+            (Self::F32, _) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        match self {
+            Self::I8 => bytes[0] as i8 as u32,
+            Self::U8 => bytes[0] as u32,
+            Self::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            Self::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            Self::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            Self::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u32,
+        }
+    }
     fn from_u32(value: u32) -> Option<Self> {
         match value {
             5120 => Some(Self::I8),
@@ -1462,6 +1936,7 @@ impl ComponentType {
 }
 pub struct Primitive {
     pub attributes: Vec<(String, usize)>, // ... + accessor
+    pub targets: Vec<Vec<(String, usize)>>, // attributes of each morph target
     pub indices: usize, // accesor
 
     pub material_index: u32,
@@ -1479,6 +1954,8 @@ pub struct Primitive {
     pub index_data_u16: Vec<u16>,
     pub index_data_u32: Vec<u32>,
     pub vertex_data: Vec<Vertex>,
+    pub morph_targets: Vec<MorphTarget>,
+    pub morph_delta_offset: usize, // first of the primitive's deltas in the world's morph delta buffer
 }
 impl Primitive {
     fn construct_data(
@@ -1509,108 +1986,37 @@ impl Primitive {
             println!("Primitive has no POSITION attribute!");
         } else {
             let indices_accessor = &world_accessors[self.indices];
-            let indices_accessor_buffer_view = &world_buffer_views[indices_accessor.buffer_view];
-            let indices_accessor_buffer_view_buffer = &world_buffers[indices_accessor_buffer_view.buffer];
-            let mut byte_offset = indices_accessor_buffer_view.byte_offset;
-            let mut byte_length = indices_accessor_buffer_view.byte_length;
-            let bytes = &indices_accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
             let component_type = indices_accessor.component_type;
+            let indices = indices_accessor.read_u32s(world_buffer_views, world_buffers);
             match component_type {
-                ComponentType::U8 => {
-                    let indices: &[u8] = bytemuck::cast_slice(bytes);
-                    self.index_data_u8 = indices.to_vec();
-                },
-                ComponentType::U16 => {
-                    let indices: &[u16] = bytemuck::cast_slice(bytes);
-                    self.index_data_u16 = indices.to_vec();
-                },
-                ComponentType::U32 => {
-                    let indices: &[u32] = bytemuck::cast_slice(bytes);
-                    self.index_data_u32 = indices.to_vec();
-                },
-                _ => panic!("Unsupported index type"),
-            }
-
-            let mut positions: &[[f32; 3]] = if let Some(accessor) = position_accessor {
-                let accessor_buffer_view = &world_buffer_views[accessor.buffer_view];
-                let accessor_buffer_view_buffer = &world_buffers[accessor_buffer_view.buffer];
-                byte_offset = accessor_buffer_view.byte_offset;
-                byte_length = accessor_buffer_view.byte_length;
-                let bytes = &accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
-                bytemuck::cast_slice(bytes)
-            } else {
-                &[]
-            };
-
-            let mut normals: &[[f32; 3]] = if let Some(accessor) = normal_accessor {
-                let accessor_buffer_view = &world_buffer_views[accessor.buffer_view];
-                let accessor_buffer_view_buffer = &world_buffers[accessor_buffer_view.buffer];
-                byte_offset = accessor_buffer_view.byte_offset;
-                byte_length = accessor_buffer_view.byte_length;
-                let bytes = &accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
-                bytemuck::cast_slice(bytes)
-            } else {
-                &[]
-            };
-            
-            let mut tex_coords: &[[f32; 2]] = if let Some(accessor) = texcoord_accessor {
-                let accessor_buffer_view = &world_buffer_views[accessor.buffer_view];
-                let accessor_buffer_view_buffer = &world_buffers[accessor_buffer_view.buffer];
-                byte_offset = accessor_buffer_view.byte_offset;
-                byte_length = accessor_buffer_view.byte_length;
-                let bytes = &accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
-                bytemuck::cast_slice(bytes)
-            } else {
-                &[]
-            };
-
-            let mut joints= Vec::new();
-            if let Some(accessor) = joint_accessor {
-                let component_type = accessor.component_type;
-                let accessor_buffer_view = &world_buffer_views[accessor.buffer_view];
-                byte_offset = accessor_buffer_view.byte_offset;
-                byte_length = accessor_buffer_view.byte_length;
-                let accessor_buffer_view_buffer = &world_buffers[accessor_buffer_view.buffer];
-                let bytes = &accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
-                match component_type {
-                    ComponentType::U8 => {
-                        let raw: &[[u8; 4]] = bytemuck::cast_slice(bytes);
-                        let converted: Vec<[u32; 4]> = raw.iter().map(|x| [x[0] as u32, x[1] as u32, x[2] as u32, x[3] as u32]).collect();
-                        joints = converted;
-                    },
-                    ComponentType::U16 => {
-                        let raw: &[[u16; 4]] = bytemuck::cast_slice(bytes);
-                        let converted: Vec<[u32; 4]> = raw.iter().map(|x| [x[0] as u32, x[1] as u32, x[2] as u32, x[3] as u32]).collect();
-                        joints = converted;
-                    },
-                    ComponentType::U32 => {
-                        let raw: &[[u32; 4]] = bytemuck::cast_slice(bytes);
-                        joints = raw.to_vec();
-                    },
-                    _ => panic!("Unsupported joint index component type"),
-                }
-            }
-
-            let mut weights: &[[f32; 4]] = &[];
-            if let Some(accessor) = weight_accessor {
-                let accessor_buffer_view = &world_buffer_views[accessor.buffer_view];
-                byte_offset = accessor_buffer_view.byte_offset;
-                byte_length = accessor_buffer_view.byte_length;
-                let accessor_buffer_view_buffer = &world_buffers[accessor_buffer_view.buffer];
-                let bytes = &accessor_buffer_view_buffer.data[byte_offset..(byte_offset + byte_length)];
-                weights = bytemuck::cast_slice(bytes);
+                ComponentType::U8 => self.index_data_u8 = indices.iter().map(|&i| i as u8).collect(),
+                ComponentType::U16 => self.index_data_u16 = indices.iter().map(|&i| i as u16).collect(),
+                _ => self.index_data_u32 = indices,
             }
 
+            // quantized and normalized attributes are decoded to floats, sparse accessors have their substitutions applied.
+            // Every primitive shares the float Vertex layout of the geometry pipelines, so quantized models load correctly but do not
+            // keep their smaller footprint in memory, which would take packed vertex formats and pipelines for them.
+            let read_floats = |accessor: Option<&Accessor>| accessor.map_or(Vec::new(), |accessor| accessor.read_floats(world_buffer_views, world_buffers));
+            let positions = read_floats(position_accessor);
+            let normals = read_floats(normal_accessor);
+            let tex_coords = read_floats(texcoord_accessor);
+            let weights = read_floats(weight_accessor);
+            let joints = joint_accessor.map_or(Vec::new(), |accessor| accessor.read_u32s(world_buffer_views, world_buffers));
+
+            let vec2 = |values: &[f32], i: usize| values.get(i * 2..i * 2 + 2).map_or([0.0; 2], |v| [v[0], v[1]]);
+            let vec3 = |values: &[f32], i: usize| values.get(i * 3..i * 3 + 3).map_or([0.0; 3], |v| [v[0], v[1], v[2]]);
+            let vec4 = |values: &[f32], i: usize| values.get(i * 4..i * 4 + 4).map_or([0.0; 4], |v| [v[0], v[1], v[2], v[3]]);
             let mut vertices = Vec::new();
-            for i in 0..positions.len() {
+            for i in 0..positions.len() / 3 {
                 vertices.push(RefCell::new(Vertex {
-                    position: positions[i],
-                    normal: *normals.get(i).unwrap_or(&[0.0; 3]),
-                    uv: *tex_coords.get(i).unwrap_or(&[0.0; 2]),
+                    position: vec3(&positions, i),
+                    normal: vec3(&normals, i),
+                    uv: vec2(&tex_coords, i),
                     tangent: [0.0; 3],
                     bitangent: [0.0; 3],
-                    joint_indices: *joints.get(i).unwrap_or(&[0; 4]),
-                    joint_weights: *weights.get(i).unwrap_or(&[0.0; 4]),
+                    joint_indices: joints.get(i * 4..i * 4 + 4).map_or([0; 4], |j| [j[0], j[1], j[2], j[3]]),
+                    joint_weights: vec4(&weights, i),
                 }));
             }
             match component_type {
@@ -1629,9 +2035,57 @@ impl Primitive {
                 .into_iter()
                 .map(|v| v.into_inner())
                 .collect();
+
+            let vertex_count = self.vertex_data.len();
+            self.morph_targets = self.targets.iter().map(|target| {
+                let mut morph_target = MorphTarget::default();
+                for (name, accessor_index) in target.iter() {
+                    let deltas = Primitive::vec3_data(&world_accessors[*accessor_index], world_buffer_views, world_buffers);
+                    let mut deltas = deltas[..deltas.len().min(vertex_count)].to_vec();
+                    deltas.resize(vertex_count, [0.0; 3]);
+                    match name.as_str() {
+                        "POSITION" => morph_target.positions = deltas,
+                        "NORMAL" => morph_target.normals = deltas,
+                        "TANGENT" => morph_target.tangents = deltas,
+                        _ => (),
+                    }
+                }
+                morph_target
+            }).collect();
         }
     }
 
+    /// VEC3 data of an accessor as floats, empty for other types
+    fn vec3_data(accessor: &Accessor, world_buffer_views: &[BufferView], world_buffers: &[Buffer]) -> Vec<[f32; 3]> {
+        if accessor.r#type != "VEC3" {
+            return Vec::new()
+        }
+        accessor.read_floats(world_buffer_views, world_buffers)
+            .chunks_exact(3)
+            .map(|v| [v[0], v[1], v[2]])
+            .collect()
+    }
+
+    ///* The morph target deltas in the layout the vertex shaders read them in: grouped by vertex, with one delta per target
+    ///* in each group. Missing normal and tangent deltas are zero.
+    pub fn interleaved_morph_deltas(&self) -> Vec<MorphDelta> {
+        let mut deltas = Vec::with_capacity(self.vertex_data.len() * self.morph_targets.len());
+        for vertex in 0..self.vertex_data.len() {
+            for target in self.morph_targets.iter() {
+                let delta = |values: &Vec<[f32; 3]>| {
+                    let value = values.get(vertex).unwrap_or(&[0.0; 3]);
+                    [value[0], value[1], value[2], 0.0]
+                };
+                deltas.push(MorphDelta {
+                    position: delta(&target.positions),
+                    normal: delta(&target.normals),
+                    tangent: delta(&target.tangents),
+                });
+            }
+        }
+        deltas
+    }
+
     fn construct_tangents<T: AsUsize>(vertices: &mut Vec<RefCell<Vertex>>, index_data: &Vec<T>) {
         for vertex in vertices.iter() {
             let mut v = vertex.borrow_mut();
@@ -1639,10 +2093,14 @@ impl Primitive {
             v.bitangent = [0.0, 0.0, 0.0];
         }
 
-        for i in (0..index_data.len()).step_by(3) {
-            let i0 = index_data[i].as_usize();
-            let i1 = index_data[i + 1].as_usize();
-            let i2 = index_data[i + 2].as_usize();
+        for triangle in index_data.chunks_exact(3) {
+            let i0 = triangle[0].as_usize();
+            let i1 = triangle[1].as_usize();
+            let i2 = triangle[2].as_usize();
+            // malformed indices past the last vertex get no tangents
+            if i0.max(i1).max(i2) >= vertices.len() {
+                continue;
+            }
 
             let (e1, e2, delta_uv1, delta_uv2) = {
                 let v1 = vertices[i0].borrow();
@@ -1781,6 +2239,22 @@ pub struct Vertex {
     pub joint_weights: [f32; 4],
 }
 
+/// Per vertex offsets of one blend shape, empty for the attributes the target does not move
+#[derive(Clone, Debug, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 3]>,
+}
+
+#[derive(Clone, Debug, Copy)]
+#[repr(C)]
+pub struct MorphDelta {
+    pub position: [f32; 4],
+    pub normal: [f32; 4],
+    pub tangent: [f32; 4],
+}
+
 pub struct Mesh {
     pub name: String,
     pub primitives: Vec<Primitive>
@@ -1806,10 +2280,13 @@ pub struct Node {
 
     pub mesh: Option<usize>,
     pub skin: Option<i32>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>, // KHR_lights_punctual
     pub name: String,
     pub rotation: Vector,
     pub scale: Vector,
     pub translation: Vector,
+    pub weights: Vec<f32>, // morph target weights
 
     pub needs_update: bool,
 
@@ -1842,17 +2319,10 @@ impl Skin {
         world_buffer_views: &Vec<BufferView>,
         world_buffers: &Vec<Buffer>,
     ) {
-        let inverse_bind_matrices_accessor = &self.inverse_bind_matrices_accessor;
-        let buffer_view = &world_buffer_views[world_accessors[*inverse_bind_matrices_accessor].buffer_view];
-        let byte_offset = buffer_view.byte_offset;
-        let byte_length = buffer_view.byte_length;
-
-        let buffer = &world_buffers[buffer_view.buffer];
-        let bytes = &buffer.data[byte_offset..(byte_offset + byte_length)];
-        let inverse_bind_matrices: &[[f32; 16]] = bytemuck::cast_slice(bytes);
+        let inverse_bind_matrices = world_accessors[self.inverse_bind_matrices_accessor].read_floats(world_buffer_views, world_buffers);
         self.inverse_bind_matrices.clear();
-        for matrix_data in inverse_bind_matrices.iter() {
-            self.inverse_bind_matrices.push(Matrix::new_manual(matrix_data.clone()));
+        for matrix_data in inverse_bind_matrices.chunks_exact(16) {
+            self.inverse_bind_matrices.push(Matrix::new_manual(matrix_data.try_into().unwrap()));
         }
 
         self.joint_matrices.clear();
@@ -1879,8 +2349,8 @@ impl Skin {
 
 pub struct Animation {
     pub name: String,
-    pub channels: Vec<(usize, usize, String)>, // sampler index, impacted node, target transform component
-    pub samplers: Vec<(Vec<f32>, String, Vec<Vector>)>, // input times, interpolation method, output vectors
+    pub channels: Vec<(usize, usize, String)>, // sampler index, impacted node, target path (translation, rotation, scale or weights)
+    pub samplers: Vec<AnimationSampler>,
     pub start_time: SystemTime,
     pub duration: f32,
     pub running: bool,
@@ -1888,48 +2358,13 @@ pub struct Animation {
     pub snap_back: bool,
 }
 impl Animation {
-    fn new(name: String, world: &World, channels: Vec<(usize, usize, String)>, samplers: Vec<(usize, String, usize)>) -> Self { // samplers are accessors
+    fn new(name: String, world: &World, channels: Vec<(usize, usize, String)>, samplers: Vec<(usize, Interpolation, usize)>) -> Self { // samplers are accessors
         let mut compiled_samplers = Vec::new();
         for sampler in samplers.iter() {
-            let sampler_accessors = (
-                &world.accessors[sampler.0],
-                &world.accessors[sampler.2]
-            );
-            let sampler_buffer_views = (
-                &world.buffer_views[sampler_accessors.0.buffer_view],
-                &world.buffer_views[sampler_accessors.1.buffer_view]
-            );
-            let sampler_buffers = (
-                &world.buffers[sampler_buffer_views.0.buffer],
-                &world.buffers[sampler_buffer_views.1.buffer]
-            );
-            let mut byte_offset = sampler_buffer_views.0.byte_offset;
-            let mut byte_length = sampler_buffer_views.0.byte_length;
-            let mut bytes = &sampler_buffers.0.data[byte_offset..(byte_offset + byte_length)];
-            let times: &[f32] = bytemuck::cast_slice(bytes);
-
-            byte_offset = sampler_buffer_views.1.byte_offset;
-            byte_length = sampler_buffer_views.1.byte_length;
-            bytes = &sampler_buffers.1.data[byte_offset..(byte_offset + byte_length)];
-            let mut vectors = Vec::new();
-            if sampler_accessors.1.r#type.eq("VEC3") {
-                let vec3s: &[[f32; 3]] = bytemuck::cast_slice(bytes);
-                for vec3 in vec3s.iter() {
-                    vectors.push(Vector::new3(vec3[0], vec3[1], vec3[2]));
-                }
-                compiled_samplers.push((times.to_vec(), sampler.1.clone(), vectors));
-            } else if sampler_accessors.1.r#type.eq("VEC4") {
-                let vec4s: &[[f32; 4]] = bytemuck::cast_slice(bytes);
-                for vec4 in vec4s.iter() {
-                    vectors.push(Vector::new4(vec4[0], vec4[1], vec4[2], vec4[3]));
-                }
-                compiled_samplers.push((times.to_vec(), sampler.1.clone(), vectors));
-            } else {
-                panic!("Illogical animation sampler output type! Should be VEC3 or VEC4");
-            }
+            compiled_samplers.push(AnimationSampler::new(world.accessor_floats(sampler.0), sampler.1, world.accessor_floats(sampler.2)));
         }
         let duration = compiled_samplers.iter()
-            .map(|s| *s.0.last().unwrap_or(&0.0))
+            .map(|s| *s.times.last().unwrap_or(&0.0))
             .fold(0.0_f32, f32::max);
         Self {
             name,
@@ -1944,11 +2379,265 @@ impl Animation {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,
+}
+
+///* Keyframes of an animation sampler. Values are flattened with `width` floats each, 3 or 4 for transforms and the number of
+///* morph targets for weights. Cubic spline keyframes store an in-tangent, the value and an out-tangent, in that order.
+#[derive(Clone)]
+pub struct AnimationSampler {
+    pub times: Vec<f32>,
+    pub interpolation: Interpolation,
+    pub values: Vec<f32>,
+    pub width: usize,
+}
+impl AnimationSampler {
+    pub fn new(times: Vec<f32>, interpolation: Interpolation, values: Vec<f32>) -> Self {
+        let elements_per_keyframe = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let width = if times.is_empty() { 0 } else { values.len() / (times.len() * elements_per_keyframe) };
+        // samplers with too few values only keep the keyframes they have values for
+        let keyframes = if width == 0 { 0 } else { times.len().min(values.len() / (width * elements_per_keyframe)) };
+        Self {
+            times: times[..keyframes].to_vec(),
+            interpolation,
+            values,
+            width,
+        }
+    }
+
+    fn element(&self, keyframe: usize, element: usize) -> &[f32] {
+        let start = if self.interpolation == Interpolation::CubicSpline {
+            (keyframe * 3 + element) * self.width
+        } else {
+            keyframe * self.width
+        };
+        &self.values[start..start + self.width]
+    }
+    pub fn value(&self, keyframe: usize) -> &[f32] {
+        self.element(keyframe, 1)
+    }
+
+    ///* The value at a time, held at the first and last keyframes outside of their range. With rotation set the values are
+    ///* quaternions, which are slerped when linear and normalized when cubic. None if the sampler has no keyframes.
+    pub fn sample(&self, time: f32, rotation: bool) -> Option<Vec<f32>> {
+        let keyframes = self.times.len();
+        if keyframes == 0 {
+            return None
+        }
+        if keyframes == 1 || time <= self.times[0] {
+            return Some(self.value(0).to_vec())
+        }
+        // the keyframe at or before the time
+        let k = self.times.partition_point(|&keyframe_time| keyframe_time <= time) - 1;
+        if k >= keyframes - 1 {
+            return Some(self.value(keyframes - 1).to_vec())
+        }
+
+        let delta = self.times[k + 1] - self.times[k];
+        let t = if delta > 0.0 { ((time - self.times[k]) / delta).clamp(0.0, 1.0) } else { 0.0 };
+        let (v0, v1) = (self.value(k), self.value(k + 1));
+        Some(match self.interpolation {
+            Interpolation::Step => v0.to_vec(),
+            Interpolation::Linear if rotation && self.width == 4 => {
+                Vector::spherical_lerp(&Vector::from_array(v0), &Vector::from_array(v1), t).to_array4().to_vec()
+            }
+            Interpolation::Linear => v0.iter().zip(v1).map(|(a, b)| a + (b - a) * t).collect(),
+            Interpolation::CubicSpline => {
+                let (out_tangent, in_tangent) = (self.element(k, 2), self.element(k + 1, 0));
+                let (t2, t3) = (t * t, t * t * t);
+                let value: Vec<f32> = (0..self.width).map(|i| {
+                    (2.0 * t3 - 3.0 * t2 + 1.0) * v0[i]
+                        + delta * (t3 - 2.0 * t2 + t) * out_tangent[i]
+                        + (-2.0 * t3 + 3.0 * t2) * v1[i]
+                        + delta * (t3 - t2) * in_tangent[i]
+                }).collect();
+                if rotation && self.width == 4 {
+                    Vector::from_array(&value).normalize4().to_array4().to_vec()
+                } else {
+                    value
+                }
+            }
+        })
+    }
+}
+
+pub enum GltfLightType {
+    Point,
+    Directional,
+    Spot { inner_cone_angle: f32, outer_cone_angle: f32 }, // radians
+}
+/// A KHR_lights_punctual light, shining down the -z axis of its nodes
+pub struct GltfLight {
+    pub name: String,
+    pub light_type: GltfLightType,
+    pub color: Vector,
+    pub intensity: f32, // candela for point and spot lights, lux for directional ones
+    pub range: Option<f32>, // None for unlimited
+}
+
+pub enum GltfProjection {
+    Perspective { y_fov: f32, aspect_ratio: Option<f32>, z_near: f32, z_far: Option<f32> }, // radians, no far plane for infinite projections
+    Orthographic { x_mag: f32, y_mag: f32, z_near: f32, z_far: f32 },
+}
+/// A camera looking down the -z axis of its nodes
+pub struct GltfCamera {
+    pub name: String,
+    pub projection: GltfProjection,
+}
+
 pub struct GltfScene {
     pub name: String,
     pub nodes: Vec<usize>,
 }
 
+/// Reads a .gltf file, or the JSON and binary chunks of a .glb container, told apart by the glb magic rather than the extension
+fn read_gltf(path: &str) -> Result<(JsonValue, Option<Vec<u8>>), GltfError> {
+    let reader = GltfReader { file: path };
+    let malformed = |message: String| reader.error("", GltfErrorKind::Malformed(message));
+    let parse_json = |bytes: &[u8]| -> Result<JsonValue, GltfError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| malformed(format!("json is not valid utf-8, {}", e)))?;
+        json::parse(text).map_err(|e| malformed(format!("json parse error, {}", e)))
+    };
+
+    let bytes = fs::read(path).map_err(|e| reader.error("", GltfErrorKind::Io(e)))?;
+    if !bytes.starts_with(b"glTF") {
+        return Ok((parse_json(&bytes)?, None))
+    }
+
+    let read_u32 = |offset: usize| bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    match read_u32(4) {
+        Some(2) => {}
+        version => return Err(malformed(format!("unsupported glb version {:?}", version))),
+    }
+    let length = (read_u32(8).ok_or_else(|| malformed(String::from("truncated glb header")))? as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut binary_chunk = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset).unwrap() as usize;
+        let chunk_type = read_u32(offset + 4).unwrap();
+        let chunk = bytes.get(offset + 8..offset + 8 + chunk_length).ok_or_else(|| malformed(format!("truncated glb chunk at byte {}", offset)))?;
+        match chunk_type {
+            0x4E4F534A => json = Some(parse_json(chunk)?), // "JSON"
+            0x004E4942 if binary_chunk.is_none() => binary_chunk = Some(chunk.to_vec()), // "BIN\0"
+            _ => {} // unknown chunks are skipped
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((json.ok_or_else(|| malformed(String::from("glb has no json chunk")))?, binary_chunk))
+}
+
+/// A material's reference to a texture of the model, with its KHR_texture_transform
+#[derive(Clone, Copy)]
+struct TextureInfo {
+    index: i32,
+    offset: Option<[f32; 2]>,
+    scale: Option<[f32; 2]>,
+}
+fn read_texture_info(reader: &GltfReader, json: &JsonValue, path: &str, texture_count: usize) -> Result<Option<TextureInfo>, GltfError> {
+    if json.is_null() {
+        return Ok(None)
+    }
+    let transform = &json["extensions"]["KHR_texture_transform"];
+    let transform_path = format!("{}.extensions.KHR_texture_transform", path);
+    Ok(Some(TextureInfo {
+        index: reader.index(&json["index"], &format!("{}.index", path), texture_count)? as i32,
+        offset: reader.opt_floats::<2>(&transform["offset"], &format!("{}.offset", transform_path))?,
+        scale: reader.opt_floats::<2>(&transform["scale"], &format!("{}.scale", transform_path))?,
+    }))
+}
+
+/// Lengths of the world's glTF arrays before a model is loaded, so a model that fails to load can be removed again
+struct WorldLengths {
+    buffers: usize,
+    buffer_views: usize,
+    accessors: usize,
+    images: usize,
+    skins: usize,
+    textures: usize,
+    materials: usize,
+    primitives: usize,
+    animations: usize,
+    nodes: usize,
+    meshes: usize,
+    scenes: usize,
+    lights: usize,
+    cameras: usize,
+}
+impl WorldLengths {
+    fn of(world: &World) -> Self {
+        Self {
+            buffers: world.buffers.len(),
+            buffer_views: world.buffer_views.len(),
+            accessors: world.accessors.len(),
+            images: world.images.len(),
+            skins: world.skins.len(),
+            textures: world.textures.len(),
+            materials: world.materials.len(),
+            primitives: world.primitive_count,
+            animations: world.animations.len(),
+            nodes: world.nodes.len(),
+            meshes: world.meshes.len(),
+            scenes: world.scenes.len(),
+            lights: world.lights.len(),
+            cameras: world.cameras.len(),
+        }
+    }
+    fn truncate(&self, world: &mut World) {
+        world.buffers.truncate(self.buffers);
+        world.buffer_views.truncate(self.buffer_views);
+        world.accessors.truncate(self.accessors);
+        world.images.truncate(self.images);
+        world.skins.truncate(self.skins);
+        world.textures.truncate(self.textures);
+        world.materials.truncate(self.materials);
+        world.primitive_count = self.primitives;
+        world.animations.truncate(self.animations);
+        world.nodes.truncate(self.nodes);
+        world.meshes.truncate(self.meshes);
+        world.scenes.truncate(self.scenes);
+        world.lights.truncate(self.lights);
+        world.cameras.truncate(self.cameras);
+    }
+}
+
+/// Splits a data uri into its mime type and decoded bytes, only base64 data is supported
+fn decode_data_uri(uri: &str) -> Option<(String, Vec<u8>)> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    Some((String::from(mime_type), decode_base64(data)?))
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        accumulator = (accumulator << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
 fn resolve_gltf_uri(gltf_path: &str, uri: &str) -> PathBuf {
     let gltf_dir = Path::new(gltf_path).parent().unwrap_or_else(|| Path::new(""));
 
@@ -1960,3 +2649,138 @@ fn resolve_gltf_uri(gltf_path: &str, uri: &str) -> PathBuf {
         gltf_dir.join(uri_path).canonicalize().unwrap_or_else(|_| gltf_dir.join(uri_path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A buffer view for each of the byte slices, all in one buffer
+    fn buffer_views(slices: &[&[u8]]) -> (Vec<BufferView>, Vec<Buffer>) {
+        let mut data = Vec::new();
+        let views = slices.iter().map(|slice| {
+            let view = BufferView { buffer: 0, byte_length: slice.len(), byte_offset: data.len(), byte_stride: None, target: 0 };
+            data.extend_from_slice(slice);
+            view
+        }).collect();
+        (views, vec![Buffer { uri: PathBuf::new(), byte_length: data.len(), data }])
+    }
+    fn accessor(buffer_view: Option<usize>, component_type: ComponentType, normalized: bool, count: usize, r#type: &str) -> Accessor {
+        Accessor {
+            buffer_view,
+            byte_offset: 0,
+            component_type,
+            normalized,
+            count,
+            r#type: String::from(r#type),
+            min: None,
+            max: None,
+            sparse: None,
+            data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normalized_integers_are_mapped_to_unit_ranges() {
+        let (views, buffers) = buffer_views(&[
+            &[127, -127i8 as u8, -128i8 as u8, 0],
+            &[32767i16, -32768, -16384, 0].map(i16::to_le_bytes).concat(),
+            &[255, 0, 51],
+        ]);
+        // -128 and -32768 are clamped to -1, like -127 and -32767
+        assert_eq!(accessor(Some(0), ComponentType::I8, true, 4, "SCALAR").read_floats(&views, &buffers), vec![1.0, -1.0, -1.0, 0.0]);
+        assert_eq!(
+            accessor(Some(1), ComponentType::I16, true, 2, "VEC2").read_floats(&views, &buffers),
+            vec![1.0, -1.0, -16384.0 / 32767.0, 0.0],
+        );
+        assert_eq!(accessor(Some(2), ComponentType::U8, true, 3, "SCALAR").read_floats(&views, &buffers), vec![1.0, 0.0, 0.2]);
+        // quantized positions keep their integer values
+        assert_eq!(accessor(Some(0), ComponentType::I8, false, 4, "SCALAR").read_floats(&views, &buffers), vec![127.0, -127.0, -128.0, 0.0]);
+    }
+
+    #[test]
+    fn sparse_substitutes_replace_elements() {
+        let (views, buffers) = buffer_views(&[
+            &[0, 2],
+            &[1.0f32, 2.0, 3.0, 4.0].map(f32::to_le_bytes).concat(),
+            &[10u16, 20, 30, 40].map(u16::to_le_bytes).concat(),
+            &[1u16, 3].map(u16::to_le_bytes).concat(),
+            &[7u16, 9].map(u16::to_le_bytes).concat(),
+        ]);
+
+        // u8 indices into an accessor without a buffer view, which starts as zeros
+        let mut zeros = accessor(None, ComponentType::F32, false, 3, "VEC2");
+        zeros.sparse = Some(SparseAccessor {
+            count: 2,
+            indices_buffer_view: 0,
+            indices_byte_offset: 0,
+            indices_component_type: ComponentType::U8,
+            values_buffer_view: 1,
+            values_byte_offset: 0,
+        });
+        assert_eq!(zeros.read_floats(&views, &buffers), vec![1.0, 2.0, 0.0, 0.0, 3.0, 4.0]);
+
+        // u16 indices over the accessor's own values
+        let mut values = accessor(Some(2), ComponentType::U16, false, 4, "SCALAR");
+        values.sparse = Some(SparseAccessor {
+            count: 2,
+            indices_buffer_view: 3,
+            indices_byte_offset: 0,
+            indices_component_type: ComponentType::U16,
+            values_buffer_view: 4,
+            values_byte_offset: 0,
+        });
+        assert_eq!(values.read_floats(&views, &buffers), vec![10.0, 7.0, 30.0, 9.0]);
+        assert_eq!(values.read_u32s(&views, &buffers), vec![10, 7, 30, 9]);
+    }
+
+    #[test]
+    fn base64_is_decoded_with_and_without_padding() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9v").unwrap(), b"foo");
+        assert_eq!(decode_base64("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(decode_base64("Zm9v\nYmFy").unwrap(), b"foobar");
+        // the url safe alphabet too
+        assert_eq!(decode_base64("-_8=").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode_base64("+/8=").unwrap(), [0xfb, 0xff]);
+        assert!(decode_base64("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn data_uris_are_split_into_mime_type_and_bytes() {
+        let (mime_type, bytes) = decode_data_uri("data:application/octet-stream;base64,AAEC").unwrap();
+        assert_eq!(mime_type, "application/octet-stream");
+        assert_eq!(bytes, [0, 1, 2]);
+        assert!(decode_data_uri("data:text/plain,hello").is_none());
+        assert!(decode_data_uri("buffer.bin").is_none());
+    }
+
+    #[test]
+    fn cubic_spline_samples_the_hermite_curve() {
+        // in tangent, value, out tangent per keyframe, tangents are per second and scaled by the 2s between the keyframes
+        let sampler = AnimationSampler::new(vec![0.0, 2.0], Interpolation::CubicSpline, vec![0.0, 0.0, 2.0, 0.0, 4.0, 0.0]);
+        assert_eq!(sampler.width, 1);
+        // 0.5 * 0 + 2 * 0.125 * 2 + 0.5 * 4 - 2 * 0.125 * 0
+        assert!((sampler.sample(1.0, false).unwrap()[0] - 2.5).abs() < 1e-6);
+        assert_eq!(sampler.sample(2.0, false).unwrap(), vec![4.0]);
+    }
+
+    #[test]
+    fn step_holds_the_value_until_the_next_keyframe() {
+        let sampler = AnimationSampler::new(vec![0.0, 1.0, 2.0], Interpolation::Step, vec![1.0, 5.0, 9.0]);
+        assert_eq!(sampler.sample(0.99, false).unwrap(), vec![1.0]);
+        assert_eq!(sampler.sample(1.0, false).unwrap(), vec![5.0]);
+        assert_eq!(sampler.sample(1.5, false).unwrap(), vec![5.0]);
+        assert_eq!(sampler.sample(5.0, false).unwrap(), vec![9.0]);
+    }
+
+    #[test]
+    fn weights_samplers_interpolate_every_weight() {
+        // three morph target weights per keyframe
+        let sampler = AnimationSampler::new(vec![0.0, 1.0], Interpolation::Linear, vec![0.0, 1.0, 0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(sampler.width, 3);
+        assert_eq!(sampler.value(1), &[1.0, 0.0, 0.5]);
+        assert_eq!(sampler.sample(0.5, false).unwrap(), vec![0.5, 0.5, 0.25]);
+    }
+}
@@ -1,10 +1,15 @@
 use crate::engine::{get_command_buffer, EngineRef};
 use std::cell::RefCell;
 use std::sync::Arc;
-use mlua::{FromLua, UserData, UserDataFields, UserDataMethods, Value};
+use mlua::{FromLua, MetaMethod, UserData, UserDataFields, UserDataMethods, Value};
 use crate::math::Vector;
-use crate::scene::scene::Scene;
-use crate::scripting::lua_engine::Lua;
+use crate::scene::animation_controller::AnimationParameter;
+use crate::scene::events::SceneEvent;
+use crate::scene::history::{SceneEdit, TransformField};
+use crate::scene::ik::{IkConstraint, IkSolver, IkTarget};
+use crate::scene::spatial_index::Aabb;
+use crate::scene::scene::{AnimationComponent, AnimationMarker, Entity, PhysicsInterpolation, Scene, ScriptComponent};
+use crate::scripting::lua_engine::{field_from_lua, field_to_lua, Lua, SCENE_EVENT_HANDLERS};
 
 macro_rules! with_scene {
     ($lua:expr => $scene:ident) => {
@@ -27,8 +32,19 @@ impl UserData for SceneRef {
             Ok(scene.running)
         });
         fields.add_field_method_set("running", |lua, this, val: bool| {
+            // the snapshot taken when the scene starts running keeps what instances left from an earlier run
+            store_script_instance_fields(lua)?;
             with_scene_mut!(lua => scene);
-            scene.running = val;
+            scene.set_running(val).map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+
+        fields.add_field_method_get("revert_on_stop", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.revert_on_stop)
+        });
+        fields.add_field_method_set("revert_on_stop", |lua, _, val: bool| {
+            with_scene_mut!(lua => scene);
+            scene.revert_on_stop = val;
             Ok(())
         });
 
@@ -36,24 +52,199 @@ impl UserData for SceneRef {
             with_scene!(lua => scene);
             Ok(scene.runtime)
         });
+
+        fields.add_field_method_get("can_undo", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.history.can_undo())
+        });
+        fields.add_field_method_get("can_redo", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.history.can_redo())
+        });
+
+        fields.add_field_method_get("physics_rate", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.physics_rate)
+        });
+        fields.add_field_method_set("physics_rate", |lua, _, val: f32| {
+            if !val.is_finite() || val <= 0.0 {
+                return Err(mlua::Error::RuntimeError(format!("Physics rate must be positive, got {}", val)))
+            }
+            with_scene_mut!(lua => scene);
+            scene.physics_rate = val;
+            Ok(())
+        });
+        fields.add_field_method_get("max_physics_steps", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.max_physics_steps)
+        });
+        fields.add_field_method_set("max_physics_steps", |lua, _, val: usize| {
+            if val < 1 {
+                return Err(mlua::Error::RuntimeError(format!("Max physics steps must be at least 1, got {}", val)))
+            }
+            with_scene_mut!(lua => scene);
+            scene.max_physics_steps = val;
+            Ok(())
+        });
+        fields.add_field_method_get("physics_interpolation", |lua, _| {
+            with_scene!(lua => scene);
+            Ok(scene.physics_interpolation.name())
+        });
+        fields.add_field_method_set("physics_interpolation", |lua, _, val: String| {
+            let interpolation = PhysicsInterpolation::from_name(val.as_str()).ok_or_else(|| mlua::Error::RuntimeError(
+                format!("Unknown physics interpolation '{}', expected one of {}", val, PhysicsInterpolation::NAMES.join(", "))
+            ))?;
+            with_scene_mut!(lua => scene);
+            scene.physics_interpolation = interpolation;
+            Ok(())
+        });
     }
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method("get_entity", |lua, this, index: usize| {
-            Ok(lua.create_userdata(EntityPointer {
-                index
-            }))
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(EntityPointer::new(&scene, index)?))
         });
         methods.add_method("get_render_component", |lua, this, index: usize| {
-            Ok(lua.create_userdata(RenderComponentPointer { index }))
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(RenderComponentPointer::new(&scene, index)?))
+        });
+        methods.add_method("query", |lua, _, component_names: Vec<String>| {
+            with_scene!(lua => scene);
+            let names: Vec<&str> = component_names.iter().map(|name| name.as_str()).collect();
+            let matches = scene.query_indices(&names).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+
+            let rows = lua.create_table()?;
+            for (i, (entity_index, component_indices)) in matches.into_iter().enumerate() {
+                let row = lua.create_table()?;
+                row.set("entity", EntityPointer::new(&scene, entity_index)?)?;
+                for (&name, component_index) in names.iter().zip(component_indices) {
+                    row.set(name, component_to_lua(lua, &scene, name, component_index)?)?;
+                }
+                rows.set(i + 1, row)?;
+            }
+            Ok(rows)
+        });
+        methods.add_method("on", |lua, _, (event_name, handler): (String, mlua::Function)| {
+            if !SceneEvent::NAMES.contains(&event_name.as_str()) {
+                return Err(mlua::Error::RuntimeError(format!("Unknown scene event '{}'", event_name)))
+            }
+            let handlers: mlua::Table = lua.named_registry_value(SCENE_EVENT_HANDLERS)?;
+            let event_handlers = match handlers.get::<_, Option<mlua::Table>>(event_name.as_str())? {
+                Some(event_handlers) => event_handlers,
+                None => {
+                    let event_handlers = lua.create_table()?;
+                    handlers.set(event_name.as_str(), event_handlers.clone())?;
+                    event_handlers
+                }
+            };
+            event_handlers.push(handler)
+        });
+        methods.add_method("off", |lua, _, (event_name, handler): (String, mlua::Function)| {
+            let handlers: mlua::Table = lua.named_registry_value(SCENE_EVENT_HANDLERS)?;
+            if let Some(event_handlers) = handlers.get::<_, Option<mlua::Table>>(event_name.as_str())? {
+                let remaining = lua.create_table()?;
+                for existing in event_handlers.sequence_values::<mlua::Function>() {
+                    let existing = existing?;
+                    if existing != handler {
+                        remaining.push(existing)?;
+                    }
+                }
+                handlers.set(event_name.as_str(), remaining)?;
+            }
+            Ok(())
+        });
+        methods.add_method("register_component", |lua, _, (name, defaults): (String, mlua::Table)| {
+            with_scene_mut!(lua => scene);
+            let mut fields = Vec::new();
+            for pair in defaults.pairs::<String, Value>() {
+                let (field_name, value) = pair?;
+                let field = field_from_lua(&value).ok_or_else(|| mlua::Error::RuntimeError(
+                    format!("Field '{}' of component '{}' cannot be a {}", field_name, name, value.type_name())
+                ))?;
+                fields.push((field_name, field));
+            }
+            // table iteration order is arbitrary
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            scene.register_component(name.as_str(), fields);
+            Ok(())
+        });
+        methods.add_method("find_by_name", |lua, _, name: String| {
+            with_scene!(lua => scene);
+            scene.find_by_name(name.as_str()).map(|index| EntityPointer::new(&scene, index)).transpose()
+        });
+        methods.add_method("find_by_path", |lua, _, path: String| {
+            with_scene!(lua => scene);
+            scene.find_by_path(path.as_str()).map(|index| EntityPointer::new(&scene, index)).transpose()
+        });
+        methods.add_method("entities_with_tag", |lua, _, tag: String| {
+            with_scene!(lua => scene);
+            entity_table(lua, &scene, &scene.entities_with_tag(tag.as_str()))
+        });
+        methods.add_method("overlap_sphere", |lua, _, (center, radius): (Vector, f32)| {
+            with_scene!(lua => scene);
+            entity_table(lua, &scene, &scene.overlap_sphere(&center, radius))
+        });
+        methods.add_method("overlap_box", |lua, _, (min, max): (Vector, Vector)| {
+            with_scene!(lua => scene);
+            entity_table(lua, &scene, &scene.overlap_aabb(&Aabb::new(min, max)))
+        });
+        methods.add_method("raycast_bounds", |lua, _, (origin, direction, max_distance): (Vector, Vector, Option<f32>)| {
+            with_scene!(lua => scene);
+            let hits = lua.create_table()?;
+            for (i, (entity_index, distance)) in scene.raycast_bounds(&origin, &direction, max_distance.unwrap_or(f32::INFINITY)).into_iter().enumerate() {
+                let hit = lua.create_table()?;
+                hit.set("entity", EntityPointer::new(&scene, entity_index)?)?;
+                hit.set("distance", distance)?;
+                hits.set(i + 1, hit)?;
+            }
+            Ok(hits)
+        });
+        methods.add_method("entities_in_layers", |lua, _, layer_mask: u32| {
+            with_scene!(lua => scene);
+            entity_table(lua, &scene, &scene.entities_in_layers(layer_mask))
         });
         methods.add_method("get_transform", |lua, this, index: usize| {
-            Ok(lua.create_userdata(TransformPointer { index }))
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(TransformPointer::new(&scene, index)?))
         });
         methods.add_method("get_rigid_body", |lua, this, index: usize| {
-            Ok(lua.create_userdata(RigidBodyPointer { index }))
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(RigidBodyPointer::new(&scene, index)?))
         });
         methods.add_method("get_camera", |lua, this, index: usize| {
-            Ok(lua.create_userdata(CameraPointer { index }))
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(CameraPointer::new(&scene, index)?))
+        });
+        methods.add_method("get_animation", |lua, _, index: usize| {
+            with_scene!(lua => scene);
+            Ok(lua.create_userdata(AnimationPointer::new(&scene, index)?))
+        });
+        methods.add_method("crossfade", |lua, _, (from, to, duration): (usize, usize, f32)| {
+            with_scene_mut!(lua => scene);
+            animation_component(&scene, from)?;
+            animation_component(&scene, to)?;
+            scene.crossfade(from, to, duration);
+            Ok(())
+        });
+        methods.add_method("add_ik_constraint", |lua, _, (solver_name, chain, target): (String, Vec<usize>, Value)| {
+            let solver = IkSolver::from_name(solver_name.as_str()).ok_or_else(|| mlua::Error::RuntimeError(
+                format!("Unknown IK solver '{}', expected one of {}", solver_name, IkSolver::NAMES.join(", "))
+            ))?;
+            let target = ik_target_from_lua(lua, target)?;
+            with_scene_mut!(lua => scene);
+            let index = scene.add_ik_constraint(solver, chain, target).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            IkConstraintPointer::new(&scene, index)
+        });
+        methods.add_method("get_ik_constraint", |lua, _, index: usize| {
+            with_scene!(lua => scene);
+            IkConstraintPointer::new(&scene, index)
+        });
+        // the last constraint takes the removed one's index
+        methods.add_method("remove_ik_constraint", |lua, _, index: usize| {
+            with_scene_mut!(lua => scene);
+            ik_constraint(&scene, index)?;
+            scene.remove_ik_constraint(index);
+            Ok(())
         });
 
         methods.add_method("load_model", |lua, this, parent: usize| {
@@ -63,12 +254,24 @@ impl UserData for SceneRef {
                 .add_filter("GLTF Models", &["gltf", "glb"])
                 .pick_file();
             if let Some(file) = file {
-                scene.new_entity_from_model(parent, file.to_str().unwrap());
+                let root_index = scene.new_entity_from_model(parent, &file.to_string_lossy())
+                    .map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+                scene.record_spawn(root_index);
             }
 
             Ok(())
         });
 
+        methods.add_method("save", |lua, _, path: String| {
+            store_script_instance_fields(lua)?;
+            with_scene!(lua => scene);
+            scene.save(path.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+        methods.add_method("load", |lua, _, path: String| {
+            with_scene_mut!(lua => scene);
+            scene.load(path.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+
         methods.add_method("step", |lua, this, dt: f32| unsafe {
             with_scene_mut!(lua => scene);
 
@@ -77,6 +280,85 @@ impl UserData for SceneRef {
             Ok(())
         });
 
+        methods.add_method("remove_entity", |lua, _, (index, recursive): (usize, bool)| {
+            with_scene_mut!(lua => scene);
+            EntityPointer::new(&scene, index)?;
+            if index == 0 {
+                return Err(mlua::Error::RuntimeError("The root entity cannot be removed".into()))
+            }
+            scene.record_removal(index, recursive);
+            scene.remove_entity(index, recursive);
+            Ok(())
+        });
+        methods.add_method("duplicate_entity", |lua, _, index: usize| {
+            with_scene_mut!(lua => scene);
+            EntityPointer::new(&scene, index)?;
+            if index == 0 {
+                return Err(mlua::Error::RuntimeError("The root entity cannot be duplicated".into()))
+            }
+            let copy_index = scene.duplicate_entity(index);
+            scene.record_spawn(copy_index);
+            Ok(lua.create_userdata(EntityPointer::new(&scene, copy_index)?))
+        });
+
+        methods.add_method("save_prefab", |lua, _, (index, path): (usize, String)| {
+            store_script_instance_fields(lua)?;
+            with_scene_mut!(lua => scene);
+            scene.save_prefab(index, path.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            Ok(())
+        });
+        methods.add_method("instantiate", |lua, _, (path, parent): (String, usize)| {
+            with_scene_mut!(lua => scene);
+            let root_index = scene.instantiate_prefab(path.as_str(), parent).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            scene.record_spawn(root_index);
+            Ok(lua.create_userdata(EntityPointer::new(&scene, root_index)?))
+        });
+        methods.add_method("resync_prefab", |lua, _, path: String| {
+            with_scene_mut!(lua => scene);
+            let instances = scene.resync_prefab(path.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            entity_table(lua, &scene, &instances)
+        });
+
+        // return the name of the undone or redone entry, nil if there was nothing to undo or redo
+        methods.add_method("undo", |lua, _, ()| {
+            with_scene_mut!(lua => scene);
+            scene.undo().map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+        methods.add_method("redo", |lua, _, ()| {
+            with_scene_mut!(lua => scene);
+            scene.redo().map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+        methods.add_method("begin_transaction", |lua, _, name: String| {
+            with_scene_mut!(lua => scene);
+            scene.begin_transaction(name.as_str());
+            Ok(())
+        });
+        methods.add_method("commit_transaction", |lua, _, ()| {
+            with_scene_mut!(lua => scene);
+            scene.commit_transaction();
+            Ok(())
+        });
+        methods.add_method("cancel_transaction", |lua, _, ()| {
+            with_scene_mut!(lua => scene);
+            scene.cancel_transaction().map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+        });
+        methods.add_method("history", |lua, _, ()| {
+            with_scene!(lua => scene);
+            let history = lua.create_table()?;
+            for (name, done) in scene.history_entries() {
+                let entry = lua.create_table()?;
+                entry.set("name", name)?;
+                entry.set("done", done)?;
+                history.push(entry)?;
+            }
+            Ok(history)
+        });
+        methods.add_method("clear_history", |lua, _, ()| {
+            with_scene_mut!(lua => scene);
+            scene.clear_history();
+            Ok(())
+        });
+
         methods.add_method("reset_outlines", |lua, this, ()| {
             with_scene_mut!(lua => scene);
             scene.outlined_components.clear();
@@ -96,32 +378,119 @@ impl UserData for SceneRef {
     }
 }
 
+///* The table scene event handlers are called with. It always has the event's name and entity_index, and entity while the entity exists.
+///* generation is the entity's at the time of the event, so entity is left out if the index has been reused since.
+pub(crate) fn scene_event_to_lua<'lua>(lua: &'lua mlua::Lua, event: &SceneEvent, generation: u32) -> mlua::Result<mlua::Table<'lua>> {
+    with_scene!(lua => scene);
+    let table = lua.create_table()?;
+    table.set("name", event.name())?;
+    table.set("entity_index", event.entity())?;
+    if !matches!(event, SceneEvent::EntityRemoved { .. }) {
+        let index = event.entity();
+        table.set("entity", scene.get_entity(index, generation).map(|_| EntityPointer { index, generation }))?;
+    }
+    match *event {
+        SceneEvent::EntityReparented { old_parent, new_parent, .. } => {
+            table.set("old_parent_index", old_parent)?;
+            table.set("parent_index", new_parent)?;
+        }
+        SceneEvent::RigidBodyAttached { entity, rigid_body } => {
+            table.set("rigid_body", RigidBodyPointer { owner: EntityPointer { index: entity, generation }, index: rigid_body })?;
+        }
+        SceneEvent::TransformChanged { entity, transform } => {
+            table.set("transform", TransformPointer { owner: EntityPointer { index: entity, generation }, index: transform })?;
+        }
+        SceneEvent::AnimationStarted { animation, .. } | SceneEvent::AnimationStopped { animation, .. } => {
+            table.set("animation_index", animation)?;
+        }
+        SceneEvent::AnimationMarker { animation, ref marker, .. } => {
+            table.set("animation_index", animation)?;
+            table.set("marker", marker.as_str())?;
+        }
+        SceneEvent::EntityAdded { .. } | SceneEvent::EntityRemoved { .. } => {}
+    }
+    Ok(table)
+}
+
+/// Pointer userdata for the component types that have one, the component index for the rest
+fn component_to_lua<'lua>(lua: &'lua mlua::Lua, scene: &Scene, name: &str, index: usize) -> mlua::Result<Value<'lua>> {
+    match name {
+        "transform" => lua.create_userdata(TransformPointer::new(scene, index)?).map(Value::UserData),
+        "render_component" => lua.create_userdata(RenderComponentPointer::new(scene, index)?).map(Value::UserData),
+        "rigid_body" => lua.create_userdata(RigidBodyPointer::new(scene, index)?).map(Value::UserData),
+        "camera" => lua.create_userdata(CameraPointer::new(scene, index)?).map(Value::UserData),
+        "light" | "sun" => Ok(Value::Integer(index as i64)),
+        _ => {
+            let component = &scene.custom_components[index];
+            lua.create_userdata(CustomComponentPointer {
+                owner: EntityPointer::new(scene, component.owner)?,
+                schema: component.schema,
+            }).map(Value::UserData)
+        }
+    }
+}
+fn entity_table<'lua>(lua: &'lua mlua::Lua, scene: &Scene, indices: &[usize]) -> mlua::Result<mlua::Table<'lua>> {
+    let table = lua.create_table()?;
+    for (i, index) in indices.iter().enumerate() {
+        table.set(i + 1, EntityPointer::new(scene, *index)?)?;
+    }
+    Ok(table)
+}
+
+#[derive(Clone, Copy)]
 pub struct EntityPointer {
     index: usize,
+    generation: u32,
+}
+impl EntityPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        match scene.entities.get(index) {
+            Some(entity) if entity.alive => Ok(Self { index, generation: entity.generation }),
+            _ => Err(mlua::Error::RuntimeError(format!("Entity {} does not exist", index)))
+        }
+    }
+    /// Fails if the entity has been removed, even if its index has since been reused
+    pub fn get<'a>(&self, scene: &'a Scene) -> mlua::Result<&'a Entity> {
+        scene.get_entity(self.index, self.generation).ok_or_else(|| mlua::Error::RuntimeError(
+            format!("Entity {} has been removed", self.index)
+        ))
+    }
+    fn script_indices(&self, lua: &mlua::Lua) -> mlua::Result<Vec<usize>> {
+        with_scene!(lua => scene);
+        self.get(&scene)?;
+        Ok(scene.script_components.iter().enumerate()
+            .filter(|(_, script)| script.owner == self.index)
+            .map(|(script_index, _)| script_index)
+            .collect())
+    }
 }
 impl UserData for EntityPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
         fields.add_field_method_get("index", |_, this| {
            Ok(this.index)
         });
+        fields.add_field_method_get("valid", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene).is_ok())
+        });
 
         fields.add_field_method_get("parent_index", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.entities[this.index].parent)
+            Ok(this.get(&scene)?.parent)
         });
         fields.add_field_method_get("parent", |lua, this| {
             with_scene!(lua => scene);
-            Ok(EntityPointer{ index: scene.entities[this.index].parent })
+            EntityPointer::new(&scene, this.get(&scene)?.parent)
         });
 
         fields.add_field_method_get("transform_index", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.entities[this.index].transform)
+            Ok(this.get(&scene)?.transform)
         });
 
         fields.add_field_method_get("children_indices", |lua, this| {
             with_scene!(lua => scene);
-            let children = &scene.entities[this.index].children_indices;
+            let children = &this.get(&scene)?.children_indices;
             let table = lua.create_table()?;
             for (i, child_index) in children.iter().enumerate() {
                 table.set(i + 1, *child_index)?;
@@ -131,7 +500,7 @@ impl UserData for EntityPointer {
 
         fields.add_field_method_get("render_component_indices", |lua, this| {
             with_scene!(lua => scene);
-            let render_components = &scene.entities[this.index].render_objects;
+            let render_components = &this.get(&scene)?.render_objects;
             let table = lua.create_table()?;
             for (i, element_index) in render_components.iter().enumerate() {
                 table.set(i + 1, *element_index)?;
@@ -139,230 +508,1168 @@ impl UserData for EntityPointer {
             Ok(table)
         });
 
+        fields.add_field_method_get("animation_indices", |lua, this| {
+            with_scene!(lua => scene);
+            lua.create_sequence_from(this.get(&scene)?.animation_objects.iter().copied())
+        });
+
+        fields.add_field_method_get("script_component_indices", |lua, this| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            let table = lua.create_table()?;
+            for (i, (script_index, _)) in scene.script_components.iter().enumerate()
+                .filter(|(_, script)| script.owner == this.index)
+                .enumerate()
+            {
+                table.set(i + 1, script_index)?;
+            }
+            Ok(table)
+        });
+
         fields.add_field_method_get("rigid_body_index", |lua, this| {
             with_scene!(lua => scene);
-            return if let Some(rigid_body) = scene.entities[this.index].rigid_body {
+            return if let Some(rigid_body) = this.get(&scene)?.rigid_body {
                 Ok(rigid_body as i32)
             } else {
                 Ok(-1)
             }
         });
 
+        fields.add_field_method_get("camera_index", |lua, this| {
+            with_scene!(lua => scene);
+            return if let Some(camera) = this.get(&scene)?.camera {
+                Ok(camera as i32)
+            } else {
+                Ok(-1)
+            }
+        });
+
         fields.add_field_method_get("name", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.entities[this.index].name.clone())
+            Ok(this.get(&scene)?.name.clone())
+        });
+        fields.add_field_method_set("name", |lua, this, name: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            let before = std::mem::replace(&mut scene.entities[this.index].name, name.clone());
+            let entity = scene.entity_handle(this.index);
+            scene.record(SceneEdit::Name { entity, before, after: name });
+            Ok(())
+        });
+
+        fields.add_field_method_get("component_names", |lua, this| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            lua.create_sequence_from(scene.custom_components.iter()
+                .filter(|component| component.owner == this.index)
+                .map(|component| scene.component_schemas[component.schema].name.clone()))
+        });
+
+        fields.add_field_method_get("morph_weights", |lua, this| {
+            with_scene!(lua => scene);
+            lua.create_sequence_from(this.get(&scene)?.current_morph_weights().iter().copied())
+        });
+        fields.add_field_method_set("morph_weights", |lua, this, weights: Vec<f32>| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            scene.entities[this.index].morph_weights = weights;
+            scene.unupdated_entities.push(this.index);
+            Ok(())
+        });
+
+        fields.add_field_method_get("tags", |lua, this| {
+            with_scene!(lua => scene);
+            lua.create_sequence_from(this.get(&scene)?.tags.iter().cloned())
+        });
+        fields.add_field_method_get("layers", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.layers)
+        });
+        fields.add_field_method_set("layers", |lua, this, layers: u32| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            let before = std::mem::replace(&mut scene.entities[this.index].layers, layers);
+            let entity = scene.entity_handle(this.index);
+            scene.record(SceneEdit::Layers { entity, before, after: layers });
+            Ok(())
         });
     }
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method("get_render_component", |lua, this, index: usize| {
             with_scene!(lua => scene);
-            Ok(lua.create_userdata(RenderComponentPointer { index: scene.entities[this.index].render_objects[index] }))
+            let render_component_index = *this.get(&scene)?.render_objects.get(index).ok_or_else(|| mlua::Error::RuntimeError(
+                format!("Entity {} has no render component {}", this.index, index)
+            ))?;
+            Ok(lua.create_userdata(RenderComponentPointer { owner: *this, index: render_component_index }))
         });
 
         methods.add_method("get_render_component_index", |lua, this, index: usize| {
             with_scene!(lua => scene);
-            Ok(scene.entities[this.index].render_objects[index])
+            Ok(this.get(&scene)?.render_objects[index])
+        });
+
+        methods.add_method("remove", |lua, this, recursive: bool| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            if this.index == 0 {
+                return Err(mlua::Error::RuntimeError("The root entity cannot be removed".into()))
+            }
+            scene.record_removal(this.index, recursive);
+            scene.remove_entity(this.index, recursive);
+            Ok(())
+        });
+        methods.add_method("remove_rigid_body", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            if let Some(body_index) = this.get(&scene)?.rigid_body {
+                scene.remove_rigid_body(body_index);
+            }
+            Ok(())
+        });
+        methods.add_method("remove_render_component", |lua, this, index: usize| {
+            with_scene_mut!(lua => scene);
+            let render_component_index = *this.get(&scene)?.render_objects.get(index).ok_or_else(|| mlua::Error::RuntimeError(
+                format!("Entity {} has no render component {}", this.index, index)
+            ))?;
+            scene.remove_render_component(render_component_index);
+            Ok(())
+        });
+        methods.add_method("add_script", |lua, this, uri: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            Ok(ScriptPointer { owner: *this, index: scene.attach_script(this.index, uri.as_str()) })
+        });
+        methods.add_method("get_script", |lua, this, index: usize| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            scene.script_components.iter().enumerate()
+                .filter(|(_, script)| script.owner == this.index)
+                .nth(index)
+                .map(|(script_index, _)| ScriptPointer { owner: *this, index: script_index })
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("Entity {} has no script {}", this.index, index)))
+        });
+        // fields of the entity's first script that exports them
+        methods.add_method("get_animation", |lua, this, index: usize| {
+            with_scene!(lua => scene);
+            this.get(&scene)?.animation_objects.get(index)
+                .map(|&animation_index| AnimationPointer { owner: *this, index: animation_index })
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("Entity {} has no animation {}", this.index, index)))
+        });
+        methods.add_method("get_field", |lua, this, name: String| {
+            for script_index in this.script_indices(lua)? {
+                if let Some(value) = get_script_field(lua, script_index, name.as_str())? {
+                    return Ok(value)
+                }
+            }
+            Ok(Value::Nil)
+        });
+        methods.add_method("set_field", |lua, this, (name, value): (String, Value)| {
+            for script_index in this.script_indices(lua)? {
+                if set_script_field(lua, script_index, name.as_str(), value.clone())? {
+                    return Ok(())
+                }
+            }
+            Err(mlua::Error::RuntimeError(format!("No script of entity {} has a field '{}'", this.index, name)))
+        });
+        methods.add_method("duplicate", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            if this.index == 0 {
+                return Err(mlua::Error::RuntimeError("The root entity cannot be duplicated".into()))
+            }
+            let copy_index = scene.duplicate_entity(this.index);
+            scene.record_spawn(copy_index);
+            Ok(lua.create_userdata(EntityPointer::new(&scene, copy_index)?))
+        });
+        methods.add_method("set_parent", |lua, this, (parent_index, keep_world_transform): (usize, bool)| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            let before = scene.placement(this.index);
+            scene.set_parent(this.index, parent_index, keep_world_transform).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            if scene.entities[this.index].parent != before.parent.0 {
+                let after = scene.placement(this.index);
+                let entity = scene.entity_handle(this.index);
+                scene.record(SceneEdit::Parent { entity, before, after });
+            }
+            Ok(())
+        });
+        methods.add_method("find_child", |lua, this, path: String| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            scene.find_child_by_path(this.index, path.as_str()).map(|index| EntityPointer::new(&scene, index)).transpose()
+        });
+
+        methods.add_method("add_component", |lua, this, name: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            let component_index = scene.attach_custom_component(this.index, name.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            Ok(CustomComponentPointer { owner: *this, schema: scene.custom_components[component_index].schema })
+        });
+        methods.add_method("get_component", |lua, this, name: String| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            Ok(scene.get_custom_component(this.index, name.as_str())
+                .map(|component_index| CustomComponentPointer { owner: *this, schema: scene.custom_components[component_index].schema }))
+        });
+        methods.add_method("remove_component", |lua, this, name: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            Ok(scene.detach_custom_component(this.index, name.as_str()))
+        });
+
+        methods.add_method("add_animation_controller", |lua, this, path: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            scene.attach_animation_controller(this.index, path.as_str()).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            Ok(AnimationControllerPointer { owner: *this })
+        });
+        methods.add_method("get_animation_controller", |lua, this, ()| {
+            with_scene!(lua => scene);
+            this.get(&scene)?;
+            Ok(scene.get_animation_controller(this.index).map(|_| AnimationControllerPointer { owner: *this }))
+        });
+        methods.add_method("remove_animation_controller", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            let controller_index = scene.get_animation_controller(this.index);
+            if let Some(controller_index) = controller_index {
+                scene.remove_animation_controller(controller_index);
+            }
+            Ok(controller_index.is_some())
+        });
+
+        methods.add_method("has_tag", |lua, this, tag: String| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.has_tag(tag.as_str()))
+        });
+        methods.add_method("add_tag", |lua, this, tag: String| {
+            with_scene_mut!(lua => scene);
+            if !this.get(&scene)?.has_tag(tag.as_str()) {
+                scene.entities[this.index].tags.push(tag);
+            }
+            Ok(())
+        });
+        methods.add_method("remove_tag", |lua, this, tag: String| {
+            with_scene_mut!(lua => scene);
+            this.get(&scene)?;
+            scene.entities[this.index].tags.retain(|t| *t != tag);
+            Ok(())
         });
     }
 }
 
+///* The component pointers below keep their owner like CustomComponentPointer, and check on every access that it still has the component.
+///* Removing a component moves the last one into its index, so a pointer to a removed component fails instead of reaching the moved one.
+fn component_removed(kind: &str, index: usize, owner: &EntityPointer) -> mlua::Error {
+    mlua::Error::RuntimeError(format!("{} {} no longer belongs to entity {}", kind, index, owner.index))
+}
+
+#[derive(Clone, Copy)]
 pub struct TransformPointer {
+    owner: EntityPointer,
     index: usize,
 }
+impl TransformPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        let transform = scene.transforms.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("Transform {} does not exist", index)))?;
+        Ok(Self { owner: EntityPointer::new(scene, transform.owner)?, index })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        self.owner.get(scene)?;
+        match scene.transforms.get(self.index) {
+            Some(transform) if transform.owner == self.owner.index => Ok(self.index),
+            _ => Err(component_removed("Transform", self.index, &self.owner)),
+        }
+    }
+    /// Sets a local transform field and records it, consecutive sets of the same field are merged into one edit
+    fn set_field(&self, lua: &mlua::Lua, field: TransformField, value: Vector) -> mlua::Result<()> {
+        with_scene_mut!(lua => scene);
+        let transform_index = self.index(&scene)?;
+        let before = scene.transform_field(transform_index, field);
+        scene.set_transform_field(transform_index, field, value);
+        let edit = scene.transform_edit(transform_index, field, before, value);
+        scene.record(edit);
+        Ok(())
+    }
+}
 impl UserData for TransformPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
         fields.add_field_method_get("index", |lua, this|{
-            Ok(this.index)
+            with_scene!(lua => scene);
+            this.index(&scene)
         });
 
         fields.add_field_method_get("owner_index", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.transforms[this.index].owner)
+            this.index(&scene)?;
+            Ok(this.owner.index)
         });
         fields.add_field_method_get("owner", |lua, this| {
             with_scene!(lua => scene);
-            Ok(EntityPointer{ index: scene.transforms[this.index].owner })
+            this.index(&scene)?;
+            Ok(this.owner)
         });
 
         fields.add_field_method_get("translation", |lua, this|{
             with_scene!(lua => scene);
-            Ok(scene.transforms[this.index].local_translation)
+            Ok(scene.transforms[this.index(&scene)?].local_translation)
         });
         fields.add_field_method_get("rotation", |lua, this|{
             with_scene!(lua => scene);
-            Ok(scene.transforms[this.index].local_rotation)
+            Ok(scene.transforms[this.index(&scene)?].local_rotation)
         });
         fields.add_field_method_get("scale", |lua, this|{
             with_scene!(lua => scene);
-            Ok(scene.transforms[this.index].local_scale)
+            Ok(scene.transforms[this.index(&scene)?].local_scale)
         });
 
         fields.add_field_method_set("translation", |lua, this, vector: Vector|{
-            with_scene_mut!(lua => scene);
-            // one level of the hierarchy up, for safety and to make this work properly for editing transforms of render components (non-entity components)
-            let owner = scene.entities[scene.transforms[this.index].owner].parent;
-            scene.unupdated_entities.push(owner);
-            Ok(scene.transforms[this.index].local_translation = vector)
+            this.set_field(lua, TransformField::Translation, vector)
         });
         fields.add_field_method_set("rotation", |lua, this, vector: Vector|{
-            with_scene_mut!(lua => scene);
-            // one level of the hierarchy up, for safety and to make this work properly for editing transforms of render components (non-entity components)
-            let owner = scene.entities[scene.transforms[this.index].owner].parent;
-            scene.unupdated_entities.push(owner);
-            Ok(scene.transforms[this.index].local_rotation = vector)
+            this.set_field(lua, TransformField::Rotation, vector)
         });
         fields.add_field_method_set("scale", |lua, this, vector: Vector|{
-            with_scene_mut!(lua => scene);
-            // one level of the hierarchy up, for safety and to make this work properly for editing transforms of render components (non-entity components)
-            let owner = scene.entities[scene.transforms[this.index].owner].parent;
-            scene.unupdated_entities.push(owner);
-            Ok(scene.transforms[this.index].local_scale = vector)
+            this.set_field(lua, TransformField::Scale, vector)
         });
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct RenderComponentPointer {
-    pub index: usize,
+    owner: EntityPointer,
+    index: usize,
+}
+impl RenderComponentPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        let owner_index = scene.entities.iter().position(|entity| entity.alive && entity.render_objects.contains(&index))
+            .ok_or_else(|| mlua::Error::RuntimeError(format!("Render component {} does not exist", index)))?;
+        Ok(Self { owner: EntityPointer::new(scene, owner_index)?, index })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        if self.owner.get(scene)?.render_objects.contains(&self.index) {
+            Ok(self.index)
+        } else {
+            Err(component_removed("Render component", self.index, &self.owner))
+        }
+    }
 }
 impl UserData for RenderComponentPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
         fields.add_field_method_get("index", |lua, this|{
-            Ok(this.index)
+            with_scene!(lua => scene);
+            this.index(&scene)
+        });
+        fields.add_field_method_get("owner", |lua, this| {
+            with_scene!(lua => scene);
+            this.index(&scene)?;
+            Ok(this.owner)
         });
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct RigidBodyPointer {
-    pub index: usize,
+    owner: EntityPointer,
+    index: usize,
+}
+impl RigidBodyPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        let body = scene.rigid_body_components.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("Rigid body {} does not exist", index)))?;
+        Ok(Self { owner: EntityPointer::new(scene, body.owner)?, index })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        if self.owner.get(scene)?.rigid_body == Some(self.index) {
+            Ok(self.index)
+        } else {
+            Err(component_removed("Rigid body", self.index, &self.owner))
+        }
+    }
 }
 impl UserData for RigidBodyPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
         fields.add_field_method_get("index", |lua, this|{
-            Ok(this.index)
+            with_scene!(lua => scene);
+            this.index(&scene)
         });
         fields.add_field_method_get("owner_index", |lua, this|{
             with_scene!(lua => scene);
-            Ok(scene.rigid_body_components[this.index].owner)
+            Ok(scene.rigid_body_components[this.index(&scene)?].owner)
         });
 
         fields.add_field_method_get("static", |lua, this|{
             with_scene!(lua => scene);
-            Ok(scene.rigid_body_components[this.index].is_static)
+            Ok(scene.rigid_body_components[this.index(&scene)?].is_static)
         });
         fields.add_field_method_set("static", |lua, this, val: bool| {
             with_scene_mut!(lua => scene);
-
-            let hitbox_index = scene.rigid_body_components[this.index].hitbox;
-
-            let hitbox = unsafe {
-                &*(&scene.hitbox_components[hitbox_index].hitbox as *const _)
-            };
-            let transforms = unsafe {
-                &*(&scene.transforms as *const _)
-            };
-            
-            let rigid_body = &mut scene.rigid_body_components[this.index];
-
-            rigid_body.set_static(hitbox, transforms, val);
-
+            let index = this.index(&scene)?;
+            let before = scene.rigid_body_components[index].is_static;
+            scene.set_rigid_body_static(index, val);
+            let entity = scene.entity_handle(scene.rigid_body_components[index].owner);
+            scene.record(SceneEdit::Static { entity, before, after: val });
             Ok(())
         });
 
         fields.add_field_method_get("velocity", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.rigid_body_components[this.index].velocity)
+            Ok(scene.rigid_body_components[this.index(&scene)?].velocity)
         });
         fields.add_field_method_set("velocity", |lua, this, val: Value| {
             with_scene_mut!(lua => scene);
-            scene.rigid_body_components[this.index].velocity = Vector::from_lua(val, lua)?;
+            let index = this.index(&scene)?;
+            scene.rigid_body_components[index].velocity = Vector::from_lua(val, lua)?;
             Ok(())
         });
 
         fields.add_field_method_get("angular_velocity", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.rigid_body_components[this.index].angular_velocity)
+            Ok(scene.rigid_body_components[this.index(&scene)?].angular_velocity)
         });
         fields.add_field_method_set("angular_velocity", |lua, this, val: Value| {
             with_scene_mut!(lua => scene);
-            scene.rigid_body_components[this.index].angular_velocity = Vector::from_lua(val, lua)?;
+            let index = this.index(&scene)?;
+            scene.rigid_body_components[index].angular_velocity = Vector::from_lua(val, lua)?;
             Ok(())
         });
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct CameraPointer {
-    pub index: usize,
+    owner: EntityPointer,
+    index: usize,
+}
+impl CameraPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        let camera = scene.camera_components.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("Camera {} does not exist", index)))?;
+        Ok(Self { owner: EntityPointer::new(scene, camera.owner)?, index })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        if self.owner.get(scene)?.camera == Some(self.index) {
+            Ok(self.index)
+        } else {
+            Err(component_removed("Camera", self.index, &self.owner))
+        }
+    }
 }
 impl UserData for CameraPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
         fields.add_field_method_get("owner", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].owner)
+            Ok(scene.camera_components[this.index(&scene)?].owner)
         });
 
         fields.add_field_method_get("transform", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].transform)
+            Ok(scene.camera_components[this.index(&scene)?].transform)
         });
 
         fields.add_field_method_get("fov_y", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].fov_y)
+            Ok(scene.camera_components[this.index(&scene)?].fov_y)
         });
         fields.add_field_method_set("fov_y", |lua, this, val: f32| {
             with_scene_mut!(lua => scene);
-            scene.camera_components[this.index].fov_y = val;
+            let index = this.index(&scene)?;
+            scene.camera_components[index].fov_y = val;
+            scene.dirty_camera_components.push(index);
             Ok(())
         });
 
         fields.add_field_method_get("aspect_ratio", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].aspect_ratio)
+            Ok(scene.camera_components[this.index(&scene)?].aspect_ratio)
         });
         fields.add_field_method_set("aspect_ratio", |lua, this, val: f32| {
             with_scene_mut!(lua => scene);
-            scene.camera_components[this.index].aspect_ratio = val;
+            let index = this.index(&scene)?;
+            scene.camera_components[index].aspect_ratio = val;
+            scene.dirty_camera_components.push(index);
             Ok(())
         });
 
         fields.add_field_method_get("near", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].near)
+            Ok(scene.camera_components[this.index(&scene)?].near)
         });
         fields.add_field_method_set("near", |lua, this, val: f32| {
             with_scene_mut!(lua => scene);
-            scene.camera_components[this.index].near = val;
+            let index = this.index(&scene)?;
+            scene.camera_components[index].near = val;
+            scene.dirty_camera_components.push(index);
             Ok(())
         });
 
         fields.add_field_method_get("far", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].far)
+            Ok(scene.camera_components[this.index(&scene)?].far)
         });
         fields.add_field_method_set("far", |lua, this, val: f32| {
             with_scene_mut!(lua => scene);
-            scene.camera_components[this.index].far = val;
+            let index = this.index(&scene)?;
+            scene.camera_components[index].far = val;
+            scene.dirty_camera_components.push(index);
             Ok(())
         });
-    }
-}
 
-#[derive(Clone)]
+        fields.add_field_method_get("orthographic", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(scene.camera_components[this.index(&scene)?].orthographic.map(|(half_width, half_height)| vec![half_width, half_height]))
+        });
+        fields.add_field_method_set("orthographic", |lua, this, val: Option<Vec<f32>>| {
+            let orthographic = match val.as_deref() {
+                None => None,
+                Some(&[half_width, half_height]) => Some((half_width, half_height)),
+                Some(_) => return Err(mlua::Error::RuntimeError(String::from("orthographic takes { half_width, half_height } or nil"))),
+            };
+            with_scene_mut!(lua => scene);
+            let index = this.index(&scene)?;
+            scene.camera_components[index].orthographic = orthographic;
+            scene.dirty_camera_components.push(index);
+            Ok(())
+        });
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct AnimationPointer {
+    owner: EntityPointer,
+    index: usize,
+}
+impl AnimationPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        Ok(Self { owner: EntityPointer::new(scene, animation_component(scene, index)?.owner_entity)?, index })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        if self.owner.get(scene)?.animation_objects.contains(&self.index) {
+            Ok(self.index)
+        } else {
+            Err(component_removed("Animation component", self.index, &self.owner))
+        }
+    }
+    fn get<'a>(&self, scene: &'a Scene) -> mlua::Result<&'a AnimationComponent> {
+        Ok(&scene.animation_components[self.index(scene)?])
+    }
+    fn get_mut<'a>(&self, scene: &'a mut Scene) -> mlua::Result<&'a mut AnimationComponent> {
+        let index = self.index(scene)?;
+        Ok(&mut scene.animation_components[index])
+    }
+}
+impl UserData for AnimationPointer {
+    fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("index", |lua, this| {
+            with_scene!(lua => scene);
+            this.index(&scene)
+        });
+        fields.add_field_method_get("owner", |lua, this| {
+            with_scene!(lua => scene);
+            this.index(&scene)?;
+            Ok(this.owner)
+        });
+        fields.add_field_method_get("name", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.name.clone())
+        });
+        fields.add_field_method_get("duration", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.duration)
+        });
+        fields.add_field_method_get("running", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.running)
+        });
+
+        fields.add_field_method_get("paused", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.paused)
+        });
+        fields.add_field_method_set("paused", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.paused = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("speed", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.speed)
+        });
+        fields.add_field_method_set("speed", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.speed = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("time", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.time)
+        });
+        fields.add_field_method_set("time", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.seek(val);
+            Ok(())
+        });
+
+        fields.add_field_method_get("normalized_time", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.normalized_time())
+        });
+        fields.add_field_method_set("normalized_time", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.set_normalized_time(val);
+            Ok(())
+        });
+
+        fields.add_field_method_get("repeat", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.repeat)
+        });
+        fields.add_field_method_set("repeat", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.repeat = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("ping_pong", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.ping_pong)
+        });
+        fields.add_field_method_set("ping_pong", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.ping_pong = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("snap_back", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.snap_back)
+        });
+        fields.add_field_method_set("snap_back", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.snap_back = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("weight", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.weight)
+        });
+        fields.add_field_method_set("weight", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            let animation = this.get_mut(&mut scene)?;
+            animation.weight = val;
+            animation.fade = None;
+            Ok(())
+        });
+
+        fields.add_field_method_get("layer", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.layer)
+        });
+        fields.add_field_method_set("layer", |lua, this, val: u32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.layer = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("additive", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.additive)
+        });
+        fields.add_field_method_set("additive", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.additive = val;
+            Ok(())
+        });
+
+        fields.add_field_method_get("mask", |lua, this| {
+            with_scene!(lua => scene);
+            this.get(&scene)?.mask.as_ref()
+                .map(|mask| lua.create_sequence_from(mask.iter().copied()))
+                .transpose()
+        });
+
+        fields.add_field_method_get("markers", |lua, this| {
+            with_scene!(lua => scene);
+            let table = lua.create_table()?;
+            for (i, marker) in this.get(&scene)?.markers.iter().enumerate() {
+                let marker_table = lua.create_table()?;
+                marker_table.set("name", marker.name.as_str())?;
+                marker_table.set("time", marker.time)?;
+                table.set(i + 1, marker_table)?;
+            }
+            Ok(table)
+        });
+    }
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("start", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.start();
+            Ok(())
+        });
+        methods.add_method("stop", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            let index = this.index(&scene)?;
+            let scene = &mut *scene;
+            scene.animation_components[index].stop(&mut scene.entities);
+            Ok(())
+        });
+        methods.add_method("seek", |lua, this, time: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.seek(time);
+            Ok(())
+        });
+        methods.add_method("add_marker", |lua, this, (name, time): (String, f32)| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.markers.push(AnimationMarker { name, time });
+            Ok(())
+        });
+        methods.add_method("fade_to", |lua, this, (weight, duration, stop): (f32, f32, Option<bool>)| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.fade_to(weight, duration, stop.unwrap_or(false));
+            Ok(())
+        });
+        methods.add_method("crossfade_to", |lua, this, (other_index, duration): (usize, f32)| {
+            with_scene_mut!(lua => scene);
+            let index = this.index(&scene)?;
+            animation_component(&scene, other_index)?;
+            scene.crossfade(index, other_index, duration);
+            Ok(())
+        });
+        methods.add_method("set_mask", |lua, this, (entity_index, recursive): (usize, bool)| {
+            with_scene_mut!(lua => scene);
+            let index = this.index(&scene)?;
+            EntityPointer::new(&scene, entity_index)?;
+            scene.set_animation_mask(index, entity_index, recursive);
+            Ok(())
+        });
+        methods.add_method("clear_mask", |lua, this, ()| {
+            with_scene_mut!(lua => scene);
+            let index = this.index(&scene)?;
+            scene.clear_animation_mask(index);
+            Ok(())
+        });
+        // removes every marker with the name
+        methods.add_method("remove_marker", |lua, this, name: String| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.markers.retain(|marker| marker.name != name);
+            Ok(())
+        });
+    }
+}
+fn animation_component(scene: &Scene, index: usize) -> mlua::Result<&AnimationComponent> {
+    scene.animation_components.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("Animation component {} does not exist", index)))
+}
+
+/// An entity's animation controller, found through its owner on every access like CustomComponentPointer
+#[derive(Clone, Copy)]
+pub struct AnimationControllerPointer {
+    owner: EntityPointer,
+}
+impl AnimationControllerPointer {
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        self.owner.get(scene)?;
+        scene.get_animation_controller(self.owner.index).ok_or_else(|| mlua::Error::RuntimeError(
+            format!("Entity {} no longer has an animation controller", self.owner.index)
+        ))
+    }
+    fn set_parameter(&self, lua: &mlua::Lua, name: &str, value: Value) -> mlua::Result<()> {
+        with_scene_mut!(lua => scene);
+        let controller_index = self.index(&scene)?;
+        let controller = &mut scene.animation_controllers[controller_index];
+        let parameter = match (controller.parameters.get(name), value) {
+            (Some(AnimationParameter::Trigger(_)), Value::Boolean(b)) => AnimationParameter::Trigger(b),
+            (_, Value::Boolean(b)) => AnimationParameter::Bool(b),
+            (_, Value::Number(n)) => AnimationParameter::Float(n as f32),
+            (_, Value::Integer(i)) => AnimationParameter::Float(i as f32),
+            (_, value) => return Err(mlua::Error::RuntimeError(format!("Parameter '{}' cannot be set to a {}", name, value.type_name()))),
+        };
+        controller.set_parameter(name, parameter).map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+    }
+}
+impl UserData for AnimationControllerPointer {
+    fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("owner", |_, this| {
+            Ok(this.owner)
+        });
+        fields.add_field_method_get("uri", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(scene.animation_controllers[this.index(&scene)?].uri.clone())
+        });
+        fields.add_field_method_get("state", |lua, this| {
+            with_scene!(lua => scene);
+            let controller = &scene.animation_controllers[this.index(&scene)?];
+            Ok(controller.states[controller.current_state].name.clone())
+        });
+        fields.add_field_method_get("state_names", |lua, this| {
+            with_scene!(lua => scene);
+            lua.create_sequence_from(scene.animation_controllers[this.index(&scene)?].states.iter().map(|state| state.name.clone()))
+        });
+        fields.add_field_method_get("parameter_names", |lua, this| {
+            with_scene!(lua => scene);
+            let mut names: Vec<String> = scene.animation_controllers[this.index(&scene)?].parameters.keys().cloned().collect();
+            names.sort();
+            lua.create_sequence_from(names)
+        });
+    }
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("get_parameter", |lua, this, name: String| {
+            with_scene!(lua => scene);
+            Ok(match scene.animation_controllers[this.index(&scene)?].parameters.get(name.as_str()) {
+                Some(AnimationParameter::Float(value)) => Value::Number(*value as f64),
+                Some(AnimationParameter::Bool(value) | AnimationParameter::Trigger(value)) => Value::Boolean(*value),
+                None => Value::Nil,
+            })
+        });
+        methods.add_method("set_parameter", |lua, this, (name, value): (String, Value)| {
+            this.set_parameter(lua, name.as_str(), value)
+        });
+        methods.add_method("set_trigger", |lua, this, name: String| {
+            this.set_parameter(lua, name.as_str(), Value::Boolean(true))
+        });
+        methods.add_method("reset_trigger", |lua, this, name: String| {
+            this.set_parameter(lua, name.as_str(), Value::Boolean(false))
+        });
+        methods.add_method("play", |lua, this, (state_name, duration): (String, Option<f32>)| {
+            with_scene_mut!(lua => scene);
+            let controller_index = this.index(&scene)?;
+            let controller = &scene.animation_controllers[controller_index];
+            let state_index = controller.states.iter().position(|state| state.name == state_name).ok_or_else(|| mlua::Error::RuntimeError(
+                format!("Animation controller '{}' has no state '{}'", controller.uri, state_name)
+            ))?;
+            let current_animation = scene.find_animation(controller.owner, controller.states[controller.current_state].clip.as_str())
+                .filter(|_| controller.entered);
+            scene.enter_controller_state(controller_index, current_animation, state_index, duration.unwrap_or(0.0));
+            Ok(())
+        });
+    }
+}
+
+/// An IK constraint, found through its chain on every access since removing a constraint moves the last one into its index
+#[derive(Clone)]
+pub struct IkConstraintPointer {
+    owner: EntityPointer, // the first joint
+    chain: Vec<usize>,
+}
+impl IkConstraintPointer {
+    pub fn new(scene: &Scene, index: usize) -> mlua::Result<Self> {
+        let chain = ik_constraint(scene, index)?.chain.clone();
+        Ok(Self { owner: EntityPointer::new(scene, chain[0])?, chain })
+    }
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        self.owner.get(scene)?;
+        scene.ik_constraints.iter().position(|constraint| constraint.chain == self.chain).ok_or_else(|| mlua::Error::RuntimeError(
+            format!("The IK constraint of entity {} has been removed", self.owner.index)
+        ))
+    }
+    fn get<'a>(&self, scene: &'a Scene) -> mlua::Result<&'a IkConstraint> {
+        Ok(&scene.ik_constraints[self.index(scene)?])
+    }
+    fn get_mut<'a>(&self, scene: &'a mut Scene) -> mlua::Result<&'a mut IkConstraint> {
+        let index = self.index(scene)?;
+        Ok(&mut scene.ik_constraints[index])
+    }
+}
+impl UserData for IkConstraintPointer {
+    fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("index", |lua, this| {
+            with_scene!(lua => scene);
+            this.index(&scene)
+        });
+        fields.add_field_method_get("solver", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.solver.name())
+        });
+        fields.add_field_method_get("chain", |lua, this| {
+            with_scene!(lua => scene);
+            entity_table(lua, &scene, &this.get(&scene)?.chain)
+        });
+        fields.add_field_method_get("target", |lua, this| {
+            with_scene!(lua => scene);
+            ik_target_to_lua(lua, &scene, &this.get(&scene)?.target)
+        });
+        fields.add_field_method_set("target", |lua, this, val: Value| {
+            let target = ik_target_from_lua(lua, val)?;
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.target = target;
+            Ok(())
+        });
+        fields.add_field_method_get("pole", |lua, this| {
+            with_scene!(lua => scene);
+            match &this.get(&scene)?.pole {
+                Some(pole) => ik_target_to_lua(lua, &scene, pole),
+                None => Ok(Value::Nil),
+            }
+        });
+        fields.add_field_method_set("pole", |lua, this, val: Value| {
+            let pole = match val {
+                Value::Nil => None,
+                val => Some(ik_target_from_lua(lua, val)?),
+            };
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.pole = pole;
+            Ok(())
+        });
+        fields.add_field_method_get("aim_axis", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.aim_axis)
+        });
+        fields.add_field_method_set("aim_axis", |lua, this, val: Vector| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.aim_axis = val.normalize3();
+            Ok(())
+        });
+        fields.add_field_method_get("weight", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.weight)
+        });
+        fields.add_field_method_set("weight", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.weight = val.clamp(0.0, 1.0);
+            Ok(())
+        });
+        fields.add_field_method_get("enabled", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.enabled)
+        });
+        fields.add_field_method_set("enabled", |lua, this, val: bool| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.enabled = val;
+            Ok(())
+        });
+        fields.add_field_method_get("iterations", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.iterations)
+        });
+        fields.add_field_method_set("iterations", |lua, this, val: usize| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.iterations = val;
+            Ok(())
+        });
+        fields.add_field_method_get("tolerance", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(this.get(&scene)?.tolerance)
+        });
+        fields.add_field_method_set("tolerance", |lua, this, val: f32| {
+            with_scene_mut!(lua => scene);
+            this.get_mut(&mut scene)?.tolerance = val.max(0.0);
+            Ok(())
+        });
+    }
+}
+fn ik_constraint(scene: &Scene, index: usize) -> mlua::Result<&IkConstraint> {
+    scene.ik_constraints.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("IK constraint {} does not exist", index)))
+}
+/// An entity target is given as an EntityPointer, a fixed one as a Vector
+fn ik_target_from_lua(lua: &mlua::Lua, val: Value) -> mlua::Result<IkTarget> {
+    if let Value::UserData(ud) = &val && let Ok(entity) = ud.borrow::<EntityPointer>() {
+        with_scene!(lua => scene);
+        entity.get(&scene)?;
+        return Ok(IkTarget::Entity(entity.index))
+    }
+    Ok(IkTarget::Position(Vector::from_lua(val, lua)?))
+}
+fn ik_target_to_lua<'lua>(lua: &'lua mlua::Lua, scene: &Scene, target: &IkTarget) -> mlua::Result<Value<'lua>> {
+    match *target {
+        IkTarget::Entity(entity_index) => Ok(Value::UserData(lua.create_userdata(EntityPointer::new(scene, entity_index)?)?)),
+        IkTarget::Position(position) => Ok(Value::UserData(lua.create_userdata(position)?)),
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct ScriptPointer {
-    pub index: usize,
+    owner: EntityPointer,
+    index: usize,
+}
+impl ScriptPointer {
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        self.owner.get(scene)?;
+        match scene.script_components.get(self.index) {
+            Some(script) if script.owner == self.owner.index => Ok(self.index),
+            _ => Err(component_removed("Script component", self.index, &self.owner)),
+        }
+    }
+    /// The index for the helpers that borrow the scene themselves
+    fn resolve(&self, lua: &mlua::Lua) -> mlua::Result<usize> {
+        with_scene!(lua => scene);
+        self.index(&scene)
+    }
 }
 impl UserData for ScriptPointer {
     fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("index", |lua, this| {
+            this.resolve(lua)
+        });
+        fields.add_field_method_get("owner_index", |lua, this| {
+            this.resolve(lua)?;
+            Ok(this.owner.index)
+        });
         fields.add_field_method_get("owner", |lua, this| {
+            this.resolve(lua)?;
+            Ok(this.owner)
+        });
+        fields.add_field_method_get("uri", |lua, this| {
             with_scene!(lua => scene);
-            Ok(scene.camera_components[this.index].owner)
+            Ok(scene.script_components[this.index(&scene)?].uri.clone())
+        });
+        fields.add_field_method_get("field_names", |lua, this| {
+            with_scene!(lua => scene);
+            let mut names: Vec<String> = scene.script_components[this.index(&scene)?].fields.keys().cloned().collect();
+            names.sort();
+            lua.create_sequence_from(names)
         });
     }
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method("call_method", |lua, this, (method_name, args): (String, mlua::MultiValue)| {
-            with_scene!(lua => scene);
-            Lua::call_script(scene.script_components[this.index].script, method_name.as_str(), Some(args))
+            let (uri, instance) = {
+                with_scene!(lua => scene);
+                let component = &scene.script_components[this.index(&scene)?];
+                (component.uri.clone(), component.instance)
+            };
+            let environment = instance
+                .map(|id| Lua::get_instance_environment(lua, id))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| mlua::Error::RuntimeError(
+                    format!("Script '{}' has no instance, instances only exist while the scene is running", uri)
+                ))?;
+            let method: mlua::Function = environment.raw_get::<_, Option<mlua::Function>>(method_name.as_str())?
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("Method '{}' not found in script '{}'", method_name, uri)))?;
+            method.call::<_, mlua::MultiValue>(args)
+        });
+        methods.add_method("get_field", |lua, this, name: String| {
+            Ok(get_script_field(lua, this.resolve(lua)?, name.as_str())?.unwrap_or(Value::Nil))
+        });
+        methods.add_method("set_field", |lua, this, (name, value): (String, Value)| {
+            let script_index = this.resolve(lua)?;
+            if set_script_field(lua, script_index, name.as_str(), value)? {
+                Ok(())
+            } else {
+                Err(mlua::Error::RuntimeError(format!("Script {} has no field '{}'", script_index, name)))
+            }
         });
     }
 
 }
+fn script_component(scene: &Scene, index: usize) -> mlua::Result<&ScriptComponent> {
+    scene.script_components.get(index).ok_or_else(|| mlua::Error::RuntimeError(format!("Script component {} does not exist", index)))
+}
+/// The live value from the script's instance while it has one, otherwise the value stored in the component
+fn get_script_field<'lua>(lua: &'lua mlua::Lua, script_index: usize, name: &str) -> mlua::Result<Option<Value<'lua>>> {
+    let (instance, field) = {
+        with_scene!(lua => scene);
+        let component = script_component(&scene, script_index)?;
+        (component.instance, component.fields.get(name).cloned())
+    };
+    let Some(field) = field else { return Ok(None) };
+
+    if let Some(environment) = instance.map(|id| Lua::get_instance_environment(lua, id)).transpose()?.flatten() {
+        return Ok(Some(environment.raw_get(name)?))
+    }
+    Ok(Some(field_to_lua(lua, &field)?))
+}
+///* Returns false if the script does not export the field.
+///* Fails if the value has a different type than the field's declaration.
+fn set_script_field<'lua>(lua: &'lua mlua::Lua, script_index: usize, name: &str, value: Value<'lua>) -> mlua::Result<bool> {
+    let field = field_from_lua(&value).ok_or_else(|| mlua::Error::RuntimeError(
+        format!("Field '{}' cannot be set to a {}", name, value.type_name())
+    ))?;
+    let (instance, field) = {
+        with_scene_mut!(lua => scene);
+        let component = scene.script_components.get(script_index).ok_or_else(|| mlua::Error::RuntimeError(
+            format!("Script component {} does not exist", script_index)
+        ))?;
+        if !component.fields.contains_key(name) {
+            return Ok(false)
+        }
+        scene.set_script_field(script_index, name, field).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+        let component = &scene.script_components[script_index];
+        (component.instance, component.fields[name].clone())
+    };
+
+    if let Some(environment) = instance.map(|id| Lua::get_instance_environment(lua, id)).transpose()?.flatten() {
+        environment.raw_set(name, field_to_lua(lua, &field)?)?;
+    }
+    Ok(true)
+}
+///* Copies the values the script instances hold for their exported fields into their components, which is what gets saved.
+///* Values a script has changed to a different type are left out.
+fn store_script_instance_fields(lua: &mlua::Lua) -> mlua::Result<()> {
+    let instances: Vec<(usize, usize, Vec<String>)> = {
+        with_scene!(lua => scene);
+        scene.script_components.iter().enumerate()
+            .filter_map(|(script_index, component)| component.instance.map(|id| (script_index, id, component.fields.keys().cloned().collect())))
+            .collect()
+    };
+    for (script_index, id, names) in instances {
+        let Some(environment) = Lua::get_instance_environment(lua, id)? else { continue };
+        for name in names {
+            let Some(field) = field_from_lua(&environment.raw_get(name.as_str())?) else { continue };
+            with_scene_mut!(lua => scene);
+            scene.set_script_field(script_index, name.as_str(), field).ok();
+        }
+    }
+    Ok(())
+}
+
+///* A custom component, found through its owner and type on every access so that it stays valid while other components are removed.
+///* Fields can be read and written directly, eg. `component.health = 10`, or with get/set.
+#[derive(Clone, Copy)]
+pub struct CustomComponentPointer {
+    owner: EntityPointer,
+    schema: usize,
+}
+impl CustomComponentPointer {
+    fn index(&self, scene: &Scene) -> mlua::Result<usize> {
+        self.owner.get(scene)?;
+        scene.custom_components.iter()
+            .position(|component| component.owner == self.owner.index && component.schema == self.schema)
+            .ok_or_else(|| mlua::Error::RuntimeError(format!(
+                "Entity {} no longer has a '{}' component", self.owner.index, scene.component_schemas[self.schema].name
+            )))
+    }
+    fn get_field<'lua>(&self, lua: &'lua mlua::Lua, name: &str) -> mlua::Result<Value<'lua>> {
+        with_scene!(lua => scene);
+        match scene.custom_components[self.index(&scene)?].fields.get(name) {
+            Some(field) => field_to_lua(lua, field),
+            None => Ok(Value::Nil),
+        }
+    }
+    fn set_field(&self, lua: &mlua::Lua, name: &str, value: Value) -> mlua::Result<()> {
+        with_scene_mut!(lua => scene);
+        let component_index = self.index(&scene)?;
+        let field = field_from_lua(&value).ok_or_else(|| mlua::Error::RuntimeError(
+            format!("Field '{}' cannot be set to a {}", name, value.type_name())
+        ))?;
+        scene.set_custom_field(component_index, name, field).map_err(|e| mlua::Error::RuntimeError(e.to_string()))
+    }
+}
+impl UserData for CustomComponentPointer {
+    fn add_fields<'lua, F: UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("owner", |_, this| {
+            Ok(this.owner)
+        });
+        fields.add_field_method_get("type_name", |lua, this| {
+            with_scene!(lua => scene);
+            Ok(scene.component_schemas[this.schema].name.clone())
+        });
+        fields.add_field_method_get("field_names", |lua, this| {
+            with_scene!(lua => scene);
+            lua.create_sequence_from(scene.component_schemas[this.schema].fields.iter().map(|(name, _)| name.clone()))
+        });
+    }
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("get", |lua, this, name: String| {
+            this.get_field(lua, name.as_str())
+        });
+        methods.add_method("set", |lua, this, (name, value): (String, Value)| {
+            this.set_field(lua, name.as_str(), value)
+        });
+        // fallbacks for names that are not fields or methods of the pointer itself
+        methods.add_meta_method(MetaMethod::Index, |lua, this, name: String| {
+            this.get_field(lua, name.as_str())
+        });
+        methods.add_meta_method(MetaMethod::NewIndex, |lua, this, (name, value): (String, Value)| {
+            this.set_field(lua, name.as_str(), value)
+        });
+    }
+}
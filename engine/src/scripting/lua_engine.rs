@@ -1,24 +1,34 @@
 use std::any::{Any, TypeId};
 use std::path::{Path, PathBuf};
 use mlua;
+use mlua::IntoLua;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::engine::EngineRef;
 use crate::math::Vector;
+use crate::scene::scene::Field as ComponentField;
 use crate::scripting::engine_api::client_api::client_api::{LuaCursorIcon, LuaKeyCode, LuaMouseButton, LuaResizeDirection};
 use crate::scripting::engine_api::gui_api::gui_api::{GUINodePointer, LuaAnchorPoint};
-use crate::scripting::engine_api::scene_api::scene_api::EntityPointer;
+use crate::scripting::engine_api::scene_api::scene_api::{scene_event_to_lua, EntityPointer};
 
 thread_local! {
     static LUA: RefCell<Option<Lua>> = RefCell::new(None);
 }
 
+/// Named registry table of instance id -> instance environment, so that the scene api can reach instances from within callbacks
+const SCRIPT_INSTANCES: &str = "script_instances";
+/// Named registry table of scene event name -> list of handler functions, added to by Scene:on
+pub(crate) const SCENE_EVENT_HANDLERS: &str = "scene_event_handlers";
+
 struct ScriptInstance {
-    owner: EntityPointer,
+    id: usize,
     instance_environment: mlua::RegistryKey,
+    has_started: bool,
 }
 pub struct Script {
     path: PathBuf,
+    source: String,
+    singleton: bool, // loaded from the script directory and run once globally, otherwise only run through instances
     environment: mlua::RegistryKey,
     start_fn: Option<mlua::RegistryKey>,
     update_fn: Option<mlua::RegistryKey>,
@@ -41,7 +51,6 @@ enum FieldType {
     Integer,
     String,
     Boolean,
-    Function,
     Table,
     UserData(TypeId),
     Unknown,
@@ -51,6 +60,8 @@ pub struct Lua {
     lua: mlua::Lua,
     scripts: Vec<Script>,
     free_script_indices: Vec<usize>,
+    next_instance_id: usize,
+    failed_script_uris: HashSet<String>,
 
     cached_calls: Vec<(usize, String, usize, usize)>, // stores script index, method name, call index, and gui index.
     reload_scripts_requested: bool,
@@ -108,6 +119,8 @@ impl Lua {
                 lua: mlua::Lua::new(),
                 scripts: Vec::new(),
                 free_script_indices: Vec::new(),
+                next_instance_id: 0,
+                failed_script_uris: HashSet::new(),
                 cached_calls: Vec::new(),
                 reload_scripts_requested: false,
                 load_scripts_requested: false,
@@ -117,6 +130,8 @@ impl Lua {
             let lua = &script_engine_ref.as_ref().unwrap().lua;
 
             lua.set_app_data(engine.clone());
+            lua.set_named_registry_value(SCRIPT_INSTANCES, lua.create_table()?)?;
+            lua.set_named_registry_value(SCENE_EVENT_HANDLERS, lua.create_table()?)?;
 
             LuaAnchorPoint::register_to_lua(lua)?;
             LuaCursorIcon::register_to_lua(lua)?;
@@ -153,43 +168,40 @@ impl Lua {
         self.lua.gc_collect().unwrap()
     }
 
-    /// Returns the script and assigned index (could be different from scripts.len())
-    fn load_script_impl(&mut self, path: &Path) -> Result<(usize), Box<dyn std::error::Error>> {
-        let script_content = std::fs::read_to_string(path)?;
+    fn create_environment(lua: &mlua::Lua) -> Result<mlua::Table<'_>, mlua::Error> {
         // create local-environment
-        let environment = self.lua.create_table()?;
+        let environment = lua.create_table()?;
         // create a metadata-table describing local access to global
-        let metatable = self.lua.create_table()?;
-        metatable.set("__index", self.lua.globals())?;
+        let metatable = lua.create_table()?;
+        metatable.set("__index", lua.globals())?;
         // apply that metadata-table to the local environment
         environment.set_metatable(Some(metatable));
+        Ok(environment)
+    }
 
-        self.lua.load(script_content)
+    /// Returns the script and assigned index (could be different from scripts.len())
+    fn load_script_impl(&mut self, path: &Path, singleton: bool) -> Result<(usize), Box<dyn std::error::Error>> {
+        let script_content = std::fs::read_to_string(path)?;
+        let environment = Self::create_environment(&self.lua)?;
+
+        self.lua.load(script_content.as_str())
             .set_environment(environment.clone())
             .exec()?;
 
-        // extract lifecycle functions
-        let start_fn = environment.get::<_, Option<mlua::Function>>("Start")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let update_fn = environment.get::<_, Option<mlua::Function>>("Update")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let on_awake_fn = environment.get::<_, Option<mlua::Function>>("Awake")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let scroll_fn = environment.get::<_, Option<mlua::Function>>("MouseScrolled")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let mouse_moved_fn = environment.get::<_, Option<mlua::Function>>("MouseMoved")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let mouse_button_pressed_fn = environment.get::<_, Option<mlua::Function>>("MouseButtonPressed")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
-        let mouse_button_released_fn = environment.get::<_, Option<mlua::Function>>("MouseButtonReleased")?
-            .map(|f| self.lua.create_registry_value(f))
-            .transpose()?;
+        // extract lifecycle functions, instanced scripts have theirs called on each instance's environment instead
+        let lifecycle_fn = |name: &str| -> Result<Option<mlua::RegistryKey>, mlua::Error> {
+            if !singleton { return Ok(None) }
+            environment.get::<_, Option<mlua::Function>>(name)?
+                .map(|f| self.lua.create_registry_value(f))
+                .transpose()
+        };
+        let start_fn = lifecycle_fn("Start")?;
+        let update_fn = lifecycle_fn("Update")?;
+        let on_awake_fn = lifecycle_fn("Awake")?;
+        let scroll_fn = lifecycle_fn("MouseScrolled")?;
+        let mouse_moved_fn = lifecycle_fn("MouseMoved")?;
+        let mouse_button_pressed_fn = lifecycle_fn("MouseButtonPressed")?;
+        let mouse_button_released_fn = lifecycle_fn("MouseButtonReleased")?;
 
         let mut fields = HashMap::new();
 
@@ -199,7 +211,7 @@ impl Lua {
             mlua::Value::Boolean(_) => FieldType::Boolean,
             mlua::Value::String(_) => FieldType::String,
             mlua::Value::Table(_) => FieldType::Table,
-            // mlua::Value::UserData(ud) => FieldType::UserData(ud.type_id()),
+            mlua::Value::UserData(ud) if ud.is::<Vector>() => FieldType::UserData(TypeId::of::<Vector>()),
             _ => FieldType::Unknown,
         };
 
@@ -231,6 +243,8 @@ impl Lua {
 
         let script = Script {
             path: PathBuf::from(path),
+            source: script_content,
+            singleton,
             environment: self.lua.create_registry_value(environment).unwrap(),
             start_fn,
             update_fn,
@@ -285,23 +299,30 @@ impl Lua {
 
         for &i in to_remove.iter().rev() {
             let script = self.scripts.remove(i);
-            self.lua.remove_registry_value(script.environment)?;
-            if let Some(start_fn) = script.start_fn {
-                self.lua.remove_registry_value(start_fn)?;
-            }
-            if let Some(update_fn) = script.update_fn {
-                self.lua.remove_registry_value(update_fn)?;
-            }
+            self.unload_script(script)?;
         }
 
         let mut indices = Vec::new();
         for path in scripts_dir {
             if path.extension().and_then(|s| s.to_str()) == Some("lua") {
-                indices.push(self.load_script_impl(path)?);
+                indices.push(self.load_script_impl(path, true)?);
             }
         }
         Ok(indices)
     }
+    fn unload_script(&self, script: Script) -> Result<(), mlua::Error> {
+        self.lua.remove_registry_value(script.environment)?;
+        if let Some(start_fn) = script.start_fn {
+            self.lua.remove_registry_value(start_fn)?;
+        }
+        if let Some(update_fn) = script.update_fn {
+            self.lua.remove_registry_value(update_fn)?;
+        }
+        for instance in script.instances {
+            self.remove_instance(instance)?;
+        }
+        Ok(())
+    }
 
     pub fn reload_scripts() {
         Self::with_mut(|lua| lua.reload_scripts_impl()).expect("failed to reload scripts");
@@ -309,26 +330,157 @@ impl Lua {
     fn reload_scripts_impl(
         &mut self,
     ) -> Result<(), mlua::Error> {
+        // scripts are reloaded in the same order so their indices stay the same, instances are recreated by the next sync
         let mut scripts_dir = Vec::new();
-        for script in self.scripts.drain(..) {
-            scripts_dir.push(script.path);
-            self.lua.remove_registry_value(script.environment)?;
-            if let Some(start_fn) = script.start_fn {
-                self.lua.remove_registry_value(start_fn)?;
-            }
-            if let Some(update_fn) = script.update_fn {
-                self.lua.remove_registry_value(update_fn)?;
-            }
+        let scripts: Vec<Script> = self.scripts.drain(..).collect();
+        for script in scripts {
+            scripts_dir.push((script.path.clone(), script.singleton));
+            self.unload_script(script)?;
         }
+        self.failed_script_uris.clear();
 
-        for path in scripts_dir {
+        for (path, singleton) in scripts_dir {
             if path.extension().and_then(|s| s.to_str()) == Some("lua") {
-                self.load_script_impl(Path::new(&path)).expect("failed to load script");
+                self.load_script_impl(Path::new(&path), singleton).expect("failed to load script");
             }
         }
         Ok(())
     }
 
+    /// Returns the index of the instanced script at the path, loading it the first time
+    fn load_script_asset_impl(&mut self, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        if let Some(index) = self.scripts.iter().position(|script| !script.singleton && script.path == path) {
+            return Ok(index)
+        }
+        self.load_script_impl(path, false)
+    }
+
+    /// The script's top-level declarations that can be stored in a script component, with their values from the script
+    fn exported_fields(&self, script_index: usize) -> Result<Vec<(String, ComponentField)>, mlua::Error> {
+        let mut exported = Vec::new();
+        for (name, field) in self.scripts[script_index].fields.iter() {
+            let exportable = match field.field_type {
+                FieldType::Number | FieldType::Integer | FieldType::Boolean | FieldType::String => true,
+                FieldType::UserData(type_id) => type_id == TypeId::of::<Vector>(),
+                _ => false,
+            };
+            if !exportable || name.starts_with('_') { continue }
+
+            let value: mlua::Value = self.lua.registry_value(&field.registry_key)?;
+            if let Some(value) = field_from_lua(&value) {
+                exported.push((name.clone(), value));
+            }
+        }
+        Ok(exported)
+    }
+
+    ///* Runs the script in a new environment with "self" set to the owner, then applies the component's fields over its declarations.
+    fn create_instance(
+        &mut self,
+        script_index: usize,
+        owner: EntityPointer,
+        fields: &HashMap<String, ComponentField>
+    ) -> Result<usize, mlua::Error> {
+        let environment = Self::create_environment(&self.lua)?;
+        environment.set("self", owner)?;
+
+        self.lua.load(self.scripts[script_index].source.as_str())
+            .set_environment(environment.clone())
+            .exec()?;
+        for (name, field) in fields.iter() {
+            environment.raw_set(name.as_str(), field_to_lua(&self.lua, field)?)?;
+        }
+
+        let id = self.next_instance_id;
+        self.next_instance_id += 1;
+        self.lua.named_registry_value::<mlua::Table>(SCRIPT_INSTANCES)?.raw_set(id, environment.clone())?;
+        self.scripts[script_index].instances.push(ScriptInstance {
+            id,
+            instance_environment: self.lua.create_registry_value(environment)?,
+            has_started: false,
+        });
+        Ok(id)
+    }
+    fn remove_instance(&self, instance: ScriptInstance) -> Result<(), mlua::Error> {
+        self.lua.named_registry_value::<mlua::Table>(SCRIPT_INSTANCES)?.raw_set(instance.id, mlua::Value::Nil)?;
+        self.lua.remove_registry_value(instance.instance_environment)
+    }
+
+    /// The environment of a live script instance, usable from within callbacks since it does not borrow the script engine
+    pub fn get_instance_environment<'lua>(lua: &'lua mlua::Lua, id: usize) -> Result<Option<mlua::Table<'lua>>, mlua::Error> {
+        lua.named_registry_value::<mlua::Table>(SCRIPT_INSTANCES)?.raw_get(id)
+    }
+    fn call_instance_method(&self, id: usize, method_name: &str) -> Result<(), mlua::Error> {
+        if let Some(environment) = Self::get_instance_environment(&self.lua, id)?
+            && let Some(method) = environment.raw_get::<_, Option<mlua::Function>>(method_name)?
+        {
+            method.call::<_, ()>(())?;
+        }
+        Ok(())
+    }
+
+    ///* Fills in the exported fields missing from each script component, and while the scene is running gives every script
+    ///* component an instance, calling Awake on the new ones. Instances whose component is gone are removed.
+    ///* Returns whether the scene is running.
+    fn sync_script_instances_impl(&mut self) -> Result<bool, mlua::Error> {
+        let engine = self.lua.app_data_ref::<EngineRef>().unwrap().clone();
+        let (running, components) = {
+            let scene = engine.scene.borrow();
+            (scene.running, scene.script_components.iter().map(|c| (c.uri.clone(), c.instance)).collect::<Vec<_>>())
+        };
+
+        let live_instances: HashSet<usize> = components.iter().filter_map(|c| c.1).collect();
+        for script_index in 0..self.scripts.len() {
+            let (kept, removed): (Vec<ScriptInstance>, Vec<ScriptInstance>) = self.scripts[script_index].instances.drain(..)
+                .partition(|instance| live_instances.contains(&instance.id));
+            self.scripts[script_index].instances = kept;
+            for instance in removed {
+                self.remove_instance(instance)?;
+            }
+        }
+        let existing_instances: HashSet<usize> = self.scripts.iter()
+            .flat_map(|script| script.instances.iter().map(|instance| instance.id))
+            .collect();
+
+        let mut new_instances = Vec::new(); // component index, instance id
+        for (component_index, (uri, instance)) in components.iter().enumerate() {
+            if instance.is_some_and(|id| existing_instances.contains(&id)) || self.failed_script_uris.contains(uri) { continue }
+
+            let script_index = match self.load_script_asset_impl(Path::new(uri)) {
+                Ok(script_index) => script_index,
+                Err(e) => {
+                    println!("Failed to load script '{}': {}", uri, e);
+                    self.failed_script_uris.insert(uri.clone());
+                    continue
+                }
+            };
+            let defaults = self.exported_fields(script_index)?;
+
+            let (owner, fields) = {
+                let mut scene = engine.scene.borrow_mut();
+                let component = &mut scene.script_components[component_index];
+                for (name, value) in defaults {
+                    component.fields.entry(name).or_insert(value);
+                }
+                if !running { continue }
+                let (owner_index, fields) = (component.owner, component.fields.clone());
+                (EntityPointer::new(&scene, owner_index)?, fields)
+            };
+            new_instances.push((component_index, self.create_instance(script_index, owner, &fields)?));
+        }
+
+        if !new_instances.is_empty() {
+            let mut scene = engine.scene.borrow_mut();
+            for &(component_index, id) in new_instances.iter() {
+                scene.script_components[component_index].instance = Some(id);
+            }
+        }
+        for (_, id) in new_instances {
+            self.call_instance_method(id, "Awake")?;
+        }
+        Ok(running)
+    }
+
     pub fn call_script(
         script_index: usize,
         method_name: &str,
@@ -405,7 +557,35 @@ impl Lua {
         self.cached_calls.clear();
     }
 
+    ///* Calls the handlers of every scene event queued since the last frame.
+    ///* The scene is not borrowed while handlers run, so they can use the whole scene api, events they cause are delivered next frame.
+    fn dispatch_scene_events_impl(&self) -> Result<(), mlua::Error> {
+        let events = {
+            let engine = self.lua.app_data_ref::<EngineRef>().unwrap();
+            let mut scene = engine.scene.borrow_mut();
+            scene.take_events()
+        };
+        if events.is_empty() { return Ok(()) }
+
+        let handlers: mlua::Table = self.lua.named_registry_value(SCENE_EVENT_HANDLERS)?;
+        for (event, generation) in events.iter() {
+            let Some(event_handlers) = handlers.get::<_, Option<mlua::Table>>(event.name())? else { continue };
+            // collected first, so that handlers can subscribe and unsubscribe while being called
+            let event_handlers: Vec<mlua::Function> = event_handlers.sequence_values().collect::<Result<_, _>>()?;
+            if event_handlers.is_empty() { continue }
+
+            let event_table = scene_event_to_lua(&self.lua, event, *generation)?;
+            for handler in event_handlers {
+                handler.call::<_, ()>(event_table.clone())?;
+            }
+        }
+        Ok(())
+    }
+
     fn run_update_methods_impl(&mut self) -> Result<(), mlua::Error> {
+        self.dispatch_scene_events_impl()?;
+        let running = self.sync_script_instances_impl()?;
+
         for i in 0..self.scripts.len() {
             if self.scripts[i].update_fn.is_some() {
                 if !self.scripts[i].has_started {
@@ -418,6 +598,19 @@ impl Lua {
                 self.call_method_by_key(self.scripts[i].update_fn.as_ref().unwrap())?
             }
         }
+
+        if running {
+            for i in 0..self.scripts.len() {
+                for j in 0..self.scripts[i].instances.len() {
+                    let id = self.scripts[i].instances[j].id;
+                    if !self.scripts[i].instances[j].has_started {
+                        self.scripts[i].instances[j].has_started = true;
+                        self.call_instance_method(id, "Start")?;
+                    }
+                    self.call_instance_method(id, "Update")?;
+                }
+            }
+        }
         Ok(())
     }
     pub fn run_update_methods() -> Result<(), mlua::Error> {
@@ -476,4 +669,25 @@ impl Lua {
     where F: FnOnce(&mlua::Lua) -> R, {
         Self::with(|lua_engine| f(&lua_engine.lua))
     }
+}
+
+pub(crate) fn field_to_lua<'lua>(lua: &'lua mlua::Lua, field: &ComponentField) -> Result<mlua::Value<'lua>, mlua::Error> {
+    match field {
+        ComponentField::Float(v) => v.into_lua(lua),
+        ComponentField::Int(v) => v.into_lua(lua),
+        ComponentField::Vec3(v) => v.into_lua(lua),
+        ComponentField::Bool(v) => v.into_lua(lua),
+        ComponentField::String(v) => v.as_str().into_lua(lua),
+    }
+}
+/// None for values that cannot be stored in a script component
+pub(crate) fn field_from_lua(value: &mlua::Value) -> Option<ComponentField> {
+    match value {
+        mlua::Value::Integer(v) => Some(ComponentField::Int(*v as i32)),
+        mlua::Value::Number(v) => Some(ComponentField::Float(*v as f32)),
+        mlua::Value::Boolean(v) => Some(ComponentField::Bool(*v)),
+        mlua::Value::String(v) => v.to_str().ok().map(|v| ComponentField::String(String::from(v))),
+        mlua::Value::UserData(ud) => ud.borrow::<Vector>().ok().map(|v| ComponentField::Vec3(*v)),
+        _ => None,
+    }
 }
\ No newline at end of file